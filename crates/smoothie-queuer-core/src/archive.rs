@@ -0,0 +1,236 @@
+use crate::queue::{TaskStatus, VideoTask};
+use crate::store::{self, sql_error};
+use rusqlite::params;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+/// What `scan` finds in the archive without reading the tasks.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ArchiveStats {
+    pub count: usize,
+    pub max_id: usize, // Highest task ID archived, so new tasks never reuse one
+}
+
+/// The newest archived tasks, for the History window.
+#[derive(Debug, Clone, Default)]
+pub struct ArchivePage {
+    pub tasks: Vec<VideoTask>, // Newest first, at most the `limit` asked for
+    pub total: usize,
+    pub energy_wh: f64,      // Over every archived task that has an estimate
+    pub energy_tasks: usize, // How many do
+}
+
+/// Archived tasks of one recipe, for the History window's statistics.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecipeStats {
+    pub recipe_path: String,
+    pub completed: usize,
+    pub cancelled: usize,
+    pub render_time: Duration, // Start to finish, over the completed tasks
+    pub energy_wh: f64,
+}
+
+/// Render speeds (seconds of input per second of rendering) observed on
+/// completed tasks, overall, per recipe and per recipe at each input resolution
+/// class. Recent tasks weigh more, so the estimates follow recipe changes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpeedModel {
+    overall: Option<f64>,
+    recipes: HashMap<String, f64>,
+    resolutions: HashMap<(String, String), f64>,
+}
+
+impl SpeedModel {
+    /// Folds one observed speed into the averages of its recipe and resolution.
+    fn fold(&mut self, recipe: &str, resolution: Option<&str>, speed: f64) {
+        let fold = |average: Option<f64>| match average {
+            Some(average) => average * 0.7 + speed * 0.3,
+            None => speed,
+        };
+        self.overall = Some(fold(self.overall));
+        let average = fold(self.recipes.get(recipe).copied());
+        self.recipes.insert(recipe.to_string(), average);
+        if let Some(resolution) = resolution {
+            let key = (recipe.to_string(), resolution.to_string());
+            let average = fold(self.resolutions.get(&key).copied());
+            self.resolutions.insert(key, average);
+        }
+    }
+
+    /// Folds in a task that just completed, until it reaches the history.
+    /// Returns whether it had a speed to record.
+    pub fn record(&mut self, task: &VideoTask) -> bool {
+        let Some(speed) = task.render_speed() else {
+            return false;
+        };
+        self.fold(&task.recipe_path.to_string_lossy(), task.resolution_class(), speed);
+        true
+    }
+
+    /// Whether no speed was recorded yet, so nothing can be estimated.
+    pub fn is_empty(&self) -> bool {
+        self.overall.is_none()
+    }
+
+    /// Speed expected for `task`: what its recipe did at the same resolution,
+    /// else at any resolution, else the overall average.
+    pub fn speed_for(&self, task: &VideoTask) -> Option<f64> {
+        let recipe = task.recipe_path.to_string_lossy().to_string();
+        let at_resolution = task
+            .resolution_class()
+            .and_then(|resolution| self.resolutions.get(&(recipe.clone(), resolution.to_string())));
+        at_resolution.or_else(|| self.recipes.get(&recipe)).copied().or(self.overall)
+    }
+
+    /// Render time still expected for `task`: all of it while pending, the part
+    /// not yet done while running (by its progress, else by the time elapsed).
+    /// `None` for finished tasks and when its length or any speed is unknown.
+    pub fn estimate_time_left(&self, task: &VideoTask) -> Option<Duration> {
+        let speed = self.speed_for(task).filter(|speed| *speed > 0.0)?;
+        let total = task.input_duration?.div_f64(speed);
+        match task.status {
+            TaskStatus::Pending | TaskStatus::Waiting | TaskStatus::Downloading => Some(total),
+            TaskStatus::Running => match task.progress {
+                Some(progress) => Some(total.mul_f64(1.0 - f64::from(progress.clamp(0.0, 1.0)))),
+                None => {
+                    let elapsed = task.started_at?.elapsed().unwrap_or_default();
+                    Some(total.saturating_sub(elapsed))
+                }
+            },
+            TaskStatus::Completed | TaskStatus::Failed(_) | TaskStatus::Cancelled => None,
+        }
+    }
+}
+
+/// The speeds of every archived task, oldest first, then of the completed
+/// tasks in `queued` that are not archived yet.
+pub fn speed_model(queued: &[VideoTask]) -> Result<SpeedModel, String> {
+    let connection = store::open()?;
+    let mut statement = connection
+        .prepare(
+            "SELECT recipe_path, resolution, speed FROM history
+             WHERE speed > 0
+             ORDER BY finished_at, seq",
+        )
+        .map_err(sql_error)?;
+    let rows = statement
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?, row.get::<_, f64>(2)?))
+        })
+        .map_err(sql_error)?;
+    let mut model = SpeedModel::default();
+    for row in rows {
+        let (recipe, resolution, speed) = row.map_err(sql_error)?;
+        model.fold(&recipe, resolution.as_deref(), speed);
+    }
+    let mut completed: Vec<&VideoTask> = queued.iter().filter(|task| task.render_speed().is_some()).collect();
+    completed.sort_by_key(|task| task.finished_at);
+    for task in completed {
+        model.record(task);
+    }
+    Ok(model)
+}
+
+/// Moves `tasks` into the archive, the `history` table of the queue database.
+pub fn append(tasks: &[VideoTask]) -> Result<(), String> {
+    if tasks.is_empty() {
+        return Ok(());
+    }
+    let mut connection = store::open()?;
+    let transaction = connection.transaction().map_err(sql_error)?;
+    store::insert_history(&transaction, tasks)?;
+    transaction.commit().map_err(sql_error)
+}
+
+/// Counts the archived tasks and finds the highest ID ever archived, pruned
+/// or not.
+pub fn scan() -> Result<ArchiveStats, String> {
+    let connection = store::open()?;
+    let (count, max_id): (i64, Option<i64>) = connection
+        .query_row(
+            "SELECT (SELECT COUNT(*) FROM history),
+                    (SELECT value FROM queue_state WHERE key = 'max_archived_id')",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(sql_error)?;
+    Ok(ArchiveStats { count: count as usize, max_id: max_id.unwrap_or(0) as usize })
+}
+
+/// Reads the `limit` newest archived tasks and totals over all of them.
+pub fn read(limit: usize) -> Result<ArchivePage, String> {
+    let connection = store::open()?;
+    let (total, energy_wh, energy_tasks): (i64, Option<f64>, i64) = connection
+        .query_row("SELECT COUNT(*), SUM(energy_wh), COUNT(energy_wh) FROM history", [], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })
+        .map_err(sql_error)?;
+
+    let mut statement = connection
+        .prepare("SELECT task FROM history ORDER BY seq DESC LIMIT ?1")
+        .map_err(sql_error)?;
+    let rows = statement
+        .query_map(params![limit as i64], |row| row.get::<_, String>(0))
+        .map_err(sql_error)?;
+    let mut tasks = Vec::new();
+    for json in rows {
+        match serde_json::from_str(&json.map_err(sql_error)?) {
+            Ok(task) => tasks.push(task),
+            Err(e) => log::warn!("Skipping an unreadable archived task: {}", e),
+        }
+    }
+    Ok(ArchivePage {
+        tasks,
+        total: total as usize,
+        energy_wh: energy_wh.unwrap_or(0.0),
+        energy_tasks: energy_tasks as usize,
+    })
+}
+
+/// Totals of the archived tasks per recipe, most used first.
+pub fn recipe_stats() -> Result<Vec<RecipeStats>, String> {
+    let connection = store::open()?;
+    let mut statement = connection
+        .prepare(
+            "SELECT recipe_path,
+                    SUM(status = 'completed'),
+                    SUM(status = 'cancelled'),
+                    SUM(CASE WHEN status = 'completed' THEN finished_at - started_at END),
+                    SUM(energy_wh)
+             FROM history
+             GROUP BY recipe_path
+             ORDER BY COUNT(*) DESC",
+        )
+        .map_err(sql_error)?;
+    let rows = statement
+        .query_map([], |row| {
+            Ok(RecipeStats {
+                recipe_path: row.get(0)?,
+                completed: row.get::<_, i64>(1)? as usize,
+                cancelled: row.get::<_, i64>(2)? as usize,
+                render_time: Duration::from_secs(row.get::<_, Option<i64>>(3)?.unwrap_or(0).max(0) as u64),
+                energy_wh: row.get::<_, Option<f64>>(4)?.unwrap_or(0.0),
+            })
+        })
+        .map_err(sql_error)?;
+    rows.collect::<Result<_, _>>().map_err(sql_error)
+}
+
+/// Deletes archived tasks that finished more than `max_age` ago. Returns how
+/// many were deleted.
+pub fn prune(max_age: Duration) -> Result<usize, String> {
+    let cutoff = SystemTime::now().checked_sub(max_age).map_or(0, store::timestamp);
+    let connection = store::open()?;
+    let deleted = connection
+        .execute("DELETE FROM history WHERE finished_at < ?1", params![cutoff])
+        .map_err(sql_error)?;
+    if deleted > 0 {
+        log::info!("Deleted {} archived task(s) older than {} day(s)", deleted, max_age.as_secs() / 86_400);
+    }
+    Ok(deleted)
+}
+
+/// Deletes the whole archive.
+pub fn clear() -> Result<(), String> {
+    store::open()?.execute("DELETE FROM history", []).map(|_| ()).map_err(sql_error)
+}
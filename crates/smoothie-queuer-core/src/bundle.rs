@@ -0,0 +1,87 @@
+use crate::settings::AppSettings;
+use crate::templates::{self, TemplateTask};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Version of the file layout, raised when a change would confuse older builds.
+const FORMAT: u32 = 1;
+
+/// The configuration written by `export`: the settings (including the profiles,
+/// the watch folder and its recipe, but not the MQTT password or ntfy token)
+/// and the saved queue templates by name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsBundle {
+    pub format: u32,
+    /// Version of the queuer that wrote the file, for troubleshooting.
+    #[serde(default)]
+    pub exported_by: String,
+    pub settings: AppSettings,
+    #[serde(default)]
+    pub templates: BTreeMap<String, Vec<TemplateTask>>,
+}
+
+/// Writes `settings` and every saved template to `path` as one JSON file. Returns
+/// how many templates were included. Credentials are left out, as the file is
+/// meant to be shared.
+pub fn export(settings: &AppSettings, path: &Path) -> Result<usize, String> {
+    let mut bundle = SettingsBundle {
+        format: FORMAT,
+        exported_by: env!("CARGO_PKG_VERSION").to_string(),
+        settings: settings.clone(),
+        templates: BTreeMap::new(),
+    };
+    bundle.settings.mqtt_password.clear();
+    bundle.settings.ntfy_token.clear();
+    for name in templates::list() {
+        match templates::load(&name) {
+            Ok(entries) => {
+                bundle.templates.insert(name, entries);
+            }
+            Err(e) => log::warn!("Not exporting template {:?}: {}", name, e),
+        }
+    }
+    let contents = serde_json::to_string_pretty(&bundle)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    fs::write(path, contents).map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
+    log::info!("Exported settings and {} template(s) to {:?}", bundle.templates.len(), path);
+    Ok(bundle.templates.len())
+}
+
+/// Reads a file written by `export`.
+pub fn read(path: &Path) -> Result<SettingsBundle, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    let bundle: SettingsBundle = serde_json::from_str(&contents)
+        .map_err(|e| format!("{:?} is not a Smoothie Queuer settings file: {}", path, e))?;
+    if bundle.format > FORMAT {
+        return Err(format!(
+            "{:?} was exported by a newer version of Smoothie Queuer ({}); update to import it",
+            path, bundle.exported_by
+        ));
+    }
+    Ok(bundle)
+}
+
+impl SettingsBundle {
+    /// Saves the bundled templates, replacing those with the same names, and
+    /// returns the bundled settings to use instead of `current`. A skipped
+    /// update and the credentials `export` leaves out stay those of this
+    /// machine.
+    pub fn apply(self, current: &AppSettings) -> Result<AppSettings, String> {
+        for (name, entries) in &self.templates {
+            templates::save_entries(name, entries)?;
+        }
+        let mut settings = self.settings;
+        settings.skipped_update = current.skipped_update.clone();
+        settings.mqtt_password = current.mqtt_password.clone();
+        settings.ntfy_token = current.ntfy_token.clone();
+        if let Some(folder) = &settings.watch_folder
+            && !folder.is_dir()
+        {
+            log::warn!("Imported watch folder {:?} does not exist on this machine", folder);
+        }
+        log::info!("Imported settings and {} template(s)", self.templates.len());
+        Ok(settings)
+    }
+}
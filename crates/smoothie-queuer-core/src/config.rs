@@ -1,11 +1,90 @@
+use crate::queue::{Backend, DeliveryOptions, EnvVar, RunBudget};
+use crate::simulate::Simulation;
 use std::fs;
 use std::path::{self, Path, PathBuf};
 use std::process::Command;
+use std::time::Duration;
 
 #[derive(Debug, Clone)]
 pub struct SmoothieConfig {
     pub executable_path: PathBuf,
     pub recipe_path: PathBuf, // Default recipe path found or selected by user
+    pub ffmpeg_path: PathBuf, // Bundled ffmpeg next to smoothie-rs, or `ffmpeg` from PATH
+    pub simulation: Option<Simulation>, // Demo mode: tasks are faked instead of run
+    pub blur_path: Option<PathBuf>,     // blur executable, if installed
+    pub rife_path: Option<PathBuf>,     // rife-ncnn-vulkan executable, if installed
+    pub task_timeout: Option<Duration>, // Renders running longer than this are killed and fail
+    pub comparison_preview: bool, // Render a side-by-side input/output clip after each task
+    pub comparison_screenshot: bool, // Save a side-by-side input/output frame after each task
+    pub delivery: DeliveryOptions, // Scaled/deinterlaced copy rendered after each task, if enabled
+    pub retries: u32,             // Passes retrying transient failures once the queue drains
+    pub max_parallel: usize,      // Tasks rendered at the same time; 1 renders them one by one
+    pub start_stagger: Duration,  // Least time between starting two tasks that run side by side
+    pub budget: RunBudget,        // The worker pauses the queue once it is used up
+    pub high_performance_power: bool, // Use the High Performance power plan while the worker runs
+    pub safe_input_names: bool,   // Render inputs with exotic file names from a renamed temp copy
+    pub stage_network_files: bool, // Render inputs and outputs on network drives through local copies
+    pub convert_vfr: bool,        // Convert variable frame rate inputs to CFR before rendering
+    pub spread_gpus: bool,        // Spread tasks across the GPUs instead of using the first one
+    pub verbose: bool,            // Pass --verbose to smoothie-rs, whose details end up in the task log
+    pub show_console: bool,       // Show smoothie-rs' own console instead of capturing its output
+    pub env: Vec<EnvVar>,         // Set on every backend process, before the task's own
+    pub extra_args: String,       // Appended to every backend command, before the task's own
+    pub working_dir: Option<PathBuf>, // Backends run here; None runs them from their executable's folder
+}
+
+impl SmoothieConfig {
+    /// Configuration for the demo mode, which needs no smoothie-rs installation.
+    pub fn simulated(simulation: Simulation) -> Self {
+        Self {
+            executable_path: PathBuf::from("smoothie-rs (simulated)"),
+            recipe_path: PathBuf::from("recipe.ini"),
+            ffmpeg_path: PathBuf::from("ffmpeg"),
+            simulation: Some(simulation),
+            blur_path: None,
+            rife_path: None,
+            task_timeout: None,
+            comparison_preview: false,
+            comparison_screenshot: false,
+            delivery: DeliveryOptions::default(),
+            retries: 0,
+            max_parallel: 1,
+            start_stagger: Duration::ZERO,
+            budget: RunBudget::default(),
+            high_performance_power: false,
+            safe_input_names: false,
+            stage_network_files: false,
+            convert_vfr: false,
+            spread_gpus: false,
+            verbose: false,
+            show_console: false,
+            env: Vec::new(),
+            extra_args: String::new(),
+            working_dir: None,
+        }
+    }
+
+    /// The Smoothie folder holding `bin/smoothie-rs`, `None` in demo mode.
+    pub fn smoothie_dir(&self) -> Option<&Path> {
+        self.executable_path.parent()?.parent().filter(|dir| !dir.as_os_str().is_empty())
+    }
+
+    /// Folder new recipes are written to, scanned by `find_recipe_files`.
+    pub fn recipes_dir(&self) -> Option<PathBuf> {
+        self.smoothie_dir().map(|dir| dir.join("recipes"))
+    }
+
+    /// Backends tasks can use with this setup; smoothie-rs is always available.
+    pub fn available_backends(&self) -> Vec<Backend> {
+        Backend::ALL
+            .into_iter()
+            .filter(|backend| match backend {
+                Backend::Smoothie => true,
+                Backend::Blur => self.blur_path.is_some(),
+                Backend::Rife => self.rife_path.is_some(),
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -40,9 +119,33 @@ pub fn find_smoothie_config_auto() -> Result<SmoothieConfig, ConfigError> {
     let recipe_path = find_default_recipe(&executable_path);
     log::info!("Using default recipe path: {:?}", recipe_path);
 
+    let ffmpeg_path = find_ffmpeg(&executable_path);
+
     Ok(SmoothieConfig {
         executable_path,
         recipe_path,
+        ffmpeg_path,
+        simulation: None,
+        blur_path: find_blur(),
+        rife_path: find_rife(),
+        task_timeout: None,
+        comparison_preview: false,
+        comparison_screenshot: false,
+        delivery: DeliveryOptions::default(),
+        retries: 0,
+        max_parallel: 1,
+        start_stagger: Duration::ZERO,
+        budget: RunBudget::default(),
+        high_performance_power: false,
+        safe_input_names: false,
+        stage_network_files: false,
+        convert_vfr: false,
+        spread_gpus: false,
+        verbose: false,
+        show_console: false,
+        env: Vec::new(),
+        extra_args: String::new(),
+        working_dir: None,
     })
 }
 
@@ -76,9 +179,33 @@ pub fn find_smoothie_config_in_dir(base_dir: &Path) -> Result<SmoothieConfig, Co
     };
     log::info!("Using recipe path: {:?}", final_recipe_path);
 
+    let ffmpeg_path = find_ffmpeg(&exe_path_in_dir);
+
     Ok(SmoothieConfig {
         executable_path: exe_path_in_dir,
         recipe_path: final_recipe_path,
+        ffmpeg_path,
+        simulation: None,
+        blur_path: find_blur(),
+        rife_path: find_rife(),
+        task_timeout: None,
+        comparison_preview: false,
+        comparison_screenshot: false,
+        delivery: DeliveryOptions::default(),
+        retries: 0,
+        max_parallel: 1,
+        start_stagger: Duration::ZERO,
+        budget: RunBudget::default(),
+        high_performance_power: false,
+        safe_input_names: false,
+        stage_network_files: false,
+        convert_vfr: false,
+        spread_gpus: false,
+        verbose: false,
+        show_console: false,
+        env: Vec::new(),
+        extra_args: String::new(),
+        working_dir: None,
     })
 }
 
@@ -95,18 +222,15 @@ pub fn find_recipe_files(base_dir: &Path) -> Vec<PathBuf> {
         for entry in entries.flatten() {
             // Use flatten to ignore read errors for individual entries
             let path = entry.path();
-            if path.is_file() {
-                if let Some(ext) = path::Path::extension(&path) {
-                    if ext == "ini" {
-                        if let Some(name) = path.file_name() {
-                            if name != "encoding_presets.ini" {
-                                log::debug!("Found recipe: {:?}", path);
-                                recipes.push(path);
-                            } else {
-                                log::debug!("Ignoring excluded recipe file: {:?}", path);
-                            }
-                        }
-                    }
+            if path.is_file()
+                && path::Path::extension(&path).is_some_and(|ext| ext == "ini")
+                && let Some(name) = path.file_name()
+            {
+                if name != "encoding_presets.ini" {
+                    log::debug!("Found recipe: {:?}", path);
+                    recipes.push(path);
+                } else {
+                    log::debug!("Ignoring excluded recipe file: {:?}", path);
                 }
             }
         }
@@ -124,21 +248,18 @@ pub fn find_recipe_files(base_dir: &Path) -> Vec<PathBuf> {
         if let Ok(entries) = fs::read_dir(&recipes_subdir) {
             for entry in entries.flatten() {
                 let path = entry.path();
-                if path.is_file() {
-                    if let Some(ext) = path::Path::extension(&path) {
-                        if ext == "ini" {
-                            if let Some(name) = path.file_name() {
-                                if name != "encoding_presets.ini" {
-                                    log::debug!("Found recipe: {:?}", path);
-                                    // Avoid duplicates if somehow present in both
-                                    if !recipes.contains(&path) {
-                                        recipes.push(path);
-                                    }
-                                } else {
-                                    log::debug!("Ignoring excluded recipe file: {:?}", path);
-                                }
-                            }
+                if path.is_file()
+                    && path::Path::extension(&path).is_some_and(|ext| ext == "ini")
+                    && let Some(name) = path.file_name()
+                {
+                    if name != "encoding_presets.ini" {
+                        log::debug!("Found recipe: {:?}", path);
+                        // Avoid duplicates if somehow present in both
+                        if !recipes.contains(&path) {
+                            recipes.push(path);
                         }
+                    } else {
+                        log::debug!("Ignoring excluded recipe file: {:?}", path);
                     }
                 }
             }
@@ -249,6 +370,68 @@ fn find_executable_auto() -> Option<PathBuf> {
     None
 }
 
+/// Finds ffmpeg, preferring the copy Smoothie ships in its `bin` folder.
+/// Falls back to the bare command name so it is resolved through PATH.
+pub fn find_ffmpeg(executable_path: &Path) -> PathBuf {
+    let ffmpeg_name = if cfg!(windows) { "ffmpeg.exe" } else { "ffmpeg" };
+    if let Some(exe_dir) = executable_path.parent() {
+        let bundled = exe_dir.join(ffmpeg_name);
+        if bundled.is_file() {
+            log::debug!("Found bundled ffmpeg: {:?}", bundled);
+            return bundled;
+        }
+    }
+    log::debug!("Bundled ffmpeg not found next to {:?}, using PATH", executable_path);
+    PathBuf::from("ffmpeg")
+}
+
+/// Finds `name` on PATH, then as `<base>/<subdir>/<name>.exe` under the usual
+/// Windows install roots for each of `install_subdirs`.
+fn find_program(name: &str, install_subdirs: &[&str]) -> Option<PathBuf> {
+    let command_name = if cfg!(windows) { "where" } else { "which" };
+    let from_path = Command::new(command_name)
+        .arg(name)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            stdout.lines().next().map(|line| PathBuf::from(line.trim()))
+        })
+        .filter(|path| path.is_file());
+
+    let program_path = from_path.or_else(|| {
+        ["LOCALAPPDATA", "ProgramFiles", "ProgramFiles(x86)"]
+            .iter()
+            .filter_map(std::env::var_os)
+            .flat_map(|base| {
+                let base = PathBuf::from(base);
+                install_subdirs
+                    .iter()
+                    .map(move |subdir| base.join(subdir).join(format!("{}.exe", name)))
+            })
+            .find(|path| path.is_file())
+    });
+
+    match &program_path {
+        Some(path) => log::info!("Found {} at {:?}", name, path),
+        None => log::debug!("{} not found", name),
+    }
+    program_path
+}
+
+/// Finds blur (the program smoothie-rs grew out of), which many users have
+/// installed alongside Smoothie. Checks PATH, then its default install folders.
+pub fn find_blur() -> Option<PathBuf> {
+    find_program("blur", &["Programs/blur", "blur"])
+}
+
+/// Finds rife-ncnn-vulkan (the RIFE build Flowframes uses) on PATH or in an
+/// extracted release folder under the install roots.
+pub fn find_rife() -> Option<PathBuf> {
+    find_program("rife-ncnn-vulkan", &["rife-ncnn-vulkan", "Programs/rife-ncnn-vulkan"])
+}
+
 /// Finds the default recipe path based on standard locations relative to the executable.
 fn find_default_recipe(executable_path: &Path) -> PathBuf {
     // 1. Check relative `./Smoothie/recipe.ini` (as per user feedback)
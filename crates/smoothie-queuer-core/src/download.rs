@@ -0,0 +1,80 @@
+use crate::queue::{self, StopFlags, UrlSource};
+use crate::settings;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+
+/// Error message returned when a download is aborted by a force stop.
+pub const CANCELLED: &str = "Download cancelled by user";
+
+/// Staging folder yt-dlp downloads into.
+pub fn downloads_dir() -> PathBuf {
+    settings::data_dir().join("downloads")
+}
+
+/// Whether pasted or dropped text should be treated as a video URL.
+pub fn is_url(text: &str) -> bool {
+    let text = text.trim();
+    (text.starts_with("http://") || text.starts_with("https://")) && !text.contains(char::is_whitespace)
+}
+
+/// Downloads `source` into `downloads_dir` with yt-dlp, killing it as soon as a
+/// force stop is requested through `stop_flags`. Returns the downloaded file and yt-dlp's messages
+/// for the task log.
+pub async fn download(
+    source: &UrlSource,
+    stop_flags: &StopFlags,
+) -> Result<(PathBuf, Vec<String>), String> {
+    let dir = downloads_dir();
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create downloads folder {:?}: {}", dir, e))?;
+
+    let mut command = Command::new(&source.ytdlp_path);
+    command.args(["--no-playlist", "--windows-filenames", "--merge-output-format", "mp4"]);
+    command.arg("-o").arg(dir.join("%(title).150B [%(id)s].%(ext)s"));
+    // Prints only the final path once merging/moving is done (implies --quiet)
+    command.args(["--print", "after_move:filepath"]);
+    command.arg(&source.url);
+    command.stdout(Stdio::piped()).stderr(Stdio::piped());
+
+    #[cfg(target_os = "windows")]
+    {
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+
+    log::debug!("Running yt-dlp: {:?}", command);
+    let mut command = tokio::process::Command::from(command);
+    command.kill_on_drop(true);
+    let child = command
+        .spawn()
+        .map_err(|e| format!("Failed to run yt-dlp ({}): {}", source.ytdlp_path, e))?;
+
+    // Dropping the child when cancelled kills yt-dlp
+    let output = tokio::select! {
+        output = child.wait_with_output() => {
+            output.map_err(|e| format!("yt-dlp failed while waiting: {}", e))?
+        }
+        () = stop_flags.force_stopped() => return Err(CANCELLED.to_string()),
+    };
+    let status = output.status;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let messages: Vec<String> = stderr.lines().map(str::to_string).collect();
+
+    if !status.success() {
+        let last_line = stderr.lines().last().unwrap_or("").trim();
+        return Err(format!("yt-dlp failed ({}): {}", status, last_line));
+    }
+
+    let path = stdout
+        .lines()
+        .map(str::trim)
+        .rfind(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .ok_or("yt-dlp did not report the downloaded file")?;
+    if !queue::is_ingestible_video(&path) {
+        return Err(format!("Downloaded file {:?} is not a supported video", path));
+    }
+    Ok((path, messages))
+}
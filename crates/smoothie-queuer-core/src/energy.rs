@@ -0,0 +1,86 @@
+use crate::gpu;
+use std::time::{Duration, Instant};
+
+/// RAPL energy counter of the CPU package in microjoules (Linux).
+#[cfg(target_os = "linux")]
+const RAPL_ENERGY: &str = "/sys/class/powercap/intel-rapl:0/energy_uj";
+
+const MICROJOULES_PER_WATT_HOUR: f64 = 3_600_000_000.0;
+
+/// Measures the energy the machine uses where it can be read: the CPU package
+/// through RAPL on Linux and NVIDIA GPUs through `nvidia-smi`. The rest of the
+/// system is not included, so estimates err on the low side.
+pub struct PowerMeter {
+    rapl: Option<u64>, // Counter value at the last reading
+    gpus: bool,
+    read_at: Instant,
+}
+
+impl PowerMeter {
+    /// `None` when neither source can be read on this machine.
+    pub fn new() -> Option<Self> {
+        let rapl = read_rapl();
+        let gpus = gpu_watts().is_some();
+        if rapl.is_none() && !gpus {
+            log::info!("No power readings available; energy use is not estimated");
+            return None;
+        }
+        log::info!(
+            "Estimating energy use from {}",
+            match (rapl.is_some(), gpus) {
+                (true, true) => "CPU package and GPU power",
+                (true, false) => "CPU package power",
+                _ => "GPU power",
+            }
+        );
+        Some(Self {
+            rapl,
+            gpus,
+            read_at: Instant::now(),
+        })
+    }
+
+    /// Time since the previous reading.
+    pub fn since_last_reading(&self) -> Duration {
+        self.read_at.elapsed()
+    }
+
+    /// Watt-hours used since the previous reading. The CPU counter is exact; GPU
+    /// power is sampled now and assumed constant since the previous reading.
+    pub fn read(&mut self) -> f64 {
+        let hours = self.read_at.elapsed().as_secs_f64() / 3600.0;
+        self.read_at = Instant::now();
+
+        let mut watt_hours = 0.0;
+        if let Some(previous) = self.rapl {
+            let current = read_rapl().unwrap_or(previous);
+            // The counter wraps around; that interval is skipped
+            if current >= previous {
+                watt_hours += (current - previous) as f64 / MICROJOULES_PER_WATT_HOUR;
+            }
+            self.rapl = Some(current);
+        }
+        if self.gpus {
+            watt_hours += gpu_watts().unwrap_or(0.0) * hours;
+        }
+        watt_hours
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn read_rapl() -> Option<u64> {
+    std::fs::read_to_string(RAPL_ENERGY).ok()?.trim().parse().ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_rapl() -> Option<u64> {
+    None
+}
+
+/// Combined power draw of all NVIDIA GPUs.
+fn gpu_watts() -> Option<f64> {
+    let rows = gpu::query("power.draw")?;
+    // "[N/A]" on GPUs that do not report it
+    let watts: Vec<f64> = rows.iter().filter_map(|row| row.first()?.parse().ok()).collect();
+    (!watts.is_empty()).then(|| watts.iter().sum())
+}
@@ -0,0 +1,115 @@
+use crate::settings::AppSettings;
+use std::time::SystemTime;
+
+/// Events kept per session; the oldest are dropped beyond this.
+const MAX_EVENTS: usize = 10_000;
+
+/// What happened in a `QueueEvent`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Added,
+    Edited,
+    Removed,
+    Started,
+    Completed,
+    Failed,
+    Cancelled,
+    Requeued,
+    QueueStarted,
+    QueueStopped,
+    QueueFinished,
+    SettingsChanged,
+}
+
+impl EventKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            EventKind::Added => "Added",
+            EventKind::Edited => "Edited",
+            EventKind::Removed => "Removed",
+            EventKind::Started => "Started",
+            EventKind::Completed => "Completed",
+            EventKind::Failed => "Failed",
+            EventKind::Cancelled => "Cancelled",
+            EventKind::Requeued => "Requeued",
+            EventKind::QueueStarted => "Queue started",
+            EventKind::QueueStopped => "Queue stopped",
+            EventKind::QueueFinished => "Queue finished",
+            EventKind::SettingsChanged => "Settings changed",
+        }
+    }
+}
+
+/// One entry of the session's event log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueueEvent {
+    pub time: SystemTime,
+    pub kind: EventKind,
+    pub task_id: Option<usize>, // None for events of the whole queue
+    pub detail: String,         // Input file name, error message, changed settings...
+}
+
+/// What happened to the queue since the app started, oldest first, for
+/// reconstructing an unattended run. Not saved with the queue.
+#[derive(Debug, Clone, Default)]
+pub struct EventLog {
+    events: Vec<QueueEvent>,
+}
+
+impl EventLog {
+    pub fn record(&mut self, kind: EventKind, task_id: Option<usize>, detail: impl Into<String>) {
+        if self.events.len() >= MAX_EVENTS {
+            self.events.remove(0);
+        }
+        self.events.push(QueueEvent {
+            time: SystemTime::now(),
+            kind,
+            task_id,
+            detail: detail.into(),
+        });
+    }
+
+    pub fn events(&self) -> &[QueueEvent] {
+        &self.events
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    /// The log as tab-separated lines of local time, event, task and detail,
+    /// for a text editor or spreadsheet.
+    pub fn export(&self) -> String {
+        let mut text = String::from("time\tevent\ttask\tdetail\n");
+        for event in &self.events {
+            let task = event.task_id.map(|id| id.to_string()).unwrap_or_default();
+            // Tabs and line breaks (multi-line errors) would break the columns
+            let detail = event.detail.replace(['\t', '\r', '\n'], " ");
+            text.push_str(&format!("{}\t{}\t{}\t{}\n", format_time(event.time), event.kind.label(), task, detail));
+        }
+        text
+    }
+}
+
+/// Local date and time of `time`, e.g. `2024-05-01 14:02:37`.
+pub fn format_time(time: SystemTime) -> String {
+    jiff::Timestamp::try_from(time)
+        .map(|timestamp| {
+            timestamp.to_zoned(jiff::tz::TimeZone::system()).strftime("%Y-%m-%d %H:%M:%S").to_string()
+        })
+        .unwrap_or_else(|_| "?".to_string())
+}
+
+/// Names of the settings that differ between `old` and `new`, as they appear
+/// in `settings.json`.
+pub fn changed_settings(old: &AppSettings, new: &AppSettings) -> Vec<String> {
+    let (Ok(serde_json::Value::Object(old)), Ok(serde_json::Value::Object(new))) =
+        (serde_json::to_value(old), serde_json::to_value(new))
+    else {
+        return Vec::new();
+    };
+    new.iter()
+        .filter(|(name, value)| old.get(*name) != Some(value))
+        .map(|(name, _)| name.clone())
+        .collect()
+}
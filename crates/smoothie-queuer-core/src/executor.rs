@@ -0,0 +1,581 @@
+use crate::exit_codes;
+use crate::ffmpeg;
+use crate::paths;
+use crate::plugins;
+use crate::process;
+use crate::queue::{EnvVar, QueueManager, StopFlags, VideoTask};
+use crate::simulate::{self, Simulation};
+use crate::worker::{StagingDir, UpdateMessage};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+use std::sync::{Arc, Mutex, OnceLock, mpsc::Sender};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::mpsc;
+
+/// Error message returned when a run is aborted by a force stop.
+pub const CANCELLED: &str = "Render cancelled by user";
+
+/// Number of trailing output lines kept for `Executor::interpret_exit`.
+const OUTPUT_TAIL: usize = 20;
+
+/// What an executor needs from the worker while it runs a task.
+pub struct RunContext<'a> {
+    pub queue_manager: &'a Arc<Mutex<QueueManager>>,
+    pub stop_flags: &'a StopFlags, // Force stop cancels the run
+    pub updates: &'a Sender<UpdateMessage>,
+    pub gpu: Option<u32>, // GPU picked by the `gpu::GpuScheduler`, if any
+    pub env: &'a [EnvVar], // From the settings; the task's own are set after them
+    pub extra_args: &'a str, // From the settings; appended before the task's own
+    pub working_dir: Option<&'a Path>, // None runs the command from its executable's folder
+}
+
+impl RunContext<'_> {
+    pub fn log(&self, task_id: usize, line: String) {
+        self.queue_manager.lock()
+            .expect("Failed to lock queue manager")
+            .append_log(task_id, line);
+    }
+
+    /// Stores the task's progress and tells the frontend it changed.
+    pub fn set_progress(&self, task_id: usize, progress: f32) {
+        self.queue_manager.lock()
+            .expect("Failed to lock queue manager")
+            .set_progress(task_id, progress);
+        // The frontend may have gone away; progress is still kept on the task
+        let _ = self.updates.send(UpdateMessage::TaskProgress(task_id, progress));
+    }
+}
+
+/// A rendering backend. The worker handles everything around the render
+/// (downloads, pre-processing, locating and remuxing the output, post commands);
+/// an executor only turns one prepared input into output files in `output_dir`.
+///
+/// `execute` runs on the worker's tokio runtime; dropping its future (on a
+/// timeout, for example) kills whatever it spawned.
+#[async_trait(?Send)]
+pub trait Executor: Send + Sync {
+    /// Short name used in logs and error messages.
+    fn name(&self) -> &str;
+
+    /// Checks the task can be run before anything is spawned.
+    fn validate(&self, _task: &VideoTask) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Builds the command rendering `input` for `task` into `output_dir`.
+    fn build_command(&self, task: &VideoTask, input: &Path, output_dir: &Path) -> Command;
+
+    /// Extracts progress (`0.0..=1.0`) from one line of the tool's output.
+    /// Lines that report progress are not copied into the task log.
+    fn parse_progress(&self, _line: &str) -> Option<f32> {
+        None
+    }
+
+    /// Whether the tool writes to a console window of its own (on Windows; the
+    /// terminal the app was started from elsewhere) instead of the task log.
+    /// Its progress is not tracked then.
+    fn shows_console(&self) -> bool {
+        false
+    }
+
+    /// Whether `build_command` sets a working directory the tool depends on,
+    /// which `RunContext::working_dir` must not replace.
+    fn fixed_working_dir(&self) -> bool {
+        false
+    }
+
+    /// Turns the exit status into the task result; `output_tail` holds the last
+    /// lines the tool printed. Known crash codes and signals are explained, see
+    /// `exit_codes::describe`.
+    fn interpret_exit(
+        &self,
+        task: &VideoTask,
+        status: ExitStatus,
+        output_tail: &[String],
+    ) -> Result<(), String> {
+        if status.success() {
+            return Ok(());
+        }
+        let mut err_msg = match exit_codes::describe(status) {
+            Some(meaning) => format!("Task {} failed: {} {} [{}]", task.id, self.name(), meaning, status),
+            None => format!("Task {} failed with status: {}", task.id, status),
+        };
+        if let Some(last_line) = output_tail.last() {
+            err_msg.push_str(&format!(" ({})", last_line.trim()));
+        }
+        Err(err_msg)
+    }
+
+    /// Runs the task. The default spawns `build_command`, streams its output
+    /// into the task log and progress, and kills it when cancelled.
+    async fn execute(
+        &self,
+        task: &VideoTask,
+        input: &Path,
+        output_dir: &Path,
+        context: &RunContext<'_>,
+    ) -> Result<(), String> {
+        let command = self.build_command(task, input, output_dir);
+        run_command(self, task, command, context).await
+    }
+}
+
+/// `command` with a relative program path (`bin/smoothie-rs`) made absolute,
+/// so it still resolves once `run_command` changes the working directory.
+/// Bare names are left to the `PATH` lookup.
+fn with_absolute_program(command: Command) -> Command {
+    let program = Path::new(command.get_program());
+    if program.is_absolute() || program.parent().is_none_or(|dir| dir.as_os_str().is_empty()) {
+        return command;
+    }
+    let mut absolute = Command::new(paths::absolute(program));
+    absolute.args(command.get_args());
+    for (name, value) in command.get_envs() {
+        match value {
+            Some(value) => absolute.env(name, value),
+            None => absolute.env_remove(name),
+        };
+    }
+    if let Some(dir) = command.get_current_dir() {
+        absolute.current_dir(dir);
+    }
+    absolute
+}
+
+/// Spawns `command` with its output captured line by line (`\r` counts as a line
+/// break so progress counters are seen as they update). The settings' extra
+/// arguments are appended, then the task's `extra_args`. The process is
+/// suspended and resumed along with `StopFlags::is_suspended`. CUDA only sees
+/// the GPU assigned to the task, unless the settings' or task's environment
+/// variables say otherwise. The command runs in `RunContext::working_dir`, by
+/// default its executable's folder, unless the executor has a
+/// `fixed_working_dir`. When the executor `shows_console`, the output is left to
+/// the console instead.
+pub async fn run_command<E: Executor + ?Sized>(
+    executor: &E,
+    task: &VideoTask,
+    command: Command,
+    context: &RunContext<'_>,
+) -> Result<(), String> {
+    let mut command = with_absolute_program(command);
+    command.args(context.extra_args.split_whitespace());
+    command.args(task.extra_args.split_whitespace());
+    let shows_console = executor.shows_console();
+    command.stdin(Stdio::null());
+    if !shows_console {
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+    #[cfg(target_os = "windows")]
+    {
+        if shows_console {
+            command.creation_flags(0x00000010); // CREATE_NEW_CONSOLE
+        } else {
+            command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+        }
+    }
+    if let Some(gpu) = context.gpu {
+        command.env("CUDA_VISIBLE_DEVICES", gpu.to_string());
+    }
+    for var in context.env.iter().chain(&task.env) {
+        command.env(&var.name, &var.value);
+    }
+    // Executables found on PATH keep the app's working directory
+    let working_dir = if executor.fixed_working_dir() {
+        None
+    } else {
+        context.working_dir.map(Path::to_path_buf).or_else(|| {
+            Path::new(command.get_program()).parent().filter(|dir| !dir.as_os_str().is_empty()).map(Path::to_path_buf)
+        })
+    };
+    if let Some(dir) = working_dir {
+        if !dir.is_dir() {
+            return Err(format!("Task {} failed: working directory {} not found", task.id, dir.display()));
+        }
+        command.current_dir(dir);
+    }
+    // Its own process group, so suspending reaches the processes it starts
+    #[cfg(unix)]
+    command.process_group(0);
+
+    log::info!("Task {}: running {} {:?}", task.id, executor.name(), command);
+    context.log(task.id, format!("> {:?}", command));
+    if shows_console {
+        context.log(task.id, format!("{} output is shown in its console window, not here", executor.name()));
+    }
+    let mut command = tokio::process::Command::from(command);
+    command.kill_on_drop(true);
+    let mut child = command.spawn().map_err(|e| {
+        format!(
+            "Task {} failed to spawn {}: {}. Is it installed?",
+            task.id,
+            executor.name(),
+            e
+        )
+    })?;
+
+    let (line_tx, mut line_rx) = mpsc::unbounded_channel();
+    let mut readers = Vec::new();
+    if let Some(stdout) = child.stdout.take() {
+        readers.push(tokio::spawn(read_lines(stdout, line_tx.clone())));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        readers.push(tokio::spawn(read_lines(stderr, line_tx.clone())));
+    }
+    drop(line_tx);
+
+    let mut tail: Vec<String> = Vec::new();
+    let mut handle_line = |line: String| {
+        if let Some(progress) = executor.parse_progress(&line) {
+            context.set_progress(task.id, progress.clamp(0.0, 1.0));
+            return;
+        }
+        context.log(task.id, line.clone());
+        tail.push(line);
+        if tail.len() > OUTPUT_TAIL {
+            tail.remove(0);
+        }
+    };
+
+    let mut suspended = false;
+    let status = loop {
+        tokio::select! {
+            status = child.wait() => {
+                break status.map_err(|e| format!("Task {} failed while waiting: {}", task.id, e))?;
+            }
+            Some(line) = line_rx.recv() => handle_line(line),
+            () = context.stop_flags.suspend_changed(suspended) => {
+                suspended = !suspended;
+                let Some(pid) = child.id() else { continue };
+                let result = if suspended { process::suspend(pid) } else { process::resume(pid) };
+                match result {
+                    Ok(()) if suspended => context.log(task.id, "Suspended".to_string()),
+                    Ok(()) => context.log(task.id, "Resumed".to_string()),
+                    Err(e) => log::error!("Task {}: {}", task.id, e),
+                }
+            }
+            () = context.stop_flags.force_stopped() => {
+                // Take down the processes the backend started as well
+                if let Some(pid) = child.id()
+                    && let Err(e) = process::kill_tree(pid)
+                {
+                    log::error!("Task {}: {}", task.id, e);
+                }
+                if let Err(e) = child.kill().await {
+                    log::error!("Failed to kill {}: {}", executor.name(), e);
+                }
+                return Err(CANCELLED.to_string());
+            }
+        }
+    };
+
+    // Collect whatever was printed right before exiting
+    for reader in readers {
+        let _ = reader.await;
+    }
+    while let Ok(line) = line_rx.try_recv() {
+        handle_line(line);
+    }
+    executor.interpret_exit(task, status, &tail).map_err(|err_msg| match plugins::diagnose(&tail) {
+        Some(missing) => format!("{}. {}", err_msg, missing),
+        None => err_msg,
+    })
+}
+
+async fn read_lines(mut pipe: impl AsyncRead + Unpin, line_tx: mpsc::UnboundedSender<String>) {
+    let mut buffer = [0u8; 4096];
+    let mut current = Vec::new();
+    loop {
+        let read = match pipe.read(&mut buffer).await {
+            Ok(0) | Err(_) => break,
+            Ok(read) => read,
+        };
+        for &byte in &buffer[..read] {
+            if byte == b'\n' || byte == b'\r' {
+                if !current.is_empty() {
+                    let line = String::from_utf8_lossy(&current).trim_end().to_string();
+                    current.clear();
+                    if line_tx.send(line).is_err() {
+                        return;
+                    }
+                }
+            } else {
+                current.push(byte);
+            }
+        }
+    }
+    if !current.is_empty() {
+        let _ = line_tx.send(String::from_utf8_lossy(&current).trim_end().to_string());
+    }
+}
+
+/// Whether the smoothie-rs at `executable` takes `--override`, going by its
+/// `--help`. Asked once per executable.
+pub fn supports_override(executable: &Path) -> bool {
+    static SUPPORTED: OnceLock<Mutex<HashMap<PathBuf, bool>>> = OnceLock::new();
+    let mut supported = SUPPORTED.get_or_init(Default::default).lock().expect("Failed to lock override support");
+    *supported.entry(executable.to_path_buf()).or_insert_with(|| {
+        let mut command = Command::new(executable);
+        command.arg("--help");
+        #[cfg(target_os = "windows")]
+        {
+            command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+        }
+        let found = command.output().is_ok_and(|output| {
+            [output.stdout, output.stderr]
+                .iter()
+                .any(|text| String::from_utf8_lossy(text).contains("--override"))
+        });
+        log::info!("{:?} {} --override", executable, if found { "supports" } else { "does not support" });
+        found
+    })
+}
+
+/// Renders with smoothie-rs: `smoothie-rs --recipe <ini> --input <file> --outdir <dir>`,
+/// plus `--override "section;key=value|..."` for the task's recipe overrides.
+pub struct SmoothieExecutor {
+    pub executable_path: PathBuf,
+    pub verbose: bool,      // Adds `--verbose`
+    pub show_console: bool, // See `Executor::shows_console`
+}
+
+#[async_trait(?Send)]
+impl Executor for SmoothieExecutor {
+    fn name(&self) -> &str {
+        "smoothie-rs"
+    }
+
+    fn shows_console(&self) -> bool {
+        self.show_console
+    }
+
+    fn validate(&self, task: &VideoTask) -> Result<(), String> {
+        if task.recipe_path.exists() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Task {} failed: Recipe file not found at path: {:?}",
+                task.id, task.recipe_path
+            ))
+        }
+    }
+
+    fn build_command(&self, task: &VideoTask, input: &Path, output_dir: &Path) -> Command {
+        let mut command = Command::new(&self.executable_path);
+        command.arg("--recipe").arg(paths::for_command(&task.recipe_path));
+        command.arg("--input").arg(paths::for_command(input));
+        command.arg("--outdir").arg(paths::for_command(output_dir));
+        if self.verbose {
+            command.arg("--verbose");
+        }
+        if !task.recipe_overrides.is_empty() {
+            let overrides: Vec<String> = task.recipe_overrides.iter().map(ToString::to_string).collect();
+            command.arg("--override").arg(overrides.join("|"));
+        }
+        command
+    }
+
+    /// vspipe's progress counter: `Frame: 1234/5678 (56.78 fps)`.
+    fn parse_progress(&self, line: &str) -> Option<f32> {
+        let counter = line.trim().strip_prefix("Frame:")?.split_whitespace().next()?;
+        let (done, total) = counter.split_once('/')?;
+        let total: f32 = total.parse().ok().filter(|total| *total > 0.0)?;
+        Some(done.parse::<f32>().ok()? / total)
+    }
+}
+
+/// Renders with blur: `blur -i <file> -o <outdir>/<name> - blur.mp4 [-c <config.cfg>]`.
+/// blur reads a `.cfg` config rather than a smoothie recipe, so the task's recipe is
+/// only passed when it is one; otherwise blur falls back to its own default config.
+pub struct BlurExecutor {
+    pub executable_path: PathBuf,
+}
+
+impl BlurExecutor {
+    fn config_path(task: &VideoTask) -> Option<&Path> {
+        let is_cfg = task.recipe_path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("cfg"));
+        is_cfg.then_some(task.recipe_path.as_path())
+    }
+}
+
+#[async_trait(?Send)]
+impl Executor for BlurExecutor {
+    fn name(&self) -> &str {
+        "blur"
+    }
+
+    fn validate(&self, task: &VideoTask) -> Result<(), String> {
+        match Self::config_path(task) {
+            Some(config) if !config.exists() => Err(format!(
+                "Task {} failed: blur config not found at path: {:?}",
+                task.id, config
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn build_command(&self, task: &VideoTask, input: &Path, output_dir: &Path) -> Command {
+        let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+        let mut command = Command::new(&self.executable_path);
+        command.arg("-i").arg(paths::for_command(input));
+        command.arg("-o").arg(paths::for_command(&output_dir.join(format!("{} - blur.mp4", stem))));
+        if let Some(config) = Self::config_path(task) {
+            command.arg("-c").arg(paths::for_command(config));
+        }
+        command
+    }
+}
+
+/// Fakes renders for the demo mode (see `simulate`).
+pub struct SimulatedExecutor {
+    pub simulation: Simulation,
+}
+
+#[async_trait(?Send)]
+impl Executor for SimulatedExecutor {
+    fn name(&self) -> &str {
+        "simulation"
+    }
+
+    /// The smoothie-rs call a real render would make. Only used for display (e.g.
+    /// exported scripts); `execute` never spawns it.
+    fn build_command(&self, task: &VideoTask, input: &Path, output_dir: &Path) -> Command {
+        let smoothie = SmoothieExecutor {
+            executable_path: PathBuf::from("smoothie-rs"),
+            verbose: false,
+            show_console: false,
+        };
+        smoothie.build_command(task, input, output_dir)
+    }
+
+    async fn execute(
+        &self,
+        task: &VideoTask,
+        _input: &Path,
+        _output_dir: &Path,
+        context: &RunContext<'_>,
+    ) -> Result<(), String> {
+        simulate::run(task, &self.simulation, context).await
+    }
+}
+
+/// Pure interpolation with rife-ncnn-vulkan. RIFE works on image sequences, so
+/// `execute` extracts the input's frames with ffmpeg, interpolates them and
+/// encodes the result (with the input's audio) to `<name> ~ rife <factor>x.mp4`.
+pub struct RifeExecutor {
+    pub executable_path: PathBuf,
+    pub ffmpeg_path: PathBuf,
+}
+
+#[async_trait(?Send)]
+impl Executor for RifeExecutor {
+    fn name(&self) -> &str {
+        "rife-ncnn-vulkan"
+    }
+
+    fn fixed_working_dir(&self) -> bool {
+        true
+    }
+
+    fn validate(&self, task: &VideoTask) -> Result<(), String> {
+        if task.rife.factor < 2 {
+            return Err(format!(
+                "Task {} failed: RIFE factor must be at least 2, got {}",
+                task.id, task.rife.factor
+            ));
+        }
+        if task.rife.model.trim().is_empty() {
+            return Err(format!("Task {} failed: no RIFE model selected", task.id));
+        }
+        Ok(())
+    }
+
+    /// The interpolation step only: `input` and `output_dir` are frame folders.
+    /// `execute` adds the target frame count (`-n`) for factors other than 2.
+    fn build_command(&self, task: &VideoTask, input: &Path, output_dir: &Path) -> Command {
+        let mut command = Command::new(&self.executable_path);
+        command.arg("-i").arg(input);
+        command.arg("-o").arg(output_dir);
+        command.arg("-m").arg(task.rife.model.trim());
+        // Model folders are resolved relative to the working directory
+        if let Some(dir) = self.executable_path.parent().filter(|dir| dir.is_dir()) {
+            command.current_dir(dir);
+        }
+        command
+    }
+
+    async fn execute(
+        &self,
+        task: &VideoTask,
+        input: &Path,
+        output_dir: &Path,
+        context: &RunContext<'_>,
+    ) -> Result<(), String> {
+        let ffmpeg_error = |e: String| {
+            if e == ffmpeg::CANCELLED {
+                CANCELLED.to_string()
+            } else {
+                format!("Task {} failed: {}", task.id, e)
+            }
+        };
+
+        let (rate_num, rate_den) = ffmpeg::probe_frame_rate(&self.ffmpeg_path, input)
+            .await
+            .map_err(|e| format!("Task {} failed: {}", task.id, e))?;
+
+        let staging = StagingDir::create(task.id, "rife")?;
+        let frames_in = staging.path().join("in");
+        let frames_out = staging.path().join("out");
+        for dir in [&frames_in, &frames_out] {
+            fs::create_dir_all(dir).map_err(|e| {
+                format!("Task {} failed to create frame folder {:?}: {}", task.id, dir, e)
+            })?;
+        }
+
+        context.log(task.id, "Extracting frames".to_string());
+        ffmpeg::extract_frames(&self.ffmpeg_path, input, &frames_in, context.stop_flags)
+            .await
+            .map_err(ffmpeg_error)?;
+        let frame_count = fs::read_dir(&frames_in)
+            .map_err(|e| format!("Task {} failed to read {:?}: {}", task.id, frames_in, e))?
+            .count();
+        if frame_count == 0 {
+            return Err(format!("Task {} failed: ffmpeg extracted no frames", task.id));
+        }
+        context.set_progress(task.id, 0.1);
+
+        let mut command = self.build_command(task, &frames_in, &frames_out);
+        command.arg("-n").arg((frame_count * task.rife.factor as usize).to_string());
+        // Vulkan numbers the GPUs like nvidia-smi on NVIDIA-only machines
+        if let Some(gpu) = context.gpu {
+            command.arg("-g").arg(gpu.to_string());
+        }
+        run_command(self, task, command, context).await?;
+        context.set_progress(task.id, 0.9);
+
+        context.log(task.id, "Encoding interpolated frames".to_string());
+        let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+        let output = output_dir.join(format!("{} ~ rife {}x.mp4", stem, task.rife.factor));
+        let frame_rate = (rate_num * task.rife.factor as u64, rate_den);
+        ffmpeg::encode_frames(
+            &self.ffmpeg_path,
+            &frames_out,
+            frame_rate,
+            input,
+            &output,
+            context.stop_flags,
+        )
+        .await
+        .map_err(ffmpeg_error)?;
+        context.set_progress(task.id, 1.0);
+        Ok(())
+    }
+}
@@ -0,0 +1,45 @@
+use std::process::ExitStatus;
+
+/// Exit codes a backend ends with when it crashes or cannot start, and what they
+/// mean. On Windows these are NTSTATUS codes, shown as e.g. `0xc0000409`.
+const EXIT_CODES: &[(u32, &str)] = &[
+    (0xC0000005, "crashed (access violation), often a faulty VapourSynth plugin"),
+    (0xC0000409, "crashed (stack buffer overrun), often a faulty VapourSynth plugin or an outdated GPU driver"),
+    (0xC00000FD, "crashed (stack overflow)"),
+    (0xC000001D, "crashed on an instruction this CPU lacks; a plugin may need AVX2"),
+    (0xC0000094, "crashed (division by zero)"),
+    (0xC0000017, "ran out of memory"),
+    (0xC0000135, "could not start: a DLL it needs is missing (is VapourSynth or the Visual C++ runtime installed?)"),
+    (0xC0000139, "could not start: a DLL is missing a function it needs (mismatched VapourSynth, Python or plugin versions)"),
+    (0xC000007B, "could not start: a DLL it loads is built for the wrong architecture (a 32-bit plugin or Python)"),
+    (0xC0000142, "could not start: a DLL failed to initialize"),
+    (0xC000013A, "was interrupted (Ctrl+C or its console was closed)"),
+    (0x40010004, "was terminated (its console was closed or the user logged off)"),
+    // Rust's exit code for a panic
+    (101, "hit an internal error (panic); see the task log"),
+];
+
+/// Signals a backend may be killed by on Linux and macOS.
+const SIGNALS: &[(i32, &str)] = &[
+    (4, "crashed on an instruction this CPU lacks; a plugin may need AVX2"),
+    (6, "aborted, usually after an error it printed to the task log"),
+    (7, "crashed (bus error)"),
+    (8, "crashed (arithmetic error)"),
+    (9, "was killed, possibly by the out-of-memory killer"),
+    (11, "crashed (segmentation fault), often a faulty VapourSynth plugin"),
+    (15, "was terminated"),
+];
+
+/// What `status` means, when it is one of the known exit codes or signals.
+pub fn describe(status: ExitStatus) -> Option<&'static str> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return SIGNALS.iter().find(|(known, _)| *known == signal).map(|(_, meaning)| *meaning);
+        }
+    }
+    // Windows reports NTSTATUS codes as negative numbers
+    let code = status.code()? as u32;
+    EXIT_CODES.iter().find(|(known, _)| *known == code).map(|(_, meaning)| *meaning)
+}
@@ -0,0 +1,141 @@
+use crate::config::SmoothieConfig;
+use crate::queue::{Backend, EnvVar, TaskStatus, VideoTask};
+use crate::worker;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::Path;
+
+/// Flavour of script the queue is exported as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptKind {
+    Batch, // Windows .bat
+    Shell, // POSIX .sh
+}
+
+impl ScriptKind {
+    /// Picks the flavour from the file extension, defaulting to the current platform's.
+    pub fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("sh") => ScriptKind::Shell,
+            Some(ext) if ext.eq_ignore_ascii_case("bat") || ext.eq_ignore_ascii_case("cmd") => {
+                ScriptKind::Batch
+            }
+            _ if cfg!(windows) => ScriptKind::Batch,
+            _ => ScriptKind::Shell,
+        }
+    }
+
+    fn quote(&self, arg: &OsStr) -> String {
+        let text = arg.to_string_lossy();
+        match self {
+            // Windows paths cannot contain quotes; `%` must be doubled in batch files
+            ScriptKind::Batch => format!("\"{}\"", text.replace('%', "%%")),
+            ScriptKind::Shell => format!("'{}'", text.replace('\'', "'\\''")),
+        }
+    }
+
+    fn comment(&self, text: &str) -> String {
+        match self {
+            ScriptKind::Batch => format!("REM {}", text),
+            ScriptKind::Shell => format!("# {}", text),
+        }
+    }
+}
+
+/// Builds a script running the backend of every pending task, in queue order,
+/// with the same command the worker would use. Steps the queuer performs around
+/// the render (downloads, pre-processing, container remux, post commands) are
+/// noted as comments rather than reproduced.
+pub fn queue_script(tasks: &[VideoTask], config: &SmoothieConfig, kind: ScriptKind) -> String {
+    let mut lines = match kind {
+        ScriptKind::Batch => vec!["@echo off".to_string()],
+        ScriptKind::Shell => vec!["#!/bin/sh".to_string()],
+    };
+    lines.push(kind.comment("Exported from Smoothie Queuer"));
+
+    for task in tasks.iter().filter(|t| t.status == TaskStatus::Pending) {
+        lines.push(String::new());
+        if let Some(source) = &task.source {
+            lines.push(kind.comment(&format!("Skipped {}: download it first", source.url)));
+            continue;
+        }
+        if let Some(sequence) = &task.image_sequence {
+            lines.push(kind.comment(&format!(
+                "Skipped {}: image sequence ({}), assemble it into a video first",
+                task.input_path.display(),
+                sequence.pattern
+            )));
+            continue;
+        }
+        if let Some(fps) = task.input_fps {
+            lines.push(kind.comment(&format!("Queued with the input retimed to {} fps, not included", fps)));
+        } else if let Some(pre_process) = task.pre_process {
+            lines.push(kind.comment(&format!("Queued with pre-processing ({:?}), not included", pre_process)));
+        }
+        let executor = match worker::executor_for(config, task) {
+            Ok(executor) => executor,
+            Err(e) => {
+                lines.push(kind.comment(&format!("Skipped {}: {}", task.input_path.display(), e)));
+                continue;
+            }
+        };
+        let command = executor.build_command(task, &task.input_path, &task.output_dir);
+        let mut words: Vec<String> = std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .chain(config.extra_args.split_whitespace().map(OsStr::new))
+            .chain(task.extra_args.split_whitespace().map(OsStr::new))
+            .map(|arg| kind.quote(arg))
+            .collect();
+        let env: Vec<&EnvVar> = config.env.iter().chain(&task.env).collect();
+        match kind {
+            ScriptKind::Batch if !env.is_empty() => {
+                // Scoped to this task's command
+                lines.push("setlocal".to_string());
+                for var in &env {
+                    lines.push(format!("set \"{}={}\"", var.name, var.value.replace('%', "%%")));
+                }
+                lines.push(words.join(" "));
+                lines.push("endlocal".to_string());
+            }
+            ScriptKind::Batch => lines.push(words.join(" ")),
+            ScriptKind::Shell => {
+                let assignments = env.iter().map(|var| format!("{}={}", var.name, kind.quote(OsStr::new(&var.value))));
+                words.splice(0..0, assignments);
+                lines.push(words.join(" "));
+            }
+        }
+        if task.backend == Backend::Rife && config.simulation.is_none() {
+            lines.push(kind.comment("rife-ncnn-vulkan works on frame folders: extract with ffmpeg first"));
+        }
+        if let Some(name) = &task.output_name {
+            lines.push(kind.comment(&format!("Output should be renamed to \"{}\"", name)));
+        }
+        if let Some(container) = task.output_container {
+            lines.push(kind.comment(&format!("Output should be remuxed to .{}", container.extension())));
+        }
+        if let Some(command) = &task.post_command {
+            lines.push(kind.comment(&format!("Post-processing command: {}", command)));
+        }
+    }
+
+    let newline = match kind {
+        ScriptKind::Batch => "\r\n",
+        ScriptKind::Shell => "\n",
+    };
+    lines.join(newline) + newline
+}
+
+/// Writes `queue_script` to `path`, marking shell scripts executable.
+pub fn write_queue_script(tasks: &[VideoTask], config: &SmoothieConfig, path: &Path) -> Result<(), String> {
+    let kind = ScriptKind::for_path(path);
+    fs::write(path, queue_script(tasks, config, kind))
+        .map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
+
+    #[cfg(unix)]
+    if kind == ScriptKind::Shell {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+            .map_err(|e| format!("Failed to make {:?} executable: {}", path, e))?;
+    }
+    Ok(())
+}
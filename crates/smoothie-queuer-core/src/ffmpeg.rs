@@ -0,0 +1,473 @@
+use crate::paths;
+use crate::queue::{DeliveryOptions, ImageSequence, StopFlags};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+
+/// Error message returned when an ffmpeg run is aborted by a force stop.
+pub const CANCELLED: &str = "ffmpeg cancelled by user";
+
+/// Builds an ffmpeg command that runs without a console window.
+fn ffmpeg_command(ffmpeg_path: &Path) -> Command {
+    let mut command = Command::new(ffmpeg_path);
+    command.args(["-hide_banner", "-loglevel", "error", "-y"]);
+
+    #[cfg(target_os = "windows")]
+    {
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+
+    command
+}
+
+/// Runs a prepared ffmpeg command, killing ffmpeg as soon as a force stop is
+/// requested through `stop_flags`. A non-zero exit is turned into an error message.
+async fn run(mut command: Command, what: &str, stop_flags: &StopFlags) -> Result<(), String> {
+    log::debug!("Running ffmpeg: {:?}", command);
+    command.stdout(Stdio::null()).stderr(Stdio::piped());
+    let mut command = tokio::process::Command::from(command);
+    command.kill_on_drop(true);
+
+    let child = command
+        .spawn()
+        .map_err(|e| format!("Failed to run ffmpeg for {}: {}", what, e))?;
+
+    // Dropping the child when cancelled kills ffmpeg
+    let output = tokio::select! {
+        output = child.wait_with_output() => {
+            output.map_err(|e| format!("ffmpeg {} failed while waiting: {}", what, e))?
+        }
+        () = stop_flags.force_stopped() => return Err(CANCELLED.to_string()),
+    };
+
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let last_line = stderr.lines().last().unwrap_or("").trim();
+    Err(format!("ffmpeg {} failed ({}): {}", what, output.status, last_line))
+}
+
+/// Copies every stream of `input` into a new container at `output` without re-encoding.
+pub async fn remux(
+    ffmpeg_path: &Path,
+    input: &Path,
+    output: &Path,
+    stop_flags: &StopFlags,
+) -> Result<(), String> {
+    let mut command = ffmpeg_command(ffmpeg_path);
+    command.arg("-i").arg(paths::for_command(input));
+    command.args(["-map", "0", "-c", "copy"]);
+    command.arg(paths::for_command(output));
+    run(command, "remux", stop_flags).await
+}
+
+/// Copies the video and audio of `input` into a new Matroska file at `output`,
+/// with fresh timestamps, leaving out subtitle and data streams MKV may not take.
+/// Gives backends a clean container when they fail to demux the original.
+pub async fn remux_to_mkv(
+    ffmpeg_path: &Path,
+    input: &Path,
+    output: &Path,
+    stop_flags: &StopFlags,
+) -> Result<(), String> {
+    let mut command = ffmpeg_command(ffmpeg_path);
+    command.args(["-fflags", "+genpts"]);
+    command.arg("-i").arg(paths::for_command(input));
+    command.args(["-map", "0:v", "-map", "0:a?", "-c", "copy"]);
+    command.arg(paths::for_command(output));
+    run(command, "remux to MKV", stop_flags).await
+}
+
+/// Re-encodes the video of `input` at a constant frame rate (lossless intermediate),
+/// copying audio as-is. Used to tame variable frame rate captures before interpolation.
+pub async fn convert_to_cfr(
+    ffmpeg_path: &Path,
+    input: &Path,
+    output: &Path,
+    stop_flags: &StopFlags,
+) -> Result<(), String> {
+    let mut command = ffmpeg_command(ffmpeg_path);
+    command.arg("-i").arg(paths::for_command(input));
+    command.args(["-map", "0:v:0", "-map", "0:a?", "-fps_mode", "cfr"]);
+    command.args(["-c:v", "libx264", "-preset", "ultrafast", "-qp", "0"]);
+    command.args(["-c:a", "copy"]);
+    command.arg(paths::for_command(output));
+    run(command, "CFR conversion", stop_flags).await
+}
+
+/// Re-encodes the video of `input` (lossless intermediate) as if it had been
+/// recorded at `fps`, for captures whose frame rate metadata is wrong: ffmpeg
+/// ignores the stored timestamps and spaces the frames evenly at that rate.
+pub async fn retime(
+    ffmpeg_path: &Path,
+    input: &Path,
+    fps: f64,
+    output: &Path,
+    stop_flags: &StopFlags,
+) -> Result<(), String> {
+    let mut command = ffmpeg_command(ffmpeg_path);
+    command.arg("-r").arg(fps.to_string());
+    command.arg("-i").arg(paths::for_command(input));
+    command.args(["-map", "0:v:0", "-map", "0:a?"]);
+    command.args(["-c:v", "libx264", "-preset", "ultrafast", "-qp", "0"]);
+    command.args(["-c:a", "copy"]);
+    command.arg(paths::for_command(output));
+    run(command, "frame rate fix", stop_flags).await
+}
+
+/// Encodes the frames of `sequence` in `dir` into a lossless video at `output`.
+/// EXR frames are linear, so they get the sRGB transfer applied on the way in.
+pub async fn assemble_sequence(
+    ffmpeg_path: &Path,
+    dir: &Path,
+    sequence: &ImageSequence,
+    output: &Path,
+    stop_flags: &StopFlags,
+) -> Result<(), String> {
+    let mut command = ffmpeg_command(ffmpeg_path);
+    command.arg("-framerate").arg(sequence.frame_rate.to_string());
+    command.arg("-start_number").arg(sequence.start_number.to_string());
+    if sequence.is_exr() {
+        command.args(["-apply_trc", "iec61966_2_1"]);
+    }
+    command.arg("-i").arg(paths::for_command(&dir.join(&sequence.pattern)));
+    command.args(["-c:v", "libx264", "-preset", "ultrafast", "-qp", "0", "-pix_fmt", "yuv444p"]);
+    command.arg(paths::for_command(output));
+    run(command, "image sequence assembly", stop_flags).await
+}
+
+/// Encodes a copy of `output` for sharing at `copy`: deinterlaced and/or scaled
+/// down to `options.max_height` (never up), as H.264/AAC MP4 that starts playing
+/// before it is fully downloaded.
+pub async fn delivery_copy(
+    ffmpeg_path: &Path,
+    output: &Path,
+    options: &DeliveryOptions,
+    copy: &Path,
+    stop_flags: &StopFlags,
+) -> Result<(), String> {
+    let mut filters = Vec::new();
+    if options.deinterlace {
+        filters.push("bwdif".to_string());
+    }
+    if let Some(height) = options.max_height {
+        filters.push(format!("scale=-2:'min({},ih)'", height));
+    }
+    filters.push("format=yuv420p".to_string());
+
+    let mut command = ffmpeg_command(ffmpeg_path);
+    command.arg("-i").arg(paths::for_command(output));
+    command.args(["-map", "0:v:0", "-map", "0:a?"]);
+    command.arg("-vf").arg(filters.join(","));
+    command.args(["-c:v", "libx264", "-crf", "20", "-preset", "medium"]);
+    command.args(["-c:a", "aac", "-b:a", "192k", "-movflags", "+faststart"]);
+    command.arg(paths::for_command(copy));
+    run(command, "delivery copy", stop_flags).await
+}
+
+/// Length of the clips made by `comparison_clip`.
+pub const COMPARISON_SECONDS: u32 = 4;
+
+/// Renders the first seconds of `input` (left) and `output` (right) side by side,
+/// scaled to 360p, into a small WebM at `clip`.
+pub async fn comparison_clip(
+    ffmpeg_path: &Path,
+    input: &Path,
+    output: &Path,
+    clip: &Path,
+    stop_flags: &StopFlags,
+) -> Result<(), String> {
+    let seconds = COMPARISON_SECONDS.to_string();
+    let mut command = ffmpeg_command(ffmpeg_path);
+    command.arg("-t").arg(&seconds).arg("-i").arg(paths::for_command(input));
+    command.arg("-t").arg(&seconds).arg("-i").arg(paths::for_command(output));
+    command.arg("-filter_complex").arg(
+        "[0:v]scale=-2:360,setsar=1[a];[1:v]scale=-2:360,setsar=1[b];\
+         [a][b]hstack=inputs=2,format=yuv420p[v]",
+    );
+    command.args(["-map", "[v]", "-an", "-c:v", "libvpx-vp9", "-crf", "40", "-b:v", "0"]);
+    command.arg(paths::for_command(clip));
+    run(command, "comparison clip", stop_flags).await
+}
+
+/// Height both frames of a `comparison_screenshot` are scaled to.
+const SCREENSHOT_HEIGHT: u32 = 720;
+
+/// Saves the frame at `at` of `input` (left) and `output` (right) side by side
+/// as a PNG at `png`, both scaled to the same height.
+pub async fn comparison_screenshot(
+    ffmpeg_path: &Path,
+    input: &Path,
+    output: &Path,
+    at: Duration,
+    png: &Path,
+    stop_flags: &StopFlags,
+) -> Result<(), String> {
+    let at = format!("{:.3}", at.as_secs_f64());
+    let mut command = ffmpeg_command(ffmpeg_path);
+    command.arg("-ss").arg(&at).arg("-i").arg(paths::for_command(input));
+    command.arg("-ss").arg(&at).arg("-i").arg(paths::for_command(output));
+    command.arg("-filter_complex").arg(format!(
+        "[0:v]scale=-2:{0},setsar=1[a];[1:v]scale=-2:{0},setsar=1[b];[a][b]hstack=inputs=2[v]",
+        SCREENSHOT_HEIGHT
+    ));
+    command.args(["-map", "[v]", "-frames:v", "1"]);
+    command.arg(paths::for_command(png));
+    run(command, "comparison screenshot", stop_flags).await
+}
+
+/// ffprobe from the same folder as `ffmpeg_path` (or from PATH).
+fn ffprobe_path(ffmpeg_path: &Path) -> PathBuf {
+    let name = if cfg!(windows) { "ffprobe.exe" } else { "ffprobe" };
+    match ffmpeg_path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        Some(dir) => dir.join(name),
+        None => PathBuf::from("ffprobe"),
+    }
+}
+
+/// Average frame rate of the first video stream, as a rational like `60000/1001`
+/// so it can be passed back to ffmpeg without rounding.
+pub async fn probe_frame_rate(ffmpeg_path: &Path, input: &Path) -> Result<(u64, u64), String> {
+    let mut command = Command::new(ffprobe_path(ffmpeg_path));
+    command.args(["-v", "error", "-select_streams", "v:0"]);
+    command.args(["-show_entries", "stream=avg_frame_rate", "-of", "default=nw=1:nk=1"]);
+    command.arg(paths::for_command(input));
+    #[cfg(target_os = "windows")]
+    {
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+
+    let output = tokio::process::Command::from(command)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let rate = stdout.trim();
+    rate.split_once('/')
+        .and_then(|(num, den)| Some((num.parse().ok()?, den.parse().ok()?)))
+        .filter(|&(num, den): &(u64, u64)| num > 0 && den > 0)
+        .ok_or_else(|| format!("ffprobe could not read the frame rate of {:?} ({:?})", input, rate))
+}
+
+/// Number of frames in the first video stream, counted from its packets (which
+/// needs no decoding, but still reads the whole file).
+pub async fn probe_frame_count(ffmpeg_path: &Path, input: &Path) -> Result<u64, String> {
+    let mut command = Command::new(ffprobe_path(ffmpeg_path));
+    command.args(["-v", "error", "-select_streams", "v:0", "-count_packets"]);
+    command.args(["-show_entries", "stream=nb_read_packets", "-of", "default=nw=1:nk=1"]);
+    command.arg(paths::for_command(input));
+    #[cfg(target_os = "windows")]
+    {
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+
+    let output = tokio::process::Command::from(command)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout.trim()
+        .parse()
+        .map_err(|_| format!("ffprobe could not count the frames of {:?}", input))
+}
+
+/// Whether ffprobe can read `input` through to its length. Returns why not, e.g.
+/// for a file whose container was never finalized; `Err` when ffprobe does not run.
+pub async fn probe_readable(ffmpeg_path: &Path, input: &Path) -> Result<Option<String>, String> {
+    let mut command = Command::new(ffprobe_path(ffmpeg_path));
+    command.args(["-v", "error", "-show_entries", "format=duration", "-of", "default=nw=1:nk=1"]);
+    command.arg(paths::for_command(input));
+    #[cfg(target_os = "windows")]
+    {
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+
+    let output = tokio::process::Command::from(command)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let reason = stderr.lines().map(str::trim).find(|line| !line.is_empty());
+    if !output.status.success() {
+        return Ok(Some(reason.unwrap_or("ffprobe could not open it").to_string()));
+    }
+    // "N/A" when the container never recorded its length
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if stdout.trim().parse::<f64>().is_err() {
+        return Ok(Some("it has no duration".to_string()));
+    }
+    Ok(None)
+}
+
+/// Length of `input` according to ffprobe. Unlike the rest of this module this
+/// blocks, so frontends can call it from a plain helper thread.
+pub fn probe_duration(ffmpeg_path: &Path, input: &Path) -> Result<Duration, String> {
+    let mut command = Command::new(ffprobe_path(ffmpeg_path));
+    command.args(["-v", "error", "-show_entries", "format=duration", "-of", "default=nw=1:nk=1"]);
+    command.arg(paths::for_command(input));
+    #[cfg(target_os = "windows")]
+    {
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+
+    let output = command.output().map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout.trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+        .map(Duration::from_secs_f64)
+        .ok_or_else(|| format!("ffprobe could not read the duration of {:?}", input))
+}
+
+/// Width and height of the first video stream of `input`, blocking like
+/// `probe_duration`.
+pub fn probe_resolution(ffmpeg_path: &Path, input: &Path) -> Result<(u32, u32), String> {
+    let mut command = Command::new(ffprobe_path(ffmpeg_path));
+    command.args(["-v", "error", "-select_streams", "v:0", "-show_entries", "stream=width,height"]);
+    command.args(["-of", "csv=p=0:s=x"]);
+    command.arg(paths::for_command(input));
+    #[cfg(target_os = "windows")]
+    {
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+
+    let output = command.output().map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+    // "1920x1080"
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout.trim()
+        .split_once('x')
+        .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+        .ok_or_else(|| format!("ffprobe could not read the resolution of {:?}", input))
+}
+
+/// Whether the first video stream of `input` has a variable frame rate, blocking
+/// like `probe_duration`. ffprobe gives no direct answer; a stream whose average
+/// rate differs from its base rate by more than 1% has frames spaced unevenly,
+/// as phone and screen recordings do.
+pub fn probe_variable_frame_rate(ffmpeg_path: &Path, input: &Path) -> Result<bool, String> {
+    let (base, average) = probe_frame_rates(ffmpeg_path, input)?;
+    Ok((base - average).abs() / base > 0.01)
+}
+
+/// Average frame rate of the first video stream of `input` in frames per
+/// second, blocking like `probe_duration`.
+pub fn probe_average_frame_rate(ffmpeg_path: &Path, input: &Path) -> Result<f64, String> {
+    probe_frame_rates(ffmpeg_path, input).map(|(_, average)| average)
+}
+
+/// Base (`r_frame_rate`) and average frame rate of the first video stream.
+fn probe_frame_rates(ffmpeg_path: &Path, input: &Path) -> Result<(f64, f64), String> {
+    let mut command = Command::new(ffprobe_path(ffmpeg_path));
+    command.args(["-v", "error", "-select_streams", "v:0"]);
+    command.args(["-show_entries", "stream=r_frame_rate,avg_frame_rate", "-of", "csv=p=0"]);
+    command.arg(paths::for_command(input));
+    #[cfg(target_os = "windows")]
+    {
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+
+    let output = command.output().map_err(|e| format!("Failed to run ffprobe: {}", e))?;
+    // "60/1,59733/1000"
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let rate = |text: &str| {
+        let (num, den) = text.trim().split_once('/')?;
+        let (num, den) = (num.parse::<f64>().ok()?, den.parse::<f64>().ok()?);
+        (num > 0.0 && den > 0.0).then(|| num / den)
+    };
+    stdout.trim()
+        .split_once(',')
+        .and_then(|(base, average)| Some((rate(base)?, rate(average)?)))
+        .ok_or_else(|| format!("ffprobe could not read the frame rates of {:?}", input))
+}
+
+/// Decodes `length` of `input` from `start` into RGBA frames of `width` x
+/// `height`, sampled at `fps` so clips of different frame rates line up frame for
+/// frame. Blocks like `probe_duration`; meant for short clips, as every frame is
+/// kept in memory.
+pub fn decode_frames(
+    ffmpeg_path: &Path,
+    input: &Path,
+    start: Duration,
+    length: Duration,
+    fps: f64,
+    (width, height): (u32, u32),
+) -> Result<Vec<Vec<u8>>, String> {
+    let mut command = ffmpeg_command(ffmpeg_path);
+    command.arg("-ss").arg(format!("{:.3}", start.as_secs_f64()));
+    command.arg("-t").arg(format!("{:.3}", length.as_secs_f64()));
+    command.arg("-i").arg(paths::for_command(input));
+    command.args(["-map", "0:v:0", "-an"]);
+    command.arg("-vf").arg(format!("fps={},scale={}:{}", fps, width, height));
+    command.args(["-f", "rawvideo", "-pix_fmt", "rgba", "-"]);
+    command.stdin(Stdio::null());
+
+    let output = command.output().map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let last_line = stderr.lines().last().unwrap_or("").trim();
+        return Err(format!("ffmpeg could not decode {:?} ({}): {}", input, output.status, last_line));
+    }
+    let frame_size = width as usize * height as usize * 4;
+    Ok(output.stdout.chunks_exact(frame_size).map(<[u8]>::to_vec).collect())
+}
+
+/// The frame of `input` at `at` scaled to `height` (keeping the aspect ratio),
+/// as its size and RGBA pixels. Blocks like `decode_frames`.
+pub fn thumbnail(ffmpeg_path: &Path, input: &Path, at: Duration, height: u32) -> Result<([usize; 2], Vec<u8>), String> {
+    let (source_width, source_height) = probe_resolution(ffmpeg_path, input)?;
+    let width = ((source_width as u64 * height as u64 / source_height.max(1) as u64) as u32).max(1);
+    let mut command = ffmpeg_command(ffmpeg_path);
+    command.arg("-ss").arg(format!("{:.3}", at.as_secs_f64()));
+    command.arg("-i").arg(paths::for_command(input));
+    command.args(["-map", "0:v:0", "-an", "-frames:v", "1"]);
+    command.arg("-vf").arg(format!("scale={}:{}", width, height));
+    command.args(["-f", "rawvideo", "-pix_fmt", "rgba", "-"]);
+    command.stdin(Stdio::null());
+
+    let output = command.output().map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+    let frame_size = width as usize * height as usize * 4;
+    if !output.status.success() || output.stdout.len() < frame_size {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let last_line = stderr.lines().last().unwrap_or("").trim();
+        return Err(format!("ffmpeg could not decode a frame of {:?} ({}): {}", input, output.status, last_line));
+    }
+    Ok(([width as usize, height as usize], output.stdout[..frame_size].to_vec()))
+}
+
+/// Writes every frame of `input` as numbered PNGs (`%08d.png`) into `frames_dir`.
+pub async fn extract_frames(
+    ffmpeg_path: &Path,
+    input: &Path,
+    frames_dir: &Path,
+    stop_flags: &StopFlags,
+) -> Result<(), String> {
+    let mut command = ffmpeg_command(ffmpeg_path);
+    command.arg("-i").arg(paths::for_command(input));
+    command.args(["-map", "0:v:0", "-fps_mode", "passthrough"]);
+    command.arg(paths::for_command(&frames_dir.join("%08d.png")));
+    run(command, "frame extraction", stop_flags).await
+}
+
+/// Encodes the numbered PNGs in `frames_dir` at `frame_rate` (`num/den`), taking
+/// the audio from `audio_source`.
+pub async fn encode_frames(
+    ffmpeg_path: &Path,
+    frames_dir: &Path,
+    frame_rate: (u64, u64),
+    audio_source: &Path,
+    output: &Path,
+    stop_flags: &StopFlags,
+) -> Result<(), String> {
+    let mut command = ffmpeg_command(ffmpeg_path);
+    command.arg("-framerate").arg(format!("{}/{}", frame_rate.0, frame_rate.1));
+    command.arg("-i").arg(paths::for_command(&frames_dir.join("%08d.png")));
+    command.arg("-i").arg(paths::for_command(audio_source));
+    command.args(["-map", "0:v", "-map", "1:a?"]);
+    command.args(["-c:v", "libx264", "-crf", "16", "-pix_fmt", "yuv420p", "-c:a", "copy"]);
+    command.arg(paths::for_command(output));
+    run(command, "frame encoding", stop_flags).await
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::process::Command;
+
+/// A GPU tasks can be spread across, as listed by `nvidia-smi`.
+#[derive(Debug, Clone)]
+pub struct Gpu {
+    pub index: u32,
+    pub name: String,
+}
+
+/// Runs `nvidia-smi --query-gpu=<fields>` and returns its CSV rows.
+pub fn query(fields: &str) -> Option<Vec<Vec<String>>> {
+    let mut command = Command::new("nvidia-smi");
+    command.arg(format!("--query-gpu={}", fields));
+    command.arg("--format=csv,noheader,nounits");
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+    let output = command.output().ok().filter(|output| output.status.success())?;
+    let rows = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.split(',').map(|field| field.trim().to_string()).collect())
+        .collect();
+    Some(rows)
+}
+
+/// The NVIDIA GPUs in this machine; empty when `nvidia-smi` is missing.
+pub fn detect() -> Vec<Gpu> {
+    query("index,name")
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|row| {
+            Some(Gpu {
+                index: row.first()?.parse().ok()?,
+                name: row.get(1).cloned().unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Current utilization (percent) of each GPU by index.
+fn utilization() -> HashMap<u32, u32> {
+    query("index,utilization.gpu")
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|row| Some((row.first()?.parse().ok()?, row.get(1)?.parse().ok()?)))
+        .collect()
+}
+
+/// Spreads the tasks of one worker run across the GPUs: each task goes to the GPU
+/// running the fewest of them, the least utilized one on a tie (so a GPU busy with
+/// a game or another app is avoided).
+pub struct GpuScheduler {
+    gpus: Vec<Gpu>,
+    assigned: HashMap<usize, u32>, // Task id -> GPU index
+}
+
+impl GpuScheduler {
+    /// `None` unless there are at least two GPUs to choose from.
+    pub fn detect() -> Option<Self> {
+        let gpus = detect();
+        if gpus.len() < 2 {
+            log::info!("Found {} NVIDIA GPU(s); tasks are not spread across GPUs", gpus.len());
+            return None;
+        }
+        log::info!("Spreading tasks across {} GPUs: {:?}", gpus.len(), gpus);
+        Some(Self {
+            gpus,
+            assigned: HashMap::new(),
+        })
+    }
+
+    /// Picks the GPU `task_id` renders on.
+    pub fn assign(&mut self, task_id: usize) -> &Gpu {
+        let utilization = utilization();
+        let tasks_on = |gpu: &Gpu| self.assigned.values().filter(|&&index| index == gpu.index).count();
+        let gpu = self
+            .gpus
+            .iter()
+            .min_by_key(|gpu| (tasks_on(gpu), utilization.get(&gpu.index).copied().unwrap_or(0)))
+            .expect("GpuScheduler has at least two GPUs");
+        self.assigned.insert(task_id, gpu.index);
+        gpu
+    }
+
+    /// Frees the GPU of a task that finished.
+    pub fn release(&mut self, task_id: usize) {
+        self.assigned.remove(&task_id);
+    }
+}
@@ -0,0 +1,178 @@
+use crate::config::SmoothieConfig;
+use crate::queue::{Backend, VideoTask};
+use crate::{executor, paths, recipes, worker};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Smoothie's table of named encoder arguments, referenced by `[output] enc args`.
+const ENCODING_PRESETS: &str = "encoding_presets.ini";
+
+/// One setting of the recipe a task renders with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecipeSetting {
+    pub section: String,
+    pub key: String,
+    pub value: String,
+    /// The default recipe's value, when it differs.
+    pub default: Option<String>,
+    /// Set by one of the task's recipe overrides.
+    pub overridden: bool,
+}
+
+/// What a task will render with, resolved the way the worker would.
+#[derive(Debug, Clone)]
+pub struct EffectiveSettings {
+    /// The `.ini` smoothie-rs reads: the task's recipe, or the one generated from
+    /// its YAML/TOML source or, for a smoothie-rs without `--override`, with its
+    /// overrides.
+    pub recipe_path: PathBuf,
+    /// The recipe's settings in file order, or why there are none.
+    pub recipe: Result<Vec<RecipeSetting>, String>,
+    /// `[output] enc args` and the encoder arguments it stands for.
+    pub encoding: Option<(String, String)>,
+    /// The backend command for the task's own input and output folder; a staged
+    /// or pre-processed input is substituted when the task runs.
+    pub command_line: Result<String, String>,
+}
+
+/// Resolves the settings `task` renders with under `config`.
+pub fn resolve(task: &VideoTask, config: &SmoothieConfig) -> EffectiveSettings {
+    let mut task = task.clone();
+    let mut recipe = match task.backend {
+        Backend::Smoothie => Ok(()),
+        Backend::Blur if task.recipe_path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("cfg")) => {
+            Err("blur reads this .cfg config itself".to_string())
+        }
+        Backend::Blur => Err("blur uses its own default config".to_string()),
+        Backend::Rife => Err("rife-ncnn-vulkan does not use a recipe".to_string()),
+    };
+    if recipe.is_ok() && recipes::is_source(&task.recipe_path) {
+        match recipes::convert(&task.recipe_path, &config.recipe_path) {
+            Ok(converted) => task.recipe_path = converted,
+            Err(e) => recipe = Err(e),
+        }
+    }
+
+    let recipe = recipe.and_then(|()| {
+        let text = fs::read_to_string(&task.recipe_path)
+            .map_err(|e| format!("Failed to read recipe {:?}: {}", task.recipe_path, e))?;
+        let defaults = fs::read_to_string(&config.recipe_path).map(|text| settings(&text)).unwrap_or_default();
+        Ok(settings(&text)
+            .into_iter()
+            .map(|(section, key, value)| {
+                let default = defaults
+                    .iter()
+                    .find(|(default_section, default_key, _)| {
+                        default_section.eq_ignore_ascii_case(&section) && default_key.eq_ignore_ascii_case(&key)
+                    })
+                    .map(|(_, _, default)| default.clone())
+                    .filter(|default| *default != value);
+                RecipeSetting { section, key, value, default, overridden: false }
+            })
+            .collect::<Vec<_>>())
+    });
+    let recipe = recipe.map(|mut settings| {
+        for value in &task.recipe_overrides {
+            let existing = settings.iter_mut().find(|setting| {
+                setting.section.eq_ignore_ascii_case(&value.section) && setting.key.eq_ignore_ascii_case(&value.key)
+            });
+            match existing {
+                Some(setting) => {
+                    setting.value = value.value.clone();
+                    setting.overridden = true;
+                }
+                None => settings.push(RecipeSetting {
+                    section: value.section.clone(),
+                    key: value.key.clone(),
+                    value: value.value.clone(),
+                    default: None,
+                    overridden: true,
+                }),
+            }
+        }
+        settings
+    });
+
+    let encoding = recipe.as_ref().ok().and_then(|settings| {
+        let enc_args = settings
+            .iter()
+            .find(|setting| setting.section.eq_ignore_ascii_case("output") && setting.key.eq_ignore_ascii_case("enc args"))?;
+        let arguments = encoding_preset(config.smoothie_dir(), &enc_args.value).unwrap_or_else(|| enc_args.value.clone());
+        Some((enc_args.value.clone(), arguments))
+    });
+
+    // As the worker does for a smoothie-rs without `--override`
+    if task.backend == Backend::Smoothie
+        && !task.recipe_overrides.is_empty()
+        && config.simulation.is_none()
+        && !executor::supports_override(&config.executable_path)
+        && let Ok(recipe_path) = recipes::with_overrides(&task.recipe_path, &task.recipe_overrides)
+    {
+        task.recipe_path = recipe_path;
+        task.recipe_overrides.clear();
+    }
+
+    let output_dir = paths::absolute(&task.output_dir);
+    let command_line = worker::executor_for(config, &task).map(|executor| {
+        let mut command = executor.build_command(&task, &task.input_path, &output_dir);
+        command.args(config.extra_args.split_whitespace());
+        command.args(task.extra_args.split_whitespace());
+        format_command(&command)
+    });
+
+    EffectiveSettings {
+        recipe_path: task.recipe_path,
+        recipe,
+        encoding,
+        command_line,
+    }
+}
+
+/// `(section, key, value)` of each `key: value` line of an `.ini` recipe.
+fn settings(text: &str) -> Vec<(String, String, String)> {
+    let mut section = String::new();
+    let mut settings = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            section = name.trim().to_string();
+        } else if let Some((key, value)) = line.split_once(':') {
+            settings.push((section.clone(), key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    settings
+}
+
+/// Encoder arguments for an `enc args` preset name such as `H264 CPU`: the `CPU`
+/// key of the `[H264]` section of Smoothie's `encoding_presets.ini`. `None` when
+/// the value is not a preset, i.e. already holds ffmpeg arguments.
+fn encoding_preset(smoothie_dir: Option<&Path>, name: &str) -> Option<String> {
+    let (codec, encoder) = name.trim().split_once(char::is_whitespace)?;
+    let dir = smoothie_dir?;
+    let text = [dir.join(ENCODING_PRESETS), dir.join("recipes").join(ENCODING_PRESETS)]
+        .iter()
+        .find_map(|path| fs::read_to_string(path).ok())?;
+    settings(&text)
+        .into_iter()
+        .find(|(section, key, _)| section.eq_ignore_ascii_case(codec) && key.eq_ignore_ascii_case(encoder.trim()))
+        .map(|(_, _, arguments)| arguments)
+}
+
+/// `command` as it would be typed in a shell, arguments with spaces quoted.
+fn format_command(command: &Command) -> String {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|part| {
+            let part = part.to_string_lossy();
+            if part.is_empty() || part.contains(char::is_whitespace) {
+                format!("\"{}\"", part)
+            } else {
+                part.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
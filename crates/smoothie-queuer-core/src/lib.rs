@@ -0,0 +1,85 @@
+//! Queueing engine of Smoothie Queuer, independent of any frontend.
+//!
+//! A frontend owns an `Arc<Mutex<queue::QueueManager>>`, adds tasks to it with
+//! `add_new_task` (which gives each its ID) and starts `worker::spawn_worker`
+//! with a `config::SmoothieConfig`. The worker renders pending tasks, up to
+//! `max_parallel` of the config at a time, through the `executor::Executor`
+//! picked for each task and reports progress as `worker::UpdateMessage`s:
+//!
+//! ```no_run
+//! use smoothie_queuer_core::{config, queue::QueueManager, worker};
+//! use std::sync::{Arc, Mutex, mpsc};
+//!
+//! let config = config::find_smoothie_config_auto().expect("smoothie-rs not found");
+//! let queue_manager = Arc::new(Mutex::new(QueueManager::new()));
+//! queue_manager.lock().unwrap().add_new_task(
+//!     "clip.mp4".into(),
+//!     ".".into(),
+//!     config.recipe_path.clone(),
+//! );
+//!
+//! let (tx, rx) = mpsc::channel();
+//! let handle = worker::spawn_worker(Arc::clone(&queue_manager), tx, config);
+//! for update in rx {
+//!     println!("{:?}", update);
+//! }
+//! handle.join().unwrap();
+//! ```
+//!
+//! The GUI, the `--rpc` mode and the control API of the `smoothie-queuer`
+//! binary are all built on this API.
+
+/// Archive of finished tasks and their statistics, shown in the History window.
+pub mod archive;
+/// Settings files for moving the configuration to another machine.
+pub mod bundle;
+/// Locating smoothie-rs, its recipes, ffmpeg and the optional backends.
+pub mod config;
+/// yt-dlp downloads for tasks queued from a URL.
+pub mod download;
+/// The `Executor` trait and the built-in rendering backends.
+pub mod executor;
+/// Estimating the energy tasks use from the power draw while they render.
+pub mod energy;
+/// The session's log of queue events.
+pub mod events;
+/// What the exit codes and signals of crashed backends mean.
+pub mod exit_codes;
+/// Exporting pending tasks as a batch or shell script.
+pub mod export;
+/// ffmpeg/ffprobe helpers for pre-processing and remuxing.
+pub mod ffmpeg;
+/// Spreading tasks across several GPUs.
+pub mod gpu;
+/// Resolving the settings, encoder arguments and command a task renders with.
+pub mod inspect;
+/// Path handling for long paths and network shares.
+pub mod paths;
+/// Spotting missing VapourSynth plugins and Python modules in failed renders.
+pub mod plugins;
+/// Switching to the High Performance power plan while rendering (Windows).
+pub mod power;
+/// Suspending and resuming running backends.
+pub mod process;
+/// Tasks, their options and the `QueueManager` holding them.
+pub mod queue;
+/// Creating recipes from built-in templates and converting YAML/TOML recipes.
+pub mod recipes;
+/// Picking a task's recipe from its input's folder or file type.
+pub mod rules;
+/// Checking recipes against the settings smoothie-rs knows.
+pub mod schema;
+/// The queue saved between launches.
+pub mod session;
+/// Persisted user settings and the application data folder.
+pub mod settings;
+/// Demo mode that fakes renders.
+pub mod simulate;
+/// The SQLite database the queue and archive are kept in.
+pub mod store;
+/// Saved queue templates.
+pub mod templates;
+/// Checks on finished outputs.
+pub mod verify;
+/// The background worker processing the queue.
+pub mod worker;
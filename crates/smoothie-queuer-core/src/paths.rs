@@ -0,0 +1,247 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+/// Paths at least this long are passed to spawned tools in extended-length form.
+/// `MAX_PATH` is 260, and a folder must leave room for the file names tools
+/// append to it.
+#[cfg(windows)]
+const LONG_PATH: usize = 240;
+
+/// `path` made absolute against the working directory, with `.`/`..` resolved
+/// and (on Windows) `/` turned into `\`. UNC paths (`\\NAS\clips\...`) stay as
+/// they are.
+pub fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// `path` as an argument for smoothie-rs, ffmpeg and the other tools. On Windows,
+/// paths close to `MAX_PATH` get the extended-length prefix (`\\?\C:\...`, or
+/// `\\?\UNC\server\share\...` for network shares) so tools that are not long
+/// path aware can still open them. Other paths are passed unchanged.
+#[cfg(windows)]
+pub fn for_command(path: &Path) -> PathBuf {
+    use std::ffi::OsString;
+    use std::path::{Component, Prefix};
+
+    if path.as_os_str().len() < LONG_PATH {
+        return path.to_path_buf();
+    }
+    // The prefix turns off all normalization, so the path must be clean first
+    let path = absolute(path);
+    let mut components = path.components();
+    let Some(Component::Prefix(prefix)) = components.next() else {
+        return path;
+    };
+    let mut extended = OsString::new();
+    match prefix.kind() {
+        Prefix::Disk(_) => {
+            extended.push(r"\\?\");
+            extended.push(prefix.as_os_str());
+        }
+        Prefix::UNC(server, share) => {
+            extended.push(r"\\?\UNC\");
+            extended.push(server);
+            extended.push(r"\");
+            extended.push(share);
+        }
+        // Already verbatim, or a device path
+        _ => return path,
+    }
+    for component in components {
+        if let Component::Normal(name) = component {
+            extended.push(r"\");
+            extended.push(name);
+        }
+    }
+    PathBuf::from(extended)
+}
+
+#[cfg(not(windows))]
+pub fn for_command(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+/// Makes sure the output folder exists before anything is rendered into it, so
+/// an offline network share (`\\NAS\clips`) fails the task right away instead of
+/// after the render.
+pub fn ensure_output_dir(dir: &Path) -> Result<(), String> {
+    if dir.is_dir() {
+        return Ok(());
+    }
+    std::fs::create_dir_all(dir).map_err(|e| {
+        if is_unc(dir) {
+            format!("Output folder {} is not reachable (is the share online?): {}", dir.display(), e)
+        } else {
+            format!("Failed to create output folder {}: {}", dir.display(), e)
+        }
+    })
+}
+
+/// Whether `path` is on a network share (`\\server\share\...`). Always false
+/// outside Windows.
+pub fn is_unc(path: &Path) -> bool {
+    use std::path::{Component, Prefix};
+
+    matches!(
+        path.components().next(),
+        Some(Component::Prefix(prefix)) if matches!(prefix.kind(), Prefix::UNC(..) | Prefix::VerbatimUNC(..))
+    )
+}
+
+/// Whether `path` is on a network drive: a share (`\\NAS\clips`) or a drive
+/// letter mapped to one on Windows, an NFS/SMB/SSHFS mount on Linux.
+pub fn is_network(path: &Path) -> bool {
+    is_unc(path) || is_network_mount(&absolute(path))
+}
+
+#[cfg(windows)]
+fn is_network_mount(path: &Path) -> bool {
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::Component;
+    use winapi::um::fileapi::GetDriveTypeW;
+
+    const DRIVE_REMOTE: u32 = 4;
+    let Some(Component::Prefix(prefix)) = path.components().next() else {
+        return false;
+    };
+    // `C:\`, null terminated
+    let root: Vec<u16> = prefix.as_os_str().encode_wide().chain("\\\0".encode_utf16()).collect();
+    unsafe { GetDriveTypeW(root.as_ptr()) == DRIVE_REMOTE }
+}
+
+/// Looks up the file system of the mount holding `path` in `/proc/self/mounts`.
+#[cfg(target_os = "linux")]
+fn is_network_mount(path: &Path) -> bool {
+    const NETWORK_FILE_SYSTEMS: [&str; 7] = ["nfs", "nfs4", "cifs", "smb3", "smbfs", "fuse.sshfs", "9p"];
+
+    let Ok(mounts) = std::fs::read_to_string("/proc/self/mounts") else {
+        return false;
+    };
+    // "//nas/clips /mnt/clips cifs rw,relatime 0 0"; spaces in the mount point are `\040`
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(' ');
+            let mount_point = fields.nth(1)?.replace("\\040", " ");
+            let file_system = fields.next()?;
+            path.starts_with(&mount_point).then_some((mount_point.len(), file_system))
+        })
+        .max_by_key(|(length, _)| *length)
+        .is_some_and(|(_, file_system)| NETWORK_FILE_SYSTEMS.contains(&file_system))
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn is_network_mount(_path: &Path) -> bool {
+    false
+}
+
+/// Whether the file name of `path` is one that some tools mishandle: anything
+/// beyond ASCII letters, digits, spaces and `-_.,()+`, a leading `-` (read as an
+/// option) or a trailing space or dot (dropped by Windows).
+pub fn has_unsafe_name(path: &Path) -> bool {
+    let Some(name) = path.file_name() else {
+        return false;
+    };
+    let Some(name) = name.to_str() else {
+        return true; // Not valid Unicode
+    };
+    name.starts_with('-')
+        || name.ends_with([' ', '.'])
+        || !name.chars().all(|c| c.is_ascii_alphanumeric() || " -_.,()+".contains(c))
+}
+
+/// `name` with every character `has_unsafe_name` objects to replaced by `_` and
+/// trailing spaces and dots removed. Never empty.
+pub fn safe_file_name(name: &OsStr) -> String {
+    let name: String = name
+        .to_string_lossy()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || " -_.,()+".contains(c) { c } else { '_' })
+        .collect();
+    let name = name.trim_end_matches([' ', '.']);
+    match name.strip_prefix('-') {
+        Some(rest) => format!("_{}", rest),
+        None if name.is_empty() => "input".to_string(),
+        None => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_paths_are_passed_unchanged() {
+        let path = Path::new("clips/input.mp4");
+        assert_eq!(for_command(path), path);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn long_paths_get_the_extended_length_prefix() {
+        let folder = "a".repeat(LONG_PATH);
+        let path = PathBuf::from(format!(r"C:\clips\{}\..\{}\input.mp4", folder, folder));
+        assert_eq!(for_command(&path), PathBuf::from(format!(r"\\?\C:\clips\{}\input.mp4", folder)));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn long_unc_paths_get_the_extended_unc_prefix() {
+        let folder = "a".repeat(LONG_PATH);
+        let path = PathBuf::from(format!(r"\\NAS\clips\{}\input.mp4", folder));
+        assert_eq!(for_command(&path), PathBuf::from(format!(r"\\?\UNC\NAS\clips\{}\input.mp4", folder)));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn verbatim_paths_are_left_alone() {
+        let path = PathBuf::from(format!(r"\\?\C:\clips\{}\input.mp4", "a".repeat(LONG_PATH)));
+        assert_eq!(for_command(&path), path);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn unc_paths_are_recognized() {
+        assert!(is_unc(Path::new(r"\\NAS\clips\input.mp4")));
+        assert!(is_unc(Path::new(r"\\?\UNC\NAS\clips\input.mp4")));
+        assert!(is_network(Path::new(r"\\NAS\clips")));
+        assert!(!is_unc(Path::new(r"C:\clips\input.mp4")));
+        assert!(!is_unc(Path::new(r"\\?\C:\clips\input.mp4")));
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn unc_paths_only_exist_on_windows() {
+        assert!(!is_unc(Path::new(r"\\NAS\clips\input.mp4")));
+        assert!(!is_unc(Path::new("/mnt/clips/input.mp4")));
+    }
+
+    #[test]
+    fn local_paths_are_not_network_paths() {
+        assert!(!is_network(&std::env::temp_dir()));
+    }
+
+    #[test]
+    fn plain_names_are_safe() {
+        assert!(!has_unsafe_name(Path::new("clips/My Clip (1)_final-v2.mp4")));
+        assert!(!has_unsafe_name(Path::new("clips/")));
+    }
+
+    #[test]
+    fn exotic_names_are_unsafe() {
+        for name in ["clip 🎬.mp4", "it's.mp4", "[60fps].mp4", "-clip.mp4", "clip.mp4 ", "clip.", "café.mp4"] {
+            assert!(has_unsafe_name(Path::new(name)), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn safe_file_names_replace_what_is_unsafe() {
+        assert_eq!(safe_file_name(OsStr::new("clip 🎬 [60fps].mp4")), "clip _ _60fps_.mp4");
+        assert_eq!(safe_file_name(OsStr::new("-clip.mp4")), "_clip.mp4");
+        assert_eq!(safe_file_name(OsStr::new("clip. . ")), "clip");
+        assert_eq!(safe_file_name(OsStr::new("...")), "input");
+        for name in ["it's.mp4", "-x", "café", "a\"b"] {
+            assert!(!has_unsafe_name(Path::new(&safe_file_name(OsStr::new(name)))), "{:?}", name);
+        }
+    }
+}
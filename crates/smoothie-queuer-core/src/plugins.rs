@@ -0,0 +1,177 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A VapourSynth plugin or Python module Smoothie's scripts use.
+#[derive(Debug, PartialEq, Eq)]
+pub struct KnownPlugin {
+    pub name: &'static str,
+    /// Plugin namespace (`core.mv`) or module name, as errors give it.
+    pub id: &'static str,
+    /// What to copy into the plugin or Python folder.
+    pub files: &'static str,
+    /// Where to download it; `None` for what only comes with Smoothie itself.
+    pub url: Option<&'static str>,
+}
+
+const KNOWN_PLUGINS: &[KnownPlugin] = &[
+    KnownPlugin {
+        name: "MVTools",
+        id: "mv",
+        files: "libmvtools.dll",
+        url: Some("https://github.com/dubhater/vapoursynth-mvtools/releases"),
+    },
+    KnownPlugin { name: "SVPflow", id: "svp1", files: "the svpflow1 .dll", url: None },
+    KnownPlugin { name: "SVPflow", id: "svp2", files: "the svpflow2 .dll", url: None },
+    KnownPlugin {
+        name: "RIFE ncnn Vulkan",
+        id: "rife",
+        files: "librife.dll and its models folder",
+        url: Some("https://github.com/styler00dollar/VapourSynth-RIFE-ncnn-Vulkan/releases"),
+    },
+    KnownPlugin { name: "Smoothie's frame blender", id: "frameblender", files: "the frameblender .dll", url: None },
+    KnownPlugin {
+        name: "akarin",
+        id: "akarin",
+        files: "akarin.dll",
+        url: Some("https://github.com/AkarinVS/vapoursynth-plugin/releases"),
+    },
+    KnownPlugin {
+        name: "MiscFilters",
+        id: "misc",
+        files: "MiscFilters.dll",
+        url: Some("https://github.com/vapoursynth/vs-miscfilters-obsolete/releases"),
+    },
+    KnownPlugin { name: "FFMS2", id: "ffms2", files: "ffms2.dll", url: Some("https://github.com/FFMS/ffms2/releases") },
+    KnownPlugin {
+        name: "L-SMASH Works",
+        id: "lsmas",
+        files: "LSMASHSource.dll",
+        url: Some("https://github.com/HomeOfAviSynthPlusEvolution/L-SMASH-Works/releases"),
+    },
+    KnownPlugin { name: "BestSource", id: "bs", files: "BestSource.dll", url: Some("https://github.com/vapoursynth/bestsource/releases") },
+];
+
+const KNOWN_MODULES: &[KnownPlugin] = &[
+    KnownPlugin {
+        name: "havsfunc",
+        id: "havsfunc",
+        files: "havsfunc.py",
+        url: Some("https://github.com/HomeOfVapourSynthEvolution/havsfunc"),
+    },
+    KnownPlugin {
+        name: "mvsfunc",
+        id: "mvsfunc",
+        files: "mvsfunc.py",
+        url: Some("https://github.com/HomeOfVapourSynthEvolution/mvsfunc"),
+    },
+    KnownPlugin {
+        name: "vsutil",
+        id: "vsutil",
+        files: "the vsutil folder",
+        url: Some("https://github.com/Irrational-Encoding-Wizardry/vsutil"),
+    },
+    KnownPlugin { name: "VapourSynth", id: "vapoursynth", files: "VapourSynth itself", url: None },
+];
+
+/// Whether a `MissingPlugin` is a VapourSynth plugin or a Python module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissingKind {
+    Plugin,
+    PythonModule,
+}
+
+/// A plugin or module a render failed without.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingPlugin {
+    pub kind: MissingKind,
+    pub id: String, // As the error names it
+    pub known: Option<&'static KnownPlugin>,
+}
+
+impl MissingPlugin {
+    pub fn name(&self) -> &str {
+        self.known.map_or(&self.id, |known| known.name)
+    }
+
+    /// Where the plugin or module belongs in the Smoothie folder: the plugin
+    /// folder of Smoothie's portable VapourSynth, or its Python's packages.
+    pub fn install_dir(&self, smoothie_dir: &Path) -> PathBuf {
+        let bin = smoothie_dir.join("bin");
+        let candidates = match self.kind {
+            MissingKind::Plugin => vec![
+                bin.join("vs-plugins"),
+                bin.join("vapoursynth64").join("plugins"),
+                smoothie_dir.join("vs-plugins"),
+                smoothie_dir.join("vapoursynth64").join("plugins"),
+            ],
+            MissingKind::PythonModule => vec![bin.join("Lib").join("site-packages"), bin.clone()],
+        };
+        candidates.iter().find(|dir| dir.is_dir()).unwrap_or(&candidates[0]).clone()
+    }
+}
+
+impl std::fmt::Display for MissingPlugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.kind, self.known) {
+            (MissingKind::Plugin, Some(known)) => {
+                write!(f, "VapourSynth plugin {} (core.{}) is missing", known.name, self.id)
+            }
+            (MissingKind::Plugin, None) => write!(f, "VapourSynth plugin core.{} is missing", self.id),
+            (MissingKind::PythonModule, _) => write!(f, "Python module {} is missing", self.id),
+        }
+    }
+}
+
+/// The missing plugin or module a render's output complains about, from its
+/// last line that does: VapourSynth's "No attribute with the name mv exists"
+/// and Python's "No module named 'havsfunc'".
+pub fn diagnose(output: &[String]) -> Option<MissingPlugin> {
+    output.iter().rev().find_map(|line| {
+        if let Some(rest) = line.split("No attribute with the name ").nth(1) {
+            let id = rest.split_whitespace().next()?;
+            return Some(MissingPlugin {
+                kind: MissingKind::Plugin,
+                id: id.to_string(),
+                known: KNOWN_PLUGINS.iter().find(|known| known.id.eq_ignore_ascii_case(id)),
+            });
+        }
+        let rest = line.split("No module named ").nth(1)?;
+        // `a.b` is missing when `a` is, which is what needs installing
+        let id = rest.trim_matches(['\'', '"']).split(['\'', '"', '.']).next()?;
+        Some(MissingPlugin {
+            kind: MissingKind::PythonModule,
+            id: id.to_string(),
+            known: KNOWN_MODULES.iter().find(|known| known.id.eq_ignore_ascii_case(id)),
+        })
+    })
+}
+
+/// Copies a downloaded plugin `.dll` or module (a `.py` file or a package
+/// folder) into `missing.install_dir`. Returns where it went.
+pub fn install(missing: &MissingPlugin, from: &Path, smoothie_dir: &Path) -> Result<PathBuf, String> {
+    let dir = missing.install_dir(smoothie_dir);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+    let name = from.file_name().ok_or_else(|| format!("{:?} is not a file or folder", from))?;
+    let to = dir.join(name);
+    if from.is_dir() {
+        copy_dir(from, &to)?;
+    } else {
+        fs::copy(from, &to).map_err(|e| format!("Failed to copy {:?} to {:?}: {}", from, to, e))?;
+    }
+    log::info!("Installed {:?} into {:?}", from, dir);
+    Ok(to)
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<(), String> {
+    fs::create_dir_all(to).map_err(|e| format!("Failed to create {:?}: {}", to, e))?;
+    let entries = fs::read_dir(from).map_err(|e| format!("Failed to read {:?}: {}", from, e))?;
+    for entry in entries.flatten() {
+        let (source, target) = (entry.path(), to.join(entry.file_name()));
+        if source.is_dir() {
+            copy_dir(&source, &target)?;
+        } else {
+            fs::copy(&source, &target).map_err(|e| format!("Failed to copy {:?} to {:?}: {}", source, target, e))?;
+        }
+    }
+    Ok(())
+}
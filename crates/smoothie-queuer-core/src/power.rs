@@ -0,0 +1,93 @@
+/// Windows' built-in High Performance plan.
+#[cfg(windows)]
+const HIGH_PERFORMANCE_GUID: &str = "8c5e7fda-e8bf-4a96-9a85-a6e23a8c635c";
+
+/// The High Performance power plan, active while this is alive. Dropping it
+/// switches back to the plan that was active before.
+pub struct HighPerformancePlan {
+    previous: String, // GUID of the plan to restore
+}
+
+impl HighPerformancePlan {
+    /// Switches to High Performance. `None` when it already is the active plan
+    /// or could not be activated (nothing is restored then).
+    pub fn activate() -> Option<Self> {
+        match switch_to_high_performance() {
+            Ok(Some(previous)) => {
+                log::info!("Switched to the High Performance power plan while rendering");
+                Some(Self { previous })
+            }
+            Ok(None) => None,
+            Err(e) => {
+                log::warn!("Failed to switch to the High Performance power plan: {}", e);
+                None
+            }
+        }
+    }
+}
+
+impl Drop for HighPerformancePlan {
+    fn drop(&mut self) {
+        match restore(&self.previous) {
+            Ok(()) => log::info!("Restored the previous power plan"),
+            Err(e) => log::warn!("Failed to restore the previous power plan: {}", e),
+        }
+    }
+}
+
+/// Runs `powercfg` with `args` and returns its output.
+#[cfg(windows)]
+fn powercfg(args: &[&str]) -> Result<String, String> {
+    use std::os::windows::process::CommandExt;
+
+    let output = std::process::Command::new("powercfg")
+        .args(args)
+        .creation_flags(0x08000000) // CREATE_NO_WINDOW
+        .output()
+        .map_err(|e| format!("Failed to run powercfg: {}", e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(format!(
+            "powercfg {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stdout).trim()
+        ))
+    }
+}
+
+/// Activates High Performance and returns the GUID of the plan it replaced,
+/// `None` if High Performance was already active.
+#[cfg(windows)]
+fn switch_to_high_performance() -> Result<Option<String>, String> {
+    // "Power Scheme GUID: 381b4222-f694-41f0-9685-ff5bb260df2e  (Balanced)"
+    let output = powercfg(&["/getactivescheme"])?;
+    let previous = output
+        .split("GUID:")
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .ok_or_else(|| format!("Unexpected powercfg output: {}", output.trim()))?
+        .to_string();
+    if previous.eq_ignore_ascii_case(HIGH_PERFORMANCE_GUID) {
+        return Ok(None);
+    }
+    // SCHEME_MIN (minimum power saving) is the alias of High Performance
+    powercfg(&["/setactive", "SCHEME_MIN"])?;
+    Ok(Some(previous))
+}
+
+#[cfg(windows)]
+fn restore(previous: &str) -> Result<(), String> {
+    powercfg(&["/setactive", previous]).map(|_| ())
+}
+
+#[cfg(not(windows))]
+fn switch_to_high_performance() -> Result<Option<String>, String> {
+    log::debug!("Power plans are only switched on Windows");
+    Ok(None)
+}
+
+#[cfg(not(windows))]
+fn restore(_previous: &str) -> Result<(), String> {
+    Ok(())
+}
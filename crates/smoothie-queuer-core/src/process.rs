@@ -0,0 +1,141 @@
+/// Stops the process tree rooted at `pid` until `resume` is called. The tree
+/// matters because smoothie-rs runs vspipe and ffmpeg as child processes.
+pub fn suspend(pid: u32) -> Result<(), String> {
+    set_suspended(pid, true).map_err(|e| format!("Failed to suspend process {}: {}", pid, e))
+}
+
+/// Lets a process tree stopped by `suspend` continue.
+pub fn resume(pid: u32) -> Result<(), String> {
+    set_suspended(pid, false).map_err(|e| format!("Failed to resume process {}: {}", pid, e))
+}
+
+/// Kills the process tree rooted at `pid`, so no vspipe or ffmpeg is left
+/// running (or suspended) after its backend is gone.
+pub fn kill_tree(pid: u32) -> Result<(), String> {
+    kill(pid).map_err(|e| format!("Failed to kill process {}: {}", pid, e))
+}
+
+/// Backends are spawned in their own process group (see `executor::run_command`),
+/// which is signalled as a whole.
+#[cfg(unix)]
+fn signal_group(pid: u32, signal: libc::c_int) -> std::io::Result<()> {
+    // A negative pid addresses the process group led by `pid`
+    if unsafe { libc::kill(-(pid as libc::pid_t), signal) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+#[cfg(unix)]
+fn set_suspended(pid: u32, suspended: bool) -> std::io::Result<()> {
+    signal_group(pid, if suspended { libc::SIGSTOP } else { libc::SIGCONT })
+}
+
+#[cfg(unix)]
+fn kill(pid: u32) -> std::io::Result<()> {
+    signal_group(pid, libc::SIGKILL)
+}
+
+/// `pid` followed by all of its descendants, from a Toolhelp snapshot.
+#[cfg(windows)]
+fn process_tree(pid: u32) -> std::io::Result<Vec<u32>> {
+    use std::mem;
+    use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+    use winapi::um::tlhelp32::{
+        CreateToolhelp32Snapshot, PROCESSENTRY32, Process32First, Process32Next, TH32CS_SNAPPROCESS,
+    };
+
+    let mut parents = Vec::new();
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+        if snapshot == INVALID_HANDLE_VALUE {
+            return Err(std::io::Error::last_os_error());
+        }
+        let mut process: PROCESSENTRY32 = mem::zeroed();
+        process.dwSize = mem::size_of::<PROCESSENTRY32>() as u32;
+        if Process32First(snapshot, &mut process) != 0 {
+            loop {
+                parents.push((process.th32ProcessID, process.th32ParentProcessID));
+                if Process32Next(snapshot, &mut process) == 0 {
+                    break;
+                }
+            }
+        }
+        CloseHandle(snapshot);
+    }
+
+    let mut tree = vec![pid];
+    let mut index = 0;
+    while index < tree.len() {
+        let parent = tree[index];
+        for &(child, child_parent) in &parents {
+            if child_parent == parent && !tree.contains(&child) {
+                tree.push(child);
+            }
+        }
+        index += 1;
+    }
+    Ok(tree)
+}
+
+/// Suspends or resumes every thread of `pid` and its descendants.
+#[cfg(windows)]
+fn set_suspended(pid: u32, suspended: bool) -> std::io::Result<()> {
+    use std::mem;
+    use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+    use winapi::um::processthreadsapi::{OpenThread, ResumeThread, SuspendThread};
+    use winapi::um::tlhelp32::{
+        CreateToolhelp32Snapshot, TH32CS_SNAPTHREAD, THREADENTRY32, Thread32First, Thread32Next,
+    };
+    use winapi::um::winnt::THREAD_SUSPEND_RESUME;
+
+    let tree = process_tree(pid)?;
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0);
+        if snapshot == INVALID_HANDLE_VALUE {
+            return Err(std::io::Error::last_os_error());
+        }
+        let mut thread: THREADENTRY32 = mem::zeroed();
+        thread.dwSize = mem::size_of::<THREADENTRY32>() as u32;
+        if Thread32First(snapshot, &mut thread) != 0 {
+            loop {
+                if tree.contains(&thread.th32OwnerProcessID) {
+                    let handle = OpenThread(THREAD_SUSPEND_RESUME, 0, thread.th32ThreadID);
+                    if !handle.is_null() {
+                        if suspended {
+                            SuspendThread(handle);
+                        } else {
+                            ResumeThread(handle);
+                        }
+                        CloseHandle(handle);
+                    }
+                }
+                if Thread32Next(snapshot, &mut thread) == 0 {
+                    break;
+                }
+            }
+        }
+        CloseHandle(snapshot);
+    }
+    Ok(())
+}
+
+/// Terminates `pid` and its descendants, children first.
+#[cfg(windows)]
+fn kill(pid: u32) -> std::io::Result<()> {
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::processthreadsapi::{OpenProcess, TerminateProcess};
+    use winapi::um::winnt::PROCESS_TERMINATE;
+
+    for process_id in process_tree(pid)?.into_iter().rev() {
+        unsafe {
+            let handle = OpenProcess(PROCESS_TERMINATE, 0, process_id);
+            if !handle.is_null() {
+                TerminateProcess(handle, 1);
+                CloseHandle(handle);
+            }
+        }
+    }
+    Ok(())
+}
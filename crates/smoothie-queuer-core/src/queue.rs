@@ -0,0 +1,1494 @@
+use crate::archive::{self, ArchiveStats};
+use crate::events::{EventKind, EventLog};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::Notify;
+
+/// Video containers ffmpeg (and so smoothie-rs) can read. Extensions allowed in
+/// the settings must come from this list.
+pub const INGESTIBLE_EXTENSIONS: [&str; 17] = [
+    "mp4", "mkv", "mov", "avi", "webm", "m4v", "ts", "mts", "m2ts", "flv", "3gp",
+    "wmv", "mpg", "mpeg", "ogv", "vob", "mxf",
+];
+
+/// Input file extensions accepted into the queue until the user edits the list.
+pub const DEFAULT_EXTENSIONS: [&str; 9] =
+    ["mp4", "mkv", "mov", "avi", "webm", "m4v", "ts", "flv", "3gp"];
+
+/// Whether `path` has one of the `allowed` extensions (lowercase, without dot).
+pub fn is_supported_video(path: &Path, allowed: &[String]) -> bool {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .is_some_and(|ext| allowed.contains(&ext.to_lowercase()))
+}
+
+/// Whether ffmpeg can read `path` at all, regardless of the allowed extensions.
+pub fn is_ingestible_video(path: &Path) -> bool {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .is_some_and(|ext| INGESTIBLE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Whether `path` is a VapourSynth script. smoothie-rs renders these through vspipe
+/// directly; ffmpeg cannot read them.
+pub fn is_vapoursynth_script(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("vpy"))
+}
+
+/// Normalizes an extension typed by the user (`.M4V` -> `m4v`) and checks that
+/// ffmpeg can ingest it.
+pub fn parse_extension(input: &str) -> Result<String, String> {
+    let extension = input.trim().trim_start_matches('.').to_lowercase();
+    if extension.is_empty() {
+        return Err("Enter an extension such as .m4v".to_string());
+    }
+    if !INGESTIBLE_EXTENSIONS.contains(&extension.as_str()) {
+        return Err(format!(".{} is not a video format smoothie-rs can read", extension));
+    }
+    Ok(extension)
+}
+
+/// Text files listing one input per line, imported as a whole queue.
+pub const PLAYLIST_EXTENSIONS: [&str; 3] = ["txt", "m3u", "m3u8"];
+
+pub fn is_playlist(path: &Path) -> bool {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .is_some_and(|ext| PLAYLIST_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Reads the entries of a .txt/.m3u playlist: one path or URL per line, `#` lines
+/// are comments (including m3u directives). Relative paths are resolved against
+/// the playlist's folder and `file://` URIs are turned back into paths. Entries are
+/// returned as-is; the caller decides which ones it can queue.
+pub fn read_playlist(path: &Path) -> Result<Vec<PathBuf>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read playlist {:?}: {}", path, e))?;
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+
+    Ok(contents
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .map(|line| line.trim().trim_matches('"'))
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| match line.strip_prefix("file://") {
+            // file:///C:/clips/a.mp4 on Windows, file:///home/me/a.mp4 elsewhere
+            Some(uri) => {
+                let uri = if cfg!(windows) { uri.trim_start_matches('/') } else { uri };
+                PathBuf::from(percent_decode(uri).unwrap_or_else(|| uri.to_string()))
+            }
+            None if line.contains("://") => PathBuf::from(line),
+            None => base_dir.join(line),
+        })
+        .collect())
+}
+
+/// Decodes `%XX` escapes in a URL component (file URIs in playlists, `smoothie-queue://` links).
+pub fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+                decoded.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 3;
+            }
+            b => {
+                decoded.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TaskStatus {
+    Pending,
+    Waiting,     // Input locked by another program or still being written
+    Downloading, // Fetching a URL source before rendering
+    Running,
+    Completed,
+    Failed(String),
+    Cancelled,
+}
+
+/// Container the finished output is remuxed into when it differs from the recipe's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutputContainer {
+    Mp4,
+    Mkv,
+    Mov,
+}
+
+impl OutputContainer {
+    pub const ALL: [OutputContainer; 3] = [
+        OutputContainer::Mp4,
+        OutputContainer::Mkv,
+        OutputContainer::Mov,
+    ];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputContainer::Mp4 => "mp4",
+            OutputContainer::Mkv => "mkv",
+            OutputContainer::Mov => "mov",
+        }
+    }
+}
+
+/// Optional ffmpeg step run on the input before smoothie-rs sees it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PreProcess {
+    Remux,        // Stream-copy into a fresh MKV to repair broken containers
+    ConvertToCfr, // Re-encode variable frame rate captures to constant frame rate
+}
+
+impl PreProcess {
+    pub const ALL: [PreProcess; 2] = [PreProcess::Remux, PreProcess::ConvertToCfr];
+}
+
+/// Program that renders a task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Backend {
+    #[default]
+    Smoothie,
+    Blur, // The original blur; uses a .cfg config instead of a recipe
+    Rife, // Plain frame interpolation with rife-ncnn-vulkan; ignores the recipe
+}
+
+impl Backend {
+    pub const ALL: [Backend; 3] = [Backend::Smoothie, Backend::Blur, Backend::Rife];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Backend::Smoothie => "smoothie-rs",
+            Backend::Blur => "blur",
+            Backend::Rife => "rife-ncnn-vulkan",
+        }
+    }
+}
+
+/// Colored tag a user can put on a task to group it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TaskLabel {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+}
+
+impl TaskLabel {
+    pub const ALL: [TaskLabel; 6] = [
+        TaskLabel::Red,
+        TaskLabel::Orange,
+        TaskLabel::Yellow,
+        TaskLabel::Green,
+        TaskLabel::Blue,
+        TaskLabel::Purple,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TaskLabel::Red => "Red",
+            TaskLabel::Orange => "Orange",
+            TaskLabel::Yellow => "Yellow",
+            TaskLabel::Green => "Green",
+            TaskLabel::Blue => "Blue",
+            TaskLabel::Purple => "Purple",
+        }
+    }
+
+    /// Display color as RGB, so frontends need not agree on a palette.
+    pub fn rgb(&self) -> [u8; 3] {
+        match self {
+            TaskLabel::Red => [230, 80, 80],
+            TaskLabel::Orange => [240, 150, 60],
+            TaskLabel::Yellow => [230, 210, 70],
+            TaskLabel::Green => [100, 200, 100],
+            TaskLabel::Blue => [90, 150, 240],
+            TaskLabel::Purple => [170, 110, 230],
+        }
+    }
+}
+
+/// Settings for `Backend::Rife` tasks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RifeOptions {
+    pub factor: u32,   // Output frame rate multiplier
+    pub model: String, // Model folder name, resolved next to rife-ncnn-vulkan
+}
+
+impl Default for RifeOptions {
+    fn default() -> Self {
+        Self {
+            factor: 2,
+            model: "rife-v4.6".to_string(),
+        }
+    }
+}
+
+/// Frame file extensions recognised in image sequence folders.
+pub const IMAGE_SEQUENCE_EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "exr"];
+
+/// Folder of numbered frames (`shot_0001.png`, `shot_0002.png`, ...) queued as one
+/// input. The worker assembles it into a lossless video before rendering.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageSequence {
+    pub pattern: String,   // ffmpeg input pattern inside the folder, e.g. `shot_%04d.png`
+    pub start_number: u64, // Number of the first frame
+    pub frame_count: usize,
+    pub frame_rate: u32,
+}
+
+impl ImageSequence {
+    /// Finds the numbered frames in `dir`. When several sequences share the folder,
+    /// the one with the most frames wins. Returns `None` without at least two frames.
+    pub fn detect(dir: &Path, frame_rate: u32) -> Option<Self> {
+        // (prefix, digit count, extension) -> frame numbers
+        let mut sequences: HashMap<(String, usize, String), Vec<u64>> = HashMap::new();
+        for entry in std::fs::read_dir(dir).ok()?.flatten() {
+            let path = entry.path();
+            let Some(extension) = path.extension().and_then(|ext| ext.to_str()) else {
+                continue;
+            };
+            if !IMAGE_SEQUENCE_EXTENSIONS.contains(&extension.to_lowercase().as_str()) {
+                continue;
+            }
+            let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let prefix = stem.trim_end_matches(|c: char| c.is_ascii_digit());
+            let digits = &stem[prefix.len()..];
+            let Ok(number) = digits.parse() else {
+                continue;
+            };
+            sequences
+                .entry((prefix.to_string(), digits.len(), extension.to_string()))
+                .or_default()
+                .push(number);
+        }
+
+        let ((prefix, width, extension), numbers) = sequences
+            .into_iter()
+            .filter(|(_, numbers)| numbers.len() >= 2)
+            .max_by_key(|(_, numbers)| numbers.len())?;
+        Some(Self {
+            pattern: format!("{}%0{}d.{}", prefix.replace('%', "%%"), width, extension),
+            start_number: *numbers.iter().min()?,
+            frame_count: numbers.len(),
+            frame_rate,
+        })
+    }
+
+    /// Playback length of the sequence at its frame rate.
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.frame_count as f64 / f64::from(self.frame_rate.max(1)))
+    }
+
+    pub fn is_exr(&self) -> bool {
+        self.pattern.to_lowercase().ends_with(".exr")
+    }
+}
+
+/// A recipe value replaced for one task, written `section;key=value` as
+/// smoothie-rs's `--override` takes it, e.g. `frame blending;fps=30`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecipeOverride {
+    pub section: String,
+    pub key: String,
+    pub value: String,
+}
+
+impl RecipeOverride {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid override {:?}: write it as section;key=value", text.trim());
+        let (section, setting) = text.split_once(';').ok_or_else(invalid)?;
+        let (key, value) = setting.split_once('=').ok_or_else(invalid)?;
+        let (section, key, value) = (section.trim(), key.trim(), value.trim());
+        // `|` separates overrides on the command line
+        if section.is_empty() || key.is_empty() || [section, key, value].iter().any(|part| part.contains('|')) {
+            return Err(invalid());
+        }
+        Ok(Self {
+            section: section.to_string(),
+            key: key.to_string(),
+            value: value.to_string(),
+        })
+    }
+
+    /// Parses one override per non-empty line.
+    pub fn parse_lines(text: &str) -> Result<Vec<Self>, String> {
+        text.lines().filter(|line| !line.trim().is_empty()).map(Self::parse).collect()
+    }
+}
+
+impl std::fmt::Display for RecipeOverride {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{};{}={}", self.section, self.key, self.value)
+    }
+}
+
+/// An environment variable set on a task's backend process, written
+/// `NAME=value`, e.g. `PYTHONPATH=D:\vs-plugins`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnvVar {
+    pub name: String,
+    pub value: String,
+}
+
+impl EnvVar {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid environment variable {:?}: write it as NAME=value", text.trim());
+        let (name, value) = text.split_once('=').ok_or_else(invalid)?;
+        let name = name.trim();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(invalid());
+        }
+        Ok(Self {
+            name: name.to_string(),
+            value: value.trim().to_string(),
+        })
+    }
+
+    /// Parses one variable per non-empty line.
+    pub fn parse_lines(text: &str) -> Result<Vec<Self>, String> {
+        text.lines().filter(|line| !line.trim().is_empty()).map(Self::parse).collect()
+    }
+}
+
+impl std::fmt::Display for EnvVar {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.name, self.value)
+    }
+}
+
+/// Parses a frame rate written as `59.94` or `60000/1001`. Empty text is `None`.
+pub fn parse_frame_rate(text: &str) -> Result<Option<f64>, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    let rate = match text.split_once('/') {
+        Some((num, den)) => match (num.trim().parse::<f64>(), den.trim().parse::<f64>()) {
+            (Ok(num), Ok(den)) => Some(num / den),
+            _ => None,
+        },
+        None => text.parse::<f64>().ok(),
+    };
+    rate.filter(|rate| rate.is_finite() && *rate > 0.0)
+        .map(Some)
+        .ok_or_else(|| format!("Invalid frame rate {:?}: write it as 59.94 or 60000/1001", text))
+}
+
+/// Remote video a task renders once yt-dlp has downloaded it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UrlSource {
+    pub url: String,
+    pub ytdlp_path: String,
+}
+
+/// Identifies a task; `QueueManager::next_task_id` hands them out.
+pub type TaskId = usize;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VideoTask {
+    pub id: TaskId,
+    pub input_path: PathBuf,
+    pub output_dir: PathBuf,
+    pub recipe_path: PathBuf,
+    pub status: TaskStatus,
+    pub output_container: Option<OutputContainer>, // None keeps the recipe's container
+    pub output_path: Option<PathBuf>,              // Set once the finished output is located
+    pub output_name: Option<String>,               // File stem for the output; None keeps the backend's
+    pub preview_path: Option<PathBuf>,             // Side-by-side input/output comparison clip
+    pub screenshot_path: Option<PathBuf>,          // Side-by-side input/output frame (PNG)
+    pub pre_process: Option<PreProcess>,
+    pub post_command: Option<String>, // Template run after a successful render
+    pub log: Vec<String>,             // Output captured while processing the task
+    pub source: Option<UrlSource>,    // Downloaded into `input_path` before rendering
+    pub progress: Option<f32>,        // 0.0 - 1.0 while running, if the backend reports it
+    pub backend: Backend,
+    pub rife: RifeOptions, // Only used by `Backend::Rife`
+    pub image_sequence: Option<ImageSequence>, // Set when `input_path` is a frame folder
+    pub note: String,              // Free-text note, e.g. "for montage v2"
+    pub priority: i32,             // Higher runs first; equal priorities keep queue order
+    pub extra_args: String,        // Appended to the backend's command, split on whitespace
+    #[serde(default)] // Missing from tasks saved before it existed
+    pub recipe_overrides: Vec<RecipeOverride>, // smoothie-rs only; recipe values replaced for this task
+    #[serde(default)] // Missing from tasks saved before it existed
+    pub env: Vec<EnvVar>,          // Set on the backend's process, after the ones from the settings
+    pub input_fps: Option<f64>,    // True frame rate of an input with wrong metadata; retimed before rendering
+    pub label: Option<TaskLabel>,
+    pub queued_at: SystemTime,
+    pub started_at: Option<SystemTime>,  // Left pending (downloading or running)
+    pub finished_at: Option<SystemTime>, // Completed, failed or cancelled
+    pub input_size: Option<u64>,           // Bytes, once the frontend has measured the input
+    pub input_duration: Option<Duration>,  // Once probed, or computed for image sequences
+    pub input_resolution: Option<(u32, u32)>, // Width and height, once probed
+    pub input_vfr: Option<bool>,           // Whether the input has a variable frame rate, once probed
+    pub energy_wh: Option<f64>,            // Estimated energy used rendering, where power can be read
+    pub warning: Option<String>,           // Set when a completed output looks off
+}
+
+impl VideoTask {
+    /// Creates a pending task with no optional processing steps.
+    pub fn new(id: TaskId, input_path: PathBuf, output_dir: PathBuf, recipe_path: PathBuf) -> Self {
+        Self {
+            id,
+            input_path,
+            output_dir,
+            recipe_path,
+            status: TaskStatus::Pending,
+            output_container: None,
+            output_path: None,
+            output_name: None,
+            preview_path: None,
+            screenshot_path: None,
+            pre_process: None,
+            post_command: None,
+            log: Vec::new(),
+            source: None,
+            progress: None,
+            backend: Backend::Smoothie,
+            rife: RifeOptions::default(),
+            image_sequence: None,
+            note: String::new(),
+            priority: 0,
+            extra_args: String::new(),
+            recipe_overrides: Vec::new(),
+            env: Vec::new(),
+            input_fps: None,
+            label: None,
+            queued_at: SystemTime::now(),
+            started_at: None,
+            finished_at: None,
+            input_size: None,
+            input_duration: None,
+            input_resolution: None,
+            input_vfr: None,
+            energy_wh: None,
+            warning: None,
+        }
+    }
+
+    /// A new pending task rendering the same input with the same options through
+    /// `recipe_path`. Its output is named `<input> ~ <recipe>` so it does not
+    /// replace this task's result.
+    pub fn duplicate_with_recipe(&self, id: TaskId, recipe_path: PathBuf) -> Self {
+        let input_stem = self.input_path.file_stem().unwrap_or_default().to_string_lossy();
+        let recipe_stem = recipe_path.file_stem().unwrap_or_default().to_string_lossy();
+        let output_name = format!("{} ~ {}", input_stem, recipe_stem);
+
+        let mut task = Self::new(id, self.input_path.clone(), self.output_dir.clone(), recipe_path);
+        task.output_name = Some(output_name);
+        task.output_container = self.output_container;
+        task.pre_process = self.pre_process;
+        task.post_command = self.post_command.clone();
+        task.source = self.source.clone();
+        task.backend = self.backend;
+        task.rife = self.rife.clone();
+        task.image_sequence = self.image_sequence.clone();
+        task.note = self.note.clone();
+        task.priority = self.priority;
+        task.extra_args = self.extra_args.clone();
+        task.recipe_overrides = self.recipe_overrides.clone();
+        task.env = self.env.clone();
+        task.input_fps = self.input_fps;
+        task.label = self.label;
+        task.input_size = self.input_size;
+        task.input_duration = self.input_duration;
+        task.input_resolution = self.input_resolution;
+        task.input_vfr = self.input_vfr;
+        task
+    }
+
+    /// Seconds of input rendered per second of wall time, for a completed task
+    /// whose length is known. What the history keeps to estimate render times.
+    pub fn render_speed(&self) -> Option<f64> {
+        if self.status != TaskStatus::Completed {
+            return None;
+        }
+        let input = self.input_duration.filter(|input| !input.is_zero())?;
+        let render = self.finished_at?.duration_since(self.started_at?).ok()?;
+        (!render.is_zero()).then(|| input.as_secs_f64() / render.as_secs_f64())
+    }
+
+    /// Resolution class of the input (`1080p`), once it is known. Portrait clips
+    /// are classed by their short side too.
+    pub fn resolution_class(&self) -> Option<&'static str> {
+        let (width, height) = self.input_resolution?;
+        Some(match width.min(height) {
+            0..=480 => "480p",
+            481..=720 => "720p",
+            721..=1080 => "1080p",
+            1081..=1440 => "1440p",
+            _ => "2160p",
+        })
+    }
+
+    /// Moves the task to `status`, stamping `started_at` when it leaves pending and
+    /// `finished_at` when it ends. Repeating the current status keeps the stamps.
+    fn set_status(&mut self, status: TaskStatus) {
+        if self.status == status {
+            return;
+        }
+        let now = SystemTime::now();
+        match status {
+            TaskStatus::Pending => {
+                self.started_at = None;
+                self.finished_at = None;
+                self.energy_wh = None;
+                self.warning = None;
+            }
+            // Time spent waiting for the input does not count as rendering
+            TaskStatus::Waiting => self.started_at = None,
+            TaskStatus::Downloading | TaskStatus::Running => {
+                if matches!(self.status, TaskStatus::Pending | TaskStatus::Waiting) {
+                    self.started_at = Some(now);
+                }
+            }
+            TaskStatus::Completed | TaskStatus::Failed(_) | TaskStatus::Cancelled => {
+                self.finished_at = Some(now);
+            }
+        }
+        self.status = status;
+    }
+}
+
+/// Pause, suspend and force stop requests. Clones share the same flags, so the
+/// worker can check them while rendering without locking the `QueueManager`.
+/// Each running task gets its own force stop flag through `for_task`.
+#[derive(Debug, Clone, Default)]
+pub struct StopFlags {
+    stop: Arc<AtomicBool>,       // Finish the running tasks, then stop the worker
+    force_stop: Arc<AtomicBool>, // Cancel the running task(s)
+    force_stop_notify: Arc<Notify>,
+    suspend: Arc<AtomicBool>, // Hold the running render where it is
+    suspend_notify: Arc<Notify>,
+}
+
+impl StopFlags {
+    /// Flags for one task: pausing and suspending are shared with `self`, but a
+    /// force stop only cancels that task.
+    pub fn for_task(&self) -> Self {
+        Self {
+            force_stop: Arc::default(),
+            force_stop_notify: Arc::default(),
+            ..self.clone()
+        }
+    }
+
+    pub fn request_stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Sets the force stop flag and wakes every `force_stopped` waiter.
+    pub fn request_force_stop(&self) {
+        self.force_stop.store(true, Ordering::SeqCst);
+        self.force_stop_notify.notify_waiters();
+    }
+
+    pub fn clear_stop_request(&self) {
+        self.stop.store(false, Ordering::Relaxed);
+    }
+
+    pub fn clear_force_stop(&self) {
+        self.force_stop.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_stop_requested(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    pub fn is_force_stop_requested(&self) -> bool {
+        self.force_stop.load(Ordering::SeqCst)
+    }
+
+    /// Pauses the running render immediately (and the next one, if set between
+    /// tasks) until `resume` is called.
+    pub fn request_suspend(&self) {
+        self.suspend.store(true, Ordering::SeqCst);
+        self.suspend_notify.notify_waiters();
+    }
+
+    pub fn resume(&self) {
+        self.suspend.store(false, Ordering::SeqCst);
+        self.suspend_notify.notify_waiters();
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.suspend.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once `is_suspended` no longer equals `current`.
+    pub async fn suspend_changed(&self, current: bool) {
+        loop {
+            let notified = self.suspend_notify.notified();
+            let mut notified = std::pin::pin!(notified);
+            notified.as_mut().enable();
+            if self.is_suspended() != current {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Resolves as soon as a force stop is requested (immediately if one already
+    /// is). Meant for `tokio::select!` next to the work it cancels.
+    pub async fn force_stopped(&self) {
+        loop {
+            // Register before checking the flag so a request in between is not missed
+            let notified = self.force_stop_notify.notified();
+            let mut notified = std::pin::pin!(notified);
+            notified.as_mut().enable();
+            if self.is_force_stop_requested() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Limits on how much one run of the worker starts (`SmoothieConfig::budget`);
+/// `None` leaves that limit off.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RunBudget {
+    pub max_tasks: Option<usize>,
+    pub max_duration: Option<Duration>, // No task is started once this long has passed
+}
+
+impl RunBudget {
+    pub fn is_limited(&self) -> bool {
+        self.max_tasks.is_some() || self.max_duration.is_some()
+    }
+}
+
+/// A smaller copy of each finished output for sharing (`SmoothieConfig::delivery`),
+/// scaled down and/or deinterlaced with ffmpeg next to the full-quality render.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeliveryOptions {
+    pub max_height: Option<u32>, // Taller outputs are scaled down to this height
+    pub deinterlace: bool,
+}
+
+impl DeliveryOptions {
+    pub fn is_enabled(&self) -> bool {
+        self.max_height.is_some() || self.deinterlace
+    }
+}
+
+/// What is left of the `RunBudget` of the current (or last) run, kept up to date
+/// by the worker in `QueueManager::budget`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BudgetStatus {
+    pub tasks_left: Option<usize>,
+    pub deadline: Option<Instant>,
+    pub reached: bool, // The worker stopped the queue because the budget ran out
+}
+
+impl BudgetStatus {
+    pub fn new(budget: &RunBudget) -> Self {
+        Self {
+            tasks_left: budget.max_tasks,
+            deadline: budget.max_duration.map(|limit| Instant::now() + limit),
+            reached: false,
+        }
+    }
+
+    pub fn time_left(&self) -> Option<Duration> {
+        self.deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Whether no further task may be started.
+    pub fn is_spent(&self) -> bool {
+        self.tasks_left == Some(0) || self.time_left().is_some_and(|left| left.is_zero())
+    }
+}
+
+/// The fields of a pending task the edit dialog changes in one go, see
+/// `QueueManager::edit_task`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskEdit {
+    pub input_path: PathBuf,
+    pub recipe_path: PathBuf,
+    pub output_dir: PathBuf,
+    pub output_name: Option<String>,
+    pub priority: i32,
+    pub extra_args: String,
+    pub recipe_overrides: Vec<RecipeOverride>,
+    pub env: Vec<EnvVar>,
+    pub input_fps: Option<f64>,
+}
+
+impl TaskEdit {
+    pub fn from_task(task: &VideoTask) -> Self {
+        Self {
+            input_path: task.input_path.clone(),
+            recipe_path: task.recipe_path.clone(),
+            output_dir: task.output_dir.clone(),
+            output_name: task.output_name.clone(),
+            priority: task.priority,
+            extra_args: task.extra_args.clone(),
+            recipe_overrides: task.recipe_overrides.clone(),
+            env: task.env.clone(),
+            input_fps: task.input_fps,
+        }
+    }
+}
+
+/// Changes applied to several pending tasks at once by
+/// `QueueManager::edit_tasks`; `None` leaves a field as it is on each task.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BatchEdit {
+    pub recipe_path: Option<PathBuf>,
+    pub output_dir: Option<PathBuf>,
+    pub priority: Option<i32>,
+    pub extra_args: Option<String>,
+}
+
+/// Task counts and input totals returned by `QueueManager::summary`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueueSummary {
+    pub pending: usize,
+    pub running: usize, // Waiting for the input, downloading or rendering
+    pub completed: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+    pub total_size: u64,              // Bytes of the inputs measured so far
+    pub total_duration: Duration,     // Length of the inputs probed so far
+    pub remaining_duration: Duration, // Length of the inputs still to render
+    pub remaining_unknown: usize,     // Tasks still to render whose length is unknown
+    pub energy_wh: f64,               // Estimated energy used by the tasks rendered so far
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QueueManager {
+    pub tasks: Vec<VideoTask>,
+    pub next_task_index: usize,
+    #[serde(skip)]
+    pub stop_flags: StopFlags,
+    #[serde(skip)]
+    pub task_stop_flags: HashMap<usize, StopFlags>, // Of the tasks the worker is running
+    #[serde(skip)]
+    pub budget: Option<BudgetStatus>, // Set by the worker when its run is limited
+    #[serde(skip)]
+    pub events: EventLog, // What happened to the queue this session
+    #[serde(skip)]
+    version: u64, // Bumped by every change, see `version`
+    #[serde(skip)]
+    activity: u64, // Bumped by progress and log output, see `activity`
+    #[serde(skip)]
+    archive: ArchiveStats, // Of the tasks moved to the archive
+    #[serde(skip)]
+    pub(crate) last_task_id: TaskId, // Highest ID handed out, saved by `session`
+}
+
+impl QueueManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counter bumped by every change to the queue, so frontends can keep what
+    /// they derive from it and rebuild only when it moves.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Marks the queue as changed; the methods below do when they change a
+    /// task, changes made through the public fields must.
+    pub fn touch(&mut self) {
+        self.version = self.version.wrapping_add(1);
+    }
+
+    /// Counter bumped by task progress and log lines. These arrive with every
+    /// line a backend prints, so they leave `version` alone; frontends showing
+    /// them read the tasks directly, or rebuild what depends on them when this moves.
+    pub fn activity(&self) -> u64 {
+        self.activity
+    }
+
+    /// Reads how many tasks the archive holds; `session::load` does, and
+    /// frontends again after pruning it.
+    pub fn open_archive(&mut self) {
+        self.touch();
+        match archive::scan() {
+            Ok(stats) => self.archive = stats,
+            Err(e) => log::error!("Failed to read the archive: {}", e),
+        }
+    }
+
+    /// Number of finished tasks moved to the archive.
+    pub fn archived_count(&self) -> usize {
+        self.archive.count
+    }
+
+    /// Highest ID of any task in the queue or the archive.
+    pub fn max_task_id(&self) -> TaskId {
+        self.tasks.iter().map(|t| t.id).max().unwrap_or(0).max(self.archive.max_id)
+    }
+
+    /// Reserves an ID for a task the caller builds and then queues with
+    /// `add_task`. IDs only grow: one removed from the queue is not handed out
+    /// again, in this run or the next.
+    pub fn next_task_id(&mut self) -> TaskId {
+        self.last_task_id = self.last_task_id.max(self.max_task_id()) + 1;
+        self.last_task_id
+    }
+
+    /// Queues a pending task for `input_path` under the next ID and returns the
+    /// ID. Callers holding the lock can set its options with `task_mut` before
+    /// the worker sees it.
+    pub fn add_new_task(&mut self, input_path: PathBuf, output_dir: PathBuf, recipe_path: PathBuf) -> TaskId {
+        let id = self.next_task_id();
+        self.add_task(VideoTask::new(id, input_path, output_dir, recipe_path));
+        id
+    }
+
+    /// The task with `task_id`, to change in place. Like changes through the
+    /// public fields, these must be followed by `touch`; right after
+    /// `add_new_task` under the same lock, its own `touch` covers them.
+    pub fn task_mut(&mut self, task_id: TaskId) -> Option<&mut VideoTask> {
+        self.tasks.iter_mut().find(|t| t.id == task_id)
+    }
+
+    /// Appends `tasks` to the archive file. On failure they stay where they are
+    /// and `false` is returned.
+    fn archive(&mut self, tasks: &[VideoTask]) -> bool {
+        match archive::append(tasks) {
+            Ok(()) => {
+                self.archive.count += tasks.len();
+                self.archive.max_id = tasks.iter().map(|t| t.id).fold(self.archive.max_id, usize::max);
+                true
+            }
+            Err(e) => {
+                log::error!("Failed to archive {} task(s): {}", tasks.len(), e);
+                false
+            }
+        }
+    }
+
+    /// Deletes the archive of finished tasks.
+    pub fn clear_history(&mut self) -> Result<(), String> {
+        self.touch();
+        archive::clear()?;
+        self.archive.count = 0;
+        Ok(())
+    }
+
+    pub fn add_task(&mut self, task: VideoTask) {
+        self.touch();
+        self.last_task_id = self.last_task_id.max(task.id);
+        self.events.record(EventKind::Added, Some(task.id), task.input_path.display().to_string());
+        self.tasks.push(task);
+    }
+
+    pub fn set_input_metadata(
+        &mut self,
+        task_id: usize,
+        size: Option<u64>,
+        duration: Option<Duration>,
+        resolution: Option<(u32, u32)>,
+        vfr: Option<bool>,
+    ) {
+        let Some(index) = self.tasks.iter().position(|t| t.id == task_id) else {
+            return;
+        };
+        self.touch();
+        let task = &mut self.tasks[index];
+        task.input_size = size.or(task.input_size);
+        task.input_duration = duration.or(task.input_duration);
+        task.input_resolution = resolution.or(task.input_resolution);
+        task.input_vfr = vfr.or(task.input_vfr);
+    }
+
+    /// Totals over all tasks, for a queue overview.
+    pub fn summary(&self) -> QueueSummary {
+        let mut summary = QueueSummary::default();
+        for task in &self.tasks {
+            match task.status {
+                TaskStatus::Pending => summary.pending += 1,
+                TaskStatus::Waiting | TaskStatus::Downloading | TaskStatus::Running => summary.running += 1,
+                TaskStatus::Completed => summary.completed += 1,
+                TaskStatus::Failed(_) => summary.failed += 1,
+                TaskStatus::Cancelled => summary.cancelled += 1,
+            }
+            summary.total_size += task.input_size.unwrap_or(0);
+            summary.energy_wh += task.energy_wh.unwrap_or(0.0);
+            let duration = task.input_duration.unwrap_or_default();
+            summary.total_duration += duration;
+            if !matches!(task.status, TaskStatus::Completed | TaskStatus::Failed(_) | TaskStatus::Cancelled) {
+                summary.remaining_duration += duration;
+                summary.remaining_unknown += usize::from(task.input_duration.is_none());
+            }
+        }
+        summary
+    }
+
+    /// The pending task to run next: the highest `priority`, then the earliest in
+    /// the queue.
+    pub fn next_pending_task(&mut self) -> Option<&mut VideoTask> {
+        let start = self.next_task_index.min(self.tasks.len());
+        let Some(first) = self.tasks[start..]
+            .iter()
+            .position(|t| t.status == TaskStatus::Pending)
+            .map(|offset| start + offset)
+        else {
+            self.next_task_index = self.tasks.len();
+            return None;
+        };
+        // Nothing before the first pending task is left to run
+        self.next_task_index = first;
+        let next = (first..self.tasks.len())
+            .filter(|&i| self.tasks[i].status == TaskStatus::Pending)
+            .max_by_key(|&i| (self.tasks[i].priority, Reverse(i)))?;
+        Some(&mut self.tasks[next])
+    }
+
+    pub fn mark_as_running(&mut self, task_id: usize) {
+        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == task_id && t.status != TaskStatus::Running) {
+            task.progress = None;
+            self.events.record(EventKind::Started, Some(task_id), task.backend.label());
+            task.set_status(TaskStatus::Running);
+            self.touch();
+        }
+    }
+
+    /// Counts as `activity`, not as a change to the queue.
+    pub fn set_progress(&mut self, task_id: usize, progress: f32) {
+        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == task_id && t.progress != Some(progress)) {
+            task.progress = Some(progress);
+            self.activity = self.activity.wrapping_add(1);
+        }
+    }
+
+    /// Spreads `watt_hours` used while rendering evenly over the running tasks.
+    pub fn add_energy(&mut self, watt_hours: f64) {
+        let running = self.tasks.iter().filter(|t| t.status == TaskStatus::Running).count();
+        if running == 0 {
+            return;
+        }
+        self.touch();
+        for task in self.tasks.iter_mut().filter(|t| t.status == TaskStatus::Running) {
+            *task.energy_wh.get_or_insert(0.0) += watt_hours / running as f64;
+        }
+    }
+
+    pub fn mark_as_waiting(&mut self, task_id: usize) {
+        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == task_id && t.status != TaskStatus::Waiting) {
+            task.set_status(TaskStatus::Waiting);
+            self.touch();
+        }
+    }
+
+    pub fn mark_as_downloading(&mut self, task_id: usize) {
+        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == task_id && t.status != TaskStatus::Downloading) {
+            task.set_status(TaskStatus::Downloading);
+            self.touch();
+        }
+    }
+
+    /// Points a URL task at its downloaded file; it then renders like any other task.
+    pub fn set_downloaded_input(&mut self, task_id: usize, input_path: PathBuf) {
+        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == task_id) {
+            task.input_path = input_path;
+            task.source = None;
+            self.touch();
+        }
+    }
+
+    pub fn mark_as_completed(&mut self, task_id: usize) {
+        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == task_id) {
+            if task.status != TaskStatus::Completed {
+                let output = task.output_path.as_ref().map(|path| path.display().to_string());
+                self.events.record(EventKind::Completed, Some(task_id), output.unwrap_or_default());
+            }
+            task.set_status(TaskStatus::Completed);
+            if self.next_task_index < self.tasks.len() 
+                && self.tasks[self.next_task_index].id == task_id {
+                self.next_task_index += 1;
+            }
+            self.touch();
+        }
+    }
+
+    pub fn mark_as_failed(&mut self, task_id: usize, err_msg: String) {
+        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == task_id) {
+            if !matches!(task.status, TaskStatus::Failed(_)) {
+                self.events.record(EventKind::Failed, Some(task_id), err_msg.clone());
+            }
+            task.set_status(TaskStatus::Failed(err_msg));
+            if self.next_task_index < self.tasks.len() 
+                && self.tasks[self.next_task_index].id == task_id {
+                self.next_task_index += 1;
+            }
+            self.touch();
+        }
+    }
+
+    pub fn mark_as_cancelled(&mut self, task_id: usize) {
+        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == task_id) {
+            if task.status != TaskStatus::Cancelled {
+                self.events.record(EventKind::Cancelled, Some(task_id), "");
+            }
+            task.set_status(TaskStatus::Cancelled);
+            if self.next_task_index < self.tasks.len() 
+                && self.tasks[self.next_task_index].id == task_id {
+                self.next_task_index += 1;
+            }
+            self.touch();
+        }
+    }
+
+    pub fn clear_all_tasks(&mut self) {
+        self.touch();
+        for task in &self.tasks {
+            self.events.record(EventKind::Removed, Some(task.id), "queue cleared");
+        }
+        self.tasks.clear();
+        self.next_task_index = 0;
+        self.stop_flags.clear_stop_request();
+        self.stop_flags.clear_force_stop();
+    }
+
+    /// Puts a failed task back into the queue as pending, dropping the results of
+    /// its failed attempt.
+    pub fn requeue_task(&mut self, task_id: usize) {
+        if let Some(task) = self
+            .tasks
+            .iter_mut()
+            .find(|t| t.id == task_id && matches!(t.status, TaskStatus::Failed(_)))
+        {
+            task.set_status(TaskStatus::Pending);
+            task.progress = None;
+            task.output_path = None;
+            task.preview_path = None;
+            task.screenshot_path = None;
+            self.next_task_index = 0;
+            self.events.record(EventKind::Requeued, Some(task_id), "retrying after a failure");
+            self.touch();
+        }
+    }
+
+    /// Puts a task that was cancelled to shut down (rather than by the user) back to
+    /// pending in its place in the queue, noting `reason` in its log.
+    pub fn requeue_cancelled(&mut self, task_id: usize, reason: &str) {
+        if let Some(task) = self
+            .tasks
+            .iter_mut()
+            .find(|t| t.id == task_id && t.status == TaskStatus::Cancelled)
+        {
+            task.set_status(TaskStatus::Pending);
+            task.progress = None;
+            task.log.push(reason.to_string());
+            self.next_task_index = 0;
+            self.events.record(EventKind::Requeued, Some(task_id), reason);
+            self.touch();
+        }
+    }
+
+    /// Puts tasks left waiting, downloading or running (by a previous launch that
+    /// ended mid-render) back to pending. Returns how many there were.
+    pub fn requeue_interrupted(&mut self) -> usize {
+        let mut count = 0;
+        for task in &mut self.tasks {
+            if matches!(task.status, TaskStatus::Waiting | TaskStatus::Downloading | TaskStatus::Running) {
+                task.set_status(TaskStatus::Pending);
+                task.progress = None;
+                task.log.push("Interrupted when the application closed; pending again".to_string());
+                count += 1;
+            }
+        }
+        self.next_task_index = 0;
+        if count > 0 {
+            self.touch();
+        }
+        count
+    }
+
+    /// Runs a finished (completed, failed or cancelled) task again: it goes back
+    /// to pending with a fresh log and timestamps, behind the tasks already
+    /// waiting. Returns whether the task was reset.
+    pub fn rerun_task(&mut self, task_id: usize) -> bool {
+        let Some(index) = self.tasks.iter().position(|t| {
+            t.id == task_id
+                && matches!(t.status, TaskStatus::Completed | TaskStatus::Failed(_) | TaskStatus::Cancelled)
+        }) else {
+            return false;
+        };
+        let mut task = self.tasks.remove(index);
+        task.set_status(TaskStatus::Pending);
+        task.queued_at = SystemTime::now();
+        task.log.clear();
+        task.progress = None;
+        task.output_path = None;
+        task.preview_path = None;
+        task.screenshot_path = None;
+        self.tasks.push(task);
+        self.next_task_index = 0;
+        self.events.record(EventKind::Requeued, Some(task_id), "re-run");
+        self.touch();
+        true
+    }
+
+    /// Cancels the task if it is waiting for its input, downloading or rendering. Unlike a force
+    /// stop from the controls, the worker then carries on with the queue.
+    /// Returns whether the task was running.
+    pub fn cancel_task(&mut self, task_id: usize) -> bool {
+        match self.task_stop_flags.get(&task_id) {
+            Some(flags) => {
+                flags.request_force_stop();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves a completed task from the queue into the archive.
+    pub fn archive_task(&mut self, task_id: usize) {
+        if let Some(index) = self
+            .tasks
+            .iter()
+            .position(|t| t.id == task_id && t.status == TaskStatus::Completed)
+        {
+            let task = self.tasks.remove(index);
+            if self.archive(std::slice::from_ref(&task)) {
+                self.next_task_index = 0;
+                self.touch();
+            } else {
+                self.tasks.insert(index, task);
+            }
+        }
+    }
+
+    /// Moves every task matching `finished` into the archive. Returns how many
+    /// were moved.
+    fn archive_where(&mut self, finished: impl Fn(&TaskStatus) -> bool) -> usize {
+        let moved: Vec<VideoTask> = self.tasks.iter().filter(|t| finished(&t.status)).cloned().collect();
+        if moved.is_empty() || !self.archive(&moved) {
+            return 0;
+        }
+        self.tasks.retain(|t| !finished(&t.status));
+        self.next_task_index = 0;
+        self.touch();
+        moved.len()
+    }
+
+    /// Moves every completed task into the archive. Returns how many were moved.
+    pub fn clear_completed(&mut self) -> usize {
+        self.archive_where(|status| *status == TaskStatus::Completed)
+    }
+
+    /// Moves every completed and cancelled task into the archive, keeping
+    /// pending, running and failed ones. Returns how many tasks were moved.
+    pub fn clear_finished(&mut self) -> usize {
+        self.archive_where(|status| matches!(status, TaskStatus::Completed | TaskStatus::Cancelled))
+    }
+
+    pub fn remove_task(&mut self, task_id: usize) {
+        let initial_len = self.tasks.len();
+        self.tasks.retain(|task| task.id != task_id);
+        if self.tasks.len() < initial_len {
+            self.next_task_index = 0;
+            self.events.record(EventKind::Removed, Some(task_id), "");
+            self.touch();
+        }
+    }
+
+    pub fn update_pending_recipes(&mut self, new_recipe_path: PathBuf) {
+        let mut changed = false;
+        for task in self.tasks.iter_mut().filter(|t| t.status == TaskStatus::Pending && t.recipe_path != new_recipe_path) {
+            task.recipe_path = new_recipe_path.clone();
+            changed = true;
+        }
+        if changed {
+            self.touch();
+        }
+    }
+
+    pub fn update_pending_containers(&mut self, container: Option<OutputContainer>) {
+        let mut changed = false;
+        for task in self.tasks.iter_mut().filter(|t| t.status == TaskStatus::Pending && t.output_container != container) {
+            task.output_container = container;
+            changed = true;
+        }
+        if changed {
+            self.touch();
+        }
+    }
+
+    /// Applies `edit` to a pending task. A new input drops the measured size,
+    /// length, resolution and frame rate kind. Returns whether the task was still pending.
+    pub fn edit_task(&mut self, task_id: usize, edit: TaskEdit) -> bool {
+        let Some(task) = self
+            .tasks
+            .iter_mut()
+            .find(|t| t.id == task_id && t.status == TaskStatus::Pending)
+        else {
+            return false;
+        };
+        if task.input_path != edit.input_path {
+            task.input_size = None;
+            task.input_duration = None;
+            task.input_resolution = None;
+            task.input_vfr = None;
+        }
+        task.input_path = edit.input_path;
+        task.recipe_path = edit.recipe_path;
+        task.output_dir = edit.output_dir;
+        task.output_name = edit.output_name;
+        task.priority = edit.priority;
+        task.extra_args = edit.extra_args;
+        task.recipe_overrides = edit.recipe_overrides;
+        task.env = edit.env;
+        task.input_fps = edit.input_fps;
+        self.events.record(EventKind::Edited, Some(task_id), "");
+        self.touch();
+        true
+    }
+
+    /// Applies `edit` to every task in `task_ids` that is still pending. Returns
+    /// how many tasks were changed.
+    pub fn edit_tasks(&mut self, task_ids: &HashSet<usize>, edit: &BatchEdit) -> usize {
+        let mut count = 0;
+        for task in self
+            .tasks
+            .iter_mut()
+            .filter(|t| task_ids.contains(&t.id) && t.status == TaskStatus::Pending)
+        {
+            if let Some(recipe_path) = &edit.recipe_path {
+                task.recipe_path = recipe_path.clone();
+            }
+            if let Some(output_dir) = &edit.output_dir {
+                task.output_dir = output_dir.clone();
+            }
+            if let Some(priority) = edit.priority {
+                task.priority = priority;
+            }
+            if let Some(extra_args) = &edit.extra_args {
+                task.extra_args = extra_args.clone();
+            }
+            self.events.record(EventKind::Edited, Some(task.id), "batch edit");
+            count += 1;
+        }
+        if count > 0 {
+            self.touch();
+        }
+        count
+    }
+
+    pub fn set_task_container(&mut self, task_id: usize, container: Option<OutputContainer>) {
+        if let Some(task) = self
+            .tasks
+            .iter_mut()
+            .find(|t| t.id == task_id && t.status == TaskStatus::Pending && t.output_container != container)
+        {
+            task.output_container = container;
+            self.touch();
+        }
+    }
+
+    pub fn set_task_pre_process(&mut self, task_id: usize, pre_process: Option<PreProcess>) {
+        if let Some(task) = self
+            .tasks
+            .iter_mut()
+            .find(|t| t.id == task_id && t.status == TaskStatus::Pending && t.pre_process != pre_process)
+        {
+            task.pre_process = pre_process;
+            self.touch();
+        }
+    }
+
+    /// Notes and labels can be changed in any state, unlike the render options.
+    pub fn set_task_note(&mut self, task_id: usize, note: String) {
+        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == task_id && t.note != note) {
+            task.note = note;
+            self.touch();
+        }
+    }
+
+    pub fn set_task_label(&mut self, task_id: usize, label: Option<TaskLabel>) {
+        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == task_id && t.label != label) {
+            task.label = label;
+            self.touch();
+        }
+    }
+
+    pub fn set_task_backend(&mut self, task_id: usize, backend: Backend) {
+        if let Some(task) = self
+            .tasks
+            .iter_mut()
+            .find(|t| t.id == task_id && t.status == TaskStatus::Pending && t.backend != backend)
+        {
+            task.backend = backend;
+            self.touch();
+        }
+    }
+
+    pub fn update_pending_backends(&mut self, backend: Backend) {
+        let mut changed = false;
+        for task in self.tasks.iter_mut().filter(|t| t.status == TaskStatus::Pending && t.backend != backend) {
+            task.backend = backend;
+            changed = true;
+        }
+        if changed {
+            self.touch();
+        }
+    }
+
+    pub fn update_pending_rife_options(&mut self, rife: &RifeOptions) {
+        let mut changed = false;
+        for task in self.tasks.iter_mut().filter(|t| t.status == TaskStatus::Pending && t.rife != *rife) {
+            task.rife = rife.clone();
+            changed = true;
+        }
+        if changed {
+            self.touch();
+        }
+    }
+
+    pub fn update_pending_pre_process(&mut self, pre_process: Option<PreProcess>) {
+        let mut changed = false;
+        for task in self.tasks.iter_mut().filter(|t| t.status == TaskStatus::Pending && t.pre_process != pre_process) {
+            task.pre_process = pre_process;
+            changed = true;
+        }
+        if changed {
+            self.touch();
+        }
+    }
+
+    pub fn update_pending_post_commands(&mut self, post_command: Option<String>) {
+        let mut changed = false;
+        for task in self.tasks.iter_mut().filter(|t| t.status == TaskStatus::Pending && t.post_command != post_command) {
+            task.post_command = post_command.clone();
+            changed = true;
+        }
+        if changed {
+            self.touch();
+        }
+    }
+
+    /// Counts as `activity`, not as a change to the queue.
+    pub fn append_log(&mut self, task_id: usize, line: String) {
+        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == task_id) {
+            task.log.push(line);
+            self.activity = self.activity.wrapping_add(1);
+        }
+    }
+
+    pub fn set_preview_path(&mut self, task_id: usize, preview_path: PathBuf) {
+        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == task_id && t.preview_path.as_ref() != Some(&preview_path)) {
+            task.preview_path = Some(preview_path);
+            self.touch();
+        }
+    }
+
+    pub fn set_screenshot_path(&mut self, task_id: usize, screenshot_path: PathBuf) {
+        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == task_id && t.screenshot_path.as_ref() != Some(&screenshot_path)) {
+            task.screenshot_path = Some(screenshot_path);
+            self.touch();
+        }
+    }
+
+    pub fn set_warning(&mut self, task_id: usize, warning: String) {
+        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == task_id && t.warning.as_ref() != Some(&warning)) {
+            task.warning = Some(warning);
+            self.touch();
+        }
+    }
+
+    pub fn set_output_path(&mut self, task_id: usize, output_path: PathBuf) {
+        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == task_id && t.output_path.as_ref() != Some(&output_path)) {
+            task.output_path = Some(output_path);
+            self.touch();
+        }
+    }
+
+    pub fn update_pending_output_dirs(&mut self, new_output_dir: PathBuf) {
+        let mut changed = false;
+        for task in self.tasks.iter_mut().filter(|t| t.status == TaskStatus::Pending && t.output_dir != new_output_dir) {
+            task.output_dir = new_output_dir.clone();
+            changed = true;
+        }
+        if changed {
+            self.touch();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_rates_parse_as_fractions_or_decimals() {
+        for (text, expected) in [
+            ("30000/1001", 30000.0 / 1001.0),
+            (" 60000 / 1001 ", 60000.0 / 1001.0),
+            ("59.94", 59.94),
+            ("24", 24.0),
+        ] {
+            assert_eq!(parse_frame_rate(text), Ok(Some(expected)), "{:?}", text);
+        }
+        assert_eq!(parse_frame_rate("  "), Ok(None));
+    }
+
+    #[test]
+    fn zero_and_garbage_frame_rates_are_rejected() {
+        for text in ["0", "0/1001", "30000/0", "-30", "fast", "30/", "/1001", "NaN", "inf"] {
+            assert!(parse_frame_rate(text).is_err(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn recipe_overrides_parse_section_key_and_value() {
+        assert_eq!(
+            RecipeOverride::parse(" frame blending ; fps = 30 "),
+            Ok(RecipeOverride {
+                section: "frame blending".to_string(),
+                key: "fps".to_string(),
+                value: "30".to_string(),
+            })
+        );
+        assert_eq!(RecipeOverride::parse("misc;stay on top=").map(|o| o.value), Ok(String::new()));
+        assert_eq!(RecipeOverride::parse_lines("a;b=1\n\n c;d=2 ").map(|o| o.len()), Ok(2));
+    }
+
+    #[test]
+    fn incomplete_recipe_overrides_are_rejected() {
+        for text in ["", "fps=30", "frame blending;fps", ";fps=30", "frame blending;=30", "a;b=1|c;d=2"] {
+            assert!(RecipeOverride::parse(text).is_err(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn env_vars_parse_name_and_value() {
+        assert_eq!(
+            EnvVar::parse(" PYTHONPATH = D:\\vs-plugins "),
+            Ok(EnvVar {
+                name: "PYTHONPATH".to_string(),
+                value: "D:\\vs-plugins".to_string(),
+            })
+        );
+        assert_eq!(EnvVar::parse("OPTS=a=b").map(|v| v.value), Ok("a=b".to_string()));
+        assert_eq!(EnvVar::parse("EMPTY=").map(|v| v.value), Ok(String::new()));
+    }
+
+    #[test]
+    fn env_vars_without_a_name_are_rejected() {
+        for text in ["", "PYTHONPATH", "=value", " =value", "TWO WORDS=value"] {
+            assert!(EnvVar::parse(text).is_err(), "{:?}", text);
+        }
+    }
+}
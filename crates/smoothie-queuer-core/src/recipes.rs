@@ -0,0 +1,425 @@
+use crate::queue::RecipeOverride;
+use crate::settings;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+
+/// Extensions of recipe sources, written in YAML or TOML and converted to the
+/// `.ini` smoothie-rs reads when their task renders.
+const SOURCE_EXTENSIONS: [&str; 3] = ["yaml", "yml", "toml"];
+
+/// A starting point for a new recipe: the values it changes in a copy of the
+/// default recipe, so the result keeps every other setting (and the comments) of
+/// the installed smoothie-rs version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecipeTemplate {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// File name suggested for the new recipe, without `.ini`.
+    pub file_stem: &'static str,
+    /// `(section, key, value)` set in the copy.
+    pub values: &'static [(&'static str, &'static str, &'static str)],
+}
+
+pub const RECIPE_TEMPLATES: [RecipeTemplate; 3] = [
+    RecipeTemplate {
+        name: "Smooth 60 → 240 fps",
+        description: "Interpolates 60 fps gameplay to a fluid 240 fps, without blur",
+        file_stem: "smooth-240",
+        values: &[
+            ("interpolation", "enabled", "yes"),
+            ("interpolation", "fps", "240"),
+            ("frame blending", "enabled", "no"),
+        ],
+    },
+    RecipeTemplate {
+        name: "Cinematic blur",
+        description: "Film-like 24 fps with half-frame (180° shutter) motion blur",
+        file_stem: "cinematic",
+        values: &[
+            ("interpolation", "enabled", "yes"),
+            ("interpolation", "fps", "1920"),
+            ("frame blending", "enabled", "yes"),
+            ("frame blending", "fps", "24"),
+            ("frame blending", "intensity", "0.5"),
+        ],
+    },
+    RecipeTemplate {
+        name: "Fast preview",
+        description: "Blends to 30 fps without interpolation, to check a clip quickly",
+        file_stem: "preview-fast",
+        values: &[
+            ("interpolation", "enabled", "no"),
+            ("frame blending", "enabled", "yes"),
+            ("frame blending", "fps", "30"),
+            ("frame blending", "intensity", "1.0"),
+        ],
+    },
+];
+
+/// Creates `<dir>/<name>.ini` from `template`, starting from the recipe at
+/// `default_recipe`. Never replaces an existing recipe. Returns the new path.
+pub fn create(template: &RecipeTemplate, default_recipe: &Path, dir: &Path, name: &str) -> Result<PathBuf, String> {
+    let path = new_recipe_path(dir, name)?;
+
+    let base = fs::read_to_string(default_recipe).map_err(|e| {
+        format!("Failed to read the default recipe {:?} to start from: {}", default_recipe, e)
+    })?;
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+    fs::write(&path, with_values(&base, template.values))
+        .map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
+    log::info!("Created recipe {:?} from template {:?}", path, template.name);
+    Ok(path)
+}
+
+/// Folder for recipe sources, listed next to the Smoothie recipes.
+pub fn sources_dir() -> PathBuf {
+    settings::data_dir().join("recipes")
+}
+
+/// Folder the `.ini` files converted from recipe sources are written to.
+fn generated_dir() -> PathBuf {
+    settings::data_dir().join("generated-recipes")
+}
+
+/// Whether `path` is a YAML or TOML recipe source rather than an `.ini` recipe.
+pub fn is_source(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| SOURCE_EXTENSIONS.iter().any(|source| ext.eq_ignore_ascii_case(source)))
+}
+
+/// Recipe sources in `sources_dir`, sorted.
+pub fn source_files() -> Vec<PathBuf> {
+    let mut sources: Vec<PathBuf> = fs::read_dir(sources_dir())
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.is_file() && is_source(path))
+                .collect()
+        })
+        .unwrap_or_default();
+    sources.sort();
+    sources
+}
+
+/// A value in a recipe source; sections hold only these.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum SourceValue {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl SourceValue {
+    fn to_ini(&self) -> String {
+        match self {
+            SourceValue::Bool(true) => "yes".to_string(),
+            SourceValue::Bool(false) => "no".to_string(),
+            SourceValue::Integer(value) => value.to_string(),
+            SourceValue::Float(value) => value.to_string(),
+            SourceValue::Text(value) => value.clone(),
+        }
+    }
+}
+
+/// Sections of a recipe source, each a map of keys to values (`None` for an
+/// empty YAML value).
+type Source = BTreeMap<String, BTreeMap<String, Option<SourceValue>>>;
+
+/// The `(section, key, value)` settings of the YAML or TOML recipe at `source`,
+/// with values as the `.ini` spells them.
+pub fn read_source(source: &Path) -> Result<Vec<(String, String, String)>, String> {
+    let text = fs::read_to_string(source)
+        .map_err(|e| format!("Failed to read recipe {:?}: {}", source, e))?;
+    let is_toml = source.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("toml"));
+    let parsed: Result<Source, String> = if is_toml {
+        toml::from_str(&text).map_err(|e| e.to_string())
+    } else {
+        serde_yaml::from_str(&text).map_err(|e| e.to_string())
+    };
+    let parsed = parsed.map_err(|e| {
+        format!(
+            "Invalid recipe {:?} (expected sections of `key: value` settings): {}",
+            source, e
+        )
+    })?;
+    Ok(parsed
+        .into_iter()
+        .flat_map(|(section, keys)| {
+            keys.into_iter().map(move |(key, value)| {
+                (section.clone(), key, value.as_ref().map(SourceValue::to_ini).unwrap_or_default())
+            })
+        })
+        .collect())
+}
+
+/// Converts the YAML or TOML recipe at `source` to an `.ini` in the generated
+/// folder: a copy of `default_recipe` with the source's values set, so a source
+/// only needs the settings it changes. Files are named after the source and a
+/// hash of their content, so tasks rendering the same source share one file
+/// and an edited source never changes the recipe of a task already rendering.
+pub fn convert(source: &Path, default_recipe: &Path) -> Result<PathBuf, String> {
+    let values = read_source(source)?;
+    let values: Vec<(&str, &str, &str)> = values
+        .iter()
+        .map(|(section, key, value)| (section.as_str(), key.as_str(), value.as_str()))
+        .collect();
+
+    // A source used without a default recipe still gives smoothie-rs its values
+    let base = fs::read_to_string(default_recipe).unwrap_or_else(|e| {
+        log::warn!("Converting {:?} without the default recipe {:?}: {}", source, default_recipe, e);
+        String::new()
+    });
+    let path = write_generated(source, &with_values(&base, &values))?;
+    log::debug!("Converted recipe {:?} to {:?}", source, path);
+    Ok(path)
+}
+
+/// A copy of the `.ini` recipe at `recipe` with `overrides` applied, for a
+/// smoothie-rs too old to take them as `--override`.
+pub fn with_overrides(recipe: &Path, overrides: &[RecipeOverride]) -> Result<PathBuf, String> {
+    let base = fs::read_to_string(recipe).map_err(|e| format!("Failed to read recipe {:?}: {}", recipe, e))?;
+    let values: Vec<(&str, &str, &str)> = overrides
+        .iter()
+        .map(|value| (value.section.as_str(), value.key.as_str(), value.value.as_str()))
+        .collect();
+    write_generated(recipe, &with_values(&base, &values))
+}
+
+/// Writes `ini`, generated from the recipe at `from`, to the generated folder as
+/// `<stem>-<hash>.ini`, unless an identical file is there already.
+fn write_generated(from: &Path, ini: &str) -> Result<PathBuf, String> {
+    let mut hasher = DefaultHasher::new();
+    ini.hash(&mut hasher);
+    let stem = from.file_stem().unwrap_or_default().to_string_lossy();
+    let dir = generated_dir();
+    let path = dir.join(format!("{}-{:016x}.ini", stem, hasher.finish()));
+    if !path.exists() {
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+        // Written aside and renamed, so a parallel task never reads half a file
+        let partial = path.with_extension("ini.part");
+        fs::write(&partial, ini)
+            .and_then(|()| fs::rename(&partial, &path))
+            .map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
+        log::info!("Generated recipe {:?} from {:?}", path, from);
+    }
+    Ok(path)
+}
+
+/// Folder `save` backs a recipe up to: `backups` next to it, e.g. `recipes/backups`.
+fn backups_dir(recipe: &Path) -> PathBuf {
+    recipe.parent().unwrap_or(Path::new(".")).join("backups")
+}
+
+/// Format of the time in backup names, which sorts oldest first.
+const BACKUP_TIME_FORMAT: &str = "%Y-%m-%d_%H-%M-%S";
+
+/// Writes `text` to the recipe at `recipe`, first copying the current file to
+/// `backups/<stem>.<time>.<ext>` so any earlier version can be restored.
+/// Returns the backup, `None` when the recipe did not exist yet.
+pub fn save(recipe: &Path, text: &str) -> Result<Option<PathBuf>, String> {
+    let backup = if recipe.is_file() {
+        let dir = backups_dir(recipe);
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+        let time = jiff::Zoned::now().strftime(BACKUP_TIME_FORMAT).to_string();
+        let stem = recipe.file_stem().unwrap_or_default().to_string_lossy();
+        let extension = recipe.extension().unwrap_or_default().to_string_lossy();
+        let backup = dir.join(format!("{}.{}.{}", stem, time, extension));
+        fs::copy(recipe, &backup).map_err(|e| format!("Failed to back up {:?}: {}", recipe, e))?;
+        log::info!("Backed up recipe {:?} to {:?}", recipe, backup);
+        Some(backup)
+    } else {
+        None
+    };
+    fs::write(recipe, text).map_err(|e| format!("Failed to write {:?}: {}", recipe, e))?;
+    Ok(backup)
+}
+
+/// Earlier versions of the recipe at `recipe` saved by `save`, newest first,
+/// with the time they were replaced (`2024-05-01 18:30:05`).
+pub fn backups(recipe: &Path) -> Vec<(PathBuf, String)> {
+    let stem = recipe.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let extension = recipe.extension().unwrap_or_default().to_string_lossy().to_string();
+    let Ok(entries) = fs::read_dir(backups_dir(recipe)) else {
+        return Vec::new();
+    };
+    let mut found: Vec<(PathBuf, String)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let time = name
+                .strip_prefix(&format!("{}.", stem))?
+                .strip_suffix(&format!(".{}", extension))?;
+            let time = jiff::civil::DateTime::strptime(BACKUP_TIME_FORMAT, time).ok()?;
+            Some((entry.path(), time.strftime("%Y-%m-%d %H:%M:%S").to_string()))
+        })
+        .collect();
+    found.sort_by(|a, b| b.1.cmp(&a.1));
+    found
+}
+
+/// Puts the backup at `backup` back in place of `recipe`. The version it
+/// replaces is backed up first, so a restore can be undone the same way.
+pub fn restore(recipe: &Path, backup: &Path) -> Result<(), String> {
+    let text = fs::read_to_string(backup).map_err(|e| format!("Failed to read {:?}: {}", backup, e))?;
+    save(recipe, &text)?;
+    log::info!("Restored recipe {:?} from {:?}", recipe, backup);
+    Ok(())
+}
+
+/// `<dir>/<name>.ini` for a new recipe; fails when `name` is not a plain file
+/// name or the recipe exists.
+fn new_recipe_path(dir: &Path, name: &str) -> Result<PathBuf, String> {
+    let name = name.trim().trim_end_matches(".ini");
+    let valid = !name.is_empty()
+        && name.chars().all(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.'))
+        && !name.starts_with('.');
+    if !valid {
+        return Err(format!(
+            "Invalid recipe name {:?}: use letters, numbers, spaces, '-', '_' or '.'",
+            name
+        ));
+    }
+    let path = dir.join(format!("{}.ini", name));
+    if path.exists() {
+        return Err(format!("{} already exists; pick another name", path.display()));
+    }
+    Ok(path)
+}
+
+/// The address of the plain text behind a page people share recipes on: a
+/// GitHub gist or file, or a Pastebin paste. Other URLs are used as they are.
+pub fn raw_url(url: &str) -> String {
+    let url = url.trim().trim_end_matches('/');
+    if let Some(path) = url.strip_prefix("https://gist.github.com/")
+        && !path.contains("/raw")
+    {
+        return format!("https://gist.github.com/{}/raw", path);
+    }
+    if let Some(path) = url.strip_prefix("https://github.com/")
+        && let Some((repository, file)) = path.split_once("/blob/")
+    {
+        return format!("https://raw.githubusercontent.com/{}/{}", repository, file);
+    }
+    if let Some(id) = url.strip_prefix("https://pastebin.com/")
+        && !id.contains('/')
+    {
+        return format!("https://pastebin.com/raw/{}", id);
+    }
+    url.to_string()
+}
+
+/// Name for a recipe imported from `url`: its file name when it ends in `.ini`.
+pub fn name_from_url(url: &str) -> Option<String> {
+    let file = url.trim().trim_end_matches('/').rsplit('/').next()?;
+    let file = file.split(['?', '#']).next()?;
+    let stem = file.strip_suffix(".ini")?;
+    let stem = crate::queue::percent_decode(stem).unwrap_or_else(|| stem.to_string());
+    (!stem.is_empty()).then_some(stem)
+}
+
+/// Downloads the recipe shared at `url` (see `raw_url`) and saves it as
+/// `<dir>/<name>.ini`. Fails when the download is not a recipe, e.g. a web page,
+/// and never replaces an existing recipe. Blocks on the network, so call it off
+/// the UI thread.
+pub fn import(url: &str, dir: &Path, name: &str) -> Result<PathBuf, String> {
+    let path = new_recipe_path(dir, name)?;
+    let url = raw_url(url);
+    if !url.starts_with("https://") && !url.starts_with("http://") {
+        return Err(format!("{:?} is not a web address", url));
+    }
+
+    let mut command = Command::new("curl");
+    command.args(["--silent", "--show-error", "--fail", "--location", "--max-time", "30", "--max-filesize", "1000000"]);
+    command.arg(&url);
+    #[cfg(windows)]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    let output = command.output().map_err(|e| format!("Failed to download the recipe: cannot run curl: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to download the recipe: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let text = String::from_utf8(output.stdout).map_err(|_| "The download is not a text file".to_string())?;
+
+    let lines: Vec<&str> = text.lines().map(str::trim).collect();
+    let has_section = lines.iter().any(|line| line.starts_with('[') && line.ends_with(']'));
+    let has_setting = lines.iter().any(|line| !line.starts_with('#') && !line.starts_with(';') && line.contains(':'));
+    let is_page = text.trim_start().starts_with('<');
+    if is_page || !has_section || !has_setting {
+        return Err(format!(
+            "{} is not a recipe (expected [sections] of `key: value` settings); link the raw file",
+            url
+        ));
+    }
+
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+    fs::write(&path, text).map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
+    log::info!("Imported recipe {:?} from {}", path, url);
+    Ok(path)
+}
+
+/// `base` (a recipe's text) with `values` set. Keys already present are changed
+/// in place, keeping order and comments; missing ones are added at the end of
+/// their section, or in a new section at the end.
+fn with_values(base: &str, values: &[(&str, &str, &str)]) -> String {
+    let newline = if base.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut done = vec![false; values.len()];
+    let mut lines: Vec<String> = Vec::new();
+    let mut section = String::new();
+
+    // Adds the values of `section` not seen in it, after its last non-blank line
+    let finish_section = |lines: &mut Vec<String>, section: &str, done: &mut [bool]| {
+        let at = lines.iter().rposition(|line| !line.trim().is_empty()).map_or(lines.len(), |i| i + 1);
+        let missing: Vec<String> = values
+            .iter()
+            .zip(done.iter_mut())
+            .filter(|((wanted, _, _), done)| !**done && wanted.eq_ignore_ascii_case(section))
+            .map(|((_, key, value), done)| {
+                *done = true;
+                format!("{}: {}", key, value)
+            })
+            .collect();
+        lines.splice(at..at, missing);
+    };
+
+    for line in base.lines() {
+        let trimmed = line.trim();
+        if let Some(name) = trimmed.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+            finish_section(&mut lines, &section, &mut done);
+            section = name.trim().to_string();
+            lines.push(line.to_string());
+            continue;
+        }
+        let is_comment = trimmed.starts_with('#') || trimmed.starts_with(';');
+        let replacement = line.split_once(':').filter(|_| !is_comment).and_then(|(key, _)| {
+            let index = values.iter().position(|(wanted_section, wanted_key, _)| {
+                wanted_section.eq_ignore_ascii_case(&section) && wanted_key.eq_ignore_ascii_case(key.trim())
+            })?;
+            done[index] = true;
+            Some(format!("{}: {}", key.trim_end(), values[index].2))
+        });
+        lines.push(replacement.unwrap_or_else(|| line.to_string()));
+    }
+    finish_section(&mut lines, &section, &mut done);
+
+    // Sections the default recipe does not have at all
+    for (index, (wanted_section, _, _)) in values.iter().enumerate() {
+        if done[index] {
+            continue;
+        }
+        lines.push(String::new());
+        lines.push(format!("[{}]", wanted_section));
+        finish_section(&mut lines, wanted_section, &mut done);
+    }
+    lines.join(newline) + newline
+}
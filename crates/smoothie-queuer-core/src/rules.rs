@@ -0,0 +1,177 @@
+use crate::queue::{TaskLabel, VideoTask};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+/// What an `InputRule` compares an input with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RuleKind {
+    /// Inputs inside the folder, at any depth.
+    #[default]
+    Folder,
+    /// Inputs with one of the extensions, e.g. `mov` or `mov, mxf`.
+    Extension,
+    /// File names matching a wildcard pattern (`*`, `?`), ignoring case, e.g. `*_ranked_*`.
+    Glob,
+    /// File names matching a regular expression, e.g. `(?i)^clip\d+`.
+    Regex,
+}
+
+impl RuleKind {
+    pub const ALL: [RuleKind; 4] = [RuleKind::Folder, RuleKind::Extension, RuleKind::Glob, RuleKind::Regex];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            RuleKind::Folder => "Files in folder",
+            RuleKind::Extension => "Files of type",
+            RuleKind::Glob => "Name matches",
+            RuleKind::Regex => "Name matches regex",
+        }
+    }
+}
+
+/// Options an `InputRule` sets on tasks whose input matches. A recipe given for
+/// the task itself (in the command, or the watch folder's own recipe) wins over
+/// the rule's.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputRule {
+    pub kind: RuleKind,
+    pub pattern: String,
+    pub recipe_path: Option<PathBuf>,
+    /// Folder inside the task's output folder its output goes to, e.g. `Valorant`.
+    pub output_subfolder: String,
+    pub priority: Option<i32>,
+    pub label: Option<TaskLabel>,
+}
+
+impl InputRule {
+    /// Why the rule cannot be used as entered, `None` when it can.
+    pub fn error(&self) -> Option<String> {
+        let pattern = self.pattern.trim();
+        if pattern.is_empty() {
+            return Some("Enter what to match".to_string());
+        }
+        if let Err(e) = self.pattern_regex() {
+            return Some(format!("Invalid pattern: {}", e));
+        }
+        let subfolder = Path::new(self.output_subfolder.trim());
+        if !subfolder.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Some("The output subfolder must be a plain relative path".to_string());
+        }
+        None
+    }
+
+    /// The glob or regex pattern compiled, `None` for the other kinds.
+    fn pattern_regex(&self) -> Result<Option<Regex>, regex::Error> {
+        match self.kind {
+            RuleKind::Glob => Regex::new(&glob_to_regex(self.pattern.trim())).map(Some),
+            RuleKind::Regex => Regex::new(self.pattern.trim()).map(Some),
+            RuleKind::Folder | RuleKind::Extension => Ok(None),
+        }
+    }
+
+    pub fn matches(&self, input: &Path) -> bool {
+        let pattern = self.pattern.trim();
+        if pattern.is_empty() {
+            return false;
+        }
+        match self.kind {
+            RuleKind::Folder => is_inside(input, Path::new(pattern)),
+            RuleKind::Extension => input.extension().is_some_and(|ext| {
+                let ext = ext.to_string_lossy();
+                pattern
+                    .split([',', ' '])
+                    .map(|wanted| wanted.trim().trim_start_matches('.'))
+                    .any(|wanted| !wanted.is_empty() && wanted.eq_ignore_ascii_case(&ext))
+            }),
+            RuleKind::Glob | RuleKind::Regex => {
+                let name = input.file_name().unwrap_or(input.as_os_str()).to_string_lossy();
+                match self.pattern_regex() {
+                    Ok(regex) => regex.is_some_and(|regex| regex.is_match(&name)),
+                    Err(e) => {
+                        log::warn!("Skipping rule {:?}: {}", pattern, e);
+                        false
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// What the rules matching one input set. Rules are checked in order and each
+/// option comes from the first matching rule that sets it, so a folder rule can
+/// pick the recipe while a later name rule adds a label.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RuleOutcome {
+    pub recipe_path: Option<PathBuf>,
+    pub output_subfolder: Option<PathBuf>,
+    pub priority: Option<i32>,
+    pub label: Option<TaskLabel>,
+}
+
+impl RuleOutcome {
+    /// Sets the options on a newly created `task`, leaving its recipe alone when
+    /// `keep_recipe` (one was given for it).
+    pub fn apply_to(&self, task: &mut VideoTask, keep_recipe: bool) {
+        if let Some(recipe) = &self.recipe_path
+            && !keep_recipe
+        {
+            task.recipe_path = recipe.clone();
+        }
+        if let Some(subfolder) = &self.output_subfolder {
+            task.output_dir = task.output_dir.join(subfolder);
+        }
+        if let Some(priority) = self.priority {
+            task.priority = priority;
+        }
+        if let Some(label) = self.label {
+            task.label = Some(label);
+        }
+    }
+}
+
+/// Evaluates `rules` against `input`.
+pub fn evaluate(rules: &[InputRule], input: &Path) -> RuleOutcome {
+    let mut outcome = RuleOutcome::default();
+    for rule in rules.iter().filter(|rule| rule.matches(input)) {
+        log::debug!("{:?} matches the rule for {:?}", input, rule.pattern);
+        let subfolder = Some(rule.output_subfolder.trim())
+            .filter(|subfolder| !subfolder.is_empty() && rule.error().is_none())
+            .map(PathBuf::from);
+        outcome.recipe_path = outcome.recipe_path.or_else(|| {
+            rule.recipe_path.clone().filter(|recipe| !recipe.as_os_str().is_empty())
+        });
+        outcome.output_subfolder = outcome.output_subfolder.or(subfolder);
+        outcome.priority = outcome.priority.or(rule.priority);
+        outcome.label = outcome.label.or(rule.label);
+    }
+    outcome
+}
+
+/// A case-insensitive regex matching the whole name for a wildcard pattern:
+/// `*` is any run of characters, `?` any single one.
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("(?i)^");
+    for c in glob.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+/// Whether `path` is in `folder` or one of its subfolders. Windows paths are
+/// compared ignoring case, as the file system does.
+fn is_inside(path: &Path, folder: &Path) -> bool {
+    let (path, folder) = (crate::paths::absolute(path), crate::paths::absolute(folder));
+    if cfg!(windows) {
+        let lower = |p: &Path| PathBuf::from(p.to_string_lossy().to_lowercase());
+        lower(&path).starts_with(lower(&folder))
+    } else {
+        path.starts_with(&folder)
+    }
+}
@@ -0,0 +1,254 @@
+use crate::recipes;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+/// Sections and keys of smoothie-rs recipes, as in the `recipe.ini` it ships.
+const KNOWN: &[(&str, &[&str])] = &[
+    ("interpolation", &["enabled", "masking", "fps", "speed", "tuning", "algorithm", "use gpu", "area"]),
+    ("frame blending", &["enabled", "fps", "intensity", "weighting", "bright blend"]),
+    ("flowblur", &["enabled", "masking", "amount", "do blending"]),
+    ("output", &["process", "enc args", "file format", "container"]),
+    ("preview window", &["enabled", "process", "output args"]),
+    ("artifact masking", &["enabled", "feathering", "folder path", "file name"]),
+    ("miscellaneous", &[
+        "play ding",
+        "always verbose",
+        "dedup threshold",
+        "global output folder",
+        "source indexing",
+        "ffmpeg options",
+        "ffplay options",
+    ]),
+    ("console", &["stay on top", "borderless", "position", "width", "height"]),
+    ("timescale", &["in", "out"]),
+    ("color grading", &["enabled", "brightness", "saturation", "contrast", "hue", "coring"]),
+    ("lut", &["enabled", "path", "opacity"]),
+    ("pre-interp", &["enabled", "masking", "factor", "model"]),
+];
+
+/// Sections older Smoothie versions used, with the one replacing them.
+const DEPRECATED_SECTIONS: &[(&str, &str)] = &[("misc", "miscellaneous"), ("blending", "frame blending")];
+
+/// Keys older Smoothie versions used, with what replaces them.
+const DEPRECATED_KEYS: &[(&str, &str, &str)] = &[
+    ("interpolation", "block size", "tuning"),
+    ("interpolation", "mask area", "area"),
+    ("output", "enc arg", "enc args"),
+];
+
+/// Values smoothie-rs accepts for yes/no settings.
+const BOOLEANS: [&str; 8] = ["yes", "no", "y", "n", "true", "false", "on", "off"];
+
+/// Sections and keys known to the installed smoothie-rs: the built-in list plus
+/// everything in its default recipe, so settings added by newer versions are
+/// not reported.
+#[derive(Debug, Clone, Default)]
+pub struct Schema {
+    sections: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl Schema {
+    /// The schema for the smoothie-rs whose default recipe is `default_recipe`.
+    pub fn for_default_recipe(default_recipe: &Path) -> Self {
+        let mut sections: BTreeMap<String, BTreeSet<String>> = KNOWN
+            .iter()
+            .map(|(section, keys)| (section.to_string(), keys.iter().map(|key| key.to_string()).collect()))
+            .collect();
+        if let Ok(text) = fs::read_to_string(default_recipe) {
+            for (_, line) in lines(&text) {
+                match line {
+                    Line::Section(section) => {
+                        sections.entry(section).or_default();
+                    }
+                    Line::Setting { section, key, .. } => {
+                        sections.entry(section).or_default().insert(key);
+                    }
+                    Line::Other(_) => {}
+                }
+            }
+        }
+        Self { sections }
+    }
+
+    fn has_section(&self, section: &str) -> bool {
+        self.sections.contains_key(section)
+    }
+
+    fn has_key(&self, section: &str, key: &str) -> bool {
+        self.sections.get(section).is_some_and(|keys| keys.contains(key))
+    }
+}
+
+/// A problem found in a recipe. smoothie-rs ignores unknown settings, so a typo
+/// silently renders with the default value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecipeWarning {
+    pub line: Option<usize>, // 1-based; None for YAML/TOML recipes
+    pub message: String,
+}
+
+impl std::fmt::Display for RecipeWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "Line {}: {}", line, self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// A line of a recipe that is not blank or a comment. Names are lowercase.
+enum Line {
+    Section(String),
+    Setting { section: String, key: String, value: String },
+    /// Not `key: value`, e.g. a stray word.
+    Other(String),
+}
+
+/// Checks the recipe at `path` (an `.ini` or a YAML/TOML source) against `schema`.
+/// An unreadable recipe or a blur `.cfg` gives no warnings.
+pub fn check(path: &Path, schema: &Schema) -> Vec<RecipeWarning> {
+    if recipes::is_source(path) {
+        return match recipes::read_source(path) {
+            Ok(values) => {
+                let lines = values.into_iter().map(|(section, key, value)| {
+                    let (section, key) = (section.to_lowercase(), key.to_lowercase());
+                    (None, Line::Setting { section, key, value })
+                });
+                check_lines(lines, schema)
+            }
+            Err(message) => vec![RecipeWarning { line: None, message }],
+        };
+    }
+    let is_ini = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("ini"));
+    match fs::read_to_string(path) {
+        Ok(text) if is_ini => {
+            check_lines(lines(&text).into_iter().map(|(line, parsed)| (Some(line), parsed)), schema)
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// The lines of an `.ini` recipe with their 1-based numbers.
+fn lines(text: &str) -> Vec<(usize, Line)> {
+    let mut section = String::new();
+    let mut lines = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        let parsed = if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            section = name.trim().to_lowercase();
+            Line::Section(section.clone())
+        } else if let Some((key, value)) = line.split_once(':') {
+            Line::Setting {
+                section: section.clone(),
+                key: key.trim().to_lowercase(),
+                value: value.trim().to_string(),
+            }
+        } else {
+            Line::Other(line.to_string())
+        };
+        lines.push((index + 1, parsed));
+    }
+    lines
+}
+
+fn check_lines(lines: impl IntoIterator<Item = (Option<usize>, Line)>, schema: &Schema) -> Vec<RecipeWarning> {
+    let mut warnings = Vec::new();
+    let mut warn = |line, message| warnings.push(RecipeWarning { line, message });
+    let unknown_section = |section: &str| {
+        let sections = schema.sections.keys().map(|name| format!("[{}]", name));
+        match DEPRECATED_SECTIONS.iter().find(|(old, _)| *old == section) {
+            Some((_, replacement)) => format!("[{}] is from an older Smoothie; use [{}]", section, replacement),
+            None => {
+                let name = format!("[{}]", section);
+                unknown(&format!("section {}", name), &name, sections)
+            }
+        }
+    };
+    let mut seen: BTreeMap<(String, String), Option<usize>> = BTreeMap::new();
+    for (line, parsed) in lines {
+        let (section, key, value) = match parsed {
+            Line::Section(section) => {
+                if !schema.has_section(&section) {
+                    warn(line, unknown_section(&section));
+                }
+                continue;
+            }
+            Line::Other(text) => {
+                warn(line, format!("{:?} is not a `key: value` setting", text));
+                continue;
+            }
+            Line::Setting { section, key, value } => (section, key, value),
+        };
+
+        if section.is_empty() {
+            warn(line, format!("`{}` is outside any [section]", key));
+            continue;
+        }
+        if !schema.has_section(&section) {
+            // An .ini reports it on the header; YAML/TOML on the section's first key
+            let first_in_section = seen.keys().all(|(seen_section, _)| *seen_section != section);
+            if line.is_none() && first_in_section {
+                warn(line, unknown_section(&section));
+            }
+        } else if !schema.has_key(&section, &key) {
+            let deprecated = DEPRECATED_KEYS
+                .iter()
+                .find(|(old_section, old_key, _)| *old_section == section && *old_key == key);
+            let message = match deprecated {
+                Some((_, _, replacement)) => {
+                    format!("`{}` in [{}] is from an older Smoothie; use `{}`", key, section, replacement)
+                }
+                None => {
+                    let keys = schema.sections[&section].iter().map(|known| format!("`{}`", known));
+                    let name = format!("`{}`", key);
+                    unknown(&format!("key {} in [{}]", name, section), &name, keys)
+                }
+            };
+            warn(line, message);
+        }
+
+        if key == "enabled" && !value.is_empty() && !BOOLEANS.contains(&value.to_lowercase().as_str()) {
+            warn(line, format!("`enabled` in [{}] should be yes or no, not {:?}", section, value));
+        }
+        if let Some(first) = seen.insert((section.clone(), key.clone()), line) {
+            let message = match first {
+                Some(first) => format!("`{}` in [{}] is already set on line {}", key, section, first),
+                None => format!("`{}` in [{}] is set twice", key, section),
+            };
+            warn(line, message);
+        }
+    }
+    warnings
+}
+
+/// "Unknown <what>", suggesting the closest of `candidates` to `name` when it is
+/// likely a typo.
+fn unknown(what: &str, name: &str, candidates: impl Iterator<Item = String>) -> String {
+    let closest = candidates
+        .map(|candidate| (edit_distance(name, &candidate), candidate))
+        .filter(|(distance, _)| *distance <= 2)
+        .min();
+    match closest {
+        Some((_, candidate)) => format!("Unknown {}; did you mean {}?", what, candidate),
+        None => format!("Unknown {}; smoothie-rs ignores it", what),
+    }
+}
+
+/// Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
@@ -0,0 +1,77 @@
+use crate::queue::{QueueManager, VideoTask};
+use crate::store::{self, sql_error};
+use serde::{Deserialize, Serialize};
+
+/// The part of the queue that is saved between launches.
+#[derive(Serialize, Deserialize)]
+struct SavedQueue {
+    tasks: Vec<VideoTask>,
+    next_task_index: usize,
+    last_task_id: usize,
+}
+
+/// The queue as `write` saves it; finished tasks cleared from it are in the
+/// `archive`. Frontends compare it with what they last wrote to skip unchanged
+/// saves.
+pub fn serialize(manager: &QueueManager) -> Result<String, String> {
+    serde_json::to_string(&serde_json::json!({
+        "tasks": manager.tasks,
+        "next_task_index": manager.next_task_index,
+        "last_task_id": manager.last_task_id,
+    }))
+    .map_err(|e| format!("Failed to serialize the queue: {}", e))
+}
+
+/// Replaces the queue saved in `store::database_path` in one transaction, so
+/// a crash mid-save keeps the previous queue.
+pub fn write(contents: &str) -> Result<(), String> {
+    let saved: SavedQueue =
+        serde_json::from_str(contents).map_err(|e| format!("Failed to read the serialized queue: {}", e))?;
+    let mut connection = store::open()?;
+    let transaction = connection.transaction().map_err(sql_error)?;
+    store::write_queue(&transaction, &saved.tasks, saved.next_task_index, saved.last_task_id)?;
+    transaction.commit().map_err(sql_error)
+}
+
+/// Loads the queue saved by the last launch, with its archive opened. Tasks
+/// that were downloading or rendering when it ended are pending again. An empty
+/// queue when there is no saved queue or it cannot be read.
+pub fn load() -> QueueManager {
+    let mut manager = read_saved().unwrap_or_else(|e| {
+        log::warn!("Starting with an empty queue: {}", e);
+        QueueManager::default()
+    });
+    let interrupted = manager.requeue_interrupted();
+    log::info!(
+        "Restored {} task(s) from {:?} ({} interrupted)",
+        manager.tasks.len(),
+        store::database_path(),
+        interrupted
+    );
+    manager.open_archive();
+    manager
+}
+
+fn read_saved() -> Result<QueueManager, String> {
+    let connection = store::open()?;
+    let mut statement = connection.prepare("SELECT id, task FROM queue ORDER BY position").map_err(sql_error)?;
+    let rows = statement
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))
+        .map_err(sql_error)?;
+    let mut manager = QueueManager::new();
+    for row in rows {
+        let (id, json) = row.map_err(sql_error)?;
+        match serde_json::from_str(&json) {
+            Ok(task) => manager.tasks.push(task),
+            Err(e) => log::warn!("Dropping unreadable saved task {}: {}", id, e),
+        }
+    }
+    let state = |key: &str| -> usize {
+        connection
+            .query_row("SELECT value FROM queue_state WHERE key = ?1", [key], |row| row.get::<_, i64>(0))
+            .map_or(0, |value| value as usize)
+    };
+    manager.next_task_index = state("next_task_index");
+    manager.last_task_id = state("last_task_id");
+    Ok(manager)
+}
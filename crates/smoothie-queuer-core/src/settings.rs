@@ -0,0 +1,393 @@
+use crate::config::SmoothieConfig;
+use crate::queue::{self, Backend, DeliveryOptions, EnvVar, RunBudget, DEFAULT_EXTENSIONS};
+use crate::rules::{self, InputRule, RuleOutcome};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+const SETTINGS_FILE: &str = "settings.json";
+
+/// Folder set with `set_data_dir`, replacing the per-user one.
+static DATA_DIR_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// User preferences that persist between launches.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    /// Command run after each successful task. Supports the tokens listed in
+    /// `POST_COMMAND_TOKENS`, which are substituted already quoted; empty
+    /// disables the hook.
+    pub post_command: String,
+    /// Serve the localhost control API (see `api::spawn`). Applied on restart.
+    pub api_enabled: bool,
+    pub api_port: u16,
+    /// Listen on all interfaces so the status page and queue can be read from
+    /// the local network; commands still only come from this machine.
+    pub api_lan: bool,
+    /// Publish queue events to an MQTT broker (`host` or `host:port`) under
+    /// `mqtt_topic`. An empty user name connects anonymously.
+    pub mqtt_enabled: bool,
+    pub mqtt_broker: String,
+    pub mqtt_topic: String,
+    pub mqtt_username: String,
+    pub mqtt_password: String,
+    /// Post failures and a summary when the queue finishes (plus each completed
+    /// task with `ntfy_each_task`) to `ntfy_server/ntfy_topic`. The token is only
+    /// needed for protected topics.
+    pub ntfy_enabled: bool,
+    pub ntfy_server: String,
+    pub ntfy_topic: String,
+    pub ntfy_token: String,
+    pub ntfy_each_task: bool,
+    /// Queue new videos appearing in `watch_folder` (see `watch::FolderWatcher`).
+    pub watch_enabled: bool,
+    pub watch_folder: Option<PathBuf>,
+    /// Recipe for watched files; `None` leaves it to `input_rules`, then the
+    /// recipe selected in the main window.
+    pub watch_recipe: Option<PathBuf>,
+    /// Recipe, output subfolder, priority and label for new tasks by where their
+    /// input comes from or its name, see `rules::evaluate`.
+    #[serde(alias = "recipe_rules")]
+    pub input_rules: Vec<InputRule>,
+    /// Download pasted/dropped URLs with yt-dlp and queue the result.
+    pub ytdlp_enabled: bool,
+    pub ytdlp_path: String,
+    /// Backend selected for new tasks at startup.
+    pub default_backend: Backend,
+    /// Renders taking longer are killed and marked failed; 0 disables the limit.
+    pub task_timeout_minutes: u32,
+    /// The queue pauses after starting this many tasks in one run; 0 disables the limit.
+    pub budget_tasks: usize,
+    /// No new task is started once a run has lasted this long; 0 disables the limit.
+    pub budget_hours: f32,
+    /// Input extensions (lowercase, without dot) accepted into the queue. Only
+    /// entries of `queue::INGESTIBLE_EXTENSIONS` are offered.
+    pub allowed_extensions: Vec<String>,
+    /// Frame rate image sequence folders are assembled at.
+    pub sequence_frame_rate: u32,
+    /// Queue `.vpy` VapourSynth scripts as inputs for smoothie-rs.
+    pub vpy_inputs_enabled: bool,
+    /// Render a short side-by-side input/output clip after each task.
+    pub comparison_preview: bool,
+    /// Save a PNG of one input frame next to the same output frame after each task.
+    pub comparison_screenshot: bool,
+    /// Height the delivery copy of each output is scaled down to; 0 keeps the
+    /// output's (and renders no copy unless deinterlacing).
+    pub delivery_height: u32,
+    /// Deinterlace the delivery copy of each output.
+    pub delivery_deinterlace: bool,
+    /// Give tasks that failed during a run a second attempt before it finishes.
+    pub requeue_failed: bool,
+    /// Tasks rendered at the same time; 1 renders them one after another.
+    pub max_parallel: usize,
+    /// Seconds between starting tasks that render side by side, so their
+    /// VapourSynth and ffprobe start-up does not hit the disk and GPU all at once.
+    pub start_stagger_seconds: u32,
+    /// Move tasks into the history as soon as they complete.
+    pub auto_clear_completed: bool,
+    /// Archived tasks are deleted from the history after this many days; 0
+    /// keeps them all.
+    pub history_retention_days: u32,
+    /// Start the worker whenever tasks are added while it is idle.
+    pub auto_start: bool,
+    /// Start the worker at launch when the restored queue has pending tasks.
+    pub resume_on_launch: bool,
+    /// Switch to the High Performance power plan while the worker runs (Windows).
+    pub high_performance_power: bool,
+    /// Render inputs whose file names tools may choke on from a renamed temp copy.
+    pub safe_input_names: bool,
+    /// Copy inputs on network drives to a local temp folder before rendering and
+    /// render outputs for network folders locally first.
+    pub stage_network_files: bool,
+    /// Convert inputs probed as variable frame rate to constant frame rate
+    /// before rendering, unless their task has a pre-processing step already.
+    pub convert_vfr_inputs: bool,
+    /// Spread tasks across the NVIDIA GPUs by load instead of using the first one.
+    pub spread_gpus: bool,
+    /// Run smoothie-rs with `--verbose`, for more useful task logs when a render fails.
+    pub verbose_smoothie: bool,
+    /// Run smoothie-rs in a console window of its own, to watch its native output.
+    pub show_smoothie_console: bool,
+    /// Environment variables set on every backend process, e.g. VapourSynth
+    /// plugin paths. A task's own variables take precedence.
+    pub env: Vec<EnvVar>,
+    /// Arguments appended to every backend command, e.g. `--tompv off`. A
+    /// task's own `extra_args` follow them.
+    pub extra_args: String,
+    /// Working directory of backend processes; `None` uses the folder of the
+    /// executable being run, whatever folder the app was started from.
+    pub working_dir: Option<PathBuf>,
+    /// Look for a newer release on GitHub at launch.
+    pub check_updates: bool,
+    /// Release the user chose to skip; the launch check stays quiet about it.
+    pub skipped_update: Option<String>,
+    /// Saved sets of defaults for new tasks, in the order they were created.
+    pub profiles: Vec<Profile>,
+    /// Name of the profile last switched to, applied again at launch.
+    pub active_profile: Option<String>,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            post_command: String::new(),
+            api_enabled: false,
+            api_port: 8765,
+            api_lan: false,
+            mqtt_enabled: false,
+            mqtt_broker: "localhost:1883".to_string(),
+            mqtt_topic: "smoothie-queuer".to_string(),
+            mqtt_username: String::new(),
+            mqtt_password: String::new(),
+            ntfy_enabled: false,
+            ntfy_server: "https://ntfy.sh".to_string(),
+            ntfy_topic: String::new(),
+            ntfy_token: String::new(),
+            ntfy_each_task: false,
+            watch_enabled: false,
+            watch_folder: None,
+            watch_recipe: None,
+            input_rules: Vec::new(),
+            ytdlp_enabled: false,
+            ytdlp_path: "yt-dlp".to_string(),
+            default_backend: Backend::default(),
+            task_timeout_minutes: 0,
+            budget_tasks: 0,
+            budget_hours: 0.0,
+            allowed_extensions: DEFAULT_EXTENSIONS.map(str::to_string).to_vec(),
+            sequence_frame_rate: 60,
+            vpy_inputs_enabled: false,
+            comparison_preview: false,
+            comparison_screenshot: false,
+            delivery_height: 0,
+            delivery_deinterlace: false,
+            check_updates: true,
+            skipped_update: None,
+            profiles: Vec::new(),
+            active_profile: None,
+            auto_clear_completed: false,
+            history_retention_days: 0,
+            auto_start: false,
+            resume_on_launch: false,
+            high_performance_power: false,
+            safe_input_names: false,
+            stage_network_files: false,
+            convert_vfr_inputs: false,
+            spread_gpus: false,
+            verbose_smoothie: false,
+            show_smoothie_console: false,
+            env: Vec::new(),
+            extra_args: String::new(),
+            working_dir: None,
+            requeue_failed: false,
+            max_parallel: 1,
+            start_stagger_seconds: 5,
+        }
+    }
+}
+
+/// Defaults for newly added tasks saved under a name ("Quick preview", "Final
+/// 4K"), so the whole app can be switched between them at once.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub name: String,
+    pub recipe_path: PathBuf,
+    /// `None` puts outputs next to their input.
+    pub output_folder: Option<PathBuf>,
+    /// Extra backend arguments, see `VideoTask::extra_args`.
+    pub extra_args: String,
+    /// Copied to `AppSettings::max_parallel` when switching to the profile.
+    pub max_parallel: usize,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            recipe_path: PathBuf::new(),
+            output_folder: None,
+            extra_args: String::new(),
+            max_parallel: 1,
+        }
+    }
+}
+
+/// Tokens substituted into `post_command`, with a short description for the UI.
+pub const POST_COMMAND_TOKENS: [(&str, &str); 4] = [
+    ("{input}", "input video path"),
+    ("{output}", "output video path"),
+    ("{outdir}", "output folder"),
+    ("{name}", "output file name"),
+];
+
+/// Folder holding the application's own files (settings, saved state).
+/// `%APPDATA%\smoothie-queuer` on Windows, `$XDG_CONFIG_HOME/smoothie-queuer`
+/// (or `~/.config/smoothie-queuer`) elsewhere, falling back to the working directory.
+/// A folder given to `set_data_dir` takes precedence.
+pub fn data_dir() -> PathBuf {
+    if let Some(dir) = DATA_DIR_OVERRIDE.get() {
+        return dir.clone();
+    }
+    let base = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    base.unwrap_or_else(|| PathBuf::from(".")).join("smoothie-queuer")
+}
+
+/// Keeps the application's files in `dir` for the rest of the run, e.g. for a
+/// service running under another account than the user who set it up. Only the
+/// first call has an effect.
+pub fn set_data_dir(dir: PathBuf) {
+    let _ = DATA_DIR_OVERRIDE.set(dir);
+}
+
+impl AppSettings {
+    /// Copies the settings that shape a worker run onto `config`.
+    pub fn apply_to(&self, config: &mut SmoothieConfig) {
+        config.task_timeout = self.task_timeout();
+        config.comparison_preview = self.comparison_preview;
+        config.comparison_screenshot = self.comparison_screenshot;
+        config.delivery = self.delivery();
+        config.retries = u32::from(self.requeue_failed);
+        config.max_parallel = self.max_parallel;
+        config.start_stagger = self.start_stagger();
+        config.budget = self.run_budget();
+        config.high_performance_power = self.high_performance_power;
+        config.safe_input_names = self.safe_input_names;
+        config.stage_network_files = self.stage_network_files;
+        config.convert_vfr = self.convert_vfr_inputs;
+        config.spread_gpus = self.spread_gpus;
+        config.verbose = self.verbose_smoothie;
+        config.show_console = self.show_smoothie_console;
+        config.env = self.env.clone();
+        config.extra_args = self.extra_args.clone();
+        config.working_dir = self.working_dir.clone();
+    }
+
+    pub fn profile(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Adds `profile`, replacing one with the same name, and makes it the active one.
+    pub fn save_profile(&mut self, profile: Profile) -> Result<(), String> {
+        let name = profile.name.trim();
+        if name.is_empty() {
+            return Err("Enter a name for the profile".to_string());
+        }
+        let profile = Profile { name: name.to_string(), ..profile };
+        self.active_profile = Some(profile.name.clone());
+        match self.profiles.iter_mut().find(|p| p.name.eq_ignore_ascii_case(&profile.name)) {
+            Some(existing) => *existing = profile,
+            None => self.profiles.push(profile),
+        }
+        Ok(())
+    }
+
+    pub fn delete_profile(&mut self, name: &str) {
+        self.profiles.retain(|p| !p.name.eq_ignore_ascii_case(name));
+        if self.active_profile.as_deref().is_some_and(|active| active.eq_ignore_ascii_case(name)) {
+            self.active_profile = None;
+        }
+    }
+
+    /// What `input_rules` set for a new task reading `input`.
+    pub fn rule_outcome(&self, input: &Path) -> RuleOutcome {
+        rules::evaluate(&self.input_rules, input)
+    }
+
+    /// Folder to watch, `None` when watching is disabled or no folder is set.
+    pub fn active_watch_folder(&self) -> Option<&PathBuf> {
+        self.watch_folder.as_ref().filter(|_| self.watch_enabled)
+    }
+
+    /// Limit for `SmoothieConfig::task_timeout`, `None` when disabled.
+    pub fn task_timeout(&self) -> Option<Duration> {
+        (self.task_timeout_minutes > 0)
+            .then(|| Duration::from_secs(u64::from(self.task_timeout_minutes) * 60))
+    }
+
+    /// Limits for `SmoothieConfig::budget`.
+    pub fn run_budget(&self) -> RunBudget {
+        RunBudget {
+            max_tasks: (self.budget_tasks > 0).then_some(self.budget_tasks),
+            max_duration: (self.budget_hours > 0.0)
+                .then(|| Duration::from_secs_f32(self.budget_hours * 3600.0)),
+        }
+    }
+
+    /// Options for `SmoothieConfig::delivery`.
+    pub fn delivery(&self) -> DeliveryOptions {
+        DeliveryOptions {
+            max_height: (self.delivery_height > 0).then_some(self.delivery_height),
+            deinterlace: self.delivery_deinterlace,
+        }
+    }
+
+    /// How long archived tasks are kept, for `archive::prune`.
+    pub fn history_retention(&self) -> Option<Duration> {
+        (self.history_retention_days > 0).then(|| Duration::from_secs(u64::from(self.history_retention_days) * 86_400))
+    }
+
+    /// Whether `path` may be queued as an input file.
+    pub fn accepts_input(&self, path: &Path) -> bool {
+        queue::is_supported_video(path, &self.allowed_extensions)
+            || (self.vpy_inputs_enabled && queue::is_vapoursynth_script(path))
+    }
+
+    /// Least time between starting two tasks that render side by side.
+    pub fn start_stagger(&self) -> Duration {
+        Duration::from_secs(u64::from(self.start_stagger_seconds))
+    }
+
+    /// Post-processing command for new tasks, `None` when the hook is disabled.
+    pub fn post_command_template(&self) -> Option<String> {
+        Some(self.post_command.trim().to_string()).filter(|c| !c.is_empty())
+    }
+
+    /// Loads settings from disk, falling back to defaults if missing or unreadable.
+    pub fn load() -> Self {
+        let path = data_dir().join(SETTINGS_FILE);
+        match fs::read_to_string(&path) {
+            Ok(contents) => match serde_json::from_str(&contents) {
+                Ok(settings) => {
+                    log::info!("Loaded settings from {:?}", path);
+                    settings
+                }
+                Err(e) => {
+                    log::warn!("Invalid settings file {:?}, using defaults: {}", path, e);
+                    Self::default()
+                }
+            },
+            Err(_) => {
+                log::debug!("No settings file at {:?}, using defaults", path);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let dir = data_dir();
+        if let Err(e) = fs::create_dir_all(&dir) {
+            log::error!("Failed to create settings folder {:?}: {}", dir, e);
+            return;
+        }
+        let path = dir.join(SETTINGS_FILE);
+        match serde_json::to_string_pretty(self) {
+            Ok(contents) => {
+                if let Err(e) = fs::write(&path, contents) {
+                    log::error!("Failed to write settings to {:?}: {}", path, e);
+                } else {
+                    log::info!("Saved settings to {:?}", path);
+                }
+            }
+            Err(e) => log::error!("Failed to serialize settings: {}", e),
+        }
+    }
+}
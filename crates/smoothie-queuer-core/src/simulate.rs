@@ -0,0 +1,78 @@
+use crate::executor::{self, RunContext};
+use crate::queue::VideoTask;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Settings of the demo mode, where tasks are "rendered" by waiting instead of
+/// running smoothie-rs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Simulation {
+    pub duration: Duration, // Time each task takes
+    pub failure_rate: f64,  // Chance (0.0 - 1.0) that a task fails
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Self {
+            duration: Duration::from_secs(5),
+            failure_rate: 0.2,
+        }
+    }
+}
+
+/// Cheap pseudo-random number in `0.0..1.0`; good enough to decide which
+/// simulated tasks fail without pulling in a RNG crate.
+fn roll(task_id: usize) -> f64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64);
+    // splitmix64
+    let mut x = nanos ^ (task_id as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^= x >> 31;
+    (x >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Pretends to render `task`, reporting progress like a real render. Tasks
+/// picked by `failure_rate` fail at the end.
+pub async fn run(task: &VideoTask, simulation: &Simulation, context: &RunContext<'_>) -> Result<(), String> {
+    log::info!("Task {}: simulating a {:?} render", task.id, simulation.duration);
+    context.log(
+        task.id,
+        format!(
+            "[simulation] rendering {} with {}",
+            task.input_path.display(),
+            task.recipe_path.display()
+        ),
+    );
+
+    let render = async {
+        // Time only advances while not suspended, like a stopped process
+        let mut rendered = Duration::ZERO;
+        let mut last_tick = Instant::now();
+        let mut ticks = tokio::time::interval(Duration::from_millis(100));
+        while rendered < simulation.duration {
+            ticks.tick().await;
+            let now = Instant::now();
+            if !context.stop_flags.is_suspended() {
+                rendered += now - last_tick;
+            }
+            last_tick = now;
+            let progress = rendered.as_secs_f32() / simulation.duration.as_secs_f32();
+            context.set_progress(task.id, progress.min(1.0));
+        }
+    };
+    tokio::select! {
+        () = render => {}
+        () = context.stop_flags.force_stopped() => {
+            return Err(executor::CANCELLED.to_string());
+        }
+    }
+
+    if roll(task.id) < simulation.failure_rate {
+        context.log(task.id, "[simulation] failed".to_string());
+        return Err(format!("Task {} failed: simulated failure", task.id));
+    }
+    context.set_progress(task.id, 1.0);
+    Ok(())
+}
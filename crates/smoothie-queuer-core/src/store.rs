@@ -0,0 +1,210 @@
+use crate::queue::{TaskStatus, VideoTask};
+use crate::settings;
+use rusqlite::{Connection, Transaction, params};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DATABASE_FILE: &str = "queue.db";
+/// Files the queue and history were kept in before the database; imported
+/// once, then renamed with `.migrated` appended.
+const LEGACY_QUEUE_FILE: &str = "queue.json";
+const LEGACY_HISTORY_FILE: &str = "history.jsonl";
+
+/// Bumped with every change to `SCHEMA`; kept in `PRAGMA user_version`.
+const SCHEMA_VERSION: i32 = 1;
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS queue (
+        position INTEGER PRIMARY KEY,
+        id INTEGER NOT NULL,
+        task TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS queue_state (
+        key TEXT PRIMARY KEY,
+        value INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS history (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        id INTEGER NOT NULL,
+        status TEXT NOT NULL,
+        input_path TEXT NOT NULL,
+        recipe_path TEXT NOT NULL,
+        started_at INTEGER,
+        finished_at INTEGER,
+        energy_wh REAL,
+        resolution TEXT,
+        speed REAL,
+        task TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS history_finished_at ON history (finished_at);
+";
+
+/// The database the queue, the archive of finished tasks and their statistics
+/// are kept in, in the data folder.
+pub fn database_path() -> PathBuf {
+    settings::data_dir().join(DATABASE_FILE)
+}
+
+/// Opens the database, creating it (and importing the JSON files of older
+/// versions) on first use. Connections are short-lived: each save or query
+/// opens its own, so the queue lock never waits on another thread's.
+pub fn open() -> Result<Connection, String> {
+    let dir = settings::data_dir();
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+    let path = database_path();
+    let mut connection = Connection::open(&path).map_err(|e| format!("Failed to open {:?}: {}", path, e))?;
+    connection.busy_timeout(Duration::from_secs(5)).map_err(sql_error)?;
+    // The write-ahead log makes every committed save survive a crash or power
+    // loss, without the cost of a full sync per save
+    connection
+        .execute_batch("PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL;")
+        .map_err(sql_error)?;
+
+    let version: i32 = connection.query_row("PRAGMA user_version", [], |row| row.get(0)).map_err(sql_error)?;
+    if version < SCHEMA_VERSION {
+        let transaction = connection.transaction().map_err(sql_error)?;
+        transaction.execute_batch(SCHEMA).map_err(sql_error)?;
+        let imported = import_legacy_files(&transaction)?;
+        transaction.pragma_update(None, "user_version", SCHEMA_VERSION).map_err(sql_error)?;
+        transaction.commit().map_err(sql_error)?;
+        imported.iter().for_each(|path| retire(path));
+    }
+    Ok(connection)
+}
+
+/// Seconds since the Unix epoch, as times are stored.
+pub fn timestamp(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs() as i64)
+}
+
+pub(crate) fn sql_error(e: rusqlite::Error) -> String {
+    format!("Queue database error: {}", e)
+}
+
+/// Adds `tasks` to the history table, with the columns the history and
+/// statistics queries (and the render speed estimates) use next to the full
+/// task.
+pub(crate) fn insert_history(transaction: &Transaction, tasks: &[VideoTask]) -> Result<(), String> {
+    let mut statement = transaction
+        .prepare_cached(
+            "INSERT INTO history
+                 (id, status, input_path, recipe_path, started_at, finished_at, energy_wh, resolution, speed, task)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        )
+        .map_err(sql_error)?;
+    for task in tasks {
+        let json = serde_json::to_string(task).map_err(|e| format!("Failed to serialize task {}: {}", task.id, e))?;
+        statement
+            .execute(params![
+                task.id as i64,
+                status_name(&task.status),
+                task.input_path.to_string_lossy(),
+                task.recipe_path.to_string_lossy(),
+                task.started_at.map(timestamp),
+                task.finished_at.map(timestamp),
+                task.energy_wh,
+                task.resolution_class(),
+                task.render_speed(),
+                json,
+            ])
+            .map_err(sql_error)?;
+    }
+    // Kept apart from the rows so pruning the history never frees an ID
+    if let Some(max_id) = tasks.iter().map(|task| task.id).max() {
+        transaction
+            .execute(
+                "INSERT INTO queue_state (key, value) VALUES ('max_archived_id', ?1)
+                 ON CONFLICT (key) DO UPDATE SET value = MAX(value, excluded.value)",
+                params![max_id as i64],
+            )
+            .map_err(sql_error)?;
+    }
+    Ok(())
+}
+
+/// Status as the `history.status` column holds it.
+pub(crate) fn status_name(status: &TaskStatus) -> &'static str {
+    match status {
+        TaskStatus::Pending => "pending",
+        TaskStatus::Waiting => "waiting",
+        TaskStatus::Downloading => "downloading",
+        TaskStatus::Running => "running",
+        TaskStatus::Completed => "completed",
+        TaskStatus::Failed(_) => "failed",
+        TaskStatus::Cancelled => "cancelled",
+    }
+}
+
+/// Imports `queue.json` (tasks and the history list it used to hold) and
+/// `history.jsonl` into the fresh tables. Returns the files to retire once
+/// the import is committed.
+fn import_legacy_files(transaction: &Transaction) -> Result<Vec<PathBuf>, String> {
+    let mut imported = Vec::new();
+    let dir = settings::data_dir();
+
+    let queue_path = dir.join(LEGACY_QUEUE_FILE);
+    if let Ok(contents) = fs::read_to_string(&queue_path) {
+        #[derive(serde::Deserialize)]
+        struct LegacyQueue {
+            tasks: Vec<VideoTask>,
+            #[serde(default)]
+            next_task_index: usize,
+            #[serde(default)]
+            history: Vec<VideoTask>,
+        }
+        match serde_json::from_str::<LegacyQueue>(&contents) {
+            Ok(legacy) => {
+                insert_history(transaction, &legacy.history)?;
+                write_queue(transaction, &legacy.tasks, legacy.next_task_index, 0)?;
+                log::info!("Imported {} task(s) from {:?}", legacy.tasks.len(), queue_path);
+            }
+            Err(e) => log::warn!("Ignoring unreadable saved queue {:?}: {}", queue_path, e),
+        }
+        imported.push(queue_path);
+    }
+
+    let history_path = dir.join(LEGACY_HISTORY_FILE);
+    if let Ok(contents) = fs::read_to_string(&history_path) {
+        let tasks: Vec<VideoTask> = contents.lines().filter_map(|line| serde_json::from_str(line).ok()).collect();
+        insert_history(transaction, &tasks)?;
+        log::info!("Imported {} archived task(s) from {:?}", tasks.len(), history_path);
+        imported.push(history_path);
+    }
+    Ok(imported)
+}
+
+/// Replaces the saved queue with `tasks`.
+pub(crate) fn write_queue(
+    transaction: &Transaction,
+    tasks: &[VideoTask],
+    next_task_index: usize,
+    last_task_id: usize,
+) -> Result<(), String> {
+    transaction.execute("DELETE FROM queue", []).map_err(sql_error)?;
+    let mut statement = transaction
+        .prepare_cached("INSERT INTO queue (position, id, task) VALUES (?1, ?2, ?3)")
+        .map_err(sql_error)?;
+    for (position, task) in tasks.iter().enumerate() {
+        let json = serde_json::to_string(task).map_err(|e| format!("Failed to serialize task {}: {}", task.id, e))?;
+        statement.execute(params![position as i64, task.id as i64, json]).map_err(sql_error)?;
+    }
+    let mut state = transaction
+        .prepare_cached(
+            "INSERT INTO queue_state (key, value) VALUES (?1, ?2)
+             ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+        )
+        .map_err(sql_error)?;
+    state.execute(params!["next_task_index", next_task_index as i64]).map_err(sql_error)?;
+    state.execute(params!["last_task_id", last_task_id as i64]).map_err(sql_error)?;
+    Ok(())
+}
+
+/// Keeps an imported file next to the database instead of deleting it, in
+/// case the import needs to be redone by hand.
+fn retire(path: &Path) {
+    let mut retired = path.as_os_str().to_owned();
+    retired.push(".migrated");
+    if let Err(e) = fs::rename(path, &retired) {
+        log::warn!("Failed to rename imported {:?}: {}", path, e);
+    }
+}
@@ -0,0 +1,169 @@
+use crate::queue::{
+    Backend, EnvVar, ImageSequence, OutputContainer, PreProcess, RecipeOverride, RifeOptions, TaskLabel,
+    TaskId, TaskStatus, UrlSource, VideoTask,
+};
+use crate::settings;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One task of a saved template: everything needed to queue it again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateTask {
+    pub input_path: PathBuf,
+    pub output_dir: PathBuf,
+    pub recipe_path: PathBuf,
+    #[serde(default)]
+    pub output_container: Option<OutputContainer>,
+    #[serde(default)]
+    pub output_name: Option<String>,
+    #[serde(default)]
+    pub pre_process: Option<PreProcess>,
+    #[serde(default)]
+    pub post_command: Option<String>,
+    #[serde(default)]
+    pub source: Option<UrlSource>,
+    #[serde(default)]
+    pub backend: Backend,
+    #[serde(default)]
+    pub rife: RifeOptions,
+    #[serde(default)]
+    pub image_sequence: Option<ImageSequence>,
+    #[serde(default)]
+    pub note: String,
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default)]
+    pub extra_args: String,
+    #[serde(default)]
+    pub recipe_overrides: Vec<RecipeOverride>,
+    #[serde(default)]
+    pub env: Vec<EnvVar>,
+    #[serde(default)]
+    pub input_fps: Option<f64>,
+    #[serde(default)]
+    pub label: Option<TaskLabel>,
+}
+
+impl TemplateTask {
+    fn from_task(task: &VideoTask) -> Self {
+        Self {
+            input_path: task.input_path.clone(),
+            output_dir: task.output_dir.clone(),
+            recipe_path: task.recipe_path.clone(),
+            output_container: task.output_container,
+            output_name: task.output_name.clone(),
+            pre_process: task.pre_process,
+            post_command: task.post_command.clone(),
+            source: task.source.clone(),
+            backend: task.backend,
+            rife: task.rife.clone(),
+            image_sequence: task.image_sequence.clone(),
+            note: task.note.clone(),
+            priority: task.priority,
+            extra_args: task.extra_args.clone(),
+            recipe_overrides: task.recipe_overrides.clone(),
+            env: task.env.clone(),
+            input_fps: task.input_fps,
+            label: task.label,
+        }
+    }
+
+    /// Creates a fresh pending task from the template entry.
+    pub fn into_task(self, id: TaskId) -> VideoTask {
+        let mut task = VideoTask::new(id, self.input_path, self.output_dir, self.recipe_path);
+        task.output_container = self.output_container;
+        task.output_name = self.output_name;
+        task.pre_process = self.pre_process;
+        task.post_command = self.post_command;
+        task.source = self.source;
+        task.backend = self.backend;
+        task.rife = self.rife;
+        task.image_sequence = self.image_sequence;
+        task.note = self.note;
+        task.priority = self.priority;
+        task.extra_args = self.extra_args;
+        task.recipe_overrides = self.recipe_overrides;
+        task.env = self.env;
+        task.input_fps = self.input_fps;
+        task.label = self.label;
+        task
+    }
+}
+
+/// Folder holding one `<name>.json` file per template.
+pub fn templates_dir() -> PathBuf {
+    settings::data_dir().join("templates")
+}
+
+fn template_path(name: &str) -> Result<PathBuf, String> {
+    let name = name.trim();
+    let valid = !name.is_empty()
+        && name.chars().all(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.'))
+        && !name.starts_with('.');
+    if !valid {
+        return Err(format!(
+            "Invalid template name {:?}: use letters, numbers, spaces, '-', '_' or '.'",
+            name
+        ));
+    }
+    Ok(templates_dir().join(format!("{}.json", name)))
+}
+
+/// Names of the saved templates, sorted.
+pub fn list() -> Vec<String> {
+    let Ok(entries) = fs::read_dir(templates_dir()) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| Some(path.file_stem()?.to_string_lossy().to_string()))
+        .collect();
+    names.sort_by_key(|name| name.to_lowercase());
+    names
+}
+
+/// Saves the pending tasks in `tasks` as template `name`, replacing any existing
+/// one. Returns how many tasks were saved.
+pub fn save(name: &str, tasks: &[VideoTask]) -> Result<usize, String> {
+    let path = template_path(name)?;
+    let entries: Vec<TemplateTask> = tasks
+        .iter()
+        .filter(|t| t.status == TaskStatus::Pending)
+        .map(TemplateTask::from_task)
+        .collect();
+    if entries.is_empty() {
+        return Err("There are no pending tasks to save".to_string());
+    }
+    write(&path, &entries)?;
+    log::info!("Saved template {:?} with {} task(s)", name, entries.len());
+    Ok(entries.len())
+}
+
+/// Saves `entries` as template `name`, replacing any existing one, e.g. from an
+/// imported settings file.
+pub fn save_entries(name: &str, entries: &[TemplateTask]) -> Result<(), String> {
+    write(&template_path(name)?, entries)
+}
+
+fn write(path: &Path, entries: &[TemplateTask]) -> Result<(), String> {
+    fs::create_dir_all(templates_dir())
+        .map_err(|e| format!("Failed to create templates folder: {}", e))?;
+    let contents = serde_json::to_string_pretty(entries)
+        .map_err(|e| format!("Failed to serialize template: {}", e))?;
+    fs::write(path, contents).map_err(|e| format!("Failed to write {:?}: {}", path, e))
+}
+
+pub fn load(name: &str) -> Result<Vec<TemplateTask>, String> {
+    let path = template_path(name)?;
+    let contents = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read template {:?}: {}", path, e))?;
+    serde_json::from_str(&contents).map_err(|e| format!("Invalid template {:?}: {}", path, e))
+}
+
+pub fn delete(name: &str) -> Result<(), String> {
+    let path = template_path(name)?;
+    fs::remove_file(&path).map_err(|e| format!("Failed to delete template {:?}: {}", path, e))
+}
@@ -0,0 +1,189 @@
+use crate::ffmpeg;
+use crate::queue::VideoTask;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Largest relative difference between the output frame rate and the one the
+/// recipe asks for.
+const FRAME_RATE_TOLERANCE: f64 = 0.05;
+/// Largest relative difference between the output frame count and the expected
+/// one. Looser, as the first and last frames and rounding shift it a little.
+const FRAME_COUNT_TOLERANCE: f64 = 0.1;
+
+/// Sections of a smoothie-rs recipe (`[frame blending]`) holding `key: value`
+/// lines, by lowercase name.
+type Recipe = HashMap<String, HashMap<String, String>>;
+
+fn read_recipe(path: &Path) -> Option<Recipe> {
+    let text = std::fs::read_to_string(path).ok()?;
+    let mut recipe = Recipe::new();
+    let mut section = String::new();
+    for line in text.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            section = name.trim().to_lowercase();
+        } else if let Some((key, value)) = line.split_once(':') {
+            recipe
+                .entry(section.clone())
+                .or_default()
+                .insert(key.trim().to_lowercase(), value.trim().to_lowercase());
+        }
+    }
+    Some(recipe)
+}
+
+fn value<'a>(recipe: &'a Recipe, section: &str, key: &str) -> Option<&'a str> {
+    recipe.get(section)?.get(key).map(String::as_str)
+}
+
+fn is_enabled(recipe: &Recipe, section: &str) -> bool {
+    matches!(value(recipe, section, "enabled"), Some("yes" | "y" | "true" | "on" | "1"))
+}
+
+/// A recipe frame rate: a fixed one (`60`) or a multiple of the input's (`4x`).
+fn frame_rate(recipe: &Recipe, section: &str, input_fps: f64) -> Option<f64> {
+    let fps = value(recipe, section, "fps")?;
+    match fps.strip_suffix('x') {
+        Some(factor) => Some(factor.trim().parse::<f64>().ok()? * input_fps),
+        None => fps.parse().ok(),
+    }
+}
+
+/// Frame rate the recipe renders `input_fps` footage at: the frame blending rate
+/// when blending is on, else the interpolation rate. `None` when the recipe does
+/// neither or cannot be read.
+pub fn expected_frame_rate(recipe_path: &Path, input_fps: f64) -> Option<f64> {
+    let recipe = read_recipe(recipe_path)?;
+    if is_enabled(&recipe, "frame blending") {
+        frame_rate(&recipe, "frame blending", input_fps)
+    } else if is_enabled(&recipe, "interpolation") {
+        frame_rate(&recipe, "interpolation", input_fps)
+    } else {
+        None
+    }
+}
+
+/// Whether the recipe speeds the video up or slows it down, changing its length.
+fn is_retimed(recipe_path: &Path) -> bool {
+    let Some(recipe) = read_recipe(recipe_path) else {
+        return false;
+    };
+    ["in", "out"].iter().any(|key| {
+        value(&recipe, "timescale", key)
+            .and_then(|scale| scale.parse::<f64>().ok())
+            .is_some_and(|scale| scale != 1.0)
+    })
+}
+
+fn differs(actual: f64, expected: f64, tolerance: f64) -> bool {
+    (actual - expected).abs() > expected * tolerance
+}
+
+/// Compares the frame rate and frame count of a finished `output` with what the
+/// task's recipe predicts for `input`, catching renders where interpolation or
+/// frame blending silently did not run. Returns a warning when they diverge.
+/// Anything that cannot be probed skips the check.
+pub async fn check_frame_rate(task: &VideoTask, input: &Path, output: &Path, ffmpeg_path: &Path) -> Option<String> {
+    let (num, den) = ffmpeg::probe_frame_rate(ffmpeg_path, input).await.ok()?;
+    let input_fps = num as f64 / den as f64;
+    let expected_fps = expected_frame_rate(&task.recipe_path, input_fps)?;
+
+    let (num, den) = ffmpeg::probe_frame_rate(ffmpeg_path, output).await.ok()?;
+    let output_fps = num as f64 / den as f64;
+    if differs(output_fps, expected_fps, FRAME_RATE_TOLERANCE) {
+        return Some(format!(
+            "Output is {:.2} fps but the recipe asks for {:.2} fps; interpolation or frame blending may not have run",
+            output_fps, expected_fps
+        ));
+    }
+
+    if is_retimed(&task.recipe_path) {
+        return None;
+    }
+    let duration = match task.input_duration {
+        Some(duration) => duration,
+        None => {
+            let (ffmpeg_path, input) = (ffmpeg_path.to_path_buf(), input.to_path_buf());
+            tokio::task::spawn_blocking(move || ffmpeg::probe_duration(&ffmpeg_path, &input))
+                .await
+                .ok()?
+                .ok()?
+        }
+    };
+    let expected_frames = duration.as_secs_f64() * expected_fps;
+    let frames = ffmpeg::probe_frame_count(ffmpeg_path, output).await.ok()?;
+    differs(frames as f64, expected_frames, FRAME_COUNT_TOLERANCE).then(|| {
+        format!(
+            "Output has {} frames but about {:.0} were expected at {:.2} fps; part of the video may be missing or duplicated",
+            frames, expected_frames, expected_fps
+        )
+    })
+}
+
+/// Checks that a finished `output` is a complete video file: not empty and,
+/// for MP4/MOV, not cut short and holding its index (`moov`), which is written
+/// last. Other containers must be readable to the end by ffprobe (skipped when
+/// ffprobe is missing). Returns why the file is invalid.
+pub async fn check_output_file(output: &Path, ffmpeg_path: &Path) -> Result<(), String> {
+    let size = std::fs::metadata(output)
+        .map_err(|e| format!("the file cannot be read: {}", e))?
+        .len();
+    if size == 0 {
+        return Err("the file is empty".to_string());
+    }
+    let extension = output
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if matches!(extension.as_str(), "mp4" | "mov" | "m4v") {
+        return check_mp4_boxes(output, size);
+    }
+    match ffmpeg::probe_readable(ffmpeg_path, output).await {
+        Ok(Some(reason)) => Err(reason),
+        Ok(None) => Ok(()),
+        Err(e) => {
+            log::debug!("Skipping the output check of {:?}: {}", output, e);
+            Ok(())
+        }
+    }
+}
+
+/// Walks the top-level boxes of an MP4/MOV file of `size` bytes.
+fn check_mp4_boxes(path: &Path, size: u64) -> Result<(), String> {
+    let mut file = File::open(path).map_err(|e| format!("the file cannot be read: {}", e))?;
+    let mut offset = 0;
+    let mut has_index = false;
+    while offset < size {
+        let mut header = [0u8; 8];
+        file.seek(SeekFrom::Start(offset))
+            .and_then(|_| file.read_exact(&mut header))
+            .map_err(|_| "the file is cut short".to_string())?;
+        let mut box_size = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as u64;
+        match box_size {
+            // 64-bit size after the type
+            1 => {
+                let mut large = [0u8; 8];
+                file.read_exact(&mut large)
+                    .map_err(|_| "the file is cut short".to_string())?;
+                box_size = u64::from_be_bytes(large);
+            }
+            // Runs to the end of the file
+            0 => box_size = size - offset,
+            _ => {}
+        }
+        if box_size < 8 {
+            return Err(format!("the file has a corrupt box at byte {}", offset));
+        }
+        if offset + box_size > size {
+            return Err("the file is cut short".to_string());
+        }
+        has_index |= &header[4..] == b"moov";
+        offset += box_size;
+    }
+    if has_index {
+        Ok(())
+    } else {
+        Err("the file was never finalized (no moov index)".to_string())
+    }
+}
@@ -0,0 +1,1474 @@
+use crate::config::SmoothieConfig;
+use crate::executor::{
+    self, BlurExecutor, Executor, RifeExecutor, RunContext, SimulatedExecutor, SmoothieExecutor,
+};
+use crate::energy::PowerMeter;
+use crate::events::EventKind;
+use crate::gpu::GpuScheduler;
+use crate::{download, ffmpeg, paths, power, recipes, verify};
+use crate::queue::{self, Backend, BudgetStatus, PreProcess, QueueManager, StopFlags, TaskStatus, VideoTask};
+use crate::settings::POST_COMMAND_TOKENS;
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+use std::sync::{Arc, Mutex, mpsc::Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::task::{JoinSet, LocalSet};
+
+/// Error returned by `process_next_task` when the user force stopped the task.
+const FORCE_STOPPED: &str = "Task force stopped by user";
+
+#[derive(Debug, Clone)]
+pub enum UpdateMessage {
+    TaskWaiting(usize),        // task_id; input locked or still being written
+    TaskDownloading(usize),    // task_id
+    TaskStarted(usize),        // task_id
+    TaskProgress(usize, f32),  // task_id, progress (0.0..=1.0)
+    TaskCompleted(usize),      // task_id
+    TaskFailed(usize, String), // task_id, error message
+    TaskCancelled(usize),      // task_id
+    BudgetReached(usize),      // The run budget paused the queue; pending tasks left
+    WorkerFinished,            // Worker has finished processing
+}
+
+/// Temporary folder for intermediate files of one task, removed when dropped so
+/// intermediates (pre-processed copies, extracted frames) never outlive the task,
+/// whether it succeeds or not.
+pub struct StagingDir(PathBuf);
+
+impl StagingDir {
+    /// `purpose` keeps folders of different steps of the same task apart.
+    pub fn create(task_id: usize, purpose: &str) -> Result<Self, String> {
+        let dir = std::env::temp_dir().join(format!(
+            "smoothie-queuer-{}-{}-{}",
+            std::process::id(),
+            task_id,
+            purpose
+        ));
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create temp folder {:?}: {}", dir, e))?;
+        Ok(Self(dir))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for StagingDir {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.0) {
+            log::warn!("Failed to remove temp folder {:?}: {}", self.0, e);
+        }
+    }
+}
+
+/// Whether the task's input has a variable frame rate: what the frontend probed
+/// when the task was added, or otherwise ffprobe's answer now. Inputs ffprobe
+/// cannot read count as constant; the render reports what is wrong with them.
+async fn input_is_vfr(task: &VideoTask, ffmpeg_path: &Path) -> bool {
+    if let Some(vfr) = task.input_vfr {
+        return vfr;
+    }
+    let (ffmpeg_path, input) = (ffmpeg_path.to_path_buf(), task.input_path.clone());
+    let probe = tokio::task::spawn_blocking(move || ffmpeg::probe_variable_frame_rate(&ffmpeg_path, &input)).await;
+    match probe.map_err(|e| e.to_string()).and_then(|result| result) {
+        Ok(vfr) => vfr,
+        Err(e) => {
+            log::debug!("Task {}: {}", task.id, e);
+            false
+        }
+    }
+}
+
+/// Runs the task's pre-processing step or frame rate fix, if any, and returns
+/// the path smoothie-rs should read from. The intermediate keeps the input's file stem so the output
+/// smoothie-rs names after it still matches the original clip. Image sequences
+/// are always assembled into a video first, named after their folder.
+async fn prepare_input(
+    task: &VideoTask,
+    ffmpeg_path: &Path,
+    staging: &mut Option<StagingDir>,
+    stop_flags: &StopFlags,
+) -> Result<PathBuf, String> {
+    if let Some(sequence) = &task.image_sequence {
+        let dir = staging.insert(StagingDir::create(task.id, "input")?);
+        let folder_name = task.input_path.file_name().unwrap_or_default();
+        let staged_input = dir.path().join(folder_name).with_extension("mkv");
+        log::info!(
+            "Task {}: assembling {} frames of {:?} into {:?}",
+            task.id,
+            sequence.frame_count,
+            task.input_path,
+            staged_input
+        );
+        let result = ffmpeg::assemble_sequence(
+            ffmpeg_path,
+            &task.input_path,
+            sequence,
+            &staged_input,
+            stop_flags,
+        )
+        .await;
+        return match result {
+            Ok(()) => Ok(staged_input),
+            Err(e) if e == ffmpeg::CANCELLED => Err(FORCE_STOPPED.to_string()),
+            Err(e) => Err(format!("Task {} image sequence assembly failed: {}", task.id, e)),
+        };
+    }
+
+    if task.pre_process.is_none() && task.input_fps.is_none() {
+        return Ok(task.input_path.clone());
+    }
+    if queue::is_vapoursynth_script(&task.input_path) {
+        log::info!("Task {}: skipping pre-processing of VapourSynth script", task.id);
+        return Ok(task.input_path.clone());
+    }
+
+    let dir = staging.insert(StagingDir::create(task.id, "input")?);
+    let file_stem = task.input_path.file_stem().unwrap_or_default();
+    let staged_input = dir.path().join(file_stem).with_extension("mkv");
+
+    // The retimed intermediate is lossless and constant frame rate already, so
+    // it stands in for the pre-processing step
+    if let Some(fps) = task.input_fps {
+        log::info!("Task {}: retiming {:?} to {} fps into {:?}", task.id, task.input_path, fps, staged_input);
+        return match ffmpeg::retime(ffmpeg_path, &task.input_path, fps, &staged_input, stop_flags).await {
+            Ok(()) => Ok(staged_input),
+            Err(e) if e == ffmpeg::CANCELLED => Err(FORCE_STOPPED.to_string()),
+            Err(e) => Err(format!("Task {} frame rate fix failed: {}", task.id, e)),
+        };
+    }
+    let Some(pre_process) = task.pre_process else {
+        return Ok(task.input_path.clone());
+    };
+
+    log::info!(
+        "Task {}: pre-processing ({:?}) {:?} into {:?}",
+        task.id,
+        pre_process,
+        task.input_path,
+        staged_input
+    );
+    let result = match pre_process {
+        PreProcess::Remux => {
+            ffmpeg::remux(ffmpeg_path, &task.input_path, &staged_input, stop_flags).await
+        }
+        PreProcess::ConvertToCfr => {
+            ffmpeg::convert_to_cfr(ffmpeg_path, &task.input_path, &staged_input, stop_flags)
+                .await
+        }
+    };
+
+    match result {
+        Ok(()) => Ok(staged_input),
+        Err(e) if e == ffmpeg::CANCELLED => Err(FORCE_STOPPED.to_string()),
+        Err(e) => Err(format!("Task {} pre-processing failed: {}", task.id, e)),
+    }
+}
+
+/// Copies `source` to `target` on a blocking thread, as copies of long recordings
+/// (or over the network) take a while. With `link`, a hard link is tried first.
+async fn copy_file(source: &Path, target: &Path, link: bool) -> Result<(), String> {
+    let (source, target) = (source.to_path_buf(), target.to_path_buf());
+    tokio::task::spawn_blocking(move || {
+        if link && std::fs::hard_link(&source, &target).is_ok() {
+            return Ok(());
+        }
+        std::fs::copy(&source, &target).map(|_| ())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Copies `input` from a network drive into a local staging folder, so the backend
+/// does not read it over the network. Returns the local copy.
+async fn stage_input(
+    task: &VideoTask,
+    input: PathBuf,
+    staging: &mut Option<StagingDir>,
+) -> Result<PathBuf, String> {
+    // Scripts import relative to their folder
+    if queue::is_vapoursynth_script(&input) || input.is_dir() {
+        return Ok(input);
+    }
+    let dir = staging.insert(StagingDir::create(task.id, "input")?);
+    let copy = dir.path().join(input.file_name().unwrap_or_default());
+    log::info!("Task {}: copying {:?} from the network to {:?}", task.id, input, copy);
+    copy_file(&input, &copy, false)
+        .await
+        .map_err(|e| format!("Task {} failed to copy {:?} to a temp folder: {}", task.id, input, e))?;
+    Ok(copy)
+}
+
+/// Gives `input` a file name every tool can handle when it has one they may not
+/// (see `paths::has_unsafe_name`). An intermediate in `staging` is renamed in
+/// place; the original clip is hard linked, or copied when that fails, into a
+/// new staging folder. Returns the path to render from.
+async fn safe_input(
+    task: &VideoTask,
+    input: PathBuf,
+    staging: &mut Option<StagingDir>,
+) -> Result<PathBuf, String> {
+    // Scripts import relative to their folder and sequences are folders
+    if !paths::has_unsafe_name(&input)
+        || queue::is_vapoursynth_script(&input)
+        || input.is_dir()
+    {
+        return Ok(input);
+    }
+    let file_name = paths::safe_file_name(input.file_name().unwrap_or_default());
+
+    if let Some(dir) = staging.as_ref() {
+        let renamed = dir.path().join(file_name);
+        std::fs::rename(&input, &renamed)
+            .map_err(|e| format!("Task {} failed to rename {:?}: {}", task.id, input, e))?;
+        return Ok(renamed);
+    }
+
+    let dir = staging.insert(StagingDir::create(task.id, "input")?);
+    let copy = dir.path().join(file_name);
+    log::info!("Task {}: rendering {:?} from {:?}", task.id, input, copy);
+    copy_file(&input, &copy, true)
+        .await
+        .map_err(|e| format!("Task {} failed to copy {:?} to a temp folder: {}", task.id, input, e))?;
+    Ok(copy)
+}
+
+/// Renames an output named after the safe copy of the input back after the
+/// original clip (`clip_.mp4` -> `clip😀.mp4`). Keeps the safe name if the
+/// original one is taken or cannot be used.
+fn restore_output_name(task: &VideoTask, output: PathBuf, rendered_input: &Path) -> PathBuf {
+    let (Some(original), Some(safe), Some(file_name)) = (
+        task.input_path.file_stem(),
+        rendered_input.file_stem(),
+        output.file_name().and_then(|name| name.to_str()),
+    ) else {
+        return output;
+    };
+    let Some(rest) = file_name.strip_prefix(&*safe.to_string_lossy()) else {
+        return output;
+    };
+    if original == safe {
+        return output;
+    }
+    let mut restored_name = original.to_os_string();
+    restored_name.push(rest);
+    let restored = output.with_file_name(restored_name);
+    if restored.exists() {
+        log::warn!("Task {}: keeping output name {:?}, {:?} exists", task.id, output, restored);
+        return output;
+    }
+    match std::fs::rename(&output, &restored) {
+        Ok(()) => restored,
+        Err(e) => {
+            log::warn!("Task {}: failed to rename output to {:?}: {}", task.id, restored, e);
+            output
+        }
+    }
+}
+
+/// Lists the files currently in `dir` so new outputs can be told apart afterwards.
+fn snapshot_dir(dir: &Path) -> HashSet<PathBuf> {
+    std::fs::read_dir(dir)
+        .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+        .unwrap_or_default()
+}
+
+/// Finds the file smoothie-rs wrote for `task`: the newest file that either did not
+/// exist before the run or was rewritten during it and is named after
+/// `rendered_input`. Files named after the input win over other new files, which may belong to a
+/// task rendering into the same folder at the same time.
+fn locate_output(
+    task: &VideoTask,
+    rendered_input: &Path,
+    output_dir: &Path,
+    existing: &HashSet<PathBuf>,
+    started_at: SystemTime,
+) -> Option<PathBuf> {
+    let input_stem = rendered_input.file_stem()?.to_string_lossy().to_string();
+    let entries = std::fs::read_dir(output_dir).ok()?;
+
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            if !path.is_file() || path == task.input_path || modified < started_at {
+                return None;
+            }
+            let file_name = path.file_name()?.to_string_lossy().to_string();
+            if existing.contains(&path) && !file_name.starts_with(&input_stem) {
+                return None;
+            }
+            Some((file_name.starts_with(&input_stem), modified, path))
+        })
+        .max_by_key(|(named_after_input, modified, _)| (*named_after_input, *modified))
+        .map(|(_, _, path)| path)
+}
+
+/// Remuxes `output` into the task's requested container if it is not already in it.
+/// Returns the path of the final file.
+async fn apply_output_container(
+    task: &VideoTask,
+    output: PathBuf,
+    ffmpeg_path: &Path,
+) -> Result<PathBuf, String> {
+    let Some(container) = task.output_container else {
+        return Ok(output);
+    };
+    let current_ext = output
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if current_ext == container.extension() {
+        return Ok(output);
+    }
+
+    let remuxed = output.with_extension(container.extension());
+    log::info!(
+        "Task {}: remuxing {:?} into {:?}",
+        task.id,
+        output,
+        remuxed
+    );
+    // Not cancellable: the render it finishes has already succeeded
+    ffmpeg::remux(ffmpeg_path, &output, &remuxed, &StopFlags::default())
+        .await
+        .map_err(|e| format!("Task {} output remux failed: {}", task.id, e))?;
+
+    if let Err(e) = std::fs::remove_file(&output) {
+        log::warn!("Failed to remove original output {:?}: {}", output, e);
+    }
+    Ok(remuxed)
+}
+
+/// Moves an output rendered into a staging folder to `output_dir`, as
+/// `<output_name>.<ext>` for tasks with an `output_name` and under its own name
+/// otherwise, numbering the name if that file exists.
+async fn move_output(task: &VideoTask, output: PathBuf, output_dir: &Path) -> Result<PathBuf, String> {
+    let name = match task.output_name.as_deref() {
+        Some(name) => OsString::from(name),
+        None => output.file_stem().unwrap_or_default().to_os_string(),
+    };
+    let file_name = |suffix: &str| {
+        let mut file_name = name.clone();
+        file_name.push(suffix);
+        if let Some(ext) = output.extension() {
+            file_name.push(".");
+            file_name.push(ext);
+        }
+        file_name
+    };
+    let mut target = output_dir.join(file_name(""));
+    let mut number = 2;
+    while target.exists() {
+        target = output_dir.join(file_name(&format!(" ({})", number)));
+        number += 1;
+    }
+
+    log::info!("Task {}: moving {:?} to {:?}", task.id, output, target);
+    // The staging folder may be on another drive, where renaming fails
+    if std::fs::rename(&output, &target).is_err() {
+        copy_file(&output, &target, false).await.map_err(|e| {
+            format!("Task {} failed to move its output to {:?}: {}", task.id, target, e)
+        })?;
+        let _ = std::fs::remove_file(&output);
+    }
+    Ok(target)
+}
+
+/// Substitutes the post-processing tokens in `template` for `task` and its output.
+/// The paths never become part of the command line: each token turns into a
+/// quoted reference to an environment variable holding the value, so names with
+/// spaces, `&`, `;` or `$(...)` (yt-dlp titles keep those) reach the command as
+/// one argument and are never run. Returns the command line and the variables.
+fn expand_post_command(template: &str, task: &VideoTask, output: &Path) -> (String, Vec<(&'static str, String)>) {
+    let mut command = template.to_string();
+    let mut vars = Vec::new();
+    for (token, _) in POST_COMMAND_TOKENS {
+        let (var, value) = match token {
+            "{input}" => ("SMOOTHIE_INPUT", task.input_path.display().to_string()),
+            "{output}" => ("SMOOTHIE_OUTPUT", output.display().to_string()),
+            "{outdir}" => ("SMOOTHIE_OUTDIR", output.parent().unwrap_or(Path::new(".")).display().to_string()),
+            "{name}" => (
+                "SMOOTHIE_NAME",
+                output.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default(),
+            ),
+            _ => continue,
+        };
+        // cmd's delayed `!...!` expansion, unlike `%...%`, happens after `&` and
+        // friends are parsed; sh never parses what a variable expands to
+        let reference = if cfg!(windows) { format!("\"!{}!\"", var) } else { format!("\"${}\"", var) };
+        // Tokens the user already quoted are quoted once, not twice
+        for quoted in [format!("\"{}\"", token), format!("'{}'", token), token.to_string()] {
+            command = command.replace(&quoted, &reference);
+        }
+        vars.push((var, value));
+    }
+    (command, vars)
+}
+
+/// Runs the task's post-processing command through the system shell and records
+/// its output in the task log. A failing hook is logged but does not fail the task.
+async fn run_post_command(task: &VideoTask, output: &Path, queue_manager: &Arc<Mutex<QueueManager>>) {
+    let Some(template) = task.post_command.as_deref().filter(|t| !t.trim().is_empty()) else {
+        return;
+    };
+    let (command_line, vars) = expand_post_command(template, task, output);
+    log::info!("Task {}: running post-processing command: {}", task.id, command_line);
+
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut command = Command::new("cmd");
+        command.arg("/V:ON").arg("/C").raw_arg(&command_line);
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+        command
+    };
+    #[cfg(not(target_os = "windows"))]
+    let mut command = {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(&command_line);
+        command
+    };
+    command.envs(vars.iter().map(|(var, value)| (var, value)));
+
+    let mut lines = vec![format!("> {}", command_line)];
+    lines.extend(vars.iter().map(|(var, value)| format!("  {}={}", var, value)));
+    match tokio::process::Command::from(command).output().await {
+        Ok(output) => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            lines.extend(stdout.lines().chain(stderr.lines()).map(str::to_string));
+            if !output.status.success() {
+                let err_msg = format!("Post-processing command failed with status: {}", output.status);
+                log::warn!("Task {}: {}", task.id, err_msg);
+                lines.push(err_msg);
+            }
+        }
+        Err(e) => {
+            let err_msg = format!("Failed to run post-processing command: {}", e);
+            log::warn!("Task {}: {}", task.id, err_msg);
+            lines.push(err_msg);
+        }
+    }
+
+    let mut manager = queue_manager.lock()
+        .expect("Failed to lock queue manager");
+    for line in lines {
+        manager.append_log(task.id, line);
+    }
+}
+
+/// How long an input must go unmodified before it counts as fully written.
+const INPUT_SETTLE_TIME: Duration = Duration::from_secs(5);
+/// How often a task waiting for its input checks it again.
+const INPUT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Whether another program has `path` open for writing, e.g. OBS recording to it.
+#[cfg(windows)]
+fn is_locked(path: &Path) -> bool {
+    use std::os::windows::fs::OpenOptionsExt;
+
+    const FILE_SHARE_READ: u32 = 0x1;
+    const ERROR_SHARING_VIOLATION: i32 = 32;
+    const ERROR_LOCK_VIOLATION: i32 = 33;
+    // Refusing to share write access fails while any writer has the file open
+    match std::fs::OpenOptions::new().read(true).share_mode(FILE_SHARE_READ).open(path) {
+        Ok(_) => false,
+        Err(e) => matches!(e.raw_os_error(), Some(ERROR_SHARING_VIOLATION | ERROR_LOCK_VIOLATION)),
+    }
+}
+
+/// Files are not locked against readers outside Windows; `input_busy` relies
+/// on the modification time there.
+#[cfg(not(windows))]
+fn is_locked(_path: &Path) -> bool {
+    false
+}
+
+/// Why `path` cannot be rendered yet, `None` when it can (or is missing, which
+/// the backend reports). Folders (image sequences) are never held back.
+fn input_busy(path: &Path) -> Option<&'static str> {
+    let metadata = std::fs::metadata(path).ok().filter(|m| m.is_file())?;
+    if is_locked(path) {
+        return Some("locked by another program");
+    }
+    let modified = metadata.modified().ok()?;
+    if modified.elapsed().is_ok_and(|age| age < INPUT_SETTLE_TIME) {
+        return Some("still being written");
+    }
+    None
+}
+
+/// Holds the task as `Waiting` while its input is locked or still being written
+/// (a recording or copy in progress), so it renders the whole file instead of
+/// failing on a partial one.
+async fn wait_for_input(
+    task: &VideoTask,
+    queue_manager: &Arc<Mutex<QueueManager>>,
+    stop_flags: &StopFlags,
+    tx: &Sender<UpdateMessage>,
+) -> Result<(), String> {
+    let Some(reason) = input_busy(&task.input_path) else {
+        return Ok(());
+    };
+
+    log::info!("Task {}: waiting for {:?} ({})", task.id, task.input_path, reason);
+    {
+        let mut manager = queue_manager.lock()
+            .expect("Failed to lock queue manager");
+        manager.mark_as_waiting(task.id);
+        manager.append_log(task.id, format!("Waiting for the input file: {}", reason));
+    }
+    if let Err(e) = tx.send(UpdateMessage::TaskWaiting(task.id)) {
+        log::error!("Failed to send TaskWaiting message: {}", e);
+    }
+
+    while input_busy(&task.input_path).is_some() {
+        tokio::select! {
+            () = tokio::time::sleep(INPUT_POLL_INTERVAL) => {}
+            () = stop_flags.force_stopped() => return Err(FORCE_STOPPED.to_string()),
+        }
+    }
+    log::info!("Task {}: input {:?} is ready", task.id, task.input_path);
+    queue_manager.lock()
+        .expect("Failed to lock queue manager")
+        .append_log(task.id, "Input file ready".to_string());
+    Ok(())
+}
+
+/// Downloads the task's URL source, if any, and points the task at the file.
+async fn download_source(
+    task: &mut VideoTask,
+    queue_manager: &Arc<Mutex<QueueManager>>,
+    stop_flags: &StopFlags,
+    tx: &Sender<UpdateMessage>,
+) -> Result<(), String> {
+    let Some(source) = task.source.clone() else {
+        return Ok(());
+    };
+
+    log::info!("Task {}: downloading {}", task.id, source.url);
+    queue_manager.lock()
+        .expect("Failed to lock queue manager")
+        .mark_as_downloading(task.id);
+    if let Err(e) = tx.send(UpdateMessage::TaskDownloading(task.id)) {
+        log::error!("Failed to send TaskDownloading message: {}", e);
+    }
+
+    let (path, messages) = match download::download(&source, stop_flags).await {
+        Ok(result) => result,
+        Err(e) if e == download::CANCELLED => return Err(FORCE_STOPPED.to_string()),
+        Err(e) => return Err(format!("Task {} download failed: {}", task.id, e)),
+    };
+
+    log::info!("Task {}: downloaded {:?}", task.id, path);
+    let mut manager = queue_manager.lock()
+        .expect("Failed to lock queue manager");
+    manager.append_log(task.id, format!("> yt-dlp {}", source.url));
+    for line in messages {
+        manager.append_log(task.id, line);
+    }
+    manager.append_log(task.id, format!("Downloaded to {}", path.display()));
+    manager.set_downloaded_input(task.id, path.clone());
+    task.input_path = path;
+    task.source = None;
+    Ok(())
+}
+
+/// Picks the rendering backend for `task`. Fails when the task targets a
+/// backend that is not installed.
+pub fn executor_for(config: &SmoothieConfig, task: &VideoTask) -> Result<Box<dyn Executor>, String> {
+    if let Some(simulation) = &config.simulation {
+        return Ok(Box::new(SimulatedExecutor {
+            simulation: simulation.clone(),
+        }));
+    }
+    match task.backend {
+        Backend::Smoothie => Ok(Box::new(SmoothieExecutor {
+            executable_path: config.executable_path.clone(),
+            verbose: config.verbose,
+            show_console: config.show_console,
+        })),
+        Backend::Blur => {
+            let executable_path = config.blur_path.clone().ok_or_else(|| {
+                format!("Task {} failed: blur is not installed", task.id)
+            })?;
+            Ok(Box::new(BlurExecutor { executable_path }))
+        }
+        Backend::Rife => {
+            let executable_path = config.rife_path.clone().ok_or_else(|| {
+                format!("Task {} failed: rife-ncnn-vulkan is not installed", task.id)
+            })?;
+            Ok(Box::new(RifeExecutor {
+                executable_path,
+                ffmpeg_path: config.ffmpeg_path.clone(),
+            }))
+        }
+    }
+}
+
+pub async fn process_next_task(
+    task: &VideoTask,
+    config: &SmoothieConfig,
+    queue_manager: &Arc<Mutex<QueueManager>>,
+    stop_flags: &StopFlags,
+    tx: &Sender<UpdateMessage>,
+    gpu: Option<u32>,
+) -> Result<Option<PathBuf>, String> {
+    // YAML/TOML recipes render from the .ini converted from them now, so edits
+    // to the source apply to every task that has not started yet
+    let converted;
+    let task = if task.backend == Backend::Smoothie && recipes::is_source(&task.recipe_path) {
+        match recipes::convert(&task.recipe_path, &config.recipe_path) {
+            Ok(recipe_path) => {
+                converted = VideoTask { recipe_path, ..task.clone() };
+                &converted
+            }
+            Err(e) => {
+                let err_msg = format!("Task {} failed: {}", task.id, e);
+                log::error!("{}", err_msg);
+                return Err(err_msg);
+            }
+        }
+    } else {
+        task
+    };
+    // Older smoothie-rs versions get the overrides written into a copy of the recipe
+    let with_overrides;
+    let task = if task.backend == Backend::Smoothie
+        && !task.recipe_overrides.is_empty()
+        && config.simulation.is_none()
+        && !executor::supports_override(&config.executable_path)
+    {
+        match recipes::with_overrides(&task.recipe_path, &task.recipe_overrides) {
+            Ok(recipe_path) => {
+                with_overrides = VideoTask { recipe_path, recipe_overrides: Vec::new(), ..task.clone() };
+                &with_overrides
+            }
+            Err(e) => {
+                let err_msg = format!("Task {} failed: {}", task.id, e);
+                log::error!("{}", err_msg);
+                return Err(err_msg);
+            }
+        }
+    } else {
+        task
+    };
+    // A recipe given relative to the app's working directory, e.g. on the command line
+    let with_absolute_recipe;
+    let task = if task.recipe_path.is_relative() {
+        with_absolute_recipe = VideoTask { recipe_path: paths::absolute(&task.recipe_path), ..task.clone() };
+        &with_absolute_recipe
+    } else {
+        task
+    };
+    // Interpolating between unevenly spaced frames stutters, so VFR inputs get
+    // the CFR pre-process when the settings ask for it and no other is set
+    let with_cfr;
+    let task = if config.convert_vfr
+        && config.simulation.is_none()
+        && task.pre_process.is_none()
+        && task.input_fps.is_none()
+        && task.image_sequence.is_none()
+        && !queue::is_vapoursynth_script(&task.input_path)
+        && input_is_vfr(task, &config.ffmpeg_path).await
+    {
+        log::info!("Task {}: input has a variable frame rate; converting it to CFR first", task.id);
+        with_cfr = VideoTask { pre_process: Some(PreProcess::ConvertToCfr), ..task.clone() };
+        &with_cfr
+    } else {
+        task
+    };
+
+    let executor = match executor_for(config, task).and_then(|executor| {
+        // Only smoothie-rs consumes vspipe output; the others probe or decode with ffmpeg
+        if queue::is_vapoursynth_script(&task.input_path) && task.backend != Backend::Smoothie {
+            return Err(format!(
+                "Task {} failed: {} cannot render VapourSynth scripts",
+                task.id,
+                executor.name()
+            ));
+        }
+        executor.validate(task)?;
+        Ok(executor)
+    }) {
+        Ok(executor) => executor,
+        Err(err_msg) => {
+            log::error!("{}", err_msg);
+            return Err(err_msg);
+        }
+    };
+
+    let output_dir = paths::absolute(&task.output_dir);
+    if let Err(e) = paths::ensure_output_dir(&output_dir) {
+        let err_msg = format!("Task {} failed: {}", task.id, e);
+        log::error!("{}", err_msg);
+        return Err(err_msg);
+    }
+
+    let mut staging = None;
+    let mut input_path = prepare_input(task, &config.ffmpeg_path, &mut staging, stop_flags).await?;
+    // A pre-processed input is already local
+    if config.stage_network_files && staging.is_none() && paths::is_network(&input_path) {
+        input_path = stage_input(task, input_path, &mut staging).await?;
+    }
+    if config.safe_input_names {
+        input_path = safe_input(task, input_path, &mut staging).await?;
+    }
+    // Backends do not run in the app's working directory
+    let input_path = paths::absolute(&input_path);
+
+    // Named outputs are rendered aside and moved into place afterwards, so the
+    // backend's own naming can never overwrite an earlier result. Outputs for a
+    // network folder are rendered locally and copied over once finished.
+    let stage_output = task.output_name.is_some()
+        || (config.stage_network_files && paths::is_network(&output_dir));
+    let output_staging = if stage_output {
+        Some(StagingDir::create(task.id, "output")?)
+    } else {
+        None
+    };
+    let render_dir = output_staging.as_ref().map_or(output_dir.as_path(), StagingDir::path);
+
+    let existing_files = snapshot_dir(render_dir);
+    let started_at = SystemTime::now();
+
+    let context = RunContext {
+        queue_manager,
+        stop_flags,
+        updates: tx,
+        gpu,
+        env: &config.env,
+        extra_args: &config.extra_args,
+        working_dir: config.working_dir.as_deref(),
+    };
+    let timeout_error = |limit: Duration| format!("Task {} timed out after {} minutes", task.id, limit.as_secs() / 60);
+    let render = async |input_path: &Path| {
+        let execution = async {
+            executor.execute(task, input_path, render_dir, &context).await.map_err(|mut err_msg| {
+                if err_msg != executor::CANCELLED && paths::has_unsafe_name(input_path) {
+                    err_msg.push_str(
+                        ". The file name has characters some tools cannot handle; \
+                         try rendering from a renamed temp copy (see Settings)",
+                    );
+                }
+                err_msg
+            })
+        };
+        // Dropping the execution on timeout kills the backend
+        match config.task_timeout {
+            Some(limit) => with_timeout(execution, limit, stop_flags).await.unwrap_or_else(|| Err(timeout_error(limit))),
+            None => execution.await,
+        }
+    };
+    let log_start = task_log_len(queue_manager, task.id);
+    let mut result = render(&input_path).await;
+
+    // Inputs the backend could not demux often render fine from a clean container
+    let mut remux_staging = None;
+    let mut input_path = input_path;
+    // A cancelled or timed-out render is not retried, whatever it printed
+    if let Err(err_msg) = &result
+        && err_msg != executor::CANCELLED
+        && config.task_timeout.is_none_or(|limit| *err_msg != timeout_error(limit))
+        && config.simulation.is_none()
+        && input_path.is_file()
+        && !queue::is_vapoursynth_script(&input_path)
+        && is_container_failure(err_msg, &task_log_since(queue_manager, task.id, log_start))
+    {
+        queue_manager.lock()
+            .expect("Failed to lock queue manager")
+            .append_log(task.id, "Container error; remuxing the input to MKV and trying again".to_string());
+        match remux_input(task, &input_path, &config.ffmpeg_path, &mut remux_staging, stop_flags).await {
+            Ok(remuxed) => {
+                log::info!("Task {}: retrying from {:?} after a container error", task.id, remuxed);
+                input_path = remuxed;
+                result = render(&input_path).await;
+                if result.is_ok() {
+                    let mut manager = queue_manager.lock()
+                        .expect("Failed to lock queue manager");
+                    manager.append_log(task.id, format!("Warning: {}", REMUX_WARNING));
+                    manager.set_warning(task.id, REMUX_WARNING.to_string());
+                }
+            }
+            Err(e) if e == FORCE_STOPPED => return Err(e),
+            Err(e) => {
+                log::warn!("{}", e);
+                queue_manager.lock()
+                    .expect("Failed to lock queue manager")
+                    .append_log(task.id, e);
+            }
+        }
+    }
+    match result {
+        Ok(()) => {}
+        Err(e) if e == executor::CANCELLED => return Err(FORCE_STOPPED.to_string()),
+        Err(err_msg) => {
+            log::error!("{}", err_msg);
+            return Err(err_msg);
+        }
+    }
+    log::info!("Task {} completed successfully", task.id);
+
+    let Some(output) = locate_output(task, &input_path, render_dir, &existing_files, started_at) else {
+        // A real backend that exited cleanly without writing anything did not render
+        if config.simulation.is_none() {
+            let err_msg = format!(
+                "Task {} failed: output invalid: no output file was written to {}",
+                task.id,
+                render_dir.display()
+            );
+            log::error!("{}", err_msg);
+            return Err(err_msg);
+        }
+        log::warn!(
+            "Task {}: could not locate the output file in {:?}",
+            task.id,
+            render_dir
+        );
+        if task.post_command.as_deref().is_some_and(|c| !c.trim().is_empty()) {
+            queue_manager.lock()
+                .expect("Failed to lock queue manager")
+                .append_log(
+                    task.id,
+                    "Post-processing command skipped: output file not found".to_string(),
+                );
+        }
+        return if task.output_container.is_some() {
+            Err(format!(
+                "Task {} finished but its output could not be found to remux",
+                task.id
+            ))
+        } else {
+            Ok(None)
+        };
+    };
+    if config.simulation.is_none()
+        && let Err(reason) = verify::check_output_file(&output, &config.ffmpeg_path).await
+    {
+        let err_msg = format!("Task {} failed: output invalid: {} ({})", task.id, reason, output.display());
+        log::error!("{}", err_msg);
+        return Err(err_msg);
+    }
+    let output = restore_output_name(task, output, &input_path);
+    let output = apply_output_container(task, output, &config.ffmpeg_path).await?;
+    let output = match output_staging {
+        Some(_) => move_output(task, output, &output_dir).await?,
+        None => output,
+    };
+    if task.backend == Backend::Smoothie && config.simulation.is_none() {
+        check_output(task, &input_path, &output, &config.ffmpeg_path, queue_manager).await;
+    }
+    if config.comparison_preview {
+        render_comparison(task, &input_path, &output, &config.ffmpeg_path, queue_manager, stop_flags)
+            .await;
+    }
+    if config.comparison_screenshot && config.simulation.is_none() {
+        render_screenshot(task, &input_path, &output, &config.ffmpeg_path, queue_manager, stop_flags).await;
+    }
+    if config.delivery.is_enabled() && config.simulation.is_none() {
+        render_delivery(task, &output, config, queue_manager, stop_flags).await;
+    }
+    run_post_command(task, &output, queue_manager).await;
+    Ok(Some(output))
+}
+
+/// Warning on tasks that only rendered after `remux_input`.
+const REMUX_WARNING: &str = "input remuxed to MKV after a container error";
+
+/// Error text (lowercase) of ffmpeg's demuxers and the ffms2/L-SMASH source
+/// filters failing to read the input's container rather than its video. Only
+/// whole messages: a word like "truncated" also turns up in unrelated failures,
+/// and each match costs a remux of the whole input. "Could not find codec
+/// parameters" is left out: OBS and ShadowPlay data streams print it on inputs
+/// that render fine.
+const CONTAINER_ERRORS: [&str; 9] = [
+    "moov atom not found",
+    "invalid data found when processing input",
+    "error opening input",
+    "ebml header parsing failed",
+    "error during demuxing",
+    "stream ends prematurely",
+    ": partial file",
+    "no video track found",
+    "failed to construct index",
+];
+
+/// Whether the render failed on the input's container: `err_msg` or the
+/// `output` it printed mentions a demuxer error.
+fn is_container_failure(err_msg: &str, output: &str) -> bool {
+    let text = format!("{}\n{}", err_msg, output).to_lowercase();
+    CONTAINER_ERRORS.iter().any(|pattern| text.contains(pattern))
+}
+
+fn task_log_len(queue_manager: &Arc<Mutex<QueueManager>>, task_id: usize) -> usize {
+    queue_manager.lock()
+        .expect("Failed to lock queue manager")
+        .tasks
+        .iter()
+        .find(|t| t.id == task_id)
+        .map_or(0, |t| t.log.len())
+}
+
+/// The task's log lines from `start` on, e.g. what one render printed.
+fn task_log_since(queue_manager: &Arc<Mutex<QueueManager>>, task_id: usize, start: usize) -> String {
+    queue_manager.lock()
+        .expect("Failed to lock queue manager")
+        .tasks
+        .iter()
+        .find(|t| t.id == task_id)
+        .map(|t| t.log.get(start..).unwrap_or_default().join("\n"))
+        .unwrap_or_default()
+}
+
+/// Remuxes `input` to MKV in a new folder in `staging`, keeping its file stem so
+/// outputs are named as usual. Returns the path to render from.
+async fn remux_input(
+    task: &VideoTask,
+    input: &Path,
+    ffmpeg_path: &Path,
+    staging: &mut Option<StagingDir>,
+    stop_flags: &StopFlags,
+) -> Result<PathBuf, String> {
+    let dir = staging.insert(StagingDir::create(task.id, "remux")?);
+    let remuxed = dir.path().join(input.file_name().unwrap_or_default()).with_extension("mkv");
+    match ffmpeg::remux_to_mkv(ffmpeg_path, input, &remuxed, stop_flags).await {
+        Ok(()) => Ok(remuxed),
+        Err(e) if e == ffmpeg::CANCELLED => Err(FORCE_STOPPED.to_string()),
+        Err(e) => Err(format!("Task {} could not be remuxed to MKV: {}", task.id, e)),
+    }
+}
+
+/// Awaits `future` for at most `limit`, or `None` once the limit is hit. Time
+/// spent suspended does not count towards the limit.
+async fn with_timeout<T>(future: impl Future<Output = T>, limit: Duration, stop_flags: &StopFlags) -> Option<T> {
+    let mut future = std::pin::pin!(future);
+    let mut remaining = limit;
+    let mut suspended = stop_flags.is_suspended();
+    loop {
+        let running_since = Instant::now();
+        tokio::select! {
+            output = &mut future => return Some(output),
+            () = tokio::time::sleep(remaining), if !suspended => return None,
+            () = stop_flags.suspend_changed(suspended) => {
+                if !suspended {
+                    remaining = remaining.saturating_sub(running_since.elapsed());
+                }
+                suspended = !suspended;
+            }
+        }
+    }
+}
+
+/// Warns when the output's frame rate or frame count is not what the recipe asks
+/// for. Only noted on the task; the render itself already succeeded.
+async fn check_output(
+    task: &VideoTask,
+    input: &Path,
+    output: &Path,
+    ffmpeg_path: &Path,
+    queue_manager: &Arc<Mutex<QueueManager>>,
+) {
+    if queue::is_vapoursynth_script(input) || input.is_dir() {
+        return;
+    }
+    let Some(warning) = verify::check_frame_rate(task, input, output, ffmpeg_path).await else {
+        return;
+    };
+    log::warn!("Task {}: {}", task.id, warning);
+    let mut manager = queue_manager.lock()
+        .expect("Failed to lock queue manager");
+    manager.append_log(task.id, format!("Warning: {}", warning));
+    manager.set_warning(task.id, warning);
+}
+
+/// Renders the delivery copy of `output` as `<output stem> ~ delivery.mp4`. Like
+/// the comparison clip, a failure only goes to the task log.
+async fn render_delivery(
+    task: &VideoTask,
+    output: &Path,
+    config: &SmoothieConfig,
+    queue_manager: &Arc<Mutex<QueueManager>>,
+    stop_flags: &StopFlags,
+) {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    let copy = output.with_file_name(format!("{} ~ delivery.mp4", stem));
+    log::info!("Task {}: rendering delivery copy {:?}", task.id, copy);
+
+    let result = ffmpeg::delivery_copy(&config.ffmpeg_path, output, &config.delivery, &copy, stop_flags).await;
+    let line = match result {
+        Ok(()) => format!("Delivery copy saved as {}", copy.display()),
+        Err(e) => {
+            log::warn!("Task {}: {}", task.id, e);
+            format!("Delivery copy skipped: {}", e)
+        }
+    };
+    queue_manager.lock()
+        .expect("Failed to lock queue manager")
+        .append_log(task.id, line);
+}
+
+/// Saves `<output stem> ~ compare.png`: the frame halfway through the input next
+/// to the output's frame at the same time. Like the comparison clip, a failure is
+/// only noted in the task log.
+async fn render_screenshot(
+    task: &VideoTask,
+    input: &Path,
+    output: &Path,
+    ffmpeg_path: &Path,
+    queue_manager: &Arc<Mutex<QueueManager>>,
+    stop_flags: &StopFlags,
+) {
+    if queue::is_vapoursynth_script(input) {
+        return;
+    }
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    let png = output.with_file_name(format!("{} ~ compare.png", stem));
+    let at = task.input_duration.map_or(Duration::ZERO, |duration| duration / 2);
+    log::info!("Task {}: saving comparison screenshot {:?}", task.id, png);
+
+    let result = ffmpeg::comparison_screenshot(ffmpeg_path, input, output, at, &png, stop_flags).await;
+    let mut manager = queue_manager.lock()
+        .expect("Failed to lock queue manager");
+    match result {
+        Ok(()) => manager.set_screenshot_path(task.id, png),
+        Err(e) => {
+            log::warn!("Task {}: {}", task.id, e);
+            manager.append_log(task.id, format!("Comparison screenshot skipped: {}", e));
+        }
+    }
+}
+
+/// Renders `<output stem> ~ compare.webm` next to the output. A failure is only
+/// noted in the task log; the render itself already succeeded.
+async fn render_comparison(
+    task: &VideoTask,
+    input: &Path,
+    output: &Path,
+    ffmpeg_path: &Path,
+    queue_manager: &Arc<Mutex<QueueManager>>,
+    stop_flags: &StopFlags,
+) {
+    if queue::is_vapoursynth_script(input) {
+        return;
+    }
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    let clip = output.with_file_name(format!("{} ~ compare.webm", stem));
+    log::info!("Task {}: rendering comparison clip {:?}", task.id, clip);
+
+    let result = ffmpeg::comparison_clip(ffmpeg_path, input, output, &clip, stop_flags).await;
+    let mut manager = queue_manager.lock()
+        .expect("Failed to lock queue manager");
+    match result {
+        Ok(()) => manager.set_preview_path(task.id, clip),
+        Err(e) => {
+            log::warn!("Task {}: {}", task.id, e);
+            manager.append_log(task.id, format!("Comparison clip skipped: {}", e));
+        }
+    }
+}
+
+/// How often the worker looks for newly added tasks while it has room to start one.
+const QUEUE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// How often the power draw is read to estimate the energy running tasks use.
+const ENERGY_READING_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Downloads (if needed) and renders one task, returning its id with the result.
+async fn run_task(
+    mut task: VideoTask,
+    config: Arc<SmoothieConfig>,
+    queue_manager: Arc<Mutex<QueueManager>>,
+    stop_flags: StopFlags,
+    tx: Sender<UpdateMessage>,
+    gpu: Option<u32>,
+) -> (usize, Result<Option<PathBuf>, String>) {
+    let task_id = task.id;
+    if task.source.is_none()
+        && let Err(e) = wait_for_input(&task, &queue_manager, &stop_flags, &tx).await
+    {
+        return (task_id, Err(e));
+    }
+    if let Err(e) = download_source(&mut task, &queue_manager, &stop_flags, &tx).await {
+        return (task_id, Err(e));
+    }
+    queue_manager.lock()
+        .expect("Failed to lock queue manager")
+        .mark_as_running(task_id);
+    if let Err(e) = tx.send(UpdateMessage::TaskStarted(task_id)) {
+        log::error!("Failed to send TaskStarted message: {}", e);
+    }
+    let result = process_next_task(&task, &config, &queue_manager, &stop_flags, &tx, gpu).await;
+    (task_id, result)
+}
+
+/// Marks the run budget as reached when it is used up while tasks are still
+/// pending. Returns how many are pending then.
+fn check_budget(queue_manager: &Arc<Mutex<QueueManager>>) -> Option<usize> {
+    let mut manager = queue_manager.lock()
+        .expect("Failed to lock queue manager");
+    let pending = manager.tasks.iter().filter(|t| t.status == queue::TaskStatus::Pending).count();
+    let budget = manager.budget.as_mut()?;
+    if budget.reached || !budget.is_spent() || pending == 0 {
+        return None;
+    }
+    budget.reached = true;
+    manager.touch();
+    Some(pending)
+}
+
+/// Error text (lowercase) of failures that can pass on their own: a file locked by
+/// another program, the GPU's encoder session limit, a full disk (temp files are
+/// removed after each task) and network drops. Whole phrases only, so words like
+/// "blocked" do not count; running out of memory is left out, as a recipe that
+/// needs too much does so on every try.
+const TRANSIENT_ERRORS: [&str; 14] = [
+    "being used by another process",
+    "sharing violation",
+    "the process cannot access the file",
+    "device or resource busy",
+    "resource temporarily unavailable",
+    "openencodesessionex failed",
+    "no space left on device",
+    "not enough space on the disk",
+    "disk full",
+    "not reachable",
+    "network name is no longer available",
+    "connection reset",
+    "connection timed out",
+    "temporary failure in name resolution",
+];
+
+/// Whether a failure may not happen again on a retry. Anything else (a bad
+/// recipe, an unsupported codec, a missing file) fails the same way every time.
+pub fn is_transient_failure(err_msg: &str) -> bool {
+    let err_msg = err_msg.to_lowercase();
+    TRANSIENT_ERRORS.iter().any(|pattern| err_msg.contains(pattern))
+}
+
+/// Takes the next pending task (giving this run's transient failures another pass,
+/// up to `config.retries` times, once the queue drains) and marks it as started, with its own stop flags registered so it
+/// can be cancelled on its own.
+fn take_next_task(
+    queue_manager: &Arc<Mutex<QueueManager>>,
+    config: &SmoothieConfig,
+    failed_this_run: &mut Vec<usize>,
+    retry_passes: &mut u32,
+    idle: bool,
+) -> Option<(VideoTask, StopFlags)> {
+    let mut manager = queue_manager.lock()
+        .expect("Failed to lock queue manager");
+    // Failures are retried only once nothing else is left, not even running
+    if *retry_passes < config.retries && idle && manager.next_pending_task().is_none() {
+        *retry_passes += 1;
+        let mut requeued_count = 0;
+        // Tasks failing again are collected anew for the next pass
+        for task_id in failed_this_run.drain(..) {
+            let transient = manager.tasks.iter().any(|task| {
+                task.id == task_id && matches!(&task.status, TaskStatus::Failed(err) if is_transient_failure(err))
+            });
+            if transient {
+                manager.requeue_task(task_id);
+                manager.append_log(task_id, format!("Requeued for attempt {}", *retry_passes + 1));
+                requeued_count += 1;
+            } else {
+                manager.append_log(task_id, "Not retried: the error would happen again".to_string());
+            }
+        }
+        if requeued_count > 0 {
+            log::info!("Requeued {} failed task(s) for retry pass {}", requeued_count, retry_passes);
+        }
+    }
+
+    let task = manager.next_pending_task()?.clone();
+    if let Some(budget) = manager.budget.as_mut() {
+        budget.tasks_left = budget.tasks_left.map(|left| left.saturating_sub(1));
+    }
+    // Leave pending right away so the task is not picked up twice
+    if task.source.is_some() {
+        manager.mark_as_downloading(task.id);
+    } else {
+        manager.mark_as_running(task.id);
+    }
+    let task_flags = manager.stop_flags.for_task();
+    manager.task_stop_flags.insert(task.id, task_flags.clone());
+    Some((task, task_flags))
+}
+
+pub async fn run_worker(
+    queue_manager: Arc<Mutex<QueueManager>>,
+    tx: Sender<UpdateMessage>,
+    config: SmoothieConfig,
+) {
+    log::info!("Worker started.");
+
+    // Checked between and during tasks without locking the queue
+    let stop_flags = queue_manager.lock()
+        .expect("Failed to lock queue manager")
+        .stop_flags
+        .clone();
+    let config = Arc::new(config);
+    let power_plan = if config.high_performance_power {
+        power::HighPerformancePlan::activate()
+    } else {
+        None
+    };
+    let max_parallel = config.max_parallel.max(1);
+    let mut gpus = if config.spread_gpus { GpuScheduler::detect() } else { None };
+    let mut power_meter = PowerMeter::new();
+    // Tasks that failed since the last retry pass, requeued while `config.retries` allows
+    let mut failed_this_run: Vec<usize> = Vec::new();
+    let mut retry_passes = 0;
+    let mut running = JoinSet::new();
+    let mut last_start: Option<Instant> = None;
+    {
+        let mut manager = queue_manager.lock()
+            .expect("Failed to lock queue manager");
+        manager.budget = config.budget.is_limited().then(|| BudgetStatus::new(&config.budget));
+        manager.touch();
+        let detail = format!("up to {} task(s) at once", max_parallel);
+        manager.events.record(EventKind::QueueStarted, None, detail);
+    }
+
+    loop {
+        // Start tasks while there is room, spacing out the ones that overlap
+        let mut wait = None;
+        if running.len() < max_parallel && !stop_flags.is_stop_requested() {
+            let stagger_left = match last_start {
+                Some(started) if !running.is_empty() => {
+                    config.start_stagger.saturating_sub(started.elapsed())
+                }
+                _ => Duration::ZERO,
+            };
+            if let Some(pending) = check_budget(&queue_manager) {
+                log::info!("Run budget used up; pausing the queue with {} task(s) pending", pending);
+                stop_flags.request_stop();
+                if let Err(e) = tx.send(UpdateMessage::BudgetReached(pending)) {
+                    log::error!("Failed to send BudgetReached message: {}", e);
+                }
+                continue;
+            } else if !stagger_left.is_zero() {
+                wait = Some(stagger_left);
+            } else if let Some((task, task_flags)) = take_next_task(
+                &queue_manager,
+                &config,
+                &mut failed_this_run,
+                &mut retry_passes,
+                running.is_empty(),
+            ) {
+                log::info!("Worker found pending task: {}", task.id);
+                last_start = Some(Instant::now());
+                let gpu = gpus.as_mut().map(|gpus| {
+                    let gpu = gpus.assign(task.id);
+                    let line = format!("Rendering on GPU {} ({})", gpu.index, gpu.name);
+                    log::info!("Task {}: {}", task.id, line);
+                    queue_manager.lock()
+                        .expect("Failed to lock queue manager")
+                        .append_log(task.id, line);
+                    gpu.index
+                });
+                running.spawn_local(run_task(
+                    task,
+                    Arc::clone(&config),
+                    Arc::clone(&queue_manager),
+                    task_flags,
+                    tx.clone(),
+                    gpu,
+                ));
+                continue;
+            } else if !running.is_empty() {
+                // Tasks added while others render can still start alongside them
+                wait = Some(QUEUE_POLL_INTERVAL);
+            }
+        }
+
+        if running.is_empty() {
+            let mut manager = queue_manager.lock()
+                .expect("Failed to lock queue manager");
+            if stop_flags.is_stop_requested() {
+                log::info!("Worker received stop request. Exiting loop.");
+                let pending = manager.tasks.iter().filter(|t| t.status == TaskStatus::Pending).count();
+                manager.events.record(EventKind::QueueStopped, None, format!("{} task(s) pending", pending));
+            } else {
+                log::info!("No more pending tasks. Exiting loop.");
+                manager.events.record(EventKind::QueueFinished, None, "");
+            }
+            break;
+        }
+
+        tokio::select! {
+            joined = running.join_next() => {
+                match joined {
+                    Some(Ok((task_id, result))) => {
+                        // Its share up to now, while it still counts as running
+                        record_energy(power_meter.as_mut(), &queue_manager);
+                        if let Some(gpus) = gpus.as_mut() {
+                            gpus.release(task_id);
+                        }
+                        finish_task(task_id, result, &queue_manager, &tx, &mut failed_this_run);
+                    }
+                    Some(Err(e)) => log::error!("Worker task panicked: {}", e),
+                    None => {}
+                }
+            }
+            () = tokio::time::sleep(wait.unwrap_or_default()), if wait.is_some() => {}
+            () = tokio::time::sleep(
+                ENERGY_READING_INTERVAL.saturating_sub(
+                    power_meter.as_ref().map_or(Duration::ZERO, PowerMeter::since_last_reading),
+                ),
+            ), if power_meter.is_some() => {
+                record_energy(power_meter.as_mut(), &queue_manager);
+            }
+            () = stop_flags.force_stopped() => {
+                // A force stop from the controls cancels every running task
+                let manager = queue_manager.lock()
+                    .expect("Failed to lock queue manager");
+                for task_flags in manager.task_stop_flags.values() {
+                    task_flags.request_force_stop();
+                }
+                stop_flags.clear_force_stop();
+            }
+        }
+    }
+
+    // Back to the previous power plan before anyone hears the queue is done
+    drop(power_plan);
+    log::info!("Worker sending WorkerFinished message.");
+    if let Err(e) = tx.send(UpdateMessage::WorkerFinished) {
+        log::error!("Failed to send WorkerFinished message: {}", e);
+    }
+    log::info!("Worker finished.");
+}
+
+/// Credits the energy used since the last reading to the running tasks.
+fn record_energy(power_meter: Option<&mut PowerMeter>, queue_manager: &Arc<Mutex<QueueManager>>) {
+    if let Some(meter) = power_meter {
+        let watt_hours = meter.read();
+        queue_manager.lock()
+            .expect("Failed to lock queue manager")
+            .add_energy(watt_hours);
+    }
+}
+
+/// Records the result of a task the worker ran and reports it.
+fn finish_task(
+    task_id: usize,
+    result: Result<Option<PathBuf>, String>,
+    queue_manager: &Arc<Mutex<QueueManager>>,
+    tx: &Sender<UpdateMessage>,
+    failed_this_run: &mut Vec<usize>,
+) {
+    let mut manager = queue_manager.lock()
+        .expect("Failed to lock queue manager");
+    manager.task_stop_flags.remove(&task_id);
+
+    match result {
+        Ok(output_path) => {
+            if let Some(output_path) = output_path {
+                manager.set_output_path(task_id, output_path);
+            }
+            manager.mark_as_completed(task_id);
+            if let Err(e) = tx.send(UpdateMessage::TaskCompleted(task_id)) {
+                log::error!("Failed to send TaskCompleted message: {}", e);
+            }
+        }
+        Err(err_msg) => {
+            if err_msg == FORCE_STOPPED {
+                manager.mark_as_cancelled(task_id);
+                if let Err(e) = tx.send(UpdateMessage::TaskCancelled(task_id)) {
+                    log::error!("Failed to send TaskCancelled message: {}", e);
+                }
+            } else {
+                failed_this_run.push(task_id);
+                manager.mark_as_failed(task_id, err_msg.clone());
+                if let Err(e) = tx.send(UpdateMessage::TaskFailed(task_id, err_msg)) {
+                    log::error!("Failed to send TaskFailed message: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Clears any pending stop or suspend requests and runs `run_worker` on a new thread with
+/// its own single-threaded tokio runtime, so frontends need no async runtime. Up to
+/// `config.max_parallel` tasks are rendered at once.
+pub fn spawn_worker(
+    queue_manager: Arc<Mutex<QueueManager>>,
+    tx: Sender<UpdateMessage>,
+    config: SmoothieConfig,
+) -> JoinHandle<()> {
+    let stop_flags = queue_manager.lock()
+        .expect("Failed to lock queue manager")
+        .stop_flags
+        .clone();
+    stop_flags.clear_stop_request();
+    stop_flags.clear_force_stop();
+    stop_flags.resume();
+
+    thread::spawn(move || {
+        let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+            Ok(runtime) => runtime,
+            Err(e) => {
+                log::error!("Failed to start the worker runtime: {}", e);
+                let _ = tx.send(UpdateMessage::WorkerFinished);
+                return;
+            }
+        };
+        // Tasks run side by side as local tasks of this one thread
+        let tasks = LocalSet::new();
+        runtime.block_on(tasks.run_until(run_worker(queue_manager, tx, config)));
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn busy_files_and_full_disks_are_transient() {
+        for err_msg in [
+            "Task 3 failed: The process cannot access the file because it is being used by another process. (os error 32)",
+            "ffmpeg: No space left on device",
+            "OpenEncodeSessionEx failed: out of memory (10)",
+            "Output folder not reachable: \\\\NAS\\renders",
+            "yt-dlp: Connection reset by peer",
+        ] {
+            assert!(is_transient_failure(err_msg), "{:?}", err_msg);
+        }
+    }
+
+    #[test]
+    fn bad_inputs_and_recipes_are_not_transient() {
+        for err_msg in [
+            "Task 3 failed: recipe not found: 240fps.ini",
+            "Task 3 failed: CUDA out of memory",
+            "Task 3 failed: the config file is locked for editing",
+            "Unsupported codec id 0x1e",
+            "Task 3 timed out after 30 minutes",
+        ] {
+            assert!(!is_transient_failure(err_msg), "{:?}", err_msg);
+        }
+    }
+
+    #[test]
+    fn demuxer_errors_are_container_failures() {
+        assert!(is_container_failure("Task 3 failed: smoothie-rs exited with code 1", "[mov,mp4] moov atom not found"));
+        assert!(is_container_failure("clip.mkv: Invalid data found when processing input", ""));
+        assert!(is_container_failure("", "Error: FFMS2: Failed to construct index"));
+        assert!(is_container_failure("", "L-SMASH: Stream ends prematurely at 1234"));
+    }
+
+    #[test]
+    fn other_failures_are_not_container_failures() {
+        assert!(!is_container_failure("Task 3 failed: recipe not found", ""));
+        assert!(!is_container_failure("", "Could not find codec parameters for stream 2 (Unknown: none): unknown codec"));
+        assert!(!is_container_failure("", "Output file is truncated"));
+        assert!(!is_container_failure("", ""));
+    }
+}
@@ -0,0 +1,270 @@
+use crate::dashboard;
+use serde::{Deserialize, Serialize};
+use smoothie_queuer_core::queue::{Backend, QueueManager, TaskStatus, VideoTask};
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, mpsc::Sender};
+use std::thread;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// Requests coming from outside the UI (the control API and other integrations).
+/// They are applied by the UI thread so it stays the only owner of worker state.
+#[derive(Debug, Clone)]
+pub enum ControlCommand {
+    AddTask {
+        path: PathBuf,
+        recipe: Option<PathBuf>,
+    },
+    StartWorker,
+    PauseWorker,
+}
+
+#[derive(Debug, Deserialize)]
+struct AddTaskRequest {
+    path: PathBuf,
+    recipe: Option<PathBuf>,
+}
+
+/// Task summary exposed to external tools.
+#[derive(Debug, Serialize)]
+pub struct TaskInfo {
+    pub id: usize,
+    pub input: PathBuf,
+    pub recipe: PathBuf,
+    pub status: &'static str,
+    pub error: Option<String>,
+    pub output: Option<PathBuf>,
+    pub progress: Option<f32>,
+    pub backend: Backend,
+    pub energy_wh: Option<f64>, // Estimated, where the power draw can be read
+    pub warning: Option<String>, // Set when a completed output looks off
+}
+
+impl From<&VideoTask> for TaskInfo {
+    fn from(task: &VideoTask) -> Self {
+        let (status, error) = match &task.status {
+            TaskStatus::Pending => ("pending", None),
+            TaskStatus::Waiting => ("waiting", None),
+            TaskStatus::Downloading => ("downloading", None),
+            TaskStatus::Running => ("running", None),
+            TaskStatus::Completed => ("completed", None),
+            TaskStatus::Failed(err) => ("failed", Some(err.clone())),
+            TaskStatus::Cancelled => ("cancelled", None),
+        };
+        Self {
+            id: task.id,
+            input: task.input_path.clone(),
+            recipe: task.recipe_path.clone(),
+            status,
+            error,
+            output: task.output_path.clone(),
+            progress: task.progress,
+            backend: task.backend,
+            energy_wh: task.energy_wh,
+            warning: task.warning.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ProgressInfo {
+    total: usize,
+    pending: usize,
+    running: Vec<usize>,
+    completed: usize,
+    failed: usize,
+    cancelled: usize,
+    paused: bool,
+    suspended: bool,
+    budget: Option<BudgetInfo>,
+}
+
+/// What is left of the run budget, when the run has one.
+#[derive(Debug, Serialize)]
+struct BudgetInfo {
+    tasks_left: Option<usize>,
+    seconds_left: Option<u64>,
+    reached: bool,
+}
+
+impl ProgressInfo {
+    fn from_manager(manager: &QueueManager) -> Self {
+        let count = |wanted: fn(&TaskStatus) -> bool| {
+            manager.tasks.iter().filter(|t| wanted(&t.status)).count()
+        };
+        Self {
+            total: manager.tasks.len(),
+            pending: count(|s| *s == TaskStatus::Pending),
+            running: manager
+                .tasks
+                .iter()
+                .filter(|t| {
+                    matches!(t.status, TaskStatus::Waiting | TaskStatus::Downloading | TaskStatus::Running)
+                })
+                .map(|t| t.id)
+                .collect(),
+            completed: count(|s| *s == TaskStatus::Completed),
+            failed: count(|s| matches!(s, TaskStatus::Failed(_))),
+            cancelled: count(|s| *s == TaskStatus::Cancelled),
+            paused: manager.stop_flags.is_stop_requested(),
+            suspended: manager.stop_flags.is_suspended(),
+            budget: manager.budget.map(|budget| BudgetInfo {
+                tasks_left: budget.tasks_left,
+                seconds_left: budget.time_left().map(|left| left.as_secs()),
+                reached: budget.reached,
+            }),
+        }
+    }
+}
+
+/// Starts the control API on `127.0.0.1:port` in a background thread, or on all
+/// interfaces with `lan` so phones and other machines can check on the queue.
+/// Commands (`POST`) are only accepted from this machine either way, with a
+/// JSON content type, which browsers cannot send to another site without a
+/// preflight this server never answers. Requests a web page makes from any
+/// origin other than the status page itself are refused.
+///
+/// Endpoints:
+/// - `GET /` - read-only status page for a browser (see `dashboard::render`)
+/// - `GET /queue` - all tasks
+/// - `GET /progress` - task counts and running task ids
+/// - `POST /tasks` - `{"path": "...", "recipe": "..."}` queues a file (recipe optional)
+/// - `POST /worker/start`, `POST /worker/pause`
+///
+/// `on_command` is called after each command is sent so the UI can wake up.
+pub fn spawn(
+    port: u16,
+    lan: bool,
+    queue_manager: Arc<Mutex<QueueManager>>,
+    control_tx: Sender<ControlCommand>,
+    on_command: impl Fn() + Send + 'static,
+) {
+    let address = format!("{}:{}", if lan { "0.0.0.0" } else { "127.0.0.1" }, port);
+    let server = match Server::http(&address) {
+        Ok(server) => server,
+        Err(e) => {
+            log::error!("Failed to start control API on {}: {}", address, e);
+            return;
+        }
+    };
+    log::info!("Control API listening on http://{}", address);
+
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            handle_request(request, port, &queue_manager, &control_tx, &on_command);
+        }
+    });
+}
+
+fn handle_request(
+    mut request: Request,
+    port: u16,
+    queue_manager: &Arc<Mutex<QueueManager>>,
+    control_tx: &Sender<ControlCommand>,
+    on_command: &dyn Fn(),
+) {
+    log::debug!("Control API request: {} {}", request.method(), request.url());
+
+    let from_this_machine = request.remote_addr().is_none_or(|addr| addr.ip().is_loopback());
+    if *request.method() != Method::Get && !from_this_machine {
+        return respond_error(request, 403, "Commands are only accepted from this machine");
+    }
+    if let Some(origin) = header(&request, "Origin")
+        && !is_own_origin(origin, header(&request, "Host"), port)
+    {
+        return respond_error(request, 403, "Requests from other web pages are not accepted");
+    }
+    if *request.method() != Method::Get
+        && !header(&request, "Content-Type").is_some_and(|value| value.starts_with("application/json"))
+    {
+        return respond_error(request, 415, "Commands must be sent as Content-Type: application/json");
+    }
+
+    let command = match (request.method(), request.url()) {
+        (Method::Get, "/") => {
+            let page = {
+                let manager = queue_manager.lock()
+                    .expect("Failed to lock queue manager");
+                dashboard::render(&manager)
+            };
+            let header = Header::from_bytes("Content-Type", "text/html; charset=utf-8")
+                .expect("Static header is valid");
+            if let Err(e) = request.respond(Response::from_string(page).with_header(header)) {
+                log::warn!("Failed to send control API response: {}", e);
+            }
+            return;
+        }
+        (Method::Get, "/queue") => {
+            let tasks: Vec<TaskInfo> = {
+                let manager = queue_manager.lock()
+                    .expect("Failed to lock queue manager");
+                manager.tasks.iter().map(TaskInfo::from).collect()
+            };
+            return respond_json(request, 200, &tasks);
+        }
+        (Method::Get, "/progress") => {
+            let progress = {
+                let manager = queue_manager.lock()
+                    .expect("Failed to lock queue manager");
+                ProgressInfo::from_manager(&manager)
+            };
+            return respond_json(request, 200, &progress);
+        }
+        (Method::Post, "/tasks") => {
+            let mut body = String::new();
+            if let Err(e) = request.as_reader().read_to_string(&mut body) {
+                return respond_error(request, 400, &format!("Failed to read body: {}", e));
+            }
+            match serde_json::from_str::<AddTaskRequest>(&body) {
+                Ok(add) => ControlCommand::AddTask {
+                    path: add.path,
+                    recipe: add.recipe,
+                },
+                Err(e) => return respond_error(request, 400, &format!("Invalid task: {}", e)),
+            }
+        }
+        (Method::Post, "/worker/start") => ControlCommand::StartWorker,
+        (Method::Post, "/worker/pause") => ControlCommand::PauseWorker,
+        _ => return respond_error(request, 404, "Not found"),
+    };
+
+    if control_tx.send(command).is_err() {
+        return respond_error(request, 503, "Application is shutting down");
+    }
+    on_command();
+    respond_json(request, 202, &serde_json::json!({ "accepted": true }));
+}
+
+fn header<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request.headers().iter().find(|header| header.field.equiv(name)).map(|header| header.value.as_str())
+}
+
+/// Whether `origin` is the status page served on `port`: the host the request
+/// was sent to, by address or as `localhost`. A page served under a host name
+/// that was pointed at this machine (DNS rebinding) does not count.
+fn is_own_origin(origin: &str, host: Option<&str>, port: u16) -> bool {
+    let Some(origin_host) = origin.strip_prefix("http://") else {
+        return false;
+    };
+    let Some(name) = origin_host.strip_suffix(&format!(":{}", port)) else {
+        return false;
+    };
+    host == Some(origin_host)
+        && (name == "localhost" || name.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>().is_ok())
+}
+
+fn respond_json(request: Request, status: u16, body: &impl Serialize) {
+    let json = serde_json::to_string(body).unwrap_or_else(|_| "null".to_string());
+    let header = Header::from_bytes("Content-Type", "application/json")
+        .expect("Static header is valid");
+    let response = Response::from_string(json)
+        .with_status_code(status)
+        .with_header(header);
+    if let Err(e) = request.respond(response) {
+        log::warn!("Failed to send control API response: {}", e);
+    }
+}
+
+fn respond_error(request: Request, status: u16, message: &str) {
+    respond_json(request, status, &serde_json::json!({ "error": message }));
+}
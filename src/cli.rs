@@ -0,0 +1,261 @@
+use crate::api::ControlCommand;
+use crate::daemon::StopMode;
+use smoothie_queuer_core::queue;
+use smoothie_queuer_core::simulate::Simulation;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Scheme of the `smoothie-queue://add?path=...&recipe=...` links.
+pub const URL_SCHEME: &str = "smoothie-queue";
+
+// Exit codes of the headless and RPC modes, for scripts and CI pipelines wrapping it.
+pub const EXIT_OK: i32 = 0;
+pub const EXIT_TASKS_FAILED: i32 = 1;
+pub const EXIT_CONFIG_ERROR: i32 = 2; // Invalid command line, or nothing to render
+pub const EXIT_EXECUTABLE_MISSING: i32 = 3; // smoothie-rs was needed but never found
+
+/// How the application was asked to run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mode {
+    Gui,
+    Headless,
+    Daemon,
+    /// The daemon started by the Windows service control manager.
+    Service,
+    InstallService,
+    UninstallService,
+    /// Write the settings and templates to a file (`bundle::export`).
+    ExportSettings(PathBuf),
+    /// Replace the settings with an exported file's.
+    ImportSettings(PathBuf),
+    Rpc,
+    Help,
+}
+
+/// Worker settings from the command line, overriding the saved ones in `--headless`
+/// mode.
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    pub max_parallel: Option<usize>,
+    pub priority: Option<i32>,       // Of the tasks queued from the command line
+    pub retries: Option<u32>,        // Passes retrying transient failures
+    pub timeout_minutes: Option<u32>, // 0 for no limit
+}
+
+impl RunOptions {
+    fn is_set(&self) -> bool {
+        self.max_parallel.is_some() || self.priority.is_some() || self.retries.is_some() || self.timeout_minutes.is_some()
+    }
+}
+
+/// Parsed command line.
+#[derive(Debug, Clone)]
+pub struct Args {
+    pub mode: Mode,
+    /// Files and URLs to queue on start (or hand to an already running instance).
+    pub enqueue: Vec<ControlCommand>,
+    /// Demo mode settings when `--simulate` (or one of its options) was given.
+    pub simulation: Option<Simulation>,
+    /// Headless events as JSON lines instead of text.
+    pub progress_json: bool,
+    /// Headless inputs are also read from stdin, one path per line.
+    pub read_stdin: bool,
+    /// Where `--daemon` logs to instead of `daemon::default_log_file`.
+    pub log_file: Option<PathBuf>,
+    /// What the daemon does with running tasks when asked to stop.
+    pub on_stop: StopMode,
+    /// Replaces the per-user folder for settings, the saved queue and logs.
+    pub data_dir: Option<PathBuf>,
+    pub run_options: RunOptions,
+}
+
+pub const USAGE: &str = "\
+Usage: smoothie-queuer [OPTIONS] [FILE|URL...]
+
+Options:
+  --headless                Render the given files without a window and exit when done
+  --stdin                   With --headless: also read paths from stdin, one per line,
+                            e.g. find ... | smoothie-queuer --headless --stdin
+  --progress-json           With --headless: report events as one JSON object per line
+                            (task_started, progress, task_completed, task_failed,
+                            queue_finished, ...) instead of text
+  --max-parallel <N>        With --headless: render up to N tasks at once (1-8)
+  --priority <N>            With --headless: priority of the given tasks (higher runs first)
+  --retries <N>             With --headless: retry transient failures up to N times (0-10)
+  --timeout <MINUTES>       With --headless: kill renders running longer (0 for no limit)
+  --daemon                  Run without a window until killed, rendering the saved queue
+                            and what arrives in the watch folder or through the control
+                            API (both set up in the GUI's Settings)
+  --log-file <PATH>         With --daemon: log here instead of daemon.log in the
+                            settings folder
+  --on-stop <drain|suspend> With --daemon or --install-service: on a stop request, let
+                            running tasks finish (drain, the default) or stop them and
+                            render them again on the next start (suspend); a second
+                            request while draining suspends
+  --install-service         Register --daemon as a Windows service (needs an
+                            administrator prompt) or write a systemd user unit
+  --uninstall-service       Remove the service or systemd unit again
+  --export-settings <FILE>  Save the settings and queue templates to FILE and exit
+  --import-settings <FILE>  Replace the settings with those exported to FILE, add its
+                            queue templates and exit (e.g. to set up a --daemon box)
+  --data-dir <PATH>         Keep settings, the saved queue and logs here instead of the
+                            per-user folder
+  --rpc                     Run without a window, reading JSON-RPC 2.0 requests from stdin
+                            and writing responses and event notifications to stdout
+  --simulate                Demo mode: tasks are faked instead of running smoothie-rs
+  --sim-duration <SECS>     Seconds each simulated task takes (default 5)
+  --sim-failure-rate <0-1>  Chance that a simulated task fails (default 0.2)
+  -h, --help                Show this help
+
+Video files and URLs of the form smoothie-queue://add?path=<file>&recipe=<recipe.ini>
+are queued; if the queuer is already running they are sent to that window instead.
+
+Exit codes with --headless and --rpc:
+  0  All tasks completed (or none failed)
+  1  Some tasks failed
+  2  Invalid command line, or nothing to render
+  3  smoothie-rs was not found (with --rpc: and no \"configure\" request found it)";
+
+/// Parses the command line (without the program name).
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut mode = Mode::Gui;
+    let mut enqueue = Vec::new();
+    let mut simulation: Option<Simulation> = None;
+    let mut progress_json = false;
+    let mut read_stdin = false;
+    let mut log_file = None;
+    let mut on_stop = None;
+    let mut data_dir = None;
+    let mut run_options = RunOptions::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--rpc" => mode = Mode::Rpc,
+            "--headless" => mode = Mode::Headless,
+            "--daemon" => mode = Mode::Daemon,
+            "--service" => mode = Mode::Service,
+            "--install-service" => mode = Mode::InstallService,
+            "--uninstall-service" => mode = Mode::UninstallService,
+            "--on-stop" => on_stop = Some(parse_value::<String>(&arg, args.next())?.parse::<StopMode>()?),
+            "--export-settings" => mode = Mode::ExportSettings(PathBuf::from(parse_value::<String>(&arg, args.next())?)),
+            "--import-settings" => mode = Mode::ImportSettings(PathBuf::from(parse_value::<String>(&arg, args.next())?)),
+            "--data-dir" => data_dir = Some(PathBuf::from(parse_value::<String>(&arg, args.next())?)),
+            "--log-file" => log_file = Some(PathBuf::from(parse_value::<String>(&arg, args.next())?)),
+            "--progress-json" => progress_json = true,
+            "--stdin" => read_stdin = true,
+            "--max-parallel" => {
+                let max_parallel: usize = parse_value(&arg, args.next())?;
+                if !(1..=8).contains(&max_parallel) {
+                    return Err(format!("{} must be between 1 and 8", arg));
+                }
+                run_options.max_parallel = Some(max_parallel);
+            }
+            "--priority" => run_options.priority = Some(parse_value(&arg, args.next())?),
+            "--retries" => {
+                let retries: u32 = parse_value(&arg, args.next())?;
+                if retries > 10 {
+                    return Err(format!("{} must be between 0 and 10", arg));
+                }
+                run_options.retries = Some(retries);
+            }
+            "--timeout" => run_options.timeout_minutes = Some(parse_value(&arg, args.next())?),
+            "-h" | "--help" => mode = Mode::Help,
+            "--simulate" => {
+                simulation.get_or_insert_with(Simulation::default);
+            }
+            "--sim-duration" => {
+                let secs: f64 = parse_value(&arg, args.next())?;
+                if !(0.0..=86_400.0).contains(&secs) {
+                    return Err(format!("{} must be between 0 and 86400", arg));
+                }
+                simulation.get_or_insert_with(Simulation::default).duration =
+                    Duration::from_secs_f64(secs);
+            }
+            "--sim-failure-rate" => {
+                let rate: f64 = parse_value(&arg, args.next())?;
+                if !(0.0..=1.0).contains(&rate) {
+                    return Err(format!("{} must be between 0 and 1", arg));
+                }
+                simulation.get_or_insert_with(Simulation::default).failure_rate = rate;
+            }
+            url if is_queue_url(url) => enqueue.push(parse_queue_url(url)?),
+            other if other.starts_with('-') => return Err(format!("Unknown argument: {}", other)),
+            // Explorer passes absolute paths, but the running instance has its own
+            // working directory so make shell-relative ones absolute too
+            file => enqueue.push(ControlCommand::AddTask {
+                path: std::path::absolute(file).unwrap_or_else(|_| PathBuf::from(file)),
+                recipe: None,
+            }),
+        }
+    }
+
+    if matches!(mode, Mode::Rpc | Mode::Daemon | Mode::Service) && !enqueue.is_empty() {
+        return Err("Files and URLs cannot be combined with --rpc or --daemon".to_string());
+    }
+    if log_file.is_some() && !matches!(mode, Mode::Daemon | Mode::Service) {
+        return Err("--log-file needs --daemon".to_string());
+    }
+    if on_stop.is_some() && !matches!(mode, Mode::Daemon | Mode::Service | Mode::InstallService) {
+        return Err("--on-stop needs --daemon or --install-service".to_string());
+    }
+    if (progress_json || read_stdin) && mode != Mode::Headless {
+        return Err("--progress-json and --stdin need --headless".to_string());
+    }
+    if run_options.is_set() && mode != Mode::Headless {
+        return Err("--max-parallel, --priority, --retries and --timeout need --headless".to_string());
+    }
+    if mode == Mode::Headless && enqueue.is_empty() && !read_stdin {
+        return Err("--headless needs files to render (or --stdin)".to_string());
+    }
+    Ok(Args {
+        mode,
+        enqueue,
+        simulation,
+        progress_json,
+        read_stdin,
+        log_file,
+        on_stop: on_stop.unwrap_or(StopMode::Drain),
+        data_dir,
+        run_options,
+    })
+}
+
+fn parse_value<T: std::str::FromStr>(option: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("{} needs a value", option))?;
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value for {}: {}", option, value))
+}
+
+fn is_queue_url(arg: &str) -> bool {
+    arg.get(..URL_SCHEME.len() + 1)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(&format!("{}:", URL_SCHEME)))
+}
+
+/// Parses `smoothie-queue://add?path=...&recipe=...` (recipe optional).
+fn parse_queue_url(url: &str) -> Result<ControlCommand, String> {
+    let rest = url[URL_SCHEME.len() + 1..].trim_start_matches('/');
+    let (action, query) = rest.split_once('?').unwrap_or((rest, ""));
+    // Browsers may normalize `add?` to `add/?`
+    if !action.trim_end_matches('/').eq_ignore_ascii_case("add") {
+        return Err(format!("Unsupported action in {}", url));
+    }
+
+    let mut path = None;
+    let mut recipe = None;
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        // Query values may encode spaces as `+`
+        let value = queue::percent_decode(&value.replace('+', " ")).ok_or_else(|| format!("Invalid encoding in {}", url))?;
+        match key {
+            "path" => path = Some(PathBuf::from(value)),
+            "recipe" if !value.is_empty() => recipe = Some(PathBuf::from(value)),
+            _ => log::debug!("Ignoring URL parameter {:?}", key),
+        }
+    }
+
+    match path {
+        Some(path) if !path.as_os_str().is_empty() => Ok(ControlCommand::AddTask { path, recipe }),
+        _ => Err(format!("Missing path in {}", url)),
+    }
+}
@@ -0,0 +1,254 @@
+use eframe::egui;
+use smoothie_queuer_core::ffmpeg;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Length of the stretch of video loaded at a time. Every frame of both clips is
+/// kept as a texture, so this stays short.
+const CLIP_SECONDS: f64 = 4.0;
+/// Both clips are scaled down to this height.
+const MAX_HEIGHT: u32 = 360;
+/// Outputs with a higher frame rate are sampled down to this.
+const MAX_FPS: f64 = 120.0;
+/// Playback speeds offered, as a fraction of real time.
+const SPEEDS: [f64; 4] = [1.0, 0.5, 0.25, 0.1];
+
+/// Frames of the input and output, decoded at the same points in time.
+struct Clips {
+    size: [usize; 2],
+    fps: f64,
+    input: Vec<Vec<u8>>, // RGBA
+    output: Vec<Vec<u8>>,
+}
+
+enum State {
+    Loading(Receiver<Result<Clips, String>>),
+    Failed(String),
+    Ready {
+        fps: f64,
+        frames: Vec<(egui::TextureHandle, egui::TextureHandle)>, // Input, output
+    },
+}
+
+/// Window playing a stretch of a completed task's input and output side by side,
+/// in sync, or stepping through it frame by frame. The frames are decoded with
+/// ffmpeg on a helper thread, sampled at the output's frame rate so the input's
+/// repeated frames show where the output was smoothed.
+pub struct ComparePlayer {
+    pub task_id: usize,
+    title: String,
+    input: PathBuf,
+    output: PathBuf,
+    ffmpeg_path: PathBuf,
+    max_start: f64,     // Seconds; the last start that still leaves a full clip
+    start_seconds: f64, // Where the loaded stretch begins
+    state: State,
+    position: usize,                   // Frame shown
+    playing: Option<(Instant, usize)>, // When playback started, and from which frame
+    speed: f64,
+}
+
+impl ComparePlayer {
+    /// Opens the player on the first seconds of `input` and `output`; `length`
+    /// is the input's, if known, to bound where a stretch can start.
+    pub fn open(
+        ctx: &egui::Context,
+        task_id: usize,
+        input: &Path,
+        output: &Path,
+        length: Option<Duration>,
+        ffmpeg_path: &Path,
+    ) -> Self {
+        let name = output.file_name().unwrap_or_default().to_string_lossy();
+        let mut player = Self {
+            task_id,
+            title: format!("Side by Side: {}", name),
+            input: input.to_path_buf(),
+            output: output.to_path_buf(),
+            ffmpeg_path: ffmpeg_path.to_path_buf(),
+            max_start: length.map_or(3600.0, |length| (length.as_secs_f64() - CLIP_SECONDS).max(0.0)),
+            start_seconds: 0.0,
+            state: State::Failed(String::new()),
+            position: 0,
+            playing: None,
+            speed: 1.0,
+        };
+        player.load(ctx);
+        player
+    }
+
+    /// Decodes `CLIP_SECONDS` from `start_seconds` on a helper thread.
+    fn load(&mut self, ctx: &egui::Context) {
+        let (tx, rx) = mpsc::channel();
+        let (input, output, ffmpeg_path) = (self.input.clone(), self.output.clone(), self.ffmpeg_path.clone());
+        let start = Duration::from_secs_f64(self.start_seconds);
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let _ = tx.send(load_clips(&ffmpeg_path, &input, &output, start));
+            ctx.request_repaint();
+        });
+        self.state = State::Loading(rx);
+        self.position = 0;
+        self.playing = None;
+    }
+
+    /// Draws the window. Returns `false` once it was closed.
+    pub fn show(&mut self, ctx: &egui::Context) -> bool {
+        if let State::Loading(rx) = &self.state
+            && let Ok(result) = rx.try_recv()
+        {
+            self.state = match result {
+                Ok(clips) => State::Ready { fps: clips.fps, frames: upload(ctx, clips) },
+                Err(e) => State::Failed(e),
+            };
+        }
+
+        let mut open = true;
+        let mut reload = false;
+        egui::Window::new(&self.title)
+            .id(egui::Id::new(("compare_player", self.task_id)))
+            .open(&mut open)
+            .show(ctx, |ui| {
+                match &self.state {
+                    State::Loading(_) => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("Decoding frames with ffmpeg...");
+                        });
+                    }
+                    State::Failed(e) => {
+                        ui.colored_label(egui::Color32::RED, e);
+                    }
+                    State::Ready { frames, .. } if frames.is_empty() => {
+                        ui.label("No frames in this part of the video");
+                    }
+                    State::Ready { fps, frames } => {
+                        let count = frames.len();
+                        if let Some((started, from)) = self.playing {
+                            let advanced = (started.elapsed().as_secs_f64() * fps * self.speed) as usize;
+                            self.position = (from + advanced) % count;
+                            ctx.request_repaint();
+                        }
+                        let (input, output) = &frames[self.position.min(count - 1)];
+                        ui.horizontal(|ui| {
+                            for (label, texture) in [("Input", input), ("Output", output)] {
+                                ui.vertical(|ui| {
+                                    ui.label(label);
+                                    ui.image((texture.id(), texture.size_vec2()));
+                                });
+                            }
+                        });
+
+                        ui.horizontal(|ui| {
+                            let play_label = if self.playing.is_some() { "⏸" } else { "▶" };
+                            if ui.button(play_label).clicked() {
+                                self.playing = match self.playing {
+                                    Some(_) => None,
+                                    None => Some((Instant::now(), self.position)),
+                                };
+                            }
+                            if ui.button("⏮").on_hover_text("Previous frame").clicked() {
+                                self.playing = None;
+                                self.position = (self.position + count - 1) % count;
+                            }
+                            if ui.button("⏭").on_hover_text("Next frame").clicked() {
+                                self.playing = None;
+                                self.position = (self.position + 1) % count;
+                            }
+                            let mut position = self.position;
+                            if ui.add(egui::Slider::new(&mut position, 0..=count - 1).show_value(false)).changed() {
+                                self.playing = None;
+                                self.position = position;
+                            }
+                            ui.label(format!(
+                                "Frame {} / {} ({:.2} s)",
+                                self.position + 1,
+                                count,
+                                self.start_seconds + self.position as f64 / fps
+                            ));
+                            let previous_speed = self.speed;
+                            egui::ComboBox::from_id_source(("compare_speed", self.task_id))
+                                .selected_text(format!("{}x", self.speed))
+                                .show_ui(ui, |ui| {
+                                    for speed in SPEEDS {
+                                        ui.selectable_value(&mut self.speed, speed, format!("{}x", speed));
+                                    }
+                                });
+                            // Carry on from the current frame at the new speed
+                            if self.speed != previous_speed && self.playing.is_some() {
+                                self.playing = Some((Instant::now(), self.position));
+                            }
+                        });
+                    }
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Start at");
+                    ui.add(
+                        egui::DragValue::new(&mut self.start_seconds)
+                            .clamp_range(0.0..=self.max_start)
+                            .speed(0.5)
+                            .suffix(" s"),
+                    );
+                    reload = ui.add_enabled(!matches!(self.state, State::Loading(_)), egui::Button::new("Load"))
+                        .on_hover_text(format!(
+                            "Decode {} seconds of both videos from this point. Recipes that change the speed (timescale) put the output out of step",
+                            CLIP_SECONDS
+                        ))
+                        .clicked();
+                });
+            });
+
+        if reload {
+            self.load(ctx);
+        }
+        open
+    }
+}
+
+/// Decodes the same stretch of `input` and `output` at the output's size (scaled
+/// down to `MAX_HEIGHT`) and frame rate.
+fn load_clips(ffmpeg_path: &Path, input: &Path, output: &Path, start: Duration) -> Result<Clips, String> {
+    let (width, height) = ffmpeg::probe_resolution(ffmpeg_path, output)?;
+    let scaled_height = height.clamp(2, MAX_HEIGHT);
+    // Even dimensions, as yuv420p sources need
+    let scaled_width = ((width as u64 * scaled_height as u64 / height.max(1) as u64) as u32).max(2) & !1;
+    let size = (scaled_width, scaled_height & !1);
+    let fps = ffmpeg::probe_average_frame_rate(ffmpeg_path, output)
+        .unwrap_or(60.0)
+        .min(MAX_FPS);
+
+    let length = Duration::from_secs_f64(CLIP_SECONDS);
+    let mut output_frames = ffmpeg::decode_frames(ffmpeg_path, output, start, length, fps, size)?;
+    let mut input_frames = ffmpeg::decode_frames(ffmpeg_path, input, start, length, fps, size)?;
+    let count = output_frames.len().min(input_frames.len());
+    output_frames.truncate(count);
+    input_frames.truncate(count);
+    Ok(Clips {
+        size: [size.0 as usize, size.1 as usize],
+        fps,
+        input: input_frames,
+        output: output_frames,
+    })
+}
+
+fn upload(ctx: &egui::Context, clips: Clips) -> Vec<(egui::TextureHandle, egui::TextureHandle)> {
+    let texture = |name: String, rgba: &[u8]| {
+        let image = egui::ColorImage::from_rgba_unmultiplied(clips.size, rgba);
+        ctx.load_texture(name, image, egui::TextureOptions::LINEAR)
+    };
+    clips.input
+        .iter()
+        .zip(&clips.output)
+        .enumerate()
+        .map(|(index, (input, output))| {
+            (
+                texture(format!("compare_input_{}", index), input),
+                texture(format!("compare_output_{}", index), output),
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,428 @@
+use crate::api::{self, ControlCommand};
+use crate::cli;
+use crate::headless;
+use crate::mqtt::MqttPublisher;
+use crate::ntfy::NtfyNotifier;
+use crate::watch::FolderWatcher;
+use smoothie_queuer_core::archive;
+use smoothie_queuer_core::config::SmoothieConfig;
+use smoothie_queuer_core::queue::{QueueManager, TaskStatus};
+use smoothie_queuer_core::session;
+use smoothie_queuer_core::settings::{self, AppSettings};
+use smoothie_queuer_core::worker::{self, UpdateMessage};
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// How often the daemon saves the queue and starts the worker for new tasks.
+const TICK: Duration = Duration::from_secs(1);
+
+/// What happens to the running tasks when the daemon is asked to stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum StopMode {
+    /// Let the running tasks finish, then exit. Nothing new is started.
+    Drain = 1,
+    /// Stop the running tasks right away; they are pending again on the next start.
+    Suspend = 2,
+}
+
+impl std::str::FromStr for StopMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "drain" => Ok(StopMode::Drain),
+            "suspend" => Ok(StopMode::Suspend),
+            _ => Err(format!("Unknown stop mode {:?} (expected drain or suspend)", s)),
+        }
+    }
+}
+
+impl StopMode {
+    /// Name accepted by `--on-stop`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StopMode::Drain => "drain",
+            StopMode::Suspend => "suspend",
+        }
+    }
+
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(StopMode::Drain),
+            2 => Some(StopMode::Suspend),
+            _ => None,
+        }
+    }
+}
+
+/// 0 until the daemon is asked to stop, then the `StopMode` it was asked for.
+static STOP_REQUEST: AtomicU8 = AtomicU8::new(0);
+/// `StopMode` of the first stop request, from `--on-stop`.
+static ON_STOP: AtomicU8 = AtomicU8::new(StopMode::Drain as u8);
+
+/// Asks the daemon to stop as `--on-stop` says. Asking again while it drains
+/// suspends the running tasks instead. Only touches atomics, so it is safe to
+/// call from a signal handler.
+pub fn request_stop() {
+    let mode = if STOP_REQUEST.load(Ordering::SeqCst) == 0 {
+        ON_STOP.load(Ordering::SeqCst)
+    } else {
+        StopMode::Suspend as u8
+    };
+    STOP_REQUEST.fetch_max(mode, Ordering::SeqCst);
+}
+
+/// Asks the daemon to stop with `mode` whatever `--on-stop` says, e.g. when the
+/// machine shuts down and there is no time to drain.
+#[cfg(target_os = "windows")]
+pub fn request_stop_with(mode: StopMode) {
+    STOP_REQUEST.fetch_max(mode as u8, Ordering::SeqCst);
+}
+
+/// The stop the daemon was asked for, if any.
+pub fn stop_requested() -> Option<StopMode> {
+    StopMode::from_u8(STOP_REQUEST.load(Ordering::SeqCst))
+}
+
+/// Stops the daemon on SIGTERM (what systemd sends) and SIGINT.
+#[cfg(unix)]
+fn install_stop_handlers() {
+    extern "C" fn on_signal(_: libc::c_int) {
+        request_stop();
+    }
+    let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        libc::signal(libc::SIGTERM, handler);
+        libc::signal(libc::SIGINT, handler);
+    }
+}
+
+/// Stops the daemon on Ctrl+C and Ctrl+Break in its console. The service
+/// control manager's requests are handled in `service`.
+#[cfg(target_os = "windows")]
+fn install_stop_handlers() {
+    use winapi::shared::minwindef::{BOOL, DWORD, FALSE, TRUE};
+    use winapi::um::consoleapi::SetConsoleCtrlHandler;
+    use winapi::um::wincon::{CTRL_BREAK_EVENT, CTRL_C_EVENT};
+
+    unsafe extern "system" fn on_console_event(event: DWORD) -> BOOL {
+        match event {
+            CTRL_C_EVENT | CTRL_BREAK_EVENT => {
+                request_stop();
+                TRUE
+            }
+            _ => FALSE,
+        }
+    }
+    unsafe {
+        SetConsoleCtrlHandler(Some(on_console_event), TRUE);
+    }
+}
+
+/// Log file used when `--log-file` is not given.
+pub fn default_log_file() -> PathBuf {
+    settings::data_dir().join("daemon.log")
+}
+
+/// Sends log records (at info level unless `RUST_LOG` says otherwise) to
+/// `path`, appending to it. Falls back to stderr when it cannot be opened.
+pub fn init_logging(path: &Path) {
+    let mut builder = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"));
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    match OpenOptions::new().create(true).append(true).open(path) {
+        Ok(file) => {
+            builder.target(env_logger::Target::Pipe(Box::new(file)));
+        }
+        Err(e) => eprintln!("Cannot open log file {}: {}; logging to stderr", path.display(), e),
+    }
+    builder.init();
+}
+
+/// State of the daemon's worker between ticks.
+struct Daemon {
+    queue_manager: Arc<Mutex<QueueManager>>,
+    config: SmoothieConfig,
+    settings: AppSettings,
+    worker_tx: mpsc::Sender<UpdateMessage>,
+    worker: Option<JoinHandle<()>>,
+    mqtt: Option<MqttPublisher>,
+    ntfy: Option<NtfyNotifier>,
+    paused: bool,          // Paused through the control API or by the run budget
+    draining: bool,        // Stopping once the running tasks finish
+    session_saved: String, // Queue as last written, to skip unchanged saves
+}
+
+impl Daemon {
+    fn worker_running(&self) -> bool {
+        self.worker.as_ref().is_some_and(|handle| !handle.is_finished())
+    }
+
+    fn handle_command(&mut self, command: ControlCommand) {
+        match command {
+            ControlCommand::AddTask { path, recipe } => {
+                let mut skipped = Vec::new();
+                let added = headless::add_entry(
+                    &mut self.queue_manager.lock().expect("Failed to lock queue manager"),
+                    &self.settings,
+                    path.clone(),
+                    recipe.as_deref(),
+                    &self.config.recipe_path,
+                    None,
+                    &mut skipped,
+                );
+                for line in skipped {
+                    log::warn!("{}", line);
+                }
+                if added > 0 {
+                    log::info!("Queued {} task(s) from {:?}", added, path);
+                }
+            }
+            ControlCommand::StartWorker => {
+                if self.paused {
+                    log::info!("Queue resumed");
+                }
+                self.paused = false;
+            }
+            ControlCommand::PauseWorker => {
+                log::info!("Queue paused; the running task finishes first");
+                self.paused = true;
+                self.queue_manager.lock()
+                    .expect("Failed to lock queue manager")
+                    .stop_flags
+                    .request_stop();
+            }
+        }
+    }
+
+    fn handle_update(&mut self, update: UpdateMessage) {
+        let task = |id: usize| {
+            self.queue_manager.lock()
+                .expect("Failed to lock queue manager")
+                .tasks
+                .iter()
+                .find(|t| t.id == id)
+                .cloned()
+        };
+        match update {
+            UpdateMessage::TaskStarted(id) => {
+                if let Some(task) = task(id) {
+                    log::info!("Task {} started: {:?}", id, task.input_path);
+                    if let Some(mqtt) = &self.mqtt {
+                        mqtt.task_event("task_started", &task);
+                    }
+                }
+            }
+            UpdateMessage::TaskCompleted(id) => {
+                let Some(task) = task(id) else {
+                    return;
+                };
+                log::info!("Task {} completed: {:?}", id, task.output_path.as_ref().unwrap_or(&task.input_path));
+                if let Some(mqtt) = &self.mqtt {
+                    mqtt.task_event("task_completed", &task);
+                }
+                if let Some(ntfy) = &self.ntfy {
+                    ntfy.task_completed(&task);
+                }
+                if self.settings.auto_clear_completed {
+                    self.queue_manager.lock()
+                        .expect("Failed to lock queue manager")
+                        .archive_task(id);
+                }
+            }
+            UpdateMessage::TaskFailed(id, err_msg) => {
+                log::warn!("Task {} failed: {}", id, err_msg);
+                if let Some(task) = task(id) {
+                    if let Some(mqtt) = &self.mqtt {
+                        mqtt.task_event("task_failed", &task);
+                    }
+                    if let Some(ntfy) = &self.ntfy {
+                        ntfy.task_failed(&task);
+                    }
+                }
+            }
+            UpdateMessage::TaskCancelled(id) => log::info!("Task {} cancelled", id),
+            UpdateMessage::BudgetReached(pending) => {
+                // Starting again right away would ignore the budget
+                log::info!("Run budget reached with {} task(s) pending; paused until resumed", pending);
+                self.paused = true;
+            }
+            UpdateMessage::WorkerFinished => {
+                log::info!("Queue finished");
+                let manager = self.queue_manager.lock()
+                    .expect("Failed to lock queue manager");
+                if let Some(mqtt) = &self.mqtt {
+                    mqtt.queue_idle(&manager.tasks);
+                }
+                if let Some(ntfy) = &self.ntfy {
+                    ntfy.queue_finished(&manager.tasks);
+                }
+            }
+            UpdateMessage::TaskWaiting(_) | UpdateMessage::TaskDownloading(_) | UpdateMessage::TaskProgress(..) => {}
+        }
+    }
+
+    /// Starts the worker when it is idle, not paused and tasks are pending.
+    fn start_worker_if_needed(&mut self) {
+        if self.paused || self.draining || self.worker_running() {
+            return;
+        }
+        let pending = self.queue_manager.lock()
+            .expect("Failed to lock queue manager")
+            .tasks
+            .iter()
+            .any(|t| t.status == TaskStatus::Pending);
+        if pending {
+            self.worker = Some(worker::spawn_worker(
+                Arc::clone(&self.queue_manager),
+                self.worker_tx.clone(),
+                self.config.clone(),
+            ));
+        }
+    }
+
+    /// Lets the running tasks finish without starting new ones.
+    fn drain(&mut self) {
+        self.draining = true;
+        if self.worker_running() {
+            log::info!("Stopping once the running tasks finish");
+        }
+        self.queue_manager.lock()
+            .expect("Failed to lock queue manager")
+            .stop_flags
+            .request_stop();
+    }
+
+    /// Stops the running tasks and waits for the worker, leaving those tasks
+    /// pending so they render again on the next start.
+    fn suspend(&mut self) {
+        let Some(worker) = self.worker.take() else {
+            return;
+        };
+        let running: Vec<usize> = {
+            let manager = self.queue_manager.lock()
+                .expect("Failed to lock queue manager");
+            manager.stop_flags.request_stop();
+            manager.stop_flags.request_force_stop();
+            manager.tasks
+                .iter()
+                .filter(|t| matches!(t.status, TaskStatus::Waiting | TaskStatus::Downloading | TaskStatus::Running))
+                .map(|t| t.id)
+                .collect()
+        };
+        if !running.is_empty() {
+            log::info!("Suspending {} running task(s)", running.len());
+        }
+        let _ = worker.join();
+
+        let mut manager = self.queue_manager.lock()
+            .expect("Failed to lock queue manager");
+        for id in running {
+            manager.requeue_cancelled(id, "Suspended when the daemon stopped; pending again");
+        }
+    }
+
+    /// Saves the queue with `session::write` if it changed since the last save.
+    fn save_session(&mut self) {
+        let serialized = session::serialize(&self.queue_manager.lock().expect("Failed to lock queue manager"));
+        let contents = match serialized {
+            Ok(contents) => contents,
+            Err(e) => {
+                log::error!("{}", e);
+                return;
+            }
+        };
+        if contents == self.session_saved {
+            return;
+        }
+        match session::write(&contents) {
+            Ok(()) => self.session_saved = contents,
+            Err(e) => log::error!("Failed to save the queue: {}", e),
+        }
+    }
+}
+
+/// Runs without a window until asked to stop (see `request_stop`): restores the
+/// saved queue, queues what lands in the watch folder or comes in through the
+/// control API (as configured in the GUI's settings) and renders whatever is
+/// pending. `on_stop` says what happens to the running tasks on a stop request.
+/// The queue is saved as it changes, so tasks interrupted by a kill are pending
+/// again on the next start.
+pub fn run(config: Option<SmoothieConfig>, on_stop: StopMode) -> i32 {
+    let Some(mut config) = config else {
+        log::error!("smoothie-rs not found; place smoothie-queuer next to smoothie-rs or add it to PATH");
+        return cli::EXIT_EXECUTABLE_MISSING;
+    };
+    let settings = AppSettings::load();
+    settings.apply_to(&mut config);
+    ON_STOP.store(on_stop as u8, Ordering::SeqCst);
+    install_stop_handlers();
+
+    if let Some(max_age) = settings.history_retention()
+        && let Err(e) = archive::prune(max_age)
+    {
+        log::warn!("Failed to prune the history: {}", e);
+    }
+    let queue_manager = Arc::new(Mutex::new(session::load()));
+    let (control_tx, control_rx) = mpsc::channel();
+    if settings.api_enabled {
+        api::spawn(settings.api_port, settings.api_lan, Arc::clone(&queue_manager), control_tx.clone(), || {});
+    }
+    let watcher = settings.active_watch_folder().map(|folder| {
+        FolderWatcher::spawn(
+            folder.clone(),
+            settings.watch_recipe.clone(),
+            settings.allowed_extensions.clone(),
+            control_tx.clone(),
+            || {},
+        )
+    });
+    if !settings.api_enabled && watcher.is_none() {
+        log::warn!("Neither a watch folder nor the control API is enabled in Settings; only the saved queue is rendered");
+    }
+
+    let (worker_tx, worker_rx) = mpsc::channel();
+    let mqtt = MqttPublisher::from_settings(&settings);
+    let ntfy = NtfyNotifier::from_settings(&settings);
+    let mut daemon = Daemon {
+        queue_manager,
+        config,
+        settings,
+        worker_tx,
+        worker: None,
+        mqtt,
+        ntfy,
+        paused: false,
+        draining: false,
+        session_saved: String::new(),
+    };
+    log::info!("Daemon started");
+    loop {
+        match stop_requested() {
+            Some(StopMode::Suspend) => {
+                daemon.suspend();
+                break;
+            }
+            Some(StopMode::Drain) if !daemon.draining => daemon.drain(),
+            Some(StopMode::Drain) if !daemon.worker_running() => break,
+            _ => {}
+        }
+        if let Ok(command) = control_rx.recv_timeout(TICK) {
+            daemon.handle_command(command);
+        }
+        for update in worker_rx.try_iter() {
+            daemon.handle_update(update);
+        }
+        daemon.start_worker_if_needed();
+        daemon.save_session();
+    }
+    daemon.save_session();
+    log::info!("Daemon stopped");
+    cli::EXIT_OK
+}
@@ -0,0 +1,170 @@
+use smoothie_queuer_core::queue::{QueueManager, TaskStatus, VideoTask};
+use std::fmt::Write;
+use std::time::SystemTime;
+
+/// Seconds between reloads of the status page.
+const REFRESH_SECONDS: u32 = 10;
+/// Failed tasks listed under "Recent failures", newest first.
+const RECENT_FAILURES: usize = 10;
+
+const STYLE: &str = "\
+body{font-family:system-ui,sans-serif;margin:0 auto;max-width:720px;padding:12px;background:#1b1b1b;color:#ddd}\
+h1{font-size:1.3em}h2{font-size:1.05em;margin-top:1.5em;border-bottom:1px solid #444}\
+progress{width:100%;height:1.2em}.muted{color:#888}.failed{color:#e66}.warning{color:#db4}\
+table{width:100%;border-collapse:collapse}td{padding:4px 6px;border-bottom:1px solid #333;vertical-align:top;word-break:break-all}";
+
+/// Escapes text for use in HTML content and attribute values.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn file_name(task: &VideoTask) -> String {
+    let name = task.input_path.file_name().unwrap_or(task.input_path.as_os_str());
+    escape(&name.to_string_lossy())
+}
+
+/// Local wall clock time (`14:02`) of `time`.
+fn clock_time(time: SystemTime) -> String {
+    jiff::Timestamp::try_from(time)
+        .map(|timestamp| timestamp.to_zoned(jiff::tz::TimeZone::system()).strftime("%H:%M").to_string())
+        .unwrap_or_else(|_| "?".to_string())
+}
+
+fn status_label(status: &TaskStatus) -> &'static str {
+    match status {
+        TaskStatus::Pending => "Pending",
+        TaskStatus::Waiting => "Waiting for input",
+        TaskStatus::Downloading => "Downloading",
+        TaskStatus::Running => "Rendering",
+        TaskStatus::Completed => "Completed",
+        TaskStatus::Failed(_) => "Failed",
+        TaskStatus::Cancelled => "Cancelled",
+    }
+}
+
+/// The read-only status page served at `/` by the control API: the running
+/// tasks with their progress, the queue and the latest failures. It reloads
+/// itself, so it needs no script and works on any phone browser.
+pub fn render(manager: &QueueManager) -> String {
+    let count = |wanted: fn(&TaskStatus) -> bool| manager.tasks.iter().filter(|t| wanted(&t.status)).count();
+    let running: Vec<&VideoTask> = manager
+        .tasks
+        .iter()
+        .filter(|t| matches!(t.status, TaskStatus::Waiting | TaskStatus::Downloading | TaskStatus::Running))
+        .collect();
+
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\">\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+         <meta http-equiv=\"refresh\" content=\"{}\">\
+         <title>Smoothie Queuer</title><style>{}</style></head><body><h1>Smoothie Queuer</h1>",
+        REFRESH_SECONDS, STYLE
+    );
+
+    let _ = write!(
+        html,
+        "<p>{} pending · {} running · {} completed · {} failed · {} cancelled · {} archived</p>",
+        count(|s| *s == TaskStatus::Pending),
+        running.len(),
+        count(|s| *s == TaskStatus::Completed),
+        count(|s| matches!(s, TaskStatus::Failed(_))),
+        count(|s| *s == TaskStatus::Cancelled),
+        manager.archived_count(),
+    );
+    if manager.stop_flags.is_suspended() {
+        html.push_str("<p class=\"warning\">Suspended</p>");
+    } else if manager.stop_flags.is_stop_requested() {
+        html.push_str("<p class=\"warning\">Pausing after the running task(s)</p>");
+    }
+    if manager.budget.is_some_and(|budget| budget.reached) {
+        html.push_str("<p class=\"warning\">Run budget reached; the queue is paused</p>");
+    }
+
+    html.push_str("<h2>Now rendering</h2>");
+    if running.is_empty() {
+        html.push_str("<p class=\"muted\">Nothing is running</p>");
+    }
+    for task in &running {
+        let _ = write!(html, "<p><b>{}</b> <span class=\"muted\">#{} · {}", file_name(task), task.id, status_label(&task.status));
+        if let Some(started_at) = task.started_at {
+            let _ = write!(html, " since {}", clock_time(started_at));
+        }
+        html.push_str("</span><br>");
+        match task.progress {
+            Some(progress) => {
+                let _ = write!(html, "<progress max=\"1\" value=\"{:.3}\"></progress> {:.0}%", progress, progress * 100.0);
+            }
+            None => html.push_str("<progress></progress>"),
+        }
+        html.push_str("</p>");
+    }
+
+    html.push_str("<h2>Queue</h2>");
+    if manager.tasks.is_empty() {
+        html.push_str("<p class=\"muted\">The queue is empty</p>");
+    } else {
+        html.push_str("<table>");
+        for task in &manager.tasks {
+            let class = match &task.status {
+                TaskStatus::Failed(_) => " class=\"failed\"",
+                TaskStatus::Completed | TaskStatus::Cancelled => " class=\"muted\"",
+                _ => "",
+            };
+            let _ = write!(
+                html,
+                "<tr{}><td>#{}</td><td>{}</td><td>{}",
+                class,
+                task.id,
+                file_name(task),
+                status_label(&task.status)
+            );
+            if let Some(warning) = &task.warning {
+                let _ = write!(html, " <span class=\"warning\" title=\"{}\">⚠</span>", escape(warning));
+            }
+            html.push_str("</td></tr>");
+        }
+        html.push_str("</table>");
+    }
+
+    let mut failures: Vec<&VideoTask> = manager
+        .tasks
+        .iter()
+        .filter(|t| matches!(t.status, TaskStatus::Failed(_)))
+        .collect();
+    failures.sort_by_key(|t| std::cmp::Reverse(t.finished_at));
+    html.push_str("<h2>Recent failures</h2>");
+    if failures.is_empty() {
+        html.push_str("<p class=\"muted\">None</p>");
+    }
+    for task in failures.into_iter().take(RECENT_FAILURES) {
+        let TaskStatus::Failed(error) = &task.status else {
+            continue;
+        };
+        let _ = write!(html, "<p><b>{}</b> <span class=\"muted\">#{}", file_name(task), task.id);
+        if let Some(finished_at) = task.finished_at {
+            let _ = write!(html, " at {}", clock_time(finished_at));
+        }
+        let _ = write!(html, "</span><br><span class=\"failed\">{}</span></p>", escape(error));
+    }
+
+    let _ = write!(
+        html,
+        "<p class=\"muted\">Updated {}; refreshes every {} seconds</p></body></html>",
+        clock_time(SystemTime::now()),
+        REFRESH_SECONDS
+    );
+    html
+}
@@ -0,0 +1,84 @@
+use std::path::Path;
+
+/// Starts an OS drag of `files`, so finished outputs can be dropped into
+/// Explorer, an editor or a chat window. Call it while the mouse button that
+/// started the drag is still down; it returns once the files were dropped (or
+/// the drag was cancelled), like any Windows drag loop.
+#[cfg(target_os = "windows")]
+pub fn drag_files(files: &[&Path]) -> Result<(), String> {
+    use std::ffi::c_void;
+    use std::os::windows::ffi::OsStrExt;
+    use std::ptr;
+    use winapi::shared::guiddef::GUID;
+    use winapi::shared::minwindef::DWORD;
+    use winapi::shared::windef::HWND;
+    use winapi::shared::winerror::{FAILED, HRESULT};
+    use winapi::um::unknwnbase::IUnknown;
+    use winapi::um::winuser::GetActiveWindow;
+
+    // Not in winapi 0.3; declared as shell32 exports them
+    #[link(name = "shell32")]
+    unsafe extern "system" {
+        fn ILCreateFromPathW(path: *const u16) -> *mut c_void;
+        fn ILFree(pidl: *mut c_void);
+        fn SHCreateDataObject(
+            folder: *const c_void,
+            count: u32,
+            items: *const *const c_void,
+            inner: *mut c_void,
+            riid: *const GUID,
+            object: *mut *mut c_void,
+        ) -> HRESULT;
+        fn SHDoDragDrop(hwnd: HWND, data: *mut c_void, source: *mut c_void, effects: DWORD, effect: *mut DWORD) -> HRESULT;
+    }
+    const IID_IDATAOBJECT: GUID = GUID {
+        Data1: 0x0000010e,
+        Data2: 0x0000,
+        Data3: 0x0000,
+        Data4: [0xc0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46],
+    };
+    const DROPEFFECT_COPY: DWORD = 1;
+    const DROPEFFECT_LINK: DWORD = 4;
+
+    // Absolute item ID lists are relative to the desktop, which is what a
+    // null folder stands for
+    let mut pidls = Vec::with_capacity(files.len());
+    for file in files {
+        let wide: Vec<u16> = file.as_os_str().encode_wide().chain(Some(0)).collect();
+        let pidl = unsafe { ILCreateFromPathW(wide.as_ptr()) };
+        if pidl.is_null() {
+            pidls.iter().for_each(|&pidl| unsafe { ILFree(pidl) });
+            return Err(format!("{:?} cannot be dragged; does it still exist?", file));
+        }
+        pidls.push(pidl);
+    }
+    let items: Vec<*const c_void> = pidls.iter().map(|&pidl| pidl as *const c_void).collect();
+
+    let result = unsafe {
+        let mut data: *mut c_void = ptr::null_mut();
+        let hr = SHCreateDataObject(
+            ptr::null(),
+            items.len() as u32,
+            items.as_ptr(),
+            ptr::null_mut(),
+            &IID_IDATAOBJECT,
+            &mut data,
+        );
+        if FAILED(hr) || data.is_null() {
+            Err(format!("Failed to prepare the drag (0x{:08x})", hr))
+        } else {
+            // A null drop source makes the shell supply the standard one
+            let mut effect: DWORD = 0;
+            let hr = SHDoDragDrop(GetActiveWindow(), data, ptr::null_mut(), DROPEFFECT_COPY | DROPEFFECT_LINK, &mut effect);
+            (*(data as *mut IUnknown)).Release();
+            if FAILED(hr) { Err(format!("Dragging failed (0x{:08x})", hr)) } else { Ok(()) }
+        }
+    };
+    pidls.iter().for_each(|&pidl| unsafe { ILFree(pidl) });
+    result
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn drag_files(_files: &[&Path]) -> Result<(), String> {
+    Err("Dragging outputs out of the queue is only supported on Windows".to_string())
+}
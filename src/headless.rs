@@ -0,0 +1,253 @@
+use crate::api::{ControlCommand, TaskInfo};
+use crate::cli::{self, RunOptions};
+use serde_json::{Value, json};
+use smoothie_queuer_core::config::SmoothieConfig;
+use smoothie_queuer_core::queue::{self, ImageSequence, QueueManager, TaskStatus, VideoTask};
+use smoothie_queuer_core::settings::AppSettings;
+use smoothie_queuer_core::worker::{self, UpdateMessage};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, mpsc};
+use std::time::Duration;
+
+/// Reports queue events on stdout: as one JSON object per line with
+/// `--progress-json`, otherwise as plain text.
+struct Reporter {
+    json: bool,
+    last_percent: HashMap<usize, u32>, // Progress is reported once per whole percent
+}
+
+impl Reporter {
+    fn emit(&self, event: &str, fields: Value, text: impl FnOnce() -> String) {
+        let mut stdout = io::stdout().lock();
+        let line = if self.json {
+            let mut message = json!({ "event": event });
+            if let (Some(message), Value::Object(fields)) = (message.as_object_mut(), fields) {
+                message.extend(fields);
+            }
+            message.to_string()
+        } else {
+            text()
+        };
+        if writeln!(stdout, "{}", line).and_then(|_| stdout.flush()).is_err() {
+            log::error!("Failed to write progress to stdout");
+        }
+    }
+
+    fn task_event(&self, event: &str, task: &VideoTask, text: impl FnOnce(&str) -> String) {
+        let name = task.input_path.file_name().unwrap_or_default().to_string_lossy();
+        self.emit(event, json!({ "task": TaskInfo::from(task) }), || text(&name));
+    }
+
+    fn progress(&mut self, task: &VideoTask, progress: f32) {
+        let percent = (progress * 100.0) as u32;
+        if self.last_percent.insert(task.id, percent) == Some(percent) {
+            return;
+        }
+        // Plain text output stays to one line per task event
+        if self.json {
+            self.emit("progress", json!({ "id": task.id, "progress": progress }), String::new);
+        }
+    }
+}
+
+/// Queues `path` (a video, a folder of frames or a `.txt`/`.m3u` list) and returns how
+/// many tasks were added. Its output goes next to the input. Tasks use `recipe` when
+/// given, else `default_recipe`, and then get what the input rules set; `priority`,
+/// when given, wins over theirs. Why entries were left out is added to `skipped`.
+/// Also used by the daemon.
+pub fn add_entry(
+    manager: &mut QueueManager,
+    settings: &AppSettings,
+    path: PathBuf,
+    recipe: Option<&Path>,
+    default_recipe: &Path,
+    priority: Option<i32>,
+    skipped: &mut Vec<String>,
+) -> usize {
+    if queue::is_playlist(&path) {
+        return match queue::read_playlist(&path) {
+            Ok(entries) => entries
+                .into_iter()
+                .filter(|entry| !queue::is_playlist(entry))
+                .map(|entry| add_entry(manager, settings, entry, recipe, default_recipe, priority, skipped))
+                .sum(),
+            Err(e) => {
+                skipped.push(e);
+                0
+            }
+        };
+    }
+    let image_sequence = if path.is_dir() {
+        let Some(sequence) = ImageSequence::detect(&path, settings.sequence_frame_rate) else {
+            skipped.push(format!("Skipping {}: no image sequence in this folder", path.display()));
+            return 0;
+        };
+        Some(sequence)
+    } else if !path.is_file() {
+        skipped.push(format!("Skipping {}: file not found", path.display()));
+        return 0;
+    } else if settings.accepts_input(&path) {
+        None
+    } else {
+        skipped.push(format!("Skipping {}: not a supported video type", path.display()));
+        return 0;
+    };
+
+    let output_dir = PathBuf::from(path.parent().unwrap_or(Path::new(".")));
+    let id = manager.add_new_task(path, output_dir, recipe.unwrap_or(default_recipe).to_path_buf());
+    let task = manager.task_mut(id).expect("Task was just added");
+    settings.rule_outcome(&task.input_path).apply_to(task, recipe.is_some());
+    task.image_sequence = image_sequence;
+    if let Some(priority) = priority {
+        task.priority = priority;
+    }
+    task.post_command = settings.post_command_template();
+    1
+}
+
+/// Paths piped in on stdin, one per line (blank lines are skipped). Relative
+/// paths are taken from the working directory.
+pub fn read_stdin_paths() -> Vec<ControlCommand> {
+    io::stdin()
+        .lock()
+        .lines()
+        .map_while(Result::ok)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let path = PathBuf::from(line.trim_end_matches('\r'));
+            ControlCommand::AddTask {
+                path: std::path::absolute(&path).unwrap_or(path),
+                recipe: None,
+            }
+        })
+        .collect()
+}
+
+/// Renders the files given on the command line without a window and returns the
+/// process exit code once the queue is done (see `cli::EXIT_*`). The worker uses
+/// the GUI's saved settings, with `options` from the command line taking precedence.
+pub fn run(
+    config: Option<SmoothieConfig>,
+    commands: Vec<ControlCommand>,
+    progress_json: bool,
+    options: RunOptions,
+) -> i32 {
+    let Some(mut config) = config else {
+        eprintln!("smoothie-rs not found; place smoothie-queuer next to smoothie-rs or add it to PATH");
+        return cli::EXIT_EXECUTABLE_MISSING;
+    };
+    let settings = AppSettings::load();
+
+    let mut manager = QueueManager::new();
+    let mut skipped = Vec::new();
+    for command in commands {
+        if let ControlCommand::AddTask { path, recipe } = command {
+            add_entry(
+                &mut manager,
+                &settings,
+                path,
+                recipe.as_deref(),
+                &config.recipe_path,
+                options.priority,
+                &mut skipped,
+            );
+        }
+    }
+    for line in skipped {
+        eprintln!("{}", line);
+    }
+    if manager.tasks.is_empty() {
+        eprintln!("Nothing to render");
+        return cli::EXIT_CONFIG_ERROR;
+    }
+    let queue_manager = Arc::new(Mutex::new(manager));
+
+    settings.apply_to(&mut config);
+    if let Some(max_parallel) = options.max_parallel {
+        config.max_parallel = max_parallel;
+    }
+    if let Some(retries) = options.retries {
+        config.retries = retries;
+    }
+    if let Some(minutes) = options.timeout_minutes {
+        config.task_timeout = (minutes > 0).then(|| Duration::from_secs(u64::from(minutes) * 60));
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let handle = worker::spawn_worker(Arc::clone(&queue_manager), tx, config);
+    let mut reporter = Reporter {
+        json: progress_json,
+        last_percent: HashMap::new(),
+    };
+    for update in rx {
+        let manager = queue_manager.lock()
+            .expect("Failed to lock queue manager");
+        let task = |id: usize| manager.tasks.iter().find(|t| t.id == id);
+        match update {
+            UpdateMessage::TaskWaiting(id) => {
+                if let Some(task) = task(id) {
+                    reporter.task_event("task_waiting", task, |name| format!("Waiting for {} to be written", name));
+                }
+            }
+            UpdateMessage::TaskDownloading(id) => {
+                if let Some(task) = task(id) {
+                    reporter.task_event("task_downloading", task, |name| format!("Downloading {}", name));
+                }
+            }
+            UpdateMessage::TaskStarted(id) => {
+                if let Some(task) = task(id) {
+                    reporter.task_event("task_started", task, |name| format!("Started {}", name));
+                }
+            }
+            UpdateMessage::TaskProgress(id, progress) => {
+                if let Some(task) = task(id) {
+                    reporter.progress(task, progress);
+                }
+            }
+            UpdateMessage::TaskCompleted(id) => {
+                if let Some(task) = task(id) {
+                    reporter.task_event("task_completed", task, |name| match &task.output_path {
+                        Some(output) => format!("Completed {} -> {}", name, output.display()),
+                        None => format!("Completed {}", name),
+                    });
+                }
+            }
+            UpdateMessage::TaskFailed(id, err_msg) => {
+                if let Some(task) = task(id) {
+                    reporter.task_event("task_failed", task, |name| format!("Failed {}: {}", name, err_msg));
+                }
+            }
+            UpdateMessage::TaskCancelled(id) => {
+                if let Some(task) = task(id) {
+                    reporter.task_event("task_cancelled", task, |name| format!("Cancelled {}", name));
+                }
+            }
+            UpdateMessage::BudgetReached(pending) => reporter.emit("budget_reached", json!({ "pending": pending }), || {
+                format!("Run budget reached; {} task(s) left pending", pending)
+            }),
+            UpdateMessage::WorkerFinished => break,
+        }
+    }
+    let _ = handle.join();
+
+    let manager = queue_manager.lock()
+        .expect("Failed to lock queue manager");
+    let count = |matches: fn(&TaskStatus) -> bool| manager.tasks.iter().filter(|t| matches(&t.status)).count();
+    let completed = count(|status| *status == TaskStatus::Completed);
+    let failed = count(|status| matches!(status, TaskStatus::Failed(_)));
+    let cancelled = count(|status| *status == TaskStatus::Cancelled);
+    let pending = count(|status| *status == TaskStatus::Pending);
+    reporter.emit(
+        "queue_finished",
+        json!({ "completed": completed, "failed": failed, "cancelled": cancelled, "pending": pending }),
+        || format!("{} completed, {} failed, {} cancelled, {} pending", completed, failed, cancelled, pending),
+    );
+
+    if failed > 0 {
+        cli::EXIT_TASKS_FAILED
+    } else {
+        cli::EXIT_OK
+    }
+}
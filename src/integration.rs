@@ -0,0 +1,137 @@
+use crate::cli::URL_SCHEME;
+
+/// Registers `smoothie-queue://` links for the current user so browsers and
+/// launchers open them with this executable.
+#[cfg(target_os = "windows")]
+pub fn register_url_protocol() -> Result<(), String> {
+    let exe = std::env::current_exe()
+        .map_err(|e| format!("Failed to locate the queuer executable: {}", e))?;
+    let key = format!(r"HKCU\Software\Classes\{}", URL_SCHEME);
+    let open_command = format!("\"{}\" \"%1\"", exe.display());
+
+    reg_add(&key, None, "URL:Smoothie Queue")?;
+    reg_add(&key, Some("URL Protocol"), "")?;
+    reg_add(&format!(r"{}\shell\open\command", key), None, &open_command)?;
+    log::info!("Registered {}:// links for {:?}", URL_SCHEME, exe);
+    Ok(())
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn register_url_protocol() -> Result<(), String> {
+    Err(format!(
+        "Registering {}:// links is only supported on Windows; add a desktop entry with \
+         MimeType=x-scheme-handler/{} instead",
+        URL_SCHEME, URL_SCHEME
+    ))
+}
+
+/// Sets a string value under `HKEY_CURRENT_USER` with `reg.exe` (`None` is the
+/// key's default value).
+#[cfg(target_os = "windows")]
+fn reg_add(key: &str, value_name: Option<&str>, data: &str) -> Result<(), String> {
+    use std::os::windows::process::CommandExt;
+
+    let mut command = std::process::Command::new("reg");
+    command.args(["add", key]);
+    match value_name {
+        Some(name) => command.args(["/v", name]),
+        None => command.arg("/ve"),
+    };
+    command.args(["/t", "REG_SZ", "/d", data, "/f"]);
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+    let output = command
+        .output()
+        .map_err(|e| format!("Failed to run reg.exe: {}", e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "reg add {} failed: {}",
+            key,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+/// Verb key name used for the "Queue in Smoothie" Explorer entry.
+#[cfg(target_os = "windows")]
+const SHELL_VERB: &str = "SmoothieQueue";
+
+/// Label shown in Explorer's context menu and Send To menu.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+const SHELL_LABEL: &str = "Queue in Smoothie";
+
+#[cfg(target_os = "windows")]
+fn shell_verb_key(extension: &str) -> String {
+    format!(
+        r"HKCU\Software\Classes\SystemFileAssociations\.{}\shell\{}",
+        extension, SHELL_VERB
+    )
+}
+
+/// `Send To` entry; unlike the context menu, Explorer passes every selected file
+/// to it in a single launch.
+#[cfg(target_os = "windows")]
+fn send_to_path() -> Option<std::path::PathBuf> {
+    std::env::var_os("APPDATA").map(|appdata| {
+        std::path::PathBuf::from(appdata)
+            .join(r"Microsoft\Windows\SendTo")
+            .join(format!("{}.cmd", SHELL_LABEL))
+    })
+}
+
+/// Adds "Queue in Smoothie" to the Explorer context menu of videos with one of
+/// `extensions` and to the Send To menu, for the current user.
+#[cfg(target_os = "windows")]
+pub fn install_shell_menu(extensions: &[String]) -> Result<(), String> {
+    let exe = std::env::current_exe()
+        .map_err(|e| format!("Failed to locate the queuer executable: {}", e))?;
+    let open_command = format!("\"{}\" \"%1\"", exe.display());
+
+    for extension in extensions {
+        let key = shell_verb_key(extension);
+        reg_add(&key, None, SHELL_LABEL)?;
+        reg_add(&key, Some("Icon"), &exe.display().to_string())?;
+        reg_add(&format!(r"{}\command", key), None, &open_command)?;
+    }
+
+    let send_to = send_to_path().ok_or("APPDATA is not set")?;
+    let script = format!("@start \"\" \"{}\" %*\r\n", exe.display());
+    std::fs::write(&send_to, script)
+        .map_err(|e| format!("Failed to create {:?}: {}", send_to, e))?;
+
+    log::info!("Installed Explorer menu entries for {:?}", exe);
+    Ok(())
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn install_shell_menu(_extensions: &[String]) -> Result<(), String> {
+    Err("The Explorer menu entry is only available on Windows".to_string())
+}
+
+/// Removes the entries added by `install_shell_menu`.
+#[cfg(target_os = "windows")]
+pub fn remove_shell_menu() -> Result<(), String> {
+    use std::os::windows::process::CommandExt;
+
+    // Every extension that could have been allowed when the menu was installed;
+    // fails harmlessly for keys that were never created
+    for extension in smoothie_queuer_core::queue::INGESTIBLE_EXTENSIONS {
+        let _ = std::process::Command::new("reg")
+            .args(["delete", &shell_verb_key(extension), "/f"])
+            .creation_flags(0x08000000) // CREATE_NO_WINDOW
+            .output();
+    }
+    if let Some(send_to) = send_to_path().filter(|path| path.exists()) {
+        std::fs::remove_file(&send_to)
+            .map_err(|e| format!("Failed to remove {:?}: {}", send_to, e))?;
+    }
+    log::info!("Removed Explorer menu entries");
+    Ok(())
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn remove_shell_menu() -> Result<(), String> {
+    Err("The Explorer menu entry is only available on Windows".to_string())
+}
@@ -0,0 +1,227 @@
+use crate::api::ControlCommand;
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+
+/// Pipe other programs (shell extensions, AutoHotkey scripts, ...) write to.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub const PIPE_NAME: &str = r"\\.\pipe\smoothie-queuer";
+
+/// Splits a message line into arguments, honouring double quotes so paths with
+/// spaces can be passed: `enqueue "D:\My Clips\a.mp4" "D:\recipes\60fps.ini"`.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+fn split_quoted(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_arg = false;
+
+    for c in line.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_arg = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if has_arg {
+                    args.push(std::mem::take(&mut current));
+                    has_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                has_arg = true;
+            }
+        }
+    }
+    if has_arg {
+        args.push(current);
+    }
+    args
+}
+
+/// Parses one line of the pipe protocol: `enqueue <path> [recipe]`.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub fn parse_message(line: &str) -> Option<ControlCommand> {
+    let args = split_quoted(line);
+    match args.as_slice() {
+        [command, path] if command.eq_ignore_ascii_case("enqueue") => Some(ControlCommand::AddTask {
+            path: PathBuf::from(path),
+            recipe: None,
+        }),
+        [command, path, recipe] if command.eq_ignore_ascii_case("enqueue") => {
+            Some(ControlCommand::AddTask {
+                path: PathBuf::from(path),
+                recipe: Some(PathBuf::from(recipe)),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Formats an `AddTask` command as a pipe message line; other commands have no
+/// pipe equivalent.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+fn format_message(command: &ControlCommand) -> Option<String> {
+    match command {
+        ControlCommand::AddTask { path, recipe: None } => {
+            Some(format!("enqueue \"{}\"", path.display()))
+        }
+        ControlCommand::AddTask { path, recipe: Some(recipe) } => Some(format!(
+            "enqueue \"{}\" \"{}\"",
+            path.display(),
+            recipe.display()
+        )),
+        _ => None,
+    }
+}
+
+/// Hands `commands` to the instance serving `PIPE_NAME`. Fails when no other
+/// instance is running, in which case the caller should handle them itself.
+#[cfg(target_os = "windows")]
+pub fn send_commands(commands: &[ControlCommand]) -> Result<(), String> {
+    use std::io::Write;
+    use winapi::shared::winerror::ERROR_PIPE_BUSY;
+
+    let message: String = commands
+        .iter()
+        .filter_map(format_message)
+        .map(|line| line + "\n")
+        .collect();
+    // The server handles one client at a time, so retry briefly while it is busy
+    let mut attempts = 0;
+    let mut pipe = loop {
+        match std::fs::OpenOptions::new().write(true).open(PIPE_NAME) {
+            Ok(pipe) => break pipe,
+            Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY as i32) && attempts < 20 => {
+                attempts += 1;
+                std::thread::sleep(std::time::Duration::from_millis(100));
+            }
+            Err(e) => return Err(format!("No running instance on {}: {}", PIPE_NAME, e)),
+        }
+    };
+    pipe.write_all(message.as_bytes())
+        .map_err(|e| format!("Failed to write to {}: {}", PIPE_NAME, e))
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn send_commands(_commands: &[ControlCommand]) -> Result<(), String> {
+    Err("Named pipe IPC is only available on Windows".to_string())
+}
+
+/// Serves `PIPE_NAME` on a background thread, forwarding every valid message
+/// line as a `ControlCommand`. Only one instance serves the pipe; if another
+/// running instance already owns it this returns without serving.
+#[cfg(target_os = "windows")]
+pub fn spawn_server(control_tx: Sender<ControlCommand>, on_command: impl Fn() + Send + 'static) {
+    std::thread::spawn(move || {
+        log::info!("Listening for commands on {}", PIPE_NAME);
+        loop {
+            let message = match windows_pipe::read_next_message(PIPE_NAME) {
+                Ok(message) => message,
+                Err(e) => {
+                    log::warn!("Stopped serving {}: {}", PIPE_NAME, e);
+                    return;
+                }
+            };
+
+            for line in message.lines().filter(|line| !line.trim().is_empty()) {
+                match parse_message(line) {
+                    Some(command) => {
+                        if control_tx.send(command).is_err() {
+                            return;
+                        }
+                        on_command();
+                    }
+                    None => log::warn!("Ignoring invalid pipe message: {:?}", line),
+                }
+            }
+        }
+    });
+}
+
+/// Named pipes are Windows-only; other platforms use the control API instead.
+#[cfg(not(target_os = "windows"))]
+pub fn spawn_server(_control_tx: Sender<ControlCommand>, _on_command: impl Fn() + Send + 'static) {
+    log::debug!("Named pipe IPC is only available on Windows");
+}
+
+#[cfg(target_os = "windows")]
+mod windows_pipe {
+    use std::ffi::OsStr;
+    use std::os::windows::ffi::OsStrExt;
+    use std::ptr;
+    use winapi::shared::minwindef::DWORD;
+    use winapi::shared::winerror::{ERROR_MORE_DATA, ERROR_PIPE_CONNECTED};
+    use winapi::um::errhandlingapi::GetLastError;
+    use winapi::um::fileapi::ReadFile;
+    use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+    use winapi::um::namedpipeapi::{ConnectNamedPipe, CreateNamedPipeW, DisconnectNamedPipe};
+    use winapi::um::winbase::{
+        FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_INBOUND, PIPE_READMODE_BYTE,
+        PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE, PIPE_WAIT,
+    };
+
+    const BUFFER_SIZE: usize = 4096;
+
+    /// Creates the pipe, waits for one client and reads everything it writes
+    /// until it disconnects. The default security descriptor only lets the
+    /// current user (and administrators) write to it.
+    pub fn read_next_message(name: &str) -> Result<String, String> {
+        let wide_name: Vec<u16> = OsStr::new(name).encode_wide().chain(Some(0)).collect();
+
+        // SAFETY: `wide_name` is a valid null-terminated UTF-16 string and a null
+        // security attributes pointer selects the default descriptor.
+        let handle = unsafe {
+            CreateNamedPipeW(
+                wide_name.as_ptr(),
+                PIPE_ACCESS_INBOUND | FILE_FLAG_FIRST_PIPE_INSTANCE,
+                PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                1,
+                0,
+                BUFFER_SIZE as DWORD,
+                0,
+                ptr::null_mut(),
+            )
+        };
+        if handle == INVALID_HANDLE_VALUE {
+            // ERROR_ACCESS_DENIED here means another instance already owns the pipe
+            return Err(format!("CreateNamedPipeW failed with error {}", unsafe {
+                GetLastError()
+            }));
+        }
+
+        // SAFETY: `handle` is a valid pipe handle owned by this function.
+        let connected = unsafe { ConnectNamedPipe(handle, ptr::null_mut()) } != 0
+            || unsafe { GetLastError() } == ERROR_PIPE_CONNECTED;
+
+        let mut data = Vec::new();
+        if connected {
+            let mut buffer = [0u8; BUFFER_SIZE];
+            loop {
+                let mut read: DWORD = 0;
+                // SAFETY: the buffer pointer and length describe `buffer`.
+                let ok = unsafe {
+                    ReadFile(
+                        handle,
+                        buffer.as_mut_ptr() as *mut _,
+                        BUFFER_SIZE as DWORD,
+                        &mut read,
+                        ptr::null_mut(),
+                    )
+                };
+                data.extend_from_slice(&buffer[..read as usize]);
+                // ERROR_BROKEN_PIPE (client closed) and other errors end the message
+                if (ok == 0 && unsafe { GetLastError() } != ERROR_MORE_DATA) || (ok != 0 && read == 0) {
+                    break;
+                }
+            }
+        }
+
+        // SAFETY: `handle` is still valid and is not used after being closed.
+        unsafe {
+            DisconnectNamedPipe(handle);
+            CloseHandle(handle);
+        }
+        Ok(String::from_utf8_lossy(&data).into_owned())
+    }
+}
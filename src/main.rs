@@ -1,19 +1,32 @@
-use config::ConfigError;
 use eframe::egui;
+use smoothie_queuer_core::config::{self, ConfigError};
+use smoothie_queuer_core::{bundle, settings};
 use std::sync::Arc;
 use ui::SmoothieQueueApp;
 
-mod config;
-mod queue;
+mod api;
+mod cli;
+mod compare;
+mod daemon;
+mod dashboard;
+mod dragout;
+mod headless;
+mod integration;
+mod ipc;
+mod monitor;
+mod mqtt;
+mod ntfy;
+mod obs;
+mod rpc;
+mod scripting;
+mod service;
+mod thumbnails;
 mod ui;
-mod worker;
+mod update;
+mod watch;
 
-fn main() {
-    env_logger::init();
-    log::info!("Starting Smoothie Queuer application");
-
-    // --- Find Configuration ---
-    let initial_config: Option<config::SmoothieConfig> = match config::find_smoothie_config_auto() {
+fn find_initial_config() -> Option<config::SmoothieConfig> {
+    match config::find_smoothie_config_auto() {
         Ok(cfg) => {
             log::info!("Automatic configuration successful.");
             Some(cfg)
@@ -25,9 +38,116 @@ fn main() {
             );
             None
         }
+    }
+}
+
+fn main() {
+    let args = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::USAGE);
+            std::process::exit(cli::EXIT_CONFIG_ERROR);
+        }
+    };
+    if let Some(dir) = &args.data_dir {
+        settings::set_data_dir(std::path::absolute(dir).unwrap_or_else(|_| dir.clone()));
+    }
+    if matches!(args.mode, cli::Mode::Daemon | cli::Mode::Service) {
+        daemon::init_logging(&args.log_file.clone().unwrap_or_else(daemon::default_log_file));
+    } else {
+        env_logger::init();
+    }
+
+    if args.mode == cli::Mode::Help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+
+    if matches!(args.mode, cli::Mode::InstallService | cli::Mode::UninstallService) {
+        let result = if args.mode == cli::Mode::InstallService {
+            service::install(args.on_stop)
+        } else {
+            service::uninstall()
+        };
+        match result {
+            Ok(message) => println!("{}", message),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(cli::EXIT_CONFIG_ERROR);
+            }
+        }
+        return;
+    }
+
+    if let cli::Mode::ExportSettings(path) | cli::Mode::ImportSettings(path) = &args.mode {
+        let result = if matches!(args.mode, cli::Mode::ExportSettings(_)) {
+            bundle::export(&settings::AppSettings::load(), path)
+                .map(|count| format!("Exported the settings and {} template(s) to {}", count, path.display()))
+        } else {
+            bundle::read(path).and_then(|bundle| {
+                let count = bundle.templates.len();
+                bundle.apply(&settings::AppSettings::load())?.save();
+                Ok(format!("Imported the settings and {} template(s) from {}", count, path.display()))
+            })
+        };
+        match result {
+            Ok(message) => println!("{}", message),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(cli::EXIT_CONFIG_ERROR);
+            }
+        }
+        return;
+    }
+
+    // Hand files to an already running window instead of opening a second one
+    let enqueue = args.enqueue;
+    if args.mode == cli::Mode::Gui && !enqueue.is_empty() {
+        match ipc::send_commands(&enqueue) {
+            Ok(()) => {
+                log::info!("Sent {} file(s) to the running instance", enqueue.len());
+                return;
+            }
+            Err(e) => log::debug!("{}", e),
+        }
+    }
+
+    // --- Find Configuration ---
+    let initial_config = match args.simulation {
+        Some(simulation) => {
+            log::info!("Simulation mode: {:?}", simulation);
+            Some(config::SmoothieConfig::simulated(simulation))
+        }
+        None => find_initial_config(),
     };
     // --- Initial Config Attempt Finished ---
 
+    if args.mode == cli::Mode::Headless {
+        log::info!("Starting Smoothie Queuer in headless mode");
+        let mut enqueue = enqueue;
+        if args.read_stdin {
+            enqueue.extend(headless::read_stdin_paths());
+        }
+        std::process::exit(headless::run(initial_config, enqueue, args.progress_json, args.run_options));
+    }
+
+    if args.mode == cli::Mode::Daemon {
+        log::info!("Starting Smoothie Queuer in daemon mode");
+        std::process::exit(daemon::run(initial_config, args.on_stop));
+    }
+
+    if args.mode == cli::Mode::Service {
+        log::info!("Starting Smoothie Queuer as a service");
+        std::process::exit(service::run(initial_config, args.on_stop));
+    }
+
+    if args.mode == cli::Mode::Rpc {
+        log::info!("Starting Smoothie Queuer in RPC mode");
+        std::process::exit(rpc::run(initial_config));
+    }
+
+    log::info!("Starting Smoothie Queuer application");
+
     // Try loading embedded PNG first
     let icon_png = include_bytes!("../assets/icon.png");
     let icon = image::load_from_memory(icon_png)
@@ -92,7 +212,7 @@ fn main() {
     eframe::run_native(
         "Smoothie Queuer",
         options,
-        Box::new(move |cc| Box::new(SmoothieQueueApp::new(cc, initial_config))),
+        Box::new(move |cc| Box::new(SmoothieQueueApp::new(cc, initial_config, enqueue))),
     )
     .expect("Failed to run eframe application");
 }
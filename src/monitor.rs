@@ -0,0 +1,84 @@
+use nvml_wrapper::Nvml;
+use std::time::{Duration, Instant};
+use sysinfo::System;
+
+/// Least time between two samples; CPU usage is measured over this interval.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Load of one GPU at the last sample.
+#[derive(Debug, Clone)]
+pub struct GpuUsage {
+    pub name: String,
+    pub utilization: u32, // Percent
+    pub vram_used: u64,   // Bytes
+    pub vram_total: u64,
+}
+
+/// CPU, RAM and GPU load at the last sample.
+#[derive(Debug, Clone, Default)]
+pub struct HardwareUsage {
+    pub cpu: f32, // Percent, averaged over all cores
+    pub ram_used: u64,
+    pub ram_total: u64,
+    pub gpus: Vec<GpuUsage>, // NVIDIA GPUs only; empty without the driver
+}
+
+/// Samples hardware usage for the gauges shown while the queue renders. GPUs are
+/// read through NVML, which is only loaded the first time a sample is taken.
+pub struct HardwareMonitor {
+    system: System,
+    nvml: Option<Option<Nvml>>, // Outer `None` until NVML was loaded (or failed to)
+    sampled_at: Option<Instant>,
+    usage: HardwareUsage,
+}
+
+impl HardwareMonitor {
+    pub fn new() -> Self {
+        Self {
+            system: System::new(),
+            nvml: None,
+            sampled_at: None,
+            usage: HardwareUsage::default(),
+        }
+    }
+
+    /// The current usage, sampled again when the last sample is older than
+    /// `SAMPLE_INTERVAL`.
+    pub fn sample(&mut self) -> &HardwareUsage {
+        if self.sampled_at.is_some_and(|at| at.elapsed() < SAMPLE_INTERVAL) {
+            return &self.usage;
+        }
+        self.sampled_at = Some(Instant::now());
+
+        self.system.refresh_cpu();
+        self.system.refresh_memory();
+        let nvml = self.nvml.get_or_insert_with(|| {
+            Nvml::init()
+                .map_err(|e| log::info!("GPU usage unavailable (NVML: {})", e))
+                .ok()
+        });
+        self.usage = HardwareUsage {
+            cpu: self.system.global_cpu_info().cpu_usage(),
+            ram_used: self.system.used_memory(),
+            ram_total: self.system.total_memory(),
+            gpus: nvml.as_ref().map(gpu_usage).unwrap_or_default(),
+        };
+        &self.usage
+    }
+}
+
+fn gpu_usage(nvml: &Nvml) -> Vec<GpuUsage> {
+    let count = nvml.device_count().unwrap_or(0);
+    (0..count)
+        .filter_map(|index| {
+            let device = nvml.device_by_index(index).ok()?;
+            let memory = device.memory_info().ok()?;
+            Some(GpuUsage {
+                name: device.name().unwrap_or_else(|_| format!("GPU {}", index)),
+                utilization: device.utilization_rates().ok()?.gpu,
+                vram_used: memory.used,
+                vram_total: memory.total,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,184 @@
+use crate::api::TaskInfo;
+use serde_json::{Value, json};
+use smoothie_queuer_core::queue::{TaskStatus, VideoTask};
+use smoothie_queuer_core::settings::AppSettings;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::Duration;
+
+/// Port used when the broker address has none.
+const DEFAULT_PORT: u16 = 1883;
+/// Longest a connection attempt or broker reply may take.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Where and how to connect, from `AppSettings::mqtt_*`.
+#[derive(Debug, Clone)]
+struct Broker {
+    address: String,
+    username: String,
+    password: String,
+}
+
+/// Publishes queue events to an MQTT broker from a background thread, so a slow
+/// or unreachable broker never holds up the UI or daemon. Each batch of events
+/// gets its own short connection (QoS 0), which needs no keep-alive handling and
+/// survives broker restarts.
+pub struct MqttPublisher {
+    topic: String,
+    tx: Sender<(String, Value)>,
+}
+
+impl MqttPublisher {
+    /// `None` unless MQTT publishing is enabled in the settings.
+    pub fn from_settings(settings: &AppSettings) -> Option<Self> {
+        if !settings.mqtt_enabled || settings.mqtt_broker.trim().is_empty() {
+            return None;
+        }
+        let broker = Broker {
+            address: settings.mqtt_broker.trim().to_string(),
+            username: settings.mqtt_username.clone(),
+            password: settings.mqtt_password.clone(),
+        };
+        let (tx, rx) = mpsc::channel::<(String, Value)>();
+        thread::spawn(move || {
+            for first in rx.iter() {
+                let mut batch = vec![first];
+                batch.extend(rx.try_iter());
+                if let Err(e) = publish(&broker, &batch) {
+                    log::warn!("Failed to publish {} event(s) to MQTT broker {}: {}", batch.len(), broker.address, e);
+                }
+            }
+        });
+        log::info!("Publishing queue events to MQTT broker {}", settings.mqtt_broker);
+        Some(Self {
+            topic: settings.mqtt_topic.trim().trim_end_matches('/').to_string(),
+            tx,
+        })
+    }
+
+    /// Publishes `task_started`, `task_completed` or `task_failed` with the task
+    /// as its `TaskInfo` JSON to `<topic>/<event>`.
+    pub fn task_event(&self, event: &str, task: &VideoTask) {
+        self.send(event, json!(TaskInfo::from(task)));
+    }
+
+    /// Publishes `queue_idle` with the task counts by status once the worker has
+    /// nothing left to do.
+    pub fn queue_idle(&self, tasks: &[VideoTask]) {
+        let count = |wanted: fn(&TaskStatus) -> bool| tasks.iter().filter(|t| wanted(&t.status)).count();
+        self.send(
+            "queue_idle",
+            json!({
+                "completed": count(|s| *s == TaskStatus::Completed),
+                "failed": count(|s| matches!(s, TaskStatus::Failed(_))),
+                "cancelled": count(|s| *s == TaskStatus::Cancelled),
+                "pending": count(|s| *s == TaskStatus::Pending),
+            }),
+        );
+    }
+
+    fn send(&self, event: &str, payload: Value) {
+        let topic = format!("{}/{}", self.topic, event);
+        if self.tx.send((topic, payload)).is_err() {
+            log::error!("MQTT publisher thread has stopped");
+        }
+    }
+}
+
+/// Connects, publishes `messages` (topic and JSON payload) and disconnects.
+fn publish(broker: &Broker, messages: &[(String, Value)]) -> Result<(), String> {
+    let address = if broker.address.contains(':') {
+        broker.address.clone()
+    } else {
+        format!("{}:{}", broker.address, DEFAULT_PORT)
+    };
+    let socket_address = address
+        .to_socket_addrs()
+        .map_err(|e| format!("cannot resolve {}: {}", address, e))?
+        .next()
+        .ok_or_else(|| format!("cannot resolve {}", address))?;
+    let mut stream = TcpStream::connect_timeout(&socket_address, TIMEOUT).map_err(|e| e.to_string())?;
+    stream.set_read_timeout(Some(TIMEOUT)).map_err(|e| e.to_string())?;
+    stream.set_write_timeout(Some(TIMEOUT)).map_err(|e| e.to_string())?;
+
+    stream.write_all(&connect_packet(broker)).map_err(|e| e.to_string())?;
+    let mut connack = [0u8; 4];
+    stream.read_exact(&mut connack).map_err(|e| format!("no reply to CONNECT: {}", e))?;
+    if connack[0] != 0x20 {
+        return Err("unexpected reply to CONNECT; is this an MQTT broker?".to_string());
+    }
+    match connack[3] {
+        0 => {}
+        1 => return Err("the broker does not support MQTT 3.1.1".to_string()),
+        4 => return Err("bad user name or password".to_string()),
+        5 => return Err("not authorized".to_string()),
+        code => return Err(format!("connection refused (code {})", code)),
+    }
+
+    for (topic, payload) in messages {
+        stream
+            .write_all(&publish_packet(topic, payload.to_string().as_bytes()))
+            .map_err(|e| e.to_string())?;
+    }
+    // DISCONNECT
+    stream.write_all(&[0xE0, 0x00]).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Appends a UTF-8 string with its 2-byte length prefix.
+fn put_string(packet: &mut Vec<u8>, text: &str) {
+    packet.extend_from_slice(&(text.len() as u16).to_be_bytes());
+    packet.extend_from_slice(text.as_bytes());
+}
+
+/// Prefixes `body` with the packet type byte and its variable-length size.
+fn with_header(packet_type: u8, body: Vec<u8>) -> Vec<u8> {
+    let mut packet = vec![packet_type];
+    let mut length = body.len();
+    loop {
+        let mut byte = (length % 128) as u8;
+        length /= 128;
+        if length > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if length == 0 {
+            break;
+        }
+    }
+    packet.extend(body);
+    packet
+}
+
+fn connect_packet(broker: &Broker) -> Vec<u8> {
+    let mut flags = 0x02; // Clean session
+    if !broker.username.is_empty() {
+        flags |= 0x80;
+        if !broker.password.is_empty() {
+            flags |= 0x40;
+        }
+    }
+    let mut body = Vec::new();
+    put_string(&mut body, "MQTT");
+    body.push(4); // Protocol level 3.1.1
+    body.push(flags);
+    body.extend_from_slice(&30u16.to_be_bytes()); // Keep-alive in seconds
+    put_string(&mut body, &format!("smoothie-queuer-{}", std::process::id()));
+    if !broker.username.is_empty() {
+        put_string(&mut body, &broker.username);
+        if !broker.password.is_empty() {
+            put_string(&mut body, &broker.password);
+        }
+    }
+    with_header(0x10, body)
+}
+
+/// PUBLISH at QoS 0, which has no packet id.
+fn publish_packet(topic: &str, payload: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+    put_string(&mut body, topic);
+    body.extend_from_slice(payload);
+    with_header(0x30, body)
+}
@@ -0,0 +1,140 @@
+use smoothie_queuer_core::queue::{TaskStatus, VideoTask};
+use smoothie_queuer_core::settings::AppSettings;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+/// One push message. Titles stay ASCII as they travel in an HTTP header.
+struct Notification {
+    title: &'static str,
+    tags: &'static str, // ntfy emoji short codes
+    high_priority: bool,
+    message: String,
+}
+
+/// Posts completion and failure notifications to an ntfy topic from a background
+/// thread. Posting goes through `curl` (built into Windows 10 and later, macOS and
+/// most Linux installs), as ntfy.sh is only reachable over HTTPS.
+pub struct NtfyNotifier {
+    each_task: bool,
+    tx: Sender<Notification>,
+}
+
+impl NtfyNotifier {
+    /// `None` unless ntfy notifications are enabled and a topic is set.
+    pub fn from_settings(settings: &AppSettings) -> Option<Self> {
+        let topic = settings.ntfy_topic.trim().trim_matches('/');
+        if !settings.ntfy_enabled || topic.is_empty() {
+            return None;
+        }
+        let url = format!("{}/{}", settings.ntfy_server.trim().trim_end_matches('/'), topic);
+        let token = settings.ntfy_token.trim().to_string();
+        let (tx, rx) = mpsc::channel::<Notification>();
+        thread::spawn(move || {
+            for notification in rx {
+                if let Err(e) = post(&url, &token, &notification) {
+                    log::warn!("Failed to post \"{}\" to {}: {}", notification.title, url, e);
+                }
+            }
+        });
+        log::info!("Sending ntfy notifications to topic {}", topic);
+        Some(Self {
+            each_task: settings.ntfy_each_task,
+            tx,
+        })
+    }
+
+    /// Only sent with `AppSettings::ntfy_each_task`; otherwise the queue summary
+    /// covers completed tasks.
+    pub fn task_completed(&self, task: &VideoTask) {
+        if !self.each_task {
+            return;
+        }
+        let message = match &task.output_path {
+            Some(output) => format!("{} -> {}", file_name(&task.input_path), file_name(output)),
+            None => file_name(&task.input_path),
+        };
+        self.send(Notification {
+            title: "Task completed",
+            tags: "white_check_mark",
+            high_priority: false,
+            message,
+        });
+    }
+
+    pub fn task_failed(&self, task: &VideoTask) {
+        let error = match &task.status {
+            TaskStatus::Failed(error) => error.as_str(),
+            _ => "",
+        };
+        self.send(Notification {
+            title: "Task failed",
+            tags: "x",
+            high_priority: true,
+            message: format!("{}: {}", file_name(&task.input_path), error),
+        });
+    }
+
+    pub fn queue_finished(&self, tasks: &[VideoTask]) {
+        let count = |wanted: fn(&TaskStatus) -> bool| tasks.iter().filter(|t| wanted(&t.status)).count();
+        let failed = count(|s| matches!(s, TaskStatus::Failed(_)));
+        self.send(Notification {
+            title: "Queue finished",
+            tags: if failed > 0 { "warning" } else { "checkered_flag" },
+            high_priority: false,
+            message: format!(
+                "{} completed, {} failed, {} cancelled, {} pending",
+                count(|s| *s == TaskStatus::Completed),
+                failed,
+                count(|s| *s == TaskStatus::Cancelled),
+                count(|s| *s == TaskStatus::Pending)
+            ),
+        });
+    }
+
+    fn send(&self, notification: Notification) {
+        if self.tx.send(notification).is_err() {
+            log::error!("ntfy notification thread has stopped");
+        }
+    }
+}
+
+fn file_name(path: &std::path::Path) -> String {
+    path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().into_owned()
+}
+
+/// Posts the message as the request body (read by curl from stdin, so any
+/// characters survive) with ntfy's `Title`, `Tags` and `Priority` headers.
+fn post(url: &str, token: &str, notification: &Notification) -> Result<(), String> {
+    let mut command = Command::new("curl");
+    command.args(["--silent", "--show-error", "--fail", "--max-time", "15"]);
+    command.args(["-H", &format!("Title: {}", notification.title)]);
+    command.args(["-H", &format!("Tags: {}", notification.tags)]);
+    if notification.high_priority {
+        command.args(["-H", "Priority: high"]);
+    }
+    if !token.is_empty() {
+        command.args(["-H", &format!("Authorization: Bearer {}", token)]);
+    }
+    command.args(["--data-binary", "@-", url]);
+    command.stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::piped());
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+
+    let mut child = command.spawn().map_err(|e| format!("cannot run curl: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(notification.message.as_bytes())
+            .map_err(|e| format!("cannot write to curl: {}", e))?;
+    }
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
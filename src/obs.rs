@@ -0,0 +1,75 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// OBS Studio's configuration folder for the current user.
+fn obs_config_dir() -> Option<PathBuf> {
+    if cfg!(windows) {
+        std::env::var_os("APPDATA").map(|appdata| PathBuf::from(appdata).join("obs-studio"))
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| {
+            PathBuf::from(home).join("Library/Application Support/obs-studio")
+        })
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .map(|config| config.join("obs-studio"))
+    }
+}
+
+/// Looks up `key` in `[section]` of an OBS ini file.
+fn ini_value(contents: &str, section: &str, key: &str) -> Option<String> {
+    let mut in_section = false;
+    for line in contents.trim_start_matches('\u{feff}').lines() {
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            in_section = name == section;
+        } else if let Some((_, value)) = line
+            .split_once('=')
+            .filter(|(k, _)| in_section && k.trim() == key)
+        {
+            return Some(value.trim().to_string());
+        }
+    }
+    None
+}
+
+fn read_ini(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))
+}
+
+/// Finds the recording folder of the active OBS profile.
+///
+/// Reads the profile name from `user.ini` (OBS 31+) or `global.ini`, then the
+/// simple or advanced output path from that profile's `basic.ini`.
+pub fn recordings_folder() -> Result<PathBuf, String> {
+    let config_dir = obs_config_dir().ok_or("Could not locate the OBS config folder")?;
+    let global = ["user.ini", "global.ini"]
+        .iter()
+        .map(|name| config_dir.join(name))
+        .find(|path| path.exists())
+        .ok_or_else(|| format!("OBS does not seem to be installed (no config in {:?})", config_dir))?;
+    let global = read_ini(&global)?;
+
+    let profile = ini_value(&global, "Basic", "ProfileDir")
+        .or_else(|| ini_value(&global, "Basic", "Profile"))
+        .ok_or("No active OBS profile found")?;
+    let basic = read_ini(&config_dir.join("basic/profiles").join(&profile).join("basic.ini"))?;
+
+    let advanced = ini_value(&basic, "Output", "Mode").is_some_and(|mode| mode == "Advanced");
+    let folder = if !advanced {
+        ini_value(&basic, "SimpleOutput", "FilePath")
+    } else if ini_value(&basic, "AdvOut", "RecType").is_some_and(|t| t == "FFmpeg") {
+        ini_value(&basic, "AdvOut", "FFFilePath")
+    } else {
+        ini_value(&basic, "AdvOut", "RecFilePath")
+    };
+
+    match folder.filter(|f| !f.is_empty()) {
+        Some(folder) => Ok(PathBuf::from(folder)),
+        // OBS records to the user's Videos folder until a path is chosen
+        None => std::env::var_os(if cfg!(windows) { "USERPROFILE" } else { "HOME" })
+            .map(|home| PathBuf::from(home).join("Videos"))
+            .ok_or_else(|| format!("Profile {:?} has no recording path set", profile)),
+    }
+}
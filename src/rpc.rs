@@ -0,0 +1,419 @@
+use crate::api::TaskInfo;
+use crate::cli;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use smoothie_queuer_core::config::{self, SmoothieConfig};
+use smoothie_queuer_core::queue::{self, Backend, ImageSequence, QueueManager};
+use smoothie_queuer_core::settings::AppSettings;
+use smoothie_queuer_core::worker::{self, UpdateMessage};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+// Standard JSON-RPC 2.0 error codes, plus one server error for a missing setup.
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const NOT_CONFIGURED: i64 = -32000;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct AddTaskParams {
+    path: PathBuf,
+    recipe: Option<PathBuf>,
+    output_dir: Option<PathBuf>,
+    #[serde(default)]
+    backend: Backend,
+    frame_rate: Option<u32>, // For image sequence folders
+    priority: Option<i32>, // Wins over the input rules' priority
+}
+
+#[derive(Debug, Deserialize)]
+struct ConfigureParams {
+    smoothie_dir: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+struct TaskIdParams {
+    id: usize,
+}
+
+/// Overrides of the run budget from the settings; 0 lifts a limit.
+#[derive(Debug, Deserialize)]
+struct StartParams {
+    max_tasks: Option<usize>,
+    max_hours: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RerunWithRecipeParams {
+    id: usize,
+    recipe: PathBuf,
+}
+
+type RpcResult = Result<Value, (i64, String)>;
+
+/// Writes one JSON message per line to stdout.
+fn emit(message: &Value) {
+    let mut stdout = io::stdout().lock();
+    if writeln!(stdout, "{}", message).and_then(|_| stdout.flush()).is_err() {
+        log::error!("Failed to write RPC message to stdout");
+    }
+}
+
+fn notify(method: &str, params: Value) {
+    emit(&json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+}
+
+struct RpcServer {
+    queue_manager: Arc<Mutex<QueueManager>>,
+    config: Option<SmoothieConfig>,
+    worker_tx: mpsc::Sender<UpdateMessage>,
+    worker: Option<JoinHandle<()>>,
+    settings: AppSettings, // The GUI's settings, for what inputs are accepted
+}
+
+impl RpcServer {
+    fn worker_running(&self) -> bool {
+        self.worker.as_ref().is_some_and(|handle| !handle.is_finished())
+    }
+
+    fn config(&self) -> Result<&SmoothieConfig, (i64, String)> {
+        self.config.as_ref().ok_or_else(|| {
+            (
+                NOT_CONFIGURED,
+                "smoothie-rs not found; call \"configure\" with the Smoothie folder".to_string(),
+            )
+        })
+    }
+
+    fn handle(&mut self, request: Request) -> RpcResult {
+        let params = request.params;
+        match request.method.as_str() {
+            "configure" => {
+                let params: ConfigureParams = parse_params(params)?;
+                let found = config::find_smoothie_config_in_dir(&params.smoothie_dir)
+                    .map_err(|e| (NOT_CONFIGURED, e.to_string()))?;
+                let result = json!({
+                    "executable": found.executable_path,
+                    "recipe": found.recipe_path,
+                });
+                self.config = Some(found);
+                Ok(result)
+            }
+            "add_task" => {
+                let params: AddTaskParams = parse_params(params)?;
+                let default_recipe = self.config()?.recipe_path.clone();
+                let image_sequence = if params.path.is_dir() {
+                    let frame_rate = params.frame_rate.unwrap_or(self.settings.sequence_frame_rate);
+                    let sequence = ImageSequence::detect(&params.path, frame_rate).ok_or_else(|| {
+                        (INVALID_PARAMS, format!("No image sequence in {:?}", params.path))
+                    })?;
+                    Some(sequence)
+                } else if self.settings.accepts_input(&params.path) {
+                    None
+                } else {
+                    return Err((INVALID_PARAMS, format!("Unsupported file: {:?}", params.path)));
+                };
+
+                let output_dir = params.output_dir.unwrap_or_else(|| {
+                    PathBuf::from(params.path.parent().unwrap_or(Path::new(".")))
+                });
+                let keep_recipe = params.recipe.is_some();
+                let recipe_path = params.recipe.unwrap_or(default_recipe);
+                let mut manager = self.queue_manager.lock()
+                    .expect("Failed to lock queue manager");
+                let id = manager.add_new_task(params.path, output_dir, recipe_path);
+                let task = manager.task_mut(id).expect("Task was just added");
+                self.settings.rule_outcome(&task.input_path).apply_to(task, keep_recipe);
+                task.backend = params.backend;
+                if let Some(priority) = params.priority {
+                    task.priority = priority;
+                }
+                task.image_sequence = image_sequence;
+                let info = json!(TaskInfo::from(&*task));
+                drop(manager);
+
+                notify("task_added", info.clone());
+                Ok(info)
+            }
+            "list_tasks" => {
+                let manager = self.queue_manager.lock()
+                    .expect("Failed to lock queue manager");
+                let tasks: Vec<TaskInfo> = manager.tasks.iter().map(TaskInfo::from).collect();
+                Ok(json!(tasks))
+            }
+            "remove_task" => {
+                let params: TaskIdParams = parse_params(params)?;
+                let mut manager = self.queue_manager.lock()
+                    .expect("Failed to lock queue manager");
+                let removable = manager
+                    .tasks
+                    .iter()
+                    .any(|t| t.id == params.id && t.status == queue::TaskStatus::Pending);
+                if removable {
+                    manager.remove_task(params.id);
+                }
+                Ok(json!({ "removed": removable }))
+            }
+            "rerun_task" => {
+                let params: TaskIdParams = parse_params(params)?;
+                let requeued = self.queue_manager.lock()
+                    .expect("Failed to lock queue manager")
+                    .rerun_task(params.id);
+                Ok(json!({ "requeued": requeued }))
+            }
+            "rerun_with_recipe" => {
+                let params: RerunWithRecipeParams = parse_params(params)?;
+                let mut manager = self.queue_manager.lock()
+                    .expect("Failed to lock queue manager");
+                let task = manager
+                    .tasks
+                    .iter()
+                    .find(|t| t.id == params.id && t.status == queue::TaskStatus::Completed)
+                    .ok_or_else(|| (INVALID_PARAMS, format!("No completed task {}", params.id)))?
+                    .clone();
+                let copy = task.duplicate_with_recipe(manager.next_task_id(), params.recipe);
+                let info = json!(TaskInfo::from(&copy));
+                manager.add_task(copy);
+                drop(manager);
+
+                notify("task_added", info.clone());
+                Ok(info)
+            }
+            "clear_finished" => {
+                let removed = self.queue_manager.lock()
+                    .expect("Failed to lock queue manager")
+                    .clear_finished();
+                Ok(json!({ "removed": removed }))
+            }
+            "start" => {
+                let mut config = self.config()?.clone();
+                config.retries = u32::from(self.settings.requeue_failed);
+                config.max_parallel = self.settings.max_parallel;
+                config.start_stagger = self.settings.start_stagger();
+                config.budget = self.settings.run_budget();
+                config.delivery = self.settings.delivery();
+                config.high_performance_power = self.settings.high_performance_power;
+                config.safe_input_names = self.settings.safe_input_names;
+                config.stage_network_files = self.settings.stage_network_files;
+                config.convert_vfr = self.settings.convert_vfr_inputs;
+                config.spread_gpus = self.settings.spread_gpus;
+                config.verbose = self.settings.verbose_smoothie;
+                config.show_console = self.settings.show_smoothie_console;
+                config.env = self.settings.env.clone();
+                config.extra_args = self.settings.extra_args.clone();
+                config.working_dir = self.settings.working_dir.clone();
+                if let Some(params) = parse_params::<Option<StartParams>>(params)? {
+                    if let Some(max_tasks) = params.max_tasks {
+                        config.budget.max_tasks = Some(max_tasks).filter(|&tasks| tasks > 0);
+                    }
+                    if let Some(max_hours) = params.max_hours {
+                        config.budget.max_duration = (max_hours > 0.0)
+                            .then(|| Duration::from_secs_f64(max_hours * 3600.0));
+                    }
+                }
+                if self.worker_running() {
+                    return Ok(json!({ "started": false }));
+                }
+                self.worker = Some(worker::spawn_worker(
+                    Arc::clone(&self.queue_manager),
+                    self.worker_tx.clone(),
+                    config,
+                ));
+                Ok(json!({ "started": true }))
+            }
+            "pause" => {
+                self.queue_manager.lock()
+                    .expect("Failed to lock queue manager")
+                    .stop_flags
+                    .request_stop();
+                Ok(json!({ "paused": self.worker_running() }))
+            }
+            "suspend" => {
+                self.queue_manager.lock()
+                    .expect("Failed to lock queue manager")
+                    .stop_flags
+                    .request_suspend();
+                Ok(json!({ "suspended": self.worker_running() }))
+            }
+            "resume" => {
+                self.queue_manager.lock()
+                    .expect("Failed to lock queue manager")
+                    .stop_flags
+                    .resume();
+                Ok(json!({ "resumed": true }))
+            }
+            "cancel_task" => {
+                let params: TaskIdParams = parse_params(params)?;
+                let cancelled = self.queue_manager.lock()
+                    .expect("Failed to lock queue manager")
+                    .cancel_task(params.id);
+                Ok(json!({ "cancelled": cancelled }))
+            }
+            "force_stop" => {
+                let manager = self.queue_manager.lock()
+                    .expect("Failed to lock queue manager");
+                manager.stop_flags.request_force_stop();
+                manager.stop_flags.request_stop();
+                Ok(json!({ "stopping": self.worker_running() }))
+            }
+            "shutdown" => Ok(json!({ "shutdown": true })),
+            other => Err((METHOD_NOT_FOUND, format!("Unknown method: {}", other))),
+        }
+    }
+}
+
+fn parse_params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, (i64, String)> {
+    serde_json::from_value(params).map_err(|e| (INVALID_PARAMS, format!("Invalid params: {}", e)))
+}
+
+/// Turns worker updates into JSON-RPC notifications.
+fn forward_worker_updates(
+    worker_rx: mpsc::Receiver<UpdateMessage>,
+    queue_manager: Arc<Mutex<QueueManager>>,
+) {
+    let task_info = |id: usize| {
+        let manager = queue_manager.lock()
+            .expect("Failed to lock queue manager");
+        manager
+            .tasks
+            .iter()
+            .find(|t| t.id == id)
+            .map_or(json!({ "id": id }), |task| json!(TaskInfo::from(task)))
+    };
+
+    for update in worker_rx {
+        match update {
+            UpdateMessage::TaskWaiting(id) => notify("task_waiting", task_info(id)),
+            UpdateMessage::TaskDownloading(id) => notify("task_downloading", task_info(id)),
+            UpdateMessage::TaskStarted(id) => notify("task_started", task_info(id)),
+            // Progress is reported through `list_tasks` instead of one notification per line
+            UpdateMessage::TaskProgress(..) => {}
+            UpdateMessage::TaskCompleted(id) => notify("task_completed", task_info(id)),
+            UpdateMessage::TaskFailed(id, _) => notify("task_failed", task_info(id)),
+            UpdateMessage::TaskCancelled(id) => notify("task_cancelled", task_info(id)),
+            UpdateMessage::BudgetReached(pending) => {
+                notify("budget_reached", json!({ "pending": pending }))
+            }
+            UpdateMessage::WorkerFinished => notify("worker_finished", json!({})),
+        }
+    }
+}
+
+/// Runs the headless JSON-RPC mode until `shutdown` is received or stdin closes.
+///
+/// Requests are one JSON-RPC 2.0 object per line; every request with an `id` gets a
+/// response. Queue events are sent as notifications (`task_added`, `task_waiting`, `task_downloading`,
+/// `task_started`, `task_completed`, `task_failed`, `task_cancelled`, `budget_reached`,
+/// `worker_finished`).
+/// On `shutdown` the running task is force stopped; when stdin simply closes, the
+/// worker is allowed to finish the queue first. Returns the process exit code:
+/// `cli::EXIT_TASKS_FAILED` when a task failed, `cli::EXIT_EXECUTABLE_MISSING` when a
+/// request needed smoothie-rs and it was never found.
+pub fn run(config: Option<SmoothieConfig>) -> i32 {
+    let queue_manager = Arc::new(Mutex::new(QueueManager::new()));
+    let (worker_tx, worker_rx) = mpsc::channel();
+    let forward_manager = Arc::clone(&queue_manager);
+    let forwarder = thread::spawn(move || forward_worker_updates(worker_rx, forward_manager));
+
+    let mut server = RpcServer {
+        queue_manager,
+        config,
+        worker_tx,
+        worker: None,
+        settings: AppSettings::load(),
+    };
+    notify("ready", json!({ "configured": server.config.is_some() }));
+
+    let mut shutdown = false;
+    let mut not_configured = false;
+    for line in io::stdin().lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                log::error!("Failed to read stdin: {}", e);
+                break;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let request: Request = match serde_json::from_str(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                emit(&json!({
+                    "jsonrpc": "2.0",
+                    "id": Value::Null,
+                    "error": { "code": PARSE_ERROR, "message": e.to_string() },
+                }));
+                continue;
+            }
+        };
+
+        let id = request.id.clone();
+        shutdown = request.method == "shutdown";
+        let response = match server.handle(request) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => {
+                not_configured |= code == NOT_CONFIGURED;
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": { "code": code, "message": message },
+                })
+            }
+        };
+        // Requests without an id are notifications and get no response
+        if !id.is_null() {
+            emit(&response);
+        }
+        if shutdown {
+            break;
+        }
+    }
+
+    if shutdown {
+        let manager = server.queue_manager.lock()
+            .expect("Failed to lock queue manager");
+        manager.stop_flags.request_force_stop();
+        manager.stop_flags.request_stop();
+    }
+    if let Some(handle) = server.worker.take() {
+        log::info!("Waiting for the worker to finish before exiting");
+        let _ = handle.join();
+    }
+
+    let missing_executable = not_configured && server.config.is_none();
+    let failed = server.queue_manager.lock()
+        .expect("Failed to lock queue manager")
+        .tasks
+        .iter()
+        .filter(|task| matches!(task.status, queue::TaskStatus::Failed(_)))
+        .count();
+
+    // Dropping the server closes the update channel so the forwarder drains and exits
+    drop(server);
+    let _ = forwarder.join();
+
+    if missing_executable {
+        log::error!("Exiting: smoothie-rs was not found");
+        cli::EXIT_EXECUTABLE_MISSING
+    } else if failed > 0 {
+        log::warn!("Exiting: {} task(s) failed", failed);
+        cli::EXIT_TASKS_FAILED
+    } else {
+        cli::EXIT_OK
+    }
+}
@@ -0,0 +1,243 @@
+use rhai::{AST, Dynamic, Engine, Map, Scope};
+use smoothie_queuer_core::queue::{Backend, OutputContainer, TaskStatus, VideoTask};
+use smoothie_queuer_core::settings;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Hooks a script may define. Each receives the task (or a summary) as a map.
+pub const HOOKS: [&str; 4] = [
+    "on_task_added",     // fn on_task_added(task) -> task map to apply, or () to keep it
+    "on_task_completed", // fn on_task_completed(task)
+    "on_task_failed",    // fn on_task_failed(task)
+    "on_queue_finished", // fn on_queue_finished(summary)
+];
+
+/// Hooks run on the UI thread, so scripts are cut off well before they could
+/// freeze it: operations per call, call nesting and expression depth.
+const MAX_OPERATIONS: u64 = 1_000_000;
+const MAX_CALL_LEVELS: usize = 32;
+const MAX_EXPR_DEPTH: usize = 64;
+/// How long `exec` waits for its command before killing it.
+const EXEC_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Folder scanned for `.rhai` scripts.
+pub fn scripts_dir() -> PathBuf {
+    settings::data_dir().join("scripts")
+}
+
+/// Loads user scripts and dispatches queue events to the hooks they define.
+pub struct ScriptHost {
+    engine: Engine,
+    scripts: Vec<(PathBuf, AST)>,
+}
+
+impl ScriptHost {
+    pub fn load() -> Self {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.set_max_call_levels(MAX_CALL_LEVELS);
+        engine.set_max_expr_depths(MAX_EXPR_DEPTH, MAX_EXPR_DEPTH);
+        engine.on_print(|text| log::info!("[script] {}", text));
+        engine.on_debug(|text, source, pos| {
+            log::debug!("[script {}:{}] {}", source.unwrap_or("?"), pos, text)
+        });
+        engine.register_fn("exec", exec);
+
+        let mut host = Self {
+            engine,
+            scripts: Vec::new(),
+        };
+        host.reload();
+        host
+    }
+
+    /// Re-reads every script in `scripts_dir`, skipping ones that fail to compile.
+    pub fn reload(&mut self) {
+        self.scripts.clear();
+        let dir = scripts_dir();
+        let Ok(entries) = fs::read_dir(&dir) else {
+            log::debug!("No scripts folder at {:?}", dir);
+            return;
+        };
+
+        let mut paths: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "rhai"))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            match self.engine.compile_file(path.clone()) {
+                Ok(ast) => {
+                    log::info!("Loaded script {:?}", path);
+                    self.scripts.push((path, ast));
+                }
+                Err(e) => log::error!("Failed to compile script {:?}: {}", path, e),
+            }
+        }
+    }
+
+    pub fn script_count(&self) -> usize {
+        self.scripts.len()
+    }
+
+    /// Runs `on_task_added` on every script in turn; maps they return are applied
+    /// to the task, so later scripts see earlier scripts' changes.
+    pub fn on_task_added(&self, task: &mut VideoTask) {
+        for (path, ast) in &self.scripts {
+            let result = self.call(path, ast, "on_task_added", task_to_map(task));
+            if let Some(map) = result.and_then(|value| value.try_cast::<Map>()) {
+                apply_map(task, &map);
+            }
+        }
+    }
+
+    pub fn on_task_completed(&self, task: &VideoTask) {
+        self.call_all("on_task_completed", task_to_map(task));
+    }
+
+    pub fn on_task_failed(&self, task: &VideoTask) {
+        self.call_all("on_task_failed", task_to_map(task));
+    }
+
+    pub fn on_queue_finished(&self, tasks: &[VideoTask]) {
+        let count = |wanted: fn(&TaskStatus) -> bool| {
+            tasks.iter().filter(|t| wanted(&t.status)).count() as i64
+        };
+        let mut summary = Map::new();
+        summary.insert("total".into(), (tasks.len() as i64).into());
+        summary.insert("completed".into(), count(|s| *s == TaskStatus::Completed).into());
+        summary.insert("failed".into(), count(|s| matches!(s, TaskStatus::Failed(_))).into());
+        summary.insert("cancelled".into(), count(|s| *s == TaskStatus::Cancelled).into());
+        summary.insert("pending".into(), count(|s| *s == TaskStatus::Pending).into());
+        self.call_all("on_queue_finished", summary);
+    }
+
+    fn call_all(&self, hook: &str, arg: Map) {
+        for (path, ast) in &self.scripts {
+            self.call(path, ast, hook, arg.clone());
+        }
+    }
+
+    /// Calls `hook` in one script if it defines it. Errors are logged, not propagated,
+    /// so a broken script never interrupts the queue.
+    fn call(&self, path: &Path, ast: &AST, hook: &str, arg: Map) -> Option<Dynamic> {
+        if !ast.iter_functions().any(|f| f.name == hook && f.params.len() == 1) {
+            return None;
+        }
+        let mut scope = Scope::new();
+        match self.engine.call_fn::<Dynamic>(&mut scope, ast, hook, (arg,)) {
+            Ok(result) => Some(result),
+            Err(e) => {
+                log::error!("Script {:?} failed in {}: {}", path, hook, e);
+                None
+            }
+        }
+    }
+}
+
+/// `exec(command)` for scripts: runs a shell command and returns its exit code,
+/// or -1 if it cannot start or is killed after `EXEC_TIMEOUT`. Long jobs should
+/// be started in the background by the command itself.
+fn exec(command_line: &str) -> i64 {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.arg("/C").arg(command_line);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(command_line);
+        command
+    };
+    command.stdin(Stdio::null());
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            log::error!("Script exec of {:?} failed: {}", command_line, e);
+            return -1;
+        }
+    };
+    let deadline = Instant::now() + EXEC_TIMEOUT;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return status.code().unwrap_or(-1) as i64,
+            Ok(None) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(20)),
+            Ok(None) => {
+                log::error!("Script exec of {:?} took over {:?}; killed it", command_line, EXEC_TIMEOUT);
+                let _ = child.kill();
+                let _ = child.wait();
+                return -1;
+            }
+            Err(e) => {
+                log::error!("Script exec of {:?} failed: {}", command_line, e);
+                return -1;
+            }
+        }
+    }
+}
+
+fn path_value(path: &Path) -> Dynamic {
+    path.display().to_string().into()
+}
+
+fn task_to_map(task: &VideoTask) -> Map {
+    let status = match &task.status {
+        TaskStatus::Pending => "pending",
+        TaskStatus::Waiting => "waiting",
+        TaskStatus::Downloading => "downloading",
+        TaskStatus::Running => "running",
+        TaskStatus::Completed => "completed",
+        TaskStatus::Failed(_) => "failed",
+        TaskStatus::Cancelled => "cancelled",
+    };
+    let mut map = Map::new();
+    map.insert("id".into(), (task.id as i64).into());
+    map.insert("input".into(), path_value(&task.input_path));
+    map.insert("output_dir".into(), path_value(&task.output_dir));
+    map.insert("recipe".into(), path_value(&task.recipe_path));
+    map.insert("status".into(), status.into());
+    map.insert(
+        "container".into(),
+        task.output_container
+            .map_or(Dynamic::UNIT, |c| c.extension().into()),
+    );
+    map.insert("backend".into(), task.backend.label().into());
+    map.insert(
+        "output".into(),
+        task.output_path.as_deref().map_or(Dynamic::UNIT, path_value),
+    );
+    if let TaskStatus::Failed(err) = &task.status {
+        map.insert("error".into(), err.clone().into());
+    }
+    map
+}
+
+/// Applies the fields a script is allowed to change on a newly added task.
+fn apply_map(task: &mut VideoTask, map: &Map) {
+    let string = |key: &str| {
+        map.get(key)
+            .and_then(|value| value.clone().into_string().ok())
+    };
+    if let Some(output_dir) = string("output_dir") {
+        task.output_dir = PathBuf::from(output_dir);
+    }
+    if let Some(recipe) = string("recipe") {
+        task.recipe_path = PathBuf::from(recipe);
+    }
+    if let Some(backend) = string("backend") {
+        match Backend::ALL.into_iter().find(|b| b.label().eq_ignore_ascii_case(&backend)) {
+            Some(backend) => task.backend = backend,
+            None => log::warn!("Script returned unknown backend {:?}", backend),
+        }
+    }
+    if let Some(container) = map.get("container") {
+        task.output_container = container.clone().into_string().ok().and_then(|ext| {
+            OutputContainer::ALL
+                .into_iter()
+                .find(|c| c.extension().eq_ignore_ascii_case(ext.trim_start_matches('.')))
+        });
+    }
+}
@@ -0,0 +1,311 @@
+use crate::daemon::{self, StopMode};
+#[cfg(target_os = "windows")]
+use smoothie_queuer_core::config::SmoothieConfig;
+use smoothie_queuer_core::settings;
+use std::path::PathBuf;
+
+/// Name the daemon is registered under as a Windows service.
+#[cfg(target_os = "windows")]
+const SERVICE_NAME: &str = "SmoothieQueuer";
+#[cfg(target_os = "linux")]
+const UNIT_NAME: &str = "smoothie-queuer.service";
+
+fn current_exe() -> Result<PathBuf, String> {
+    std::env::current_exe().map_err(|e| format!("Failed to locate the queuer executable: {}", e))
+}
+
+/// `~/.config/systemd/user`, where systemd looks for the user's own units.
+#[cfg(target_os = "linux")]
+fn unit_path() -> Result<PathBuf, String> {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .ok_or("Neither XDG_CONFIG_HOME nor HOME is set")?;
+    Ok(config.join("systemd").join("user").join(UNIT_NAME))
+}
+
+/// A user unit running `--daemon`. Only the daemon gets the stop signal, so the
+/// backends it runs can finish when draining; anything left when the stop
+/// timeout runs out is killed.
+#[cfg(target_os = "linux")]
+fn unit_file(exe: &std::path::Path, on_stop: StopMode) -> String {
+    let stop_timeout = match on_stop {
+        StopMode::Drain => "infinity",
+        StopMode::Suspend => "30s",
+    };
+    format!(
+        "[Unit]\n\
+         Description=Smoothie Queuer render daemon\n\
+         After=network.target\n\
+         \n\
+         [Service]\n\
+         ExecStart=\"{}\" --daemon --on-stop {} --data-dir \"{}\"\n\
+         KillMode=mixed\n\
+         KillSignal=SIGTERM\n\
+         TimeoutStopSec={}\n\
+         Restart=on-failure\n\
+         \n\
+         [Install]\n\
+         WantedBy=default.target\n",
+        exe.display(),
+        on_stop.as_str(),
+        settings::data_dir().display(),
+        stop_timeout
+    )
+}
+
+/// Writes a systemd user unit for the daemon and returns what to run to
+/// enable it.
+#[cfg(target_os = "linux")]
+pub fn install(on_stop: StopMode) -> Result<String, String> {
+    let path = unit_path()?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    std::fs::write(&path, unit_file(&current_exe()?, on_stop))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    log::info!("Wrote systemd unit {:?}", path);
+    Ok(format!(
+        "Wrote {}\n\n\
+         Start it now and on every login with:\n  \
+         systemctl --user daemon-reload\n  \
+         systemctl --user enable --now {}\n\n\
+         To keep it running while you are logged out:\n  \
+         loginctl enable-linger",
+        path.display(),
+        UNIT_NAME
+    ))
+}
+
+/// Removes the systemd user unit written by `install`, along with the link
+/// `systemctl --user enable` made to it.
+#[cfg(target_os = "linux")]
+pub fn uninstall() -> Result<String, String> {
+    let path = unit_path()?;
+    if !path.exists() {
+        return Err(format!("{} is not installed", path.display()));
+    }
+    std::fs::remove_file(&path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::remove_file(dir.join("default.target.wants").join(UNIT_NAME));
+    }
+    log::info!("Removed systemd unit {:?}", path);
+    // Stopping is left to the user: with --on-stop drain it may take a while
+    Ok(format!(
+        "Removed {}\n\n\
+         If it is still running, stop it and reload systemd with:\n  \
+         systemctl --user stop {}\n  \
+         systemctl --user daemon-reload",
+        path.display(),
+        UNIT_NAME
+    ))
+}
+
+/// Registers the daemon as a Windows service starting with the machine. It
+/// runs as LocalSystem, so the current user's settings folder is passed along.
+#[cfg(target_os = "windows")]
+pub fn install(on_stop: StopMode) -> Result<String, String> {
+    let command = format!(
+        "\"{}\" --service --on-stop {} --data-dir \"{}\"",
+        current_exe()?.display(),
+        on_stop.as_str(),
+        settings::data_dir().display()
+    );
+    sc(&[
+        "create",
+        SERVICE_NAME,
+        "binPath=",
+        &command,
+        "start=",
+        "auto",
+        "DisplayName=",
+        "Smoothie Queuer",
+    ])?;
+    sc(&[
+        "description",
+        SERVICE_NAME,
+        "Renders the Smoothie Queuer queue, watch folder and control API requests without a window",
+    ])?;
+    log::info!("Installed the {} service: {}", SERVICE_NAME, command);
+    Ok(format!(
+        "Installed the {} service; it starts with Windows. Start it now with:\n  sc.exe start {}",
+        SERVICE_NAME, SERVICE_NAME
+    ))
+}
+
+/// Removes the Windows service registered by `install`.
+#[cfg(target_os = "windows")]
+pub fn uninstall() -> Result<String, String> {
+    sc(&["delete", SERVICE_NAME])?;
+    log::info!("Removed the {} service", SERVICE_NAME);
+    Ok(format!(
+        "Removed the {} service; if it is running it is deleted once it stops",
+        SERVICE_NAME
+    ))
+}
+
+/// Runs `sc.exe`, which needs an elevated prompt to change services.
+#[cfg(target_os = "windows")]
+fn sc(args: &[&str]) -> Result<(), String> {
+    use std::os::windows::process::CommandExt;
+
+    let output = std::process::Command::new("sc.exe")
+        .args(args)
+        .creation_flags(0x08000000) // CREATE_NO_WINDOW
+        .output()
+        .map_err(|e| format!("Failed to run sc.exe: {}", e))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let message = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if output.status.code() == Some(5) {
+        // ERROR_ACCESS_DENIED
+        return Err("Changing services needs an administrator prompt".to_string());
+    }
+    Err(format!("sc.exe {} failed: {}", args[0], message))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+pub fn install(_on_stop: StopMode) -> Result<String, String> {
+    Err("Installing as a service is only supported on Windows and Linux (systemd); \
+         run smoothie-queuer --daemon from a launchd agent instead"
+        .to_string())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+pub fn uninstall() -> Result<String, String> {
+    Err("Installing as a service is only supported on Windows and Linux (systemd)".to_string())
+}
+
+/// Daemon settings handed from `run` to `service_main`, which the service
+/// control manager calls without arguments of ours.
+#[cfg(target_os = "windows")]
+static SERVICE_ARGS: std::sync::Mutex<Option<(Option<SmoothieConfig>, StopMode)>> = std::sync::Mutex::new(None);
+
+/// Entry point of `--service`: connects to the service control manager and
+/// runs the daemon until the service is stopped. Returns the exit code.
+#[cfg(target_os = "windows")]
+pub fn run(config: Option<SmoothieConfig>, on_stop: StopMode) -> i32 {
+    use winapi::um::winsvc::{SERVICE_TABLE_ENTRYW, StartServiceCtrlDispatcherW};
+
+    *SERVICE_ARGS.lock().expect("Failed to lock service arguments") = Some((config, on_stop));
+    let mut name = wide(SERVICE_NAME);
+    let table = [
+        SERVICE_TABLE_ENTRYW {
+            lpServiceName: name.as_mut_ptr(),
+            lpServiceProc: Some(service_main),
+        },
+        SERVICE_TABLE_ENTRYW {
+            lpServiceName: std::ptr::null_mut(),
+            lpServiceProc: None,
+        },
+    ];
+    // Blocks until the service has stopped
+    if unsafe { StartServiceCtrlDispatcherW(table.as_ptr()) } == 0 {
+        log::error!(
+            "Failed to connect to the service control manager ({}); --service is only for the installed service, \
+             use --daemon to run in a console",
+            std::io::Error::last_os_error()
+        );
+        return crate::cli::EXIT_CONFIG_ERROR;
+    }
+    crate::cli::EXIT_OK
+}
+
+#[cfg(target_os = "windows")]
+fn wide(s: &str) -> Vec<u16> {
+    use std::os::windows::ffi::OsStrExt;
+
+    std::ffi::OsStr::new(s).encode_wide().chain(std::iter::once(0)).collect()
+}
+
+/// Reports the service's state to the service control manager. `checkpoint`
+/// counts up while stopping to show it is not stuck; a non-zero `exit_code`
+/// (see `cli::EXIT_*`) shows up as a service-specific error.
+#[cfg(target_os = "windows")]
+fn set_status(handle: winapi::um::winsvc::SERVICE_STATUS_HANDLE, state: u32, checkpoint: u32, exit_code: i32) {
+    use winapi::shared::winerror::{ERROR_SERVICE_SPECIFIC_ERROR, NO_ERROR};
+    use winapi::um::winnt::SERVICE_WIN32_OWN_PROCESS;
+    use winapi::um::winsvc::{
+        SERVICE_ACCEPT_SHUTDOWN, SERVICE_ACCEPT_STOP, SERVICE_RUNNING, SERVICE_STATUS, SetServiceStatus,
+    };
+
+    let mut status = SERVICE_STATUS {
+        dwServiceType: SERVICE_WIN32_OWN_PROCESS,
+        dwCurrentState: state,
+        dwControlsAccepted: if state == SERVICE_RUNNING {
+            SERVICE_ACCEPT_STOP | SERVICE_ACCEPT_SHUTDOWN
+        } else {
+            0
+        },
+        dwWin32ExitCode: if exit_code == 0 { NO_ERROR } else { ERROR_SERVICE_SPECIFIC_ERROR },
+        dwServiceSpecificExitCode: exit_code as u32,
+        dwCheckPoint: checkpoint,
+        dwWaitHint: 10_000,
+    };
+    unsafe {
+        SetServiceStatus(handle, &mut status);
+    }
+}
+
+/// Stop requests from the service control manager. Stopping the service follows
+/// `--on-stop`; the machine shutting down always suspends, as there is no time
+/// to let renders finish.
+#[cfg(target_os = "windows")]
+unsafe extern "system" fn control_handler(
+    control: u32,
+    _event_type: u32,
+    _event_data: *mut winapi::ctypes::c_void,
+    _context: *mut winapi::ctypes::c_void,
+) -> u32 {
+    use winapi::shared::winerror::{ERROR_CALL_NOT_IMPLEMENTED, NO_ERROR};
+    use winapi::um::winsvc::{SERVICE_CONTROL_INTERROGATE, SERVICE_CONTROL_SHUTDOWN, SERVICE_CONTROL_STOP};
+
+    match control {
+        SERVICE_CONTROL_STOP => daemon::request_stop(),
+        SERVICE_CONTROL_SHUTDOWN => daemon::request_stop_with(StopMode::Suspend),
+        SERVICE_CONTROL_INTERROGATE => {}
+        _ => return ERROR_CALL_NOT_IMPLEMENTED,
+    }
+    NO_ERROR
+}
+
+/// Runs the daemon on its own thread, reporting it as running and then as
+/// stopping (once a second, however long draining takes) until it returns.
+#[cfg(target_os = "windows")]
+unsafe extern "system" fn service_main(_argc: u32, _argv: *mut *mut u16) {
+    use winapi::um::winsvc::{
+        RegisterServiceCtrlHandlerExW, SERVICE_RUNNING, SERVICE_STOP_PENDING, SERVICE_STOPPED,
+    };
+
+    let name = wide(SERVICE_NAME);
+    let handle = unsafe { RegisterServiceCtrlHandlerExW(name.as_ptr(), Some(control_handler), std::ptr::null_mut()) };
+    if handle.is_null() {
+        log::error!("Failed to register the service control handler: {}", std::io::Error::last_os_error());
+        return;
+    }
+    let Some((config, on_stop)) = SERVICE_ARGS.lock().expect("Failed to lock service arguments").take() else {
+        set_status(handle, SERVICE_STOPPED, 0, 0);
+        return;
+    };
+
+    set_status(handle, SERVICE_RUNNING, 0, 0);
+    let daemon = std::thread::spawn(move || daemon::run(config, on_stop));
+    let mut checkpoint = 0;
+    while !daemon.is_finished() {
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        if daemon::stop_requested().is_some() {
+            checkpoint += 1;
+            set_status(handle, SERVICE_STOP_PENDING, checkpoint, 0);
+        }
+    }
+    let exit_code = daemon.join().unwrap_or(crate::cli::EXIT_CONFIG_ERROR);
+    set_status(handle, SERVICE_STOPPED, 0, exit_code);
+}
+
+/// Elsewhere the service manager runs the daemon as is, so `--service` is the
+/// same as `--daemon`.
+#[cfg(not(target_os = "windows"))]
+pub fn run(config: Option<smoothie_queuer_core::config::SmoothieConfig>, on_stop: StopMode) -> i32 {
+    daemon::run(config, on_stop)
+}
@@ -0,0 +1,92 @@
+use eframe::egui;
+use smoothie_queuer_core::ffmpeg;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// Height thumbnails are decoded at; rows draw them smaller.
+const THUMBNAIL_HEIGHT: u32 = 72;
+
+/// An output as of one render: rerunning a task writes a new file to the same
+/// path, with a new finish time.
+type Key = (PathBuf, Option<SystemTime>);
+/// Size and RGBA pixels, as `ffmpeg::thumbnail` decodes them.
+type Decoded = Result<([usize; 2], Vec<u8>), String>;
+
+struct Request {
+    key: Key,
+    at: Duration,
+    ffmpeg_path: PathBuf,
+}
+
+enum Thumbnail {
+    Loading,
+    Ready(egui::TextureHandle),
+    Failed,
+}
+
+/// Thumbnails of finished outputs for the task list, decoded with ffmpeg one at
+/// a time on a helper thread and kept as textures for the session.
+pub struct ThumbnailCache {
+    thumbnails: HashMap<Key, Thumbnail>,
+    tx: Sender<Request>,
+    rx: Receiver<(Key, Decoded)>,
+}
+
+impl ThumbnailCache {
+    pub fn new(ctx: &egui::Context) -> Self {
+        let (tx, requests) = mpsc::channel::<Request>();
+        let (results, rx) = mpsc::channel();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            for request in requests {
+                let result = ffmpeg::thumbnail(&request.ffmpeg_path, &request.key.0, request.at, THUMBNAIL_HEIGHT);
+                if results.send((request.key, result)).is_err() {
+                    break;
+                }
+                ctx.request_repaint();
+            }
+        });
+        Self { thumbnails: HashMap::new(), tx, rx }
+    }
+
+    /// The thumbnail of `output`, finished at `finished_at`, taken `at` into it.
+    /// Asks for it on the first call and returns `None` until it is decoded, or
+    /// for good if it could not be.
+    pub fn get(
+        &mut self,
+        ctx: &egui::Context,
+        output: &Path,
+        finished_at: Option<SystemTime>,
+        at: Duration,
+        ffmpeg_path: &Path,
+    ) -> Option<&egui::TextureHandle> {
+        while let Ok((key, result)) = self.rx.try_recv() {
+            let thumbnail = match result {
+                Ok((size, rgba)) => {
+                    let image = egui::ColorImage::from_rgba_unmultiplied(size, &rgba);
+                    let name = format!("thumbnail_{}", key.0.display());
+                    Thumbnail::Ready(ctx.load_texture(name, image, egui::TextureOptions::LINEAR))
+                }
+                Err(e) => {
+                    log::debug!("No thumbnail for {:?}: {}", key.0, e);
+                    Thumbnail::Failed
+                }
+            };
+            self.thumbnails.insert(key, thumbnail);
+        }
+
+        let key = (output.to_path_buf(), finished_at);
+        if !self.thumbnails.contains_key(&key) {
+            let request = Request { key: key.clone(), at, ffmpeg_path: ffmpeg_path.to_path_buf() };
+            let _ = self.tx.send(request);
+            self.thumbnails.insert(key.clone(), Thumbnail::Loading);
+        }
+        match self.thumbnails.get(&key) {
+            Some(Thumbnail::Ready(texture)) => Some(texture),
+            Some(Thumbnail::Loading | Thumbnail::Failed) | None => None,
+        }
+    }
+}
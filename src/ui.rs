@@ -1,30 +1,166 @@
-use crate::config::{self, SmoothieConfig};
-use crate::queue::{QueueManager, TaskStatus, VideoTask};
-use crate::worker::{self, UpdateMessage};
+use crate::api::{self, ControlCommand};
+use crate::cli::URL_SCHEME;
+use crate::compare::ComparePlayer;
+use crate::monitor::{HardwareMonitor, HardwareUsage};
+use crate::mqtt::MqttPublisher;
+use crate::ntfy::NtfyNotifier;
+use crate::{dragout, integration, ipc, obs};
+use crate::scripting::{self, ScriptHost};
+use crate::thumbnails::ThumbnailCache;
+use crate::update::{self, Release};
+use crate::watch::FolderWatcher;
 use eframe::egui;
 use rfd::FileDialog;
+use smoothie_queuer_core::config::{self, SmoothieConfig};
+use smoothie_queuer_core::{bundle, download, events, export, ffmpeg, inspect, recipes, session, store, templates};
+use smoothie_queuer_core::archive::{self, ArchivePage, RecipeStats, SpeedModel};
+use smoothie_queuer_core::events::EventKind;
+use smoothie_queuer_core::plugins::{self, MissingKind, MissingPlugin};
+use smoothie_queuer_core::rules::{InputRule, RuleKind};
+use smoothie_queuer_core::schema::{self, RecipeWarning, Schema};
+use smoothie_queuer_core::queue::{
+    self, Backend, BatchEdit, BudgetStatus, EnvVar, ImageSequence, OutputContainer, PreProcess, QueueManager,
+    QueueSummary, RecipeOverride, RifeOptions, StopFlags, TaskEdit, TaskLabel, TaskStatus, UrlSource, VideoTask,
+};
+use smoothie_queuer_core::settings::{AppSettings, POST_COMMAND_TOKENS, Profile};
+use smoothie_queuer_core::worker::{self, UpdateMessage};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, mpsc};
 use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Longest a progress update waits for a repaint, so fast progress output does
+/// not redraw the window on every line.
+const PROGRESS_REPAINT_DELAY: Duration = Duration::from_millis(250);
+/// How often the UI refreshes while the worker runs, for the elapsed time of the
+/// running task and an open log window, which get no worker message of their own.
+const RUNNING_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+/// How often the queue is saved (when it changed) for restoring at the next launch.
+const SESSION_SAVE_INTERVAL: Duration = Duration::from_secs(2);
+/// What energy estimates include, shown on hover.
+const ENERGY_HINT: &str = "Estimated from the CPU package (Linux) and NVIDIA GPU power draw while rendering; the rest of the system is not included";
+/// Marks a suspended render, apart from the yellow of "pause after current".
+const SUSPENDED_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 140, 0);
+/// Archived tasks and per-recipe totals the History window shows.
+type HistoryPage = (ArchivePage, Vec<RecipeStats>);
+
+/// Archived tasks listed in the History window; the rest stay on disk.
+const HISTORY_SHOWN: usize = 500;
+/// Height of the output thumbnails on completed rows.
+const THUMBNAIL_ROW_HEIGHT: f32 = 36.0;
 
 pub struct SmoothieQueueApp {
     queue_manager: Arc<Mutex<QueueManager>>,
+    stop_flags: StopFlags, // Shared with `queue_manager`, usable without locking it
     config: Option<SmoothieConfig>,
+    settings: AppSettings,
+    settings_draft: Option<AppSettings>, // Edited copy while the settings window is open
+    new_extension: String,                // Extension being typed in the settings window
+    filter_label: Option<TaskLabel>,      // Only list tasks with this label
+    filter_text: String,                  // Only list tasks whose name or note contains this
+    filter_status: HashSet<StatusFilter>, // Only list tasks with these statuses; empty lists all
+    view: QueueView,                      // What the main window shows of the queue
+    speeds: SpeedModel,                   // From the history, for render time estimates
+    history_open: bool,                   // Completed task history window
+    history: Option<(usize, Result<HistoryPage, String>)>, // What the history window shows, read at this archived count
+    events_open: bool,                    // Session event log window
+    extension_error: Option<String>,      // Why the last typed extension was rejected
+    log_task: Option<usize>,             // Task whose log window is open
+    screenshot: Option<(PathBuf, Result<egui::TextureHandle, String>)>, // Comparison screenshot shown in the log window
+    inspect: Option<InspectWindow>,      // Open effective settings window
+    plugin_help: Option<PluginHelpWindow>, // Open missing plugin help
+    compare: Option<ComparePlayer>,        // Open side-by-side player
+    thumbnails: ThumbnailCache,            // Completed tasks' output thumbnails
+    plugin_problems: HashMap<usize, (usize, Option<MissingPlugin>)>, // Failed tasks' missing plugin, by the log length it was found in
+    integration_status: Option<String>,  // Result of the last shell registration
+    transfer_status: Option<String>,     // Result of the last settings import/export
+    templates: Option<TemplatesWindow>,  // Open templates window
+    new_recipe: Option<NewRecipeWindow>, // Open new recipe dialog
+    recipe_editor: Option<RecipeEditorWindow>, // Open recipe editor
+    import_recipe: Option<ImportRecipeWindow>, // Open recipe import dialog
+    edit_window: Option<EditTaskWindow>, // Open edit dialog of a pending task
+    selected_tasks: HashSet<usize>,      // Pending tasks ticked for batch editing
+    batch_edit: Option<BatchEditWindow>, // Open batch edit dialog for `selected_tasks`
+    update: Option<UpdateWindow>,        // Update check in progress or its result
+    relaunch: Option<PathBuf>,           // Updated executable to start on exit
+    scripts: ScriptHost,
+    mqtt: Option<MqttPublisher>, // Recreated when the settings are saved
+    ntfy: Option<NtfyNotifier>,  // Likewise
     output_folder: Option<PathBuf>,
     recipe_path: PathBuf,
+    extra_args: String,           // Extra backend arguments for new tasks
+    profile_name: Option<String>, // Name being typed to save the selections as a profile
+    output_container: Option<OutputContainer>,
+    pre_process: Option<PreProcess>,
+    backend: Backend,
+    rife: RifeOptions,
     worker_running: bool,
-    last_id: usize,
     files_dropped: bool,
+    tasks_added: bool, // Tasks were queued this frame, for auto-start
+    session_saved: String,   // Queue as last written by `save_session`
+    session_checked: Instant, // Last time `save_session` ran
     available_recipes: Vec<PathBuf>,
+    recipe_warnings: HashMap<PathBuf, Vec<RecipeWarning>>, // Schema check of each listed recipe
     worker_tx: mpsc::Sender<UpdateMessage>,
     worker_rx: mpsc::Receiver<UpdateMessage>,
+    control_tx: mpsc::Sender<ControlCommand>,
+    control_rx: mpsc::Receiver<ControlCommand>,
+    metadata_tx: mpsc::Sender<MetadataRequest>,
+    watcher: Option<FolderWatcher>,
+    monitor: HardwareMonitor, // CPU/RAM/GPU gauges while rendering
+}
+
+/// Input of a newly added task to measure on the metadata thread.
+struct MetadataRequest {
+    task_id: usize,
+    input: PathBuf,
+    ffmpeg_path: Option<PathBuf>, // None skips the duration and resolution probes
 }
 
 impl SmoothieQueueApp {
-    pub fn new(cc: &eframe::CreationContext<'_>, initial_config: Option<SmoothieConfig>) -> Self {
+    /// `enqueue` holds files passed on the command line; they are queued on the
+    /// first frame like any other control command.
+    pub fn new(
+        cc: &eframe::CreationContext<'_>,
+        initial_config: Option<SmoothieConfig>,
+        enqueue: Vec<ControlCommand>,
+    ) -> Self {
         cc.egui_ctx.set_visuals(egui::Visuals::dark());
 
-        let (worker_tx, worker_rx) = mpsc::channel();
+        let (worker_tx, worker_rx) = forward_worker_updates(&cc.egui_ctx);
+        let (control_tx, control_rx) = mpsc::channel();
+        let settings = AppSettings::load();
+        let default_backend = settings.default_backend;
+        if let Some(max_age) = settings.history_retention()
+            && let Err(e) = archive::prune(max_age)
+        {
+            log::warn!("Failed to prune the history: {}", e);
+        }
+        let restored = session::load();
+        let speeds = load_speeds(&restored.tasks);
+        let resume = settings.resume_on_launch
+            && restored.tasks.iter().any(|t| t.status == TaskStatus::Pending);
+        let queue_manager = Arc::new(Mutex::new(restored));
+
+        let metadata_tx = spawn_metadata_probe(Arc::clone(&queue_manager), cc.egui_ctx.clone());
+
+        let ctx = cc.egui_ctx.clone();
+        ipc::spawn_server(control_tx.clone(), move || ctx.request_repaint());
+        for command in enqueue {
+            let _ = control_tx.send(command);
+        }
+
+        if settings.api_enabled {
+            let ctx = cc.egui_ctx.clone();
+            api::spawn(
+                settings.api_port,
+                settings.api_lan,
+                Arc::clone(&queue_manager),
+                control_tx.clone(),
+                move || ctx.request_repaint(),
+            );
+        }
 
         let initial_recipe_path = initial_config.as_ref().map_or_else(
             || {
@@ -34,7 +170,7 @@ impl SmoothieQueueApp {
             |cfg| cfg.recipe_path.clone(),
         );
 
-        let available_recipes = initial_config
+        let mut available_recipes = initial_config
             .as_ref()
             .and_then(|cfg| cfg.executable_path.parent()?.parent())
             .map_or_else(
@@ -48,19 +184,89 @@ impl SmoothieQueueApp {
                 },
                 config::find_recipe_files,
             );
+        available_recipes.extend(recipes::source_files());
+        let recipe_warnings = check_recipes(&available_recipes, &initial_recipe_path);
 
-        Self {
-            queue_manager: Arc::new(Mutex::new(QueueManager::new())),
+        let stop_flags = queue_manager.lock()
+            .expect("Failed to lock queue manager")
+            .stop_flags
+            .clone();
+        let mqtt = MqttPublisher::from_settings(&settings);
+        let ntfy = NtfyNotifier::from_settings(&settings);
+        let mut app = Self {
+            queue_manager,
+            stop_flags,
             config: initial_config,
+            settings,
+            settings_draft: None,
+            new_extension: String::new(),
+            filter_label: None,
+            filter_text: String::new(),
+            filter_status: HashSet::new(),
+            view: QueueView::default(),
+            speeds,
+            history_open: false,
+            history: None,
+            events_open: false,
+            extension_error: None,
+            log_task: None,
+            screenshot: None,
+            inspect: None,
+            plugin_help: None,
+            compare: None,
+            thumbnails: ThumbnailCache::new(&cc.egui_ctx),
+            plugin_problems: HashMap::new(),
+            edit_window: None,
+            selected_tasks: HashSet::new(),
+            batch_edit: None,
+            update: None,
+            relaunch: None,
+            integration_status: None,
+            transfer_status: None,
+            templates: None,
+            new_recipe: None,
+            recipe_editor: None,
+            import_recipe: None,
+            scripts: ScriptHost::load(),
+            mqtt,
+            ntfy,
             output_folder: None,
             recipe_path: initial_recipe_path,
+            extra_args: String::new(),
+            profile_name: None,
+            output_container: None,
+            pre_process: None,
+            backend: default_backend,
+            rife: RifeOptions::default(),
             worker_running: false,
-            last_id: 0,
             files_dropped: false,
+            tasks_added: false,
+            session_saved: String::new(),
+            session_checked: Instant::now(),
             worker_tx,
             worker_rx,
+            control_tx,
+            control_rx,
+            metadata_tx,
+            watcher: None,
+            monitor: HardwareMonitor::new(),
             available_recipes,
+            recipe_warnings,
+        };
+        if let Some(profile) = app.settings.active_profile.as_ref().and_then(|name| app.settings.profile(name)) {
+            let profile = profile.clone();
+            app.use_profile(&profile);
         }
+        app.restart_watcher(&cc.egui_ctx);
+        update::clean_up_previous();
+        if app.settings.check_updates {
+            app.start_update_check(&cc.egui_ctx, false);
+        }
+        if resume {
+            log::info!("Resuming the restored queue");
+            app.start_worker();
+        }
+        app
     }
 }
 
@@ -68,39 +274,110 @@ impl eframe::App for SmoothieQueueApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         while let Ok(update) = self.worker_rx.try_recv() {
             match update {
-                UpdateMessage::TaskStarted(id) => {
+                UpdateMessage::TaskWaiting(id) => {
                     let mut manager = self.queue_manager.lock()
                         .expect("Failed to lock queue manager");
-                    manager.mark_as_running(id);
+                    manager.mark_as_waiting(id);
                 }
-                UpdateMessage::TaskCompleted(id) => {
+                UpdateMessage::TaskDownloading(id) => {
                     let mut manager = self.queue_manager.lock()
                         .expect("Failed to lock queue manager");
-                    manager.mark_as_completed(id);
+                    manager.mark_as_downloading(id);
                 }
-                UpdateMessage::TaskFailed(id, err_msg) => {
+                UpdateMessage::TaskStarted(id) => {
                     let mut manager = self.queue_manager.lock()
                         .expect("Failed to lock queue manager");
-                    manager.mark_as_failed(id, err_msg);
+                    manager.mark_as_running(id);
+                    if let (Some(mqtt), Some(task)) = (&self.mqtt, manager.tasks.iter().find(|t| t.id == id)) {
+                        mqtt.task_event("task_started", task);
+                    }
+                }
+                // Already stored on the task by the worker; only needs a repaint
+                UpdateMessage::TaskProgress(..) => {}
+                UpdateMessage::TaskCompleted(id) => {
+                    let task = {
+                        let mut manager = self.queue_manager.lock()
+                            .expect("Failed to lock queue manager");
+                        manager.mark_as_completed(id);
+                        manager.tasks.iter().find(|t| t.id == id).cloned()
+                    };
+                    if let Some(task) = task {
+                        if self.speeds.record(&task) {
+                            self.view.invalidate();
+                        }
+                        self.scripts.on_task_completed(&task);
+                        if let Some(mqtt) = &self.mqtt {
+                            mqtt.task_event("task_completed", &task);
+                        }
+                        if let Some(ntfy) = &self.ntfy {
+                            ntfy.task_completed(&task);
+                        }
+                        if self.settings.auto_clear_completed {
+                            self.queue_manager.lock()
+                                .expect("Failed to lock queue manager")
+                                .archive_task(id);
+                        }
+                    }
+                }
+                UpdateMessage::TaskFailed(id, err_msg) => {
+                    let task = {
+                        let mut manager = self.queue_manager.lock()
+                            .expect("Failed to lock queue manager");
+                        manager.mark_as_failed(id, err_msg);
+                        manager.tasks.iter().find(|t| t.id == id).cloned()
+                    };
+                    if let Some(task) = task {
+                        self.scripts.on_task_failed(&task);
+                        if let Some(mqtt) = &self.mqtt {
+                            mqtt.task_event("task_failed", &task);
+                        }
+                        if let Some(ntfy) = &self.ntfy {
+                            ntfy.task_failed(&task);
+                        }
+                    }
                 }
                 UpdateMessage::TaskCancelled(id) => {
                     let mut manager = self.queue_manager.lock()
                         .expect("Failed to lock queue manager");
                     manager.mark_as_cancelled(id);
                 }
+                // The worker already paused the queue; the budget line shows what is left
+                UpdateMessage::BudgetReached(_) => {}
                 UpdateMessage::WorkerFinished => {
                     self.worker_running = false;
+                    let tasks = self.queue_manager.lock()
+                        .expect("Failed to lock queue manager")
+                        .tasks
+                        .clone();
+                    self.scripts.on_queue_finished(&tasks);
+                    if let Some(mqtt) = &self.mqtt {
+                        mqtt.queue_idle(&tasks);
+                    }
+                    if let Some(ntfy) = &self.ntfy {
+                        ntfy.queue_finished(&tasks);
+                    }
                 }
             }
         }
 
+        while let Ok(command) = self.control_rx.try_recv() {
+            self.handle_control_command(command);
+        }
+
+        self.view.refresh(
+            &self.queue_manager.lock().expect("Failed to lock queue manager"),
+            &self.speeds,
+            (&self.filter_text, self.filter_label, &self.filter_status),
+            &mut self.selected_tasks,
+        );
+
         if let Some(config) = &self.config {
+            let mut start_requested = false;
+            let mut switch_profile = None;
+            let mut save_profile = false;
+            let mut delete_profile = None;
             egui::CentralPanel::default().show(ctx, |ui| {
-                let has_tasks = {
-                    let manager = self.queue_manager.lock()
-                        .expect("Failed to lock queue manager");
-                    !manager.tasks.is_empty()
-                };
+                let has_tasks = self.view.has_tasks;
 
                 if !has_tasks && !self.files_dropped {
                     ui.vertical_centered_justified(|ui| {
@@ -113,30 +390,84 @@ impl eframe::App for SmoothieQueueApp {
                         drop_frame.show(ui, |ui| {
                             ui.vertical_centered(|ui| {
                                 ui.label(
-                                    egui::RichText::new("Drop video files here")
+                                    egui::RichText::new("Drop video files or image sequence folders here")
                                         .heading()
                                         .color(ui.visuals().text_color())
                                 );
                                 ui.label(
-                                    egui::RichText::new("Supported formats: mp4, mkv, mov, avi, webm")
+                                    egui::RichText::new(format!(
+                                        "Supported formats: {}{} (or a .txt/.m3u list of them)",
+                                        self.settings.allowed_extensions.join(", "),
+                                        if self.settings.vpy_inputs_enabled { ", vpy" } else { "" }
+                                    ))
                                         .color(ui.visuals().weak_text_color())
                                 );
                             });
                         });
                     });
                 } else {
-                    ui.heading("Smoothie Queuer");
+                    ui.horizontal(|ui| {
+                        ui.heading("Smoothie Queuer");
+                        if config.simulation.is_some() {
+                            ui.colored_label(egui::Color32::LIGHT_BLUE, "(simulation mode)");
+                        }
+                    });
 
-                    // Output Folder Selector
+                    // Profile ComboBox
                     ui.horizontal(|ui| {
-                        if ui.button("Select Output Folder").clicked() {
-                            if let Some(path) = FileDialog::new().pick_folder() {
-                                self.output_folder = Some(path.clone());
-                                let mut manager = self.queue_manager.lock()
-                                    .expect("Failed to lock queue manager");
-                                manager.update_pending_output_dirs(path);
+                        ui.label("Profile:");
+                        let mut selected = self.settings.active_profile.clone();
+                        egui::ComboBox::from_id_source("profile_select")
+                            .selected_text(selected.clone().unwrap_or_else(|| "None".to_string()))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut selected, None, "None");
+                                for profile in &self.settings.profiles {
+                                    ui.selectable_value(&mut selected, Some(profile.name.clone()), &profile.name);
+                                }
+                            })
+                            .response
+                            .on_hover_text("Switches the recipe, output folder, extra arguments and parallel renders used for newly added tasks");
+                        if selected != self.settings.active_profile {
+                            switch_profile = Some(selected);
+                        }
+
+                        if let Some(name) = &mut self.profile_name {
+                            let response = ui.add(
+                                egui::TextEdit::singleline(name)
+                                    .hint_text("e.g. Final 4K")
+                                    .desired_width(140.0),
+                            );
+                            save_profile = ui.button("Save").clicked()
+                                || (response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)));
+                            if ui.button("Cancel").clicked() {
+                                self.profile_name = None;
+                            }
+                        } else {
+                            if ui
+                                .button("Save as Profile...")
+                                .on_hover_text("Saves the current recipe, output folder, extra arguments and parallel renders under a name")
+                                .clicked()
+                            {
+                                self.profile_name = Some(self.settings.active_profile.clone().unwrap_or_default());
+                            }
+                            if let Some(active) = &self.settings.active_profile
+                                && ui.button("Delete").on_hover_text("Deletes this profile; the current selections stay").clicked()
+                            {
+                                delete_profile = Some(active.clone());
                             }
                         }
+                    });
+
+                    // Output Folder Selector
+                    ui.horizontal(|ui| {
+                        if ui.button("Select Output Folder").clicked()
+                            && let Some(path) = FileDialog::new().pick_folder()
+                        {
+                            self.output_folder = Some(path.clone());
+                            let mut manager = self.queue_manager.lock()
+                                .expect("Failed to lock queue manager");
+                            manager.update_pending_output_dirs(path);
+                        }
                         let folder_text = self.output_folder.as_ref()
                             .map_or("Default (next to input video)".to_string(), 
                                    |p| p.display().to_string());
@@ -150,31 +481,208 @@ impl eframe::App for SmoothieQueueApp {
                         let selected_recipe_filename = self.recipe_path.file_name()
                             .map_or_else(|| "Invalid".into(), |f| f.to_string_lossy());
 
-                        egui::ComboBox::from_id_source("recipe_select")
+                        let recipe_select = egui::ComboBox::from_id_source("recipe_select")
                             .selected_text(selected_recipe_filename)
                             .show_ui(ui, |ui| {
                                 for recipe in &self.available_recipes {
                                     let filename = recipe.file_name()
                                         .map_or_else(|| "Invalid Path".into(), |f| f.to_string_lossy());
-                                    ui.selectable_value(&mut self.recipe_path, recipe.clone(), filename);
+                                    match self.recipe_warnings.get(recipe).filter(|warnings| !warnings.is_empty()) {
+                                        Some(warnings) => {
+                                            ui.selectable_value(&mut self.recipe_path, recipe.clone(), format!("{} ⚠", filename))
+                                                .on_hover_text(warnings_text(warnings));
+                                        }
+                                        None => {
+                                            ui.selectable_value(&mut self.recipe_path, recipe.clone(), filename);
+                                        }
+                                    }
                                 }
                             });
+                        // Picks up recipes added or edited since, e.g. a new YAML/TOML source
+                        if recipe_select.response.clicked() {
+                            self.available_recipes = scan_recipes(config.smoothie_dir());
+                            self.recipe_warnings = check_recipes(&self.available_recipes, &config.recipe_path);
+                        }
+                        if let Some(warnings) = self.recipe_warnings.get(&self.recipe_path).filter(|warnings| !warnings.is_empty()) {
+                            ui.colored_label(egui::Color32::YELLOW, format!("⚠ {}", warnings.len()))
+                                .on_hover_text(warnings_text(warnings));
+                        }
+
+                        // Recipes outside the Smoothie folder, or blur .cfg configs
+                        if ui.button("Browse...").clicked()
+                            && let Some(path) = FileDialog::new()
+                                .add_filter("Recipe / blur config", &["ini", "cfg"])
+                                .add_filter("YAML / TOML recipe", &["yaml", "yml", "toml"])
+                                .pick_file()
+                        {
+                            self.recipe_path = path;
+                        }
+                        if ui
+                            .add_enabled(config.recipes_dir().is_some(), egui::Button::new("New Recipe..."))
+                            .on_hover_text("Creates a recipe in the Smoothie recipes folder from a template")
+                            .clicked()
+                        {
+                            self.new_recipe = Some(NewRecipeWindow::new());
+                        }
+                        if ui
+                            .add_enabled(config.recipes_dir().is_some(), egui::Button::new("Import..."))
+                            .on_hover_text("Downloads a recipe shared as a link, e.g. a GitHub gist or a Pastebin paste")
+                            .clicked()
+                        {
+                            self.import_recipe = Some(ImportRecipeWindow::default());
+                        }
+                        if ui
+                            .add_enabled(self.recipe_path.is_file(), egui::Button::new("Edit Recipe..."))
+                            .on_hover_text("Edits the selected recipe; every save keeps a backup of the previous version")
+                            .clicked()
+                        {
+                            match RecipeEditorWindow::open(&self.recipe_path) {
+                                Ok(window) => self.recipe_editor = Some(window),
+                                Err(e) => log::error!("{}", e),
+                            }
+                        }
+                        if ui
+                            .button("YAML/TOML Recipes")
+                            .on_hover_text("Opens the folder for recipes written in YAML or TOML; they are converted to .ini when their task renders")
+                            .clicked()
+                        {
+                            let dir = recipes::sources_dir();
+                            match std::fs::create_dir_all(&dir) {
+                                Ok(()) => {
+                                    let _ = opener::open(dir);
+                                }
+                                Err(e) => log::error!("Failed to create {:?}: {}", dir, e),
+                            }
+                        }
                         
                         if self.recipe_path != previous_recipe_path {
+                            if !self.recipe_warnings.contains_key(&self.recipe_path) {
+                                let warnings = schema::check(&self.recipe_path, &Schema::for_default_recipe(&config.recipe_path));
+                                self.recipe_warnings.insert(self.recipe_path.clone(), warnings);
+                            }
                             let mut manager = self.queue_manager.lock()
                                 .expect("Failed to lock queue manager");
                             manager.update_pending_recipes(self.recipe_path.clone());
                         }
                     });
 
-                    // Open Root Folder Button
+                    // Extra backend arguments
                     ui.horizontal(|ui| {
-                        if ui.button("Open Smoothie Folder").clicked() {
-                            if let Some(exe_dir) = config.executable_path.parent() {
-                                if let Some(root_dir) = exe_dir.parent() {
-                                    let _ = opener::open(root_dir);
-                                }
+                        ui.label("Extra arguments:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.extra_args)
+                                .hint_text("Appended to the backend command")
+                                .desired_width(240.0),
+                        )
+                        .on_hover_text("Used for newly added tasks; change it for queued ones with Edit");
+                    });
+
+                    // Backend ComboBox, only useful when another backend is installed too
+                    let installed_backends = config.available_backends();
+                    if installed_backends.len() > 1 {
+                        ui.horizontal(|ui| {
+                            ui.label("Backend:");
+                            let previous_backend = self.backend;
+                            egui::ComboBox::from_id_source("backend_select")
+                                .selected_text(self.backend.label())
+                                .show_ui(ui, |ui| {
+                                    for &backend in &installed_backends {
+                                        ui.selectable_value(&mut self.backend, backend, backend.label());
+                                    }
+                                })
+                                .response
+                                .on_hover_text("blur tasks use the recipe only if it is a blur .cfg config; rife-ncnn-vulkan ignores it");
+
+                            if self.backend != previous_backend {
+                                let mut manager = self.queue_manager.lock()
+                                    .expect("Failed to lock queue manager");
+                                manager.update_pending_backends(self.backend);
                             }
+                        });
+                    }
+
+                    // RIFE settings, stored on each task like the other options
+                    if self.backend == Backend::Rife {
+                        ui.horizontal(|ui| {
+                            let previous_rife = self.rife.clone();
+                            ui.label("RIFE factor:");
+                            ui.add(egui::DragValue::new(&mut self.rife.factor).clamp_range(2..=8).suffix("x"));
+                            ui.label("Model:");
+                            ui.add(egui::TextEdit::singleline(&mut self.rife.model).desired_width(120.0))
+                                .on_hover_text("Model folder next to rife-ncnn-vulkan, e.g. rife-v4.6");
+
+                            if self.rife != previous_rife {
+                                let mut manager = self.queue_manager.lock()
+                                    .expect("Failed to lock queue manager");
+                                manager.update_pending_rife_options(&self.rife);
+                            }
+                        });
+                    }
+
+                    // Output Container ComboBox
+                    ui.horizontal(|ui| {
+                        ui.label("Container:");
+                        let previous_container = self.output_container;
+                        egui::ComboBox::from_id_source("container_select")
+                            .selected_text(container_label(self.output_container))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut self.output_container, None, container_label(None));
+                                for container in OutputContainer::ALL {
+                                    ui.selectable_value(
+                                        &mut self.output_container,
+                                        Some(container),
+                                        container_label(Some(container)),
+                                    );
+                                }
+                            });
+
+                        if self.output_container != previous_container {
+                            let mut manager = self.queue_manager.lock()
+                                .expect("Failed to lock queue manager");
+                            manager.update_pending_containers(self.output_container);
+                        }
+                    });
+
+                    // Pre-processing ComboBox
+                    ui.horizontal(|ui| {
+                        ui.label("Pre-process:");
+                        let previous_pre_process = self.pre_process;
+                        egui::ComboBox::from_id_source("pre_process_select")
+                            .selected_text(pre_process_label(self.pre_process))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut self.pre_process, None, pre_process_label(None));
+                                for pre_process in PreProcess::ALL {
+                                    ui.selectable_value(
+                                        &mut self.pre_process,
+                                        Some(pre_process),
+                                        pre_process_label(Some(pre_process)),
+                                    );
+                                }
+                            })
+                            .response
+                            .on_hover_text("Runs ffmpeg on the input first, using a temporary copy");
+
+                        if self.pre_process != previous_pre_process {
+                            let mut manager = self.queue_manager.lock()
+                                .expect("Failed to lock queue manager");
+                            manager.update_pending_pre_process(self.pre_process);
+                        }
+                    });
+
+                    // Open Root Folder Button
+                    ui.horizontal(|ui| {
+                        if ui.button("Open Smoothie Folder").clicked()
+                            && let Some(root_dir) = config.executable_path.parent().and_then(Path::parent)
+                        {
+                            let _ = opener::open(root_dir);
+                        }
+
+                        if ui.button("Settings").clicked() && self.settings_draft.is_none() {
+                            self.settings_draft = Some(self.settings.clone());
+                        }
+
+                        if ui.button("Templates").clicked() && self.templates.is_none() {
+                            self.templates = Some(TemplatesWindow::new());
                         }
                     });
 
@@ -183,55 +691,52 @@ impl eframe::App for SmoothieQueueApp {
                         // Start Queue Button
                         let start_button = ui.add_enabled(!self.worker_running, egui::Button::new("Start Queue"));
                         if start_button.clicked() {
-                            self.worker_running = true;
-                            let queue_manager_clone = Arc::clone(&self.queue_manager);
-                            let tx_clone = self.worker_tx.clone();
-                            let executable_path_clone = config.executable_path.clone();
-                            
-                            let mut manager = self.queue_manager.lock()
-                                .expect("Failed to lock queue manager");
-                            manager.clear_stop_request();
-                            manager.clear_force_stop();
-
-                            thread::spawn(move || {
-                                worker::run_worker(queue_manager_clone, tx_clone, executable_path_clone);
-                            });
+                            start_requested = true;
                         }
 
-                        // Pause Queue Button
-                        let is_paused = {
-                            let manager = self.queue_manager.lock()
-                                .expect("Failed to lock queue manager");
-                            manager.is_stop_requested()
-                        };
-                        
-                        let button_text = if is_paused { "Pause Queue (Paused)" } else { "Pause Queue" };
-                        let stop_button = ui.add_enabled(self.worker_running, egui::Button::new(button_text));
+                        // Pause After Current Button
+                        let is_paused = self.stop_flags.is_stop_requested();
+                        let stop_button = ui.add_enabled(
+                            self.worker_running,
+                            egui::Button::new("Pause After Current").selected(is_paused),
+                        ).on_hover_text("Stop the queue once the running task finishes");
                         if stop_button.clicked() {
-                            let mut manager = self.queue_manager.lock()
-                                .expect("Failed to lock queue manager");
-                            if manager.is_stop_requested() {
-                                manager.clear_stop_request();
+                            if is_paused {
+                                self.stop_flags.clear_stop_request();
+                            } else {
+                                self.stop_flags.request_stop();
+                            }
+                        }
+
+                        // Pause Now / Resume Button
+                        let is_suspended = self.stop_flags.is_suspended();
+                        let button_text = if is_suspended { "Resume" } else { "Pause Now" };
+                        let suspend_button = ui.add_enabled(
+                            self.worker_running,
+                            egui::Button::new(button_text).selected(is_suspended),
+                        ).on_hover_text("Suspend the running render where it is");
+                        if suspend_button.clicked() {
+                            if is_suspended {
+                                self.stop_flags.resume();
                             } else {
-                                manager.request_stop();
+                                self.stop_flags.request_suspend();
                             }
                         }
 
                         // Force Stop Task Button
                         let force_stop_button = ui.add_enabled(self.worker_running, egui::Button::new("Force Stop Task"));
                         if force_stop_button.clicked() {
-                            let mut manager = self.queue_manager.lock()
-                                .expect("Failed to lock queue manager");
-                            manager.request_force_stop();
-                            manager.request_stop();
+                            self.stop_flags.request_force_stop();
+                            self.stop_flags.request_stop();
                         }
 
                         // Clear Queue Button
-                        let queue_empty = {
-                            let manager = self.queue_manager.lock()
-                                .expect("Failed to lock queue manager");
-                            manager.tasks.is_empty()
-                        };
+                        let (queue_empty, has_completed, has_finished, history_len) = (
+                            !self.view.has_tasks,
+                            self.view.summary.completed > 0,
+                            self.view.summary.completed + self.view.summary.cancelled > 0,
+                            self.view.history_len,
+                        );
                         let clear_button = ui.add_enabled(!self.worker_running && !queue_empty, 
                             egui::Button::new("Clear Queue"));
                         if clear_button.clicked() {
@@ -239,56 +744,457 @@ impl eframe::App for SmoothieQueueApp {
                                 .expect("Failed to lock queue manager");
                             manager.clear_all_tasks();
                         }
+
+                        if ui.add_enabled(has_completed, egui::Button::new("Clear Completed")).clicked() {
+                            let moved = self.queue_manager.lock()
+                                .expect("Failed to lock queue manager")
+                                .clear_completed();
+                            log::info!("Moved {} completed task(s) to the history", moved);
+                        }
+                        let clear_finished_button = ui.add_enabled(has_finished, egui::Button::new("Clear Finished"))
+                            .on_hover_text("Remove completed and cancelled tasks; failed tasks stay for a retry");
+                        if clear_finished_button.clicked() {
+                            let removed = self.queue_manager.lock()
+                                .expect("Failed to lock queue manager")
+                                .clear_finished();
+                            log::info!("Cleared {} finished task(s)", removed);
+                        }
+                        if ui
+                            .checkbox(&mut self.settings.auto_clear_completed, "Auto-clear")
+                            .on_hover_text("Move tasks into the history as soon as they complete")
+                            .changed()
+                        {
+                            self.settings.save();
+                        }
+                        if ui
+                            .checkbox(&mut self.settings.auto_start, "Auto-start")
+                            .on_hover_text("Start the queue as soon as files are added while it is idle")
+                            .changed()
+                        {
+                            self.settings.save();
+                        }
+                        if ui.add_enabled(history_len > 0, egui::Button::new(format!("History ({})", history_len))).clicked() {
+                            self.history_open = true;
+                        }
+                        if ui.button("Event Log")
+                            .on_hover_text("Everything that happened to the queue since the app started, with times")
+                            .clicked()
+                        {
+                            self.events_open = true;
+                        }
+
+                        // Export Script Button
+                        let export_button = ui.add_enabled(!queue_empty, egui::Button::new("Export Script..."))
+                            .on_hover_text("Saves the smoothie-rs commands for all pending tasks as a .bat/.sh");
+                        if export_button.clicked() {
+                            let default_name = if cfg!(windows) { "smoothie-queue.bat" } else { "smoothie-queue.sh" };
+                            if let Some(path) = FileDialog::new()
+                                .add_filter("Batch script", &["bat"])
+                                .add_filter("Shell script", &["sh"])
+                                .set_file_name(default_name)
+                                .save_file()
+                            {
+                                let manager = self.queue_manager.lock()
+                                    .expect("Failed to lock queue manager");
+                                match export::write_queue_script(&manager.tasks, config, &path) {
+                                    Ok(()) => log::info!("Exported queue script to {:?}", path),
+                                    Err(e) => log::error!("{}", e),
+                                }
+                            }
+                        }
                     });
 
                     ui.separator();
 
-                    let is_paused = {
-                        let manager = self.queue_manager.lock()
-                            .expect("Failed to lock queue manager");
-                        manager.is_stop_requested()
-                    };
+                    let is_suspended = self.worker_running && self.stop_flags.is_suspended();
+                    if is_suspended {
+                        ui.colored_label(SUSPENDED_COLOR, "Render Suspended - press Resume to continue");
+                    }
+                    let is_paused = self.stop_flags.is_stop_requested();
                     if is_paused {
                         ui.colored_label(egui::Color32::YELLOW, "Queue Paused - will stop after current task");
                     }
+                    let (budget, pending) = (self.view.budget, self.view.summary.pending);
+                    match budget {
+                        Some(budget) if budget.reached => {
+                            ui.colored_label(
+                                egui::Color32::YELLOW,
+                                format!("Run budget used up - {} task(s) left; Start Queue begins a new budget", pending),
+                            );
+                        }
+                        Some(budget) if self.worker_running => {
+                            let mut left = Vec::new();
+                            if let Some(tasks) = budget.tasks_left {
+                                left.push(format!("{} task(s)", tasks));
+                            }
+                            if let Some(time) = budget.time_left() {
+                                left.push(format_duration(time));
+                            }
+                            ui.label(format!("Run budget: {} left", left.join(", ")));
+                        }
+                        _ => {}
+                    }
+                    if self.worker_running {
+                        ui.horizontal_wrapped(|ui| {
+                            show_hardware_usage(ui, self.monitor.sample());
+                        });
+                    }
                     ui.separator();
 
+                    // Queue Summary
+                    let (summary, estimate) = (self.view.summary.clone(), self.view.estimate);
+                    if summary != QueueSummary::default() {
+                        ui.horizontal_wrapped(|ui| {
+                            show_queue_summary(ui, &summary, estimate);
+                        });
+                    }
+
                     // Task List Display
-                    ui.heading("Task Queue");
+                    ui.horizontal(|ui| {
+                        ui.heading("Task Queue");
+                        ui.add_space(12.0);
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.filter_text)
+                                .hint_text("Filter by name or note")
+                                .desired_width(160.0),
+                        );
+                        egui::ComboBox::from_id_source("label_filter")
+                            .selected_text(self.filter_label.map_or("Any label", |label| label.name()))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut self.filter_label, None, "Any label");
+                                for label in TaskLabel::ALL {
+                                    ui.selectable_value(&mut self.filter_label, Some(label), label_text(label));
+                                }
+                            });
+                        if !self.selected_tasks.is_empty() {
+                            ui.add_space(12.0);
+                            if ui.button(format!("Edit Selected ({})", self.selected_tasks.len())).clicked() {
+                                self.batch_edit = Some(BatchEditWindow::new(self.recipe_path.clone()));
+                            }
+                            if ui.button("Select None").clicked() {
+                                self.selected_tasks.clear();
+                            }
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        if ui.selectable_label(self.filter_status.is_empty(), "All").clicked() {
+                            self.filter_status.clear();
+                        }
+                        for filter in StatusFilter::ALL {
+                            let shown = self.filter_status.contains(&filter);
+                            let chip = format!("{} ({})", filter.name(), filter.count(&summary));
+                            if ui.selectable_label(shown, chip).on_hover_text(filter.hint()).clicked() {
+                                if shown {
+                                    self.filter_status.remove(&filter);
+                                } else {
+                                    self.filter_status.insert(filter);
+                                }
+                            }
+                        }
+                    });
                     let mut task_to_remove: Option<usize> = None;
+                    let mut task_to_cancel: Option<usize> = None;
+                    let mut task_to_rerun: Option<usize> = None;
+                    let mut task_to_edit: Option<usize> = None;
+                    let mut rerun_with_recipe: Option<(usize, PathBuf)> = None;
+                    let mut container_change: Option<(usize, Option<OutputContainer>)> = None;
+                    let mut backend_change: Option<(usize, Backend)> = None;
+                    let mut cfr_change: Option<usize> = None;
+                    let mut note_change: Option<(usize, String)> = None;
+                    let mut label_change: Option<(usize, Option<TaskLabel>)> = None;
+                    let mut output_to_drag: Option<PathBuf> = None;
                     egui::ScrollArea::vertical().auto_shrink([false; 2]).show(ui, |ui| {
                         let manager = self.queue_manager.lock()
                             .expect("Failed to lock queue manager");
+                        // The buttons above or the worker may have changed the queue since
+                        self.view.refresh(
+                            &manager,
+                            &self.speeds,
+                            (&self.filter_text, self.filter_label, &self.filter_status),
+                            &mut self.selected_tasks,
+                        );
                         if manager.tasks.is_empty() {
                             ui.label("(No tasks added yet)");
                         } else {
-                            for task in manager.tasks.iter() {
-                                ui.horizontal(|ui| {
-                                    // Remove Button
-                                    let remove_button = ui.add_enabled(
-                                        task.status == TaskStatus::Pending,
-                                        egui::Button::new("❌").small()
-                                    );
-                                    if remove_button.clicked() {
-                                        task_to_remove = Some(task.id);
+                            // Tint rows by recipe once the queue mixes several
+                            let mixed_recipes = self.view.mixed_recipes;
+                            for (task, filename) in self.view.rows.iter().map(|(index, name)| (&manager.tasks[*index], name)) {
+                                let recipe_color = recipe_color(&task.recipe_path);
+                                let background = ui.painter().add(egui::Shape::Noop);
+                                let row = ui.horizontal(|ui| {
+                                    // Selection for batch editing
+                                    let mut selected = self.selected_tasks.contains(&task.id);
+                                    if ui.add_enabled(task.status == TaskStatus::Pending, egui::Checkbox::without_text(&mut selected))
+                                        .changed()
+                                    {
+                                        if selected {
+                                            self.selected_tasks.insert(task.id);
+                                        } else {
+                                            self.selected_tasks.remove(&task.id);
+                                        }
+                                    }
+
+                                    // Cancel Button on the active task, Remove Button otherwise
+                                    if matches!(task.status, TaskStatus::Waiting | TaskStatus::Downloading | TaskStatus::Running) {
+                                        let cancel_button = ui.add(egui::Button::new("⏹").small())
+                                            .on_hover_text("Cancel this task and continue with the rest of the queue");
+                                        if cancel_button.clicked() {
+                                            task_to_cancel = Some(task.id);
+                                        }
+                                    } else {
+                                        let remove_button = ui.add_enabled(
+                                            task.status == TaskStatus::Pending,
+                                            egui::Button::new("❌").small()
+                                        );
+                                        if remove_button.clicked() {
+                                            task_to_remove = Some(task.id);
+                                        }
+                                    }
+
+                                    // Finished renders show what came out
+                                    if task.status == TaskStatus::Completed
+                                        && let Some(output) = &task.output_path
+                                        && let Some(config) = &self.config
+                                    {
+                                        let at = task.input_duration.map_or(Duration::ZERO, |duration| duration / 2);
+                                        if let Some(texture) = self.thumbnails.get(ctx, output, task.finished_at, at, &config.ffmpeg_path) {
+                                            let size = texture.size_vec2() * (THUMBNAIL_ROW_HEIGHT / texture.size_vec2().y);
+                                            let thumbnail = ui.add(egui::ImageButton::new((texture.id(), size)).frame(false))
+                                                .on_hover_ui(|ui| {
+                                                    ui.image((texture.id(), texture.size_vec2()));
+                                                    ui.label(output.display().to_string());
+                                                });
+                                            if thumbnail.clicked() {
+                                                let _ = opener::open(output);
+                                            }
+                                            if thumbnail.interact(egui::Sense::drag()).drag_started() {
+                                                output_to_drag = Some(output.clone());
+                                            }
+                                        }
                                     }
 
-                                    let filename = task.input_path.file_name()
-                                        .map_or_else(|| "Invalid Path".to_string(), 
-                                                   |name| name.to_string_lossy().to_string());
-                                    let (status_text, status_color, error_msg) = match &task.status {
-                                        TaskStatus::Pending => ("Pending", ui.visuals().text_color(), None),
-                                        TaskStatus::Running => ("Running", egui::Color32::YELLOW, None),
-                                        TaskStatus::Completed => ("Completed", egui::Color32::GREEN, None),
-                                        TaskStatus::Failed(err) => ("Failed", egui::Color32::RED, Some(err.clone())),
-                                        TaskStatus::Cancelled => ("Cancelled", egui::Color32::LIGHT_RED, None),
+                                    let (status_icon, status_color, error_msg) = match &task.status {
+                                        TaskStatus::Pending => ("⏳", ui.visuals().text_color(), None),
+                                        TaskStatus::Waiting => ("⌛", egui::Color32::LIGHT_BLUE, None),
+                                        TaskStatus::Downloading => ("⬇", egui::Color32::LIGHT_BLUE, None),
+                                        TaskStatus::Running if is_suspended => ("⏸", SUSPENDED_COLOR, None),
+                                        TaskStatus::Running => ("▶", egui::Color32::YELLOW, None),
+                                        TaskStatus::Completed => ("✔", egui::Color32::GREEN, None),
+                                        TaskStatus::Failed(err) => ("✖", egui::Color32::RED, Some(err.clone())),
+                                        TaskStatus::Cancelled => ("⏹", egui::Color32::LIGHT_RED, None),
+                                    };
+                                    let mut status_text = match task.progress.filter(|_| task.status == TaskStatus::Running) {
+                                        Some(progress) => format!("{} {:.0}%", status_icon, progress * 100.0),
+                                        None => status_icon.to_string(),
                                     };
+                                    // Ticking timer on the active task
+                                    if matches!(task.status, TaskStatus::Downloading | TaskStatus::Running)
+                                        && let Some(started_at) = task.started_at
+                                    {
+                                        let elapsed = started_at.elapsed().unwrap_or_default();
+                                        status_text = format!("{} — {}", status_text, format_elapsed(elapsed));
+                                        if task.status == TaskStatus::Running
+                                            && let Some(left) = self.speeds.estimate_time_left(task)
+                                        {
+                                            status_text = format!("{} (~{} left)", status_text, format_duration(left));
+                                        }
+                                    }
+                                    if let Some(label) = task.label {
+                                        ui.colored_label(label_color(label), "●");
+                                    }
                                     let response = ui.label(format!("{}: ", filename));
-                                    ui.colored_label(status_color, status_text);
+                                    // Completed rows drag their output out to other apps
+                                    if task.status == TaskStatus::Completed
+                                        && let Some(output) = &task.output_path
+                                        && response.interact(egui::Sense::drag()).drag_started()
+                                    {
+                                        output_to_drag = Some(output.clone());
+                                    }
+                                    response.context_menu(|ui| {
+                                        ui.label("Note:");
+                                        let mut note = task.note.clone();
+                                        if ui.text_edit_singleline(&mut note).changed() {
+                                            note_change = Some((task.id, note));
+                                        }
+                                        ui.separator();
+                                        let mut label = task.label;
+                                        ui.selectable_value(&mut label, None, "No label");
+                                        for option in TaskLabel::ALL {
+                                            ui.selectable_value(&mut label, Some(option), label_text(option));
+                                        }
+                                        if label != task.label {
+                                            label_change = Some((task.id, label));
+                                            ui.close_menu();
+                                        }
+                                    });
+                                    ui.colored_label(status_color, status_text)
+                                        .on_hover_text(status_history(task));
+                                    if let Some(warning) = &task.warning {
+                                        ui.colored_label(egui::Color32::YELLOW, "⚠").on_hover_text(warning);
+                                    }
+                                    // Interpolating a variable frame rate input stutters
+                                    if task.status == TaskStatus::Pending
+                                        && task.input_vfr == Some(true)
+                                        && task.input_fps.is_none()
+                                        && task.pre_process != Some(PreProcess::ConvertToCfr)
+                                    {
+                                        if self.settings.convert_vfr_inputs && task.pre_process.is_none() {
+                                            ui.weak("VFR")
+                                                .on_hover_text("The input has a variable frame rate; it is converted to constant frame rate before rendering");
+                                        } else if ui.add(egui::Button::new(egui::RichText::new("⚠ VFR").color(egui::Color32::YELLOW)).small())
+                                            .on_hover_text("The input has a variable frame rate, which makes interpolation stutter. Click to convert it to constant frame rate with ffmpeg before rendering.")
+                                            .clicked()
+                                        {
+                                            cfr_change = Some(task.id);
+                                        }
+                                    }
+                                    // Recipe badge; RIFE tasks ignore the recipe
+                                    if task.backend != Backend::Rife {
+                                        let recipe_name = task.recipe_path.file_stem()
+                                            .map_or_else(|| "?".into(), |stem| stem.to_string_lossy());
+                                        ui.label(egui::RichText::new(recipe_name).small().color(recipe_color))
+                                            .on_hover_text(task.recipe_path.display().to_string());
+                                    }
+                                    if task.status == TaskStatus::Pending
+                                        && let Some(estimate) = self.speeds.estimate_time_left(task)
+                                    {
+                                        ui.weak(format!("~{}", format_duration(estimate)))
+                                            .on_hover_text("Estimated render time, from past renders of this recipe");
+                                    }
+                                    if task.priority != 0 && task.status == TaskStatus::Pending {
+                                        ui.weak(format!("priority {:+}", task.priority));
+                                    }
+                                    if !task.note.is_empty() {
+                                        ui.weak(&task.note);
+                                    }
                                     if let Some(err) = error_msg {
                                         response.on_hover_text(&err);
+                                    } else if let Some(output) = &task.output_path {
+                                        response.on_hover_text(output.display().to_string());
+                                    }
+
+                                    if task.status == TaskStatus::Pending
+                                        && ui.small_button("Edit")
+                                            .on_hover_text("Change input, recipe, output, priority and extra arguments")
+                                            .clicked()
+                                    {
+                                        task_to_edit = Some(task.id);
+                                    }
+                                    if task.status == TaskStatus::Pending
+                                        && ui.small_button("Inspect")
+                                            .on_hover_text("Show the recipe values, encoder arguments, overrides and command this task will render with")
+                                            .clicked()
+                                    {
+                                        self.inspect = Some(InspectWindow::new(task, config));
+                                    }
+                                    if !task.log.is_empty() && ui.small_button("Log").clicked() {
+                                        self.log_task = Some(task.id);
+                                    }
+                                    if matches!(task.status, TaskStatus::Failed(_)) {
+                                        let missing = match self.plugin_problems.get(&task.id) {
+                                            Some((log_len, missing)) if *log_len == task.log.len() => missing.clone(),
+                                            _ => {
+                                                let missing = plugins::diagnose(&task.log);
+                                                self.plugin_problems.insert(task.id, (task.log.len(), missing.clone()));
+                                                missing
+                                            }
+                                        };
+                                        if let Some(missing) = missing
+                                            && ui.small_button("🧩 Fix Plugin").on_hover_text(missing.to_string()).clicked()
+                                        {
+                                            self.plugin_help = Some(PluginHelpWindow { task_id: task.id, missing, status: None });
+                                        }
+                                    }
+                                    if let Some(preview) = &task.preview_path
+                                        && ui.small_button("Compare")
+                                            .on_hover_text("Play the side-by-side input/output clip")
+                                            .clicked()
+                                    {
+                                        let _ = opener::open(preview);
+                                    }
+                                    if task.status == TaskStatus::Completed
+                                        && task.image_sequence.is_none()
+                                        && !queue::is_vapoursynth_script(&task.input_path)
+                                        && let Some(output) = &task.output_path
+                                        && ui.small_button("Side by Side")
+                                            .on_hover_text("Play or step through the input and output in sync")
+                                            .clicked()
+                                    {
+                                        self.compare = Some(ComparePlayer::open(
+                                            ui.ctx(),
+                                            task.id,
+                                            &task.input_path,
+                                            output,
+                                            task.input_duration,
+                                            &config.ffmpeg_path,
+                                        ));
+                                    }
+                                    if matches!(task.status, TaskStatus::Completed | TaskStatus::Failed(_) | TaskStatus::Cancelled)
+                                        && ui.small_button("Re-run")
+                                            .on_hover_text("Queue this task again behind the pending ones")
+                                            .clicked()
+                                    {
+                                        task_to_rerun = Some(task.id);
+                                    }
+                                    if task.status == TaskStatus::Completed && task.backend != Backend::Rife {
+                                        ui.menu_button("Re-run with", |ui| {
+                                            for recipe in self.available_recipes.iter().filter(|r| **r != task.recipe_path) {
+                                                let filename = recipe.file_name()
+                                                    .map_or_else(|| "Invalid Path".into(), |f| f.to_string_lossy());
+                                                if ui.button(filename).clicked() {
+                                                    rerun_with_recipe = Some((task.id, recipe.clone()));
+                                                    ui.close_menu();
+                                                }
+                                            }
+                                        }).response.on_hover_text("Queue a copy rendered with another recipe, next to this result");
+                                    }
+
+                                    // Per-task container override
+                                    if task.status == TaskStatus::Pending {
+                                        let mut container = task.output_container;
+                                        egui::ComboBox::from_id_source(("task_container", task.id))
+                                            .selected_text(container_label(container))
+                                            .show_ui(ui, |ui| {
+                                                ui.selectable_value(&mut container, None, container_label(None));
+                                                for option in OutputContainer::ALL {
+                                                    ui.selectable_value(
+                                                        &mut container,
+                                                        Some(option),
+                                                        container_label(Some(option)),
+                                                    );
+                                                }
+                                            });
+                                        if container != task.output_container {
+                                            container_change = Some((task.id, container));
+                                        }
+                                    }
+
+                                    // Per-task backend, so one queue can mix tools
+                                    if installed_backends.len() > 1 {
+                                        if task.status == TaskStatus::Pending {
+                                            let mut backend = task.backend;
+                                            egui::ComboBox::from_id_source(("task_backend", task.id))
+                                                .selected_text(backend.label())
+                                                .show_ui(ui, |ui| {
+                                                    for &option in &installed_backends {
+                                                        ui.selectable_value(&mut backend, option, option.label());
+                                                    }
+                                                });
+                                            if backend != task.backend {
+                                                backend_change = Some((task.id, backend));
+                                            }
+                                        } else {
+                                            ui.weak(task.backend.label());
+                                        }
                                     }
                                 });
+                                if mixed_recipes && task.backend != Backend::Rife {
+                                    let rect = row.response.rect.expand(2.0);
+                                    ui.painter().set(
+                                        background,
+                                        egui::Shape::rect_filled(rect, 3.0, recipe_color.gamma_multiply(0.15)),
+                                    );
+                                }
                                 ui.separator();
                             }
                         }
@@ -299,61 +1205,2887 @@ impl eframe::App for SmoothieQueueApp {
                             .expect("Failed to lock queue manager");
                         manager.remove_task(id_to_remove);
                     }
-                }
 
-                // Handle file drops
-                let dropped_files = ctx.input(|i| i.raw.dropped_files.clone());
-                if !dropped_files.is_empty() {
-                    self.files_dropped = true;
-                    let allowed_extensions = ["mp4", "mkv", "mov", "avi", "webm"];
-                    
-                    for file in dropped_files {
-                        if let Some(path) = file.path {
-                            if let Some(ext) = path.extension().and_then(|s| s.to_str()) {
-                                if allowed_extensions.contains(&ext.to_lowercase().as_str()) {
-                                    self.last_id += 1;
-                                    let task = VideoTask {
-                                        id: self.last_id,
-                                        input_path: path.clone(),
-                                        output_dir: self.output_folder.clone()
-                                            .unwrap_or_else(|| PathBuf::from(path.parent().unwrap_or(Path::new(".")))),
-                                        recipe_path: self.recipe_path.clone(),
-                                        status: TaskStatus::Pending,
-                                    };
-                                    
-                                    let mut manager = self.queue_manager.lock()
-                                        .expect("Failed to lock queue manager");
-                                    manager.add_task(task);
-                                }
-                            }
+                    if let Some(task_id) = task_to_cancel {
+                        let cancelled = self.queue_manager.lock()
+                            .expect("Failed to lock queue manager")
+                            .cancel_task(task_id);
+                        if cancelled {
+                            log::info!("Cancelling task {}", task_id);
                         }
                     }
-                    ctx.request_repaint();
-                }
-            });
-        } else {
-            egui::CentralPanel::default().show(ctx, |ui| {
-                ui.heading("Smoothie Queuer");
-                ui.colored_label(egui::Color32::RED, 
-                    "Configuration Error: Could not automatically find smoothie-rs.");
-                ui.label("Please locate the main 'Smoothie' installation folder.");
-                
-                if ui.button("Locate Smoothie Folder...").clicked() {
-                    if let Some(folder_path) = FileDialog::new().pick_folder() {
-                        match config::find_smoothie_config_in_dir(&folder_path) {
-                            Ok(found_config) => {
-                                self.config = Some(found_config.clone());
-                                self.recipe_path = found_config.recipe_path;
-                            },
-                            Err(e) => log::error!("Failed to find valid config: {}", e),
+
+                    if let Some(task_id) = task_to_rerun {
+                        self.queue_manager.lock()
+                            .expect("Failed to lock queue manager")
+                            .rerun_task(task_id);
+                    }
+
+                    if let Some(task_id) = task_to_edit {
+                        let manager = self.queue_manager.lock()
+                            .expect("Failed to lock queue manager");
+                        self.edit_window = manager.tasks.iter()
+                            .find(|t| t.id == task_id)
+                            .map(EditTaskWindow::new);
+                    }
+
+                    if let Some((task_id, recipe)) = rerun_with_recipe {
+                        let mut manager = self.queue_manager.lock()
+                            .expect("Failed to lock queue manager");
+                        if let Some(task) = manager.tasks.iter().find(|t| t.id == task_id).cloned() {
+                            let copy = task.duplicate_with_recipe(manager.next_task_id(), recipe);
+                            log::info!("Queued task {} as a copy of task {} with {:?}", copy.id, task_id, copy.recipe_path);
+                            manager.add_task(copy);
                         }
                     }
-                }
-            });
-        }
 
-        if self.worker_running {
-            ctx.request_repaint();
-        }
+                    if let Some((task_id, container)) = container_change {
+                        let mut manager = self.queue_manager.lock()
+                            .expect("Failed to lock queue manager");
+                        manager.set_task_container(task_id, container);
+                    }
+
+                    if let Some((task_id, backend)) = backend_change {
+                        let mut manager = self.queue_manager.lock()
+                            .expect("Failed to lock queue manager");
+                        manager.set_task_backend(task_id, backend);
+                    }
+
+                    // The drag loop blocks until the drop, so the queue must not be locked
+                    if let Some(output) = output_to_drag
+                        && let Err(e) = dragout::drag_files(&[&output])
+                    {
+                        log::warn!("{}", e);
+                    }
+
+                    if let Some(task_id) = cfr_change {
+                        let mut manager = self.queue_manager.lock()
+                            .expect("Failed to lock queue manager");
+                        manager.set_task_pre_process(task_id, Some(PreProcess::ConvertToCfr));
+                    }
+
+                    if let Some((task_id, note)) = note_change {
+                        let mut manager = self.queue_manager.lock()
+                            .expect("Failed to lock queue manager");
+                        manager.set_task_note(task_id, note);
+                    }
+
+                    if let Some((task_id, label)) = label_change {
+                        let mut manager = self.queue_manager.lock()
+                            .expect("Failed to lock queue manager");
+                        manager.set_task_label(task_id, label);
+                    }
+                }
+
+            });
+
+            if start_requested {
+                self.start_worker();
+            }
+            if let Some(name) = switch_profile {
+                self.switch_profile(name);
+            }
+            if save_profile {
+                let profile = Profile {
+                    name: self.profile_name.clone().unwrap_or_default(),
+                    recipe_path: self.recipe_path.clone(),
+                    output_folder: self.output_folder.clone(),
+                    extra_args: self.extra_args.trim().to_string(),
+                    max_parallel: self.settings.max_parallel,
+                };
+                match self.settings.save_profile(profile) {
+                    Ok(()) => {
+                        self.profile_name = None;
+                        self.settings.save();
+                    }
+                    Err(e) => log::warn!("{}", e),
+                }
+            }
+            if let Some(name) = delete_profile {
+                self.settings.delete_profile(&name);
+                self.settings.save();
+                log::info!("Deleted profile {:?}", name);
+            }
+
+            // Handle file drops
+            let dropped_files = ctx.input(|i| i.raw.dropped_files.clone());
+            if !dropped_files.is_empty() {
+                self.files_dropped = true;
+                for path in dropped_files.into_iter().filter_map(|file| file.path) {
+                    // Links dragged out of a browser arrive as .url shortcut files
+                    match read_url_shortcut(&path) {
+                        Some(url) => self.add_url(url, None),
+                        None => self.add_entry(path, None),
+                    };
+                }
+                ctx.request_repaint();
+            }
+
+            // Handle pasted URLs, unless a text field is taking the paste
+            if !ctx.wants_keyboard_input() {
+                let pasted: Vec<String> = ctx.input(|i| {
+                    i.events
+                        .iter()
+                        .filter_map(|event| match event {
+                            egui::Event::Paste(text) => Some(text.clone()),
+                            _ => None,
+                        })
+                        .collect()
+                });
+                for url in pasted.iter().flat_map(|text| text.lines()).filter(|line| download::is_url(line)) {
+                    if self.add_url(url.trim().to_string(), None) {
+                        self.files_dropped = true;
+                    }
+                }
+            }
+        } else {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.heading("Smoothie Queuer");
+                ui.colored_label(egui::Color32::RED, 
+                    "Configuration Error: Could not automatically find smoothie-rs.");
+                ui.label("Please locate the main 'Smoothie' installation folder.");
+                
+                if ui.button("Locate Smoothie Folder...").clicked()
+                    && let Some(folder_path) = FileDialog::new().pick_folder()
+                {
+                    match config::find_smoothie_config_in_dir(&folder_path) {
+                        Ok(found_config) => {
+                            self.config = Some(found_config.clone());
+                            self.recipe_path = found_config.recipe_path;
+                        },
+                        Err(e) => log::error!("Failed to find valid config: {}", e),
+                    }
+                }
+            });
+        }
+
+        // Drops, pastes, templates, the watch folder and IPC/API commands all
+        // queue through `push_task` or `queue_with_metadata`, which set this
+        if std::mem::take(&mut self.tasks_added) && self.settings.auto_start && !self.worker_running {
+            log::info!("Auto-starting the queue for newly added tasks");
+            self.start_worker();
+        }
+
+        self.show_settings_window(ctx);
+        self.show_templates_window(ctx);
+        self.show_new_recipe_window(ctx);
+        self.show_recipe_editor_window(ctx);
+        self.show_import_recipe_window(ctx);
+        self.show_log_window(ctx);
+        self.show_inspect_window(ctx);
+        self.show_plugin_help_window(ctx);
+        if let Some(player) = self.compare.as_mut()
+            && !player.show(ctx)
+        {
+            self.compare = None;
+        }
+        self.show_history_window(ctx);
+        self.show_events_window(ctx);
+        self.show_edit_window(ctx);
+        self.show_batch_edit_window(ctx);
+        self.show_update_window(ctx);
+
+        // Save at most every interval; a frame in between schedules one follow-up
+        // so the last change is saved even if nothing else happens
+        let since_save = self.session_checked.elapsed();
+        if since_save >= SESSION_SAVE_INTERVAL {
+            self.save_session();
+        } else {
+            ctx.request_repaint_after(SESSION_SAVE_INTERVAL - since_save);
+        }
+
+        if self.worker_running {
+            ctx.request_repaint_after(RUNNING_REFRESH_INTERVAL);
+        }
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.save_session();
+        if let Some(exe) = &self.relaunch
+            && let Err(e) = update::relaunch(exe)
+        {
+            log::error!("{}", e);
+        }
+    }
+}
+
+/// Creates the channel handed to the worker. Updates are relayed to the returned
+/// receiver by a helper thread that wakes the UI for each one, so the UI only
+/// repaints when the worker has something to show.
+fn forward_worker_updates(
+    ctx: &egui::Context,
+) -> (mpsc::Sender<UpdateMessage>, mpsc::Receiver<UpdateMessage>) {
+    let (worker_tx, forward_rx) = mpsc::channel();
+    let (forward_tx, worker_rx) = mpsc::channel();
+    let ctx = ctx.clone();
+    thread::spawn(move || {
+        for update in forward_rx {
+            let is_progress = matches!(update, UpdateMessage::TaskProgress(..));
+            if forward_tx.send(update).is_err() {
+                break;
+            }
+            if is_progress {
+                ctx.request_repaint_after(PROGRESS_REPAINT_DELAY);
+            } else {
+                ctx.request_repaint();
+            }
+        }
+    });
+    (worker_tx, worker_rx)
+}
+
+/// Starts the thread measuring the inputs of new tasks (size, and duration,
+/// resolution and frame rate kind through ffprobe) for the queue summary, so
+/// adding a batch never waits on ffprobe.
+fn spawn_metadata_probe(
+    queue_manager: Arc<Mutex<QueueManager>>,
+    ctx: egui::Context,
+) -> mpsc::Sender<MetadataRequest> {
+    let (tx, rx) = mpsc::channel::<MetadataRequest>();
+    thread::spawn(move || {
+        for request in rx {
+            let size = input_size(&request.input);
+            let (duration, resolution, vfr) = match &request.ffmpeg_path {
+                Some(ffmpeg_path) => (
+                    ffmpeg::probe_duration(ffmpeg_path, &request.input)
+                        .map_err(|e| log::debug!("Task {}: {}", request.task_id, e))
+                        .ok(),
+                    ffmpeg::probe_resolution(ffmpeg_path, &request.input)
+                        .map_err(|e| log::debug!("Task {}: {}", request.task_id, e))
+                        .ok(),
+                    ffmpeg::probe_variable_frame_rate(ffmpeg_path, &request.input)
+                        .map_err(|e| log::debug!("Task {}: {}", request.task_id, e))
+                        .ok(),
+                ),
+                None => (None, None, None),
+            };
+            if vfr == Some(true) {
+                log::warn!("Task {}: {:?} has a variable frame rate", request.task_id, request.input);
+            }
+            queue_manager.lock()
+                .expect("Failed to lock queue manager")
+                .set_input_metadata(request.task_id, size, duration, resolution, vfr);
+            ctx.request_repaint();
+        }
+    });
+    tx
+}
+
+/// Size of a file, or of the files directly inside a folder (image sequences).
+fn input_size(path: &Path) -> Option<u64> {
+    let metadata = std::fs::metadata(path).ok()?;
+    if !metadata.is_dir() {
+        return Some(metadata.len());
+    }
+    let entries = std::fs::read_dir(path).ok()?;
+    Some(
+        entries
+            .flatten()
+            .filter_map(|entry| entry.metadata().ok())
+            .filter(|metadata| metadata.is_file())
+            .map(|metadata| metadata.len())
+            .sum(),
+    )
+}
+
+impl SmoothieQueueApp {
+    /// Queues `path` if it has a supported video extension, using the current UI
+    /// selections (or `recipe` when given). Returns whether a task was added.
+    fn add_file(&mut self, path: PathBuf, recipe: Option<PathBuf>) -> bool {
+        if !self.settings.accepts_input(&path) {
+            log::warn!("Ignoring unsupported file: {:?}", path);
+            return false;
+        }
+
+        let output_dir = self.output_folder.clone()
+            .unwrap_or_else(|| PathBuf::from(path.parent().unwrap_or(Path::new("."))));
+        self.push_task(path, output_dir, recipe, None, None);
+        true
+    }
+
+    /// Queues whatever `path` refers to: a URL, a playlist of entries or a video
+    /// file. Returns whether anything was added.
+    fn add_entry(&mut self, path: PathBuf, recipe: Option<PathBuf>) -> bool {
+        if let Some(url) = path.to_str().filter(|p| download::is_url(p)) {
+            return self.add_url(url.to_string(), recipe);
+        }
+        if path.is_dir() {
+            return self.add_image_sequence(path, recipe);
+        }
+        if !queue::is_playlist(&path) {
+            return self.add_file(path, recipe);
+        }
+
+        let entries = match queue::read_playlist(&path) {
+            Ok(entries) => entries,
+            Err(e) => {
+                log::error!("{}", e);
+                return false;
+            }
+        };
+        let total = entries.len();
+        let added = entries
+            .into_iter()
+            // Playlists are not expanded recursively
+            .filter(|entry| !queue::is_playlist(entry))
+            .filter(|entry| self.add_entry(entry.clone(), recipe.clone()))
+            .count();
+        log::info!("Imported {} of {} entries from {:?}", added, total, path);
+        added > 0
+    }
+
+    /// Queues a folder of numbered frames as one task. Its output goes next to the
+    /// folder unless an output folder is selected.
+    fn add_image_sequence(&mut self, dir: PathBuf, recipe: Option<PathBuf>) -> bool {
+        let Some(sequence) = ImageSequence::detect(&dir, self.settings.sequence_frame_rate) else {
+            log::warn!("Ignoring folder without an image sequence: {:?}", dir);
+            return false;
+        };
+        log::info!("Queueing image sequence {:?} ({} frames)", dir.join(&sequence.pattern), sequence.frame_count);
+
+        let output_dir = self.output_folder.clone()
+            .unwrap_or_else(|| PathBuf::from(dir.parent().unwrap_or(Path::new("."))));
+        self.push_task(dir, output_dir, recipe, None, Some(sequence));
+        true
+    }
+
+    /// Queues a URL to be downloaded with yt-dlp before rendering, if enabled.
+    /// Its output goes to the download folder unless an output folder is selected.
+    fn add_url(&mut self, url: String, recipe: Option<PathBuf>) -> bool {
+        if !self.settings.ytdlp_enabled {
+            log::warn!("Ignoring URL {} (enable yt-dlp downloads in Settings)", url);
+            return false;
+        }
+
+        let output_dir = self.output_folder.clone().unwrap_or_else(download::downloads_dir);
+        let source = UrlSource {
+            url: url.clone(),
+            ytdlp_path: self.settings.ytdlp_path.clone(),
+        };
+        self.push_task(PathBuf::from(url), output_dir, recipe, Some(source), None);
+        true
+    }
+
+    /// Appends a task with the current UI selections to the queue and runs
+    /// script hooks on it. Drops, watch folders, the control API and the
+    /// command line all queue through here.
+    fn push_task(
+        &mut self,
+        input_path: PathBuf,
+        output_dir: PathBuf,
+        recipe: Option<PathBuf>,
+        source: Option<UrlSource>,
+        image_sequence: Option<ImageSequence>,
+    ) {
+        let keep_recipe = recipe.is_some();
+        let recipe_path = recipe.unwrap_or_else(|| self.recipe_path.clone());
+        // Only the ID is taken under the lock; the task is set up and the
+        // script hooks run before the worker can see it
+        let id = self.queue_manager.lock()
+            .expect("Failed to lock queue manager")
+            .next_task_id();
+        let mut task = VideoTask::new(id, input_path, output_dir, recipe_path);
+        // URLs have no file name to match until downloaded
+        if source.is_none() {
+            self.settings.rule_outcome(&task.input_path).apply_to(&mut task, keep_recipe);
+        }
+        task.output_container = self.output_container;
+        task.pre_process = self.pre_process;
+        task.post_command = self.settings.post_command_template();
+        task.source = source;
+        task.image_sequence = image_sequence;
+        task.backend = self.backend;
+        task.rife = self.rife.clone();
+        task.extra_args = self.extra_args.trim().to_string();
+        self.scripts.on_task_added(&mut task);
+        self.queue_with_metadata(task);
+    }
+
+    /// Makes the profile named `name` (or none) the active one. Switching to a
+    /// profile takes over its selections for new tasks; queued tasks keep theirs.
+    fn switch_profile(&mut self, name: Option<String>) {
+        if let Some(profile) = name.as_ref().and_then(|name| self.settings.profile(name)) {
+            let profile = profile.clone();
+            self.use_profile(&profile);
+            self.settings.max_parallel = profile.max_parallel;
+            if let Some(draft) = &mut self.settings_draft {
+                draft.max_parallel = profile.max_parallel;
+            }
+            log::info!("Switched to profile {:?}", profile.name);
+        }
+        self.settings.active_profile = name;
+        self.settings.save();
+    }
+
+    /// Takes over `profile`'s recipe, output folder and extra arguments.
+    fn use_profile(&mut self, profile: &Profile) {
+        if profile.recipe_path.is_file() {
+            self.recipe_path = profile.recipe_path.clone();
+        } else {
+            log::warn!("Recipe {:?} of profile {:?} not found, keeping {:?}", profile.recipe_path, profile.name, self.recipe_path);
+        }
+        self.output_folder = profile.output_folder.clone();
+        self.extra_args = profile.extra_args.clone();
+    }
+
+    /// Saves the queue with `session::write` if it changed since the last save.
+    fn save_session(&mut self) {
+        self.session_checked = Instant::now();
+        let serialized = {
+            let manager = self.queue_manager.lock()
+                .expect("Failed to lock queue manager");
+            session::serialize(&manager)
+        };
+        let contents = match serialized {
+            Ok(contents) => contents,
+            Err(e) => {
+                log::error!("{}", e);
+                return;
+            }
+        };
+        if contents == self.session_saved {
+            return;
+        }
+        match session::write(&contents) {
+            Ok(()) => self.session_saved = contents,
+            Err(e) => log::error!("Failed to save the queue: {}", e),
+        }
+    }
+
+    /// Adds `task` to the queue and has its input measured in the background.
+    fn queue_with_metadata(&mut self, mut task: VideoTask) {
+        self.request_metadata(&mut task);
+        self.queue_manager.lock()
+            .expect("Failed to lock queue manager")
+            .add_task(task);
+        self.tasks_added = true;
+    }
+
+    /// Has the input of `task` measured in the background; image sequences
+    /// know their length already.
+    fn request_metadata(&self, task: &mut VideoTask) {
+        if let Some(sequence) = &task.image_sequence {
+            task.input_duration = Some(sequence.duration());
+        }
+        // URLs are measured by nobody; scripts and sequences cannot be probed
+        if task.source.is_none() {
+            let probe = task.image_sequence.is_none() && !queue::is_vapoursynth_script(&task.input_path);
+            let request = MetadataRequest {
+                task_id: task.id,
+                input: task.input_path.clone(),
+                ffmpeg_path: self.config.as_ref().filter(|_| probe).map(|config| config.ffmpeg_path.clone()),
+            };
+            let _ = self.metadata_tx.send(request);
+        }
+    }
+
+    fn handle_control_command(&mut self, command: ControlCommand) {
+        log::info!("Received control command: {:?}", command);
+        match command {
+            ControlCommand::AddTask { path, recipe } => {
+                if self.add_entry(path, recipe) {
+                    self.files_dropped = true;
+                }
+            }
+            ControlCommand::StartWorker => self.start_worker(),
+            ControlCommand::PauseWorker => {
+                if self.worker_running {
+                    self.stop_flags.request_stop();
+                }
+            }
+        }
+    }
+
+    /// (Re)starts the watch folder poller from the current settings.
+    fn restart_watcher(&mut self, ctx: &egui::Context) {
+        self.watcher = self.settings.active_watch_folder().map(|folder| {
+            let ctx = ctx.clone();
+            FolderWatcher::spawn(
+                folder.clone(),
+                self.settings.watch_recipe.clone(),
+                self.settings.allowed_extensions.clone(),
+                self.control_tx.clone(),
+                move || ctx.request_repaint(),
+            )
+        });
+    }
+
+    /// Spawns the worker thread if a configuration is available and it is not running.
+    fn start_worker(&mut self) {
+        let Some(mut config) = self.config.clone() else {
+            log::warn!("Cannot start the queue without a smoothie-rs configuration");
+            return;
+        };
+        if self.worker_running {
+            return;
+        }
+        self.settings.apply_to(&mut config);
+
+        self.worker_running = true;
+        worker::spawn_worker(Arc::clone(&self.queue_manager), self.worker_tx.clone(), config);
+    }
+
+    fn show_settings_window(&mut self, ctx: &egui::Context) {
+        let Some(draft) = self.settings_draft.as_mut() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut save = false;
+        let mut check_updates_now = false;
+        let mut export_settings = false;
+        let mut import_settings = false;
+        egui::Window::new("Settings")
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label("Post-processing command (runs after each successful task):");
+                ui.add(
+                    egui::TextEdit::singleline(&mut draft.post_command)
+                        .hint_text("e.g. copy {output} \"\\\\NAS\\clips\"")
+                        .desired_width(f32::INFINITY),
+                )
+                .on_hover_text("Tokens are already quoted: each reaches the command as one argument, whatever characters the file name holds, and is never run as a command. They are passed as the SMOOTHIE_INPUT, SMOOTHIE_OUTPUT, SMOOTHIE_OUTDIR and SMOOTHIE_NAME environment variables. On Windows the command runs with delayed expansion, so a literal ! must be written as ^!.");
+                for (token, description) in POST_COMMAND_TOKENS {
+                    ui.label(
+                        egui::RichText::new(format!("{} - {}", token, description))
+                            .color(ui.visuals().weak_text_color()),
+                    );
+                }
+
+                ui.checkbox(&mut draft.comparison_preview, "Render a comparison clip after each task")
+                    .on_hover_text(format!(
+                        "Saves the first {} seconds of input and output side by side as \"<output> ~ compare.webm\". Applies from the next start of the queue.",
+                        ffmpeg::COMPARISON_SECONDS
+                    ));
+                ui.checkbox(&mut draft.comparison_screenshot, "Save a comparison screenshot after each task")
+                    .on_hover_text("Saves the frame halfway through the input next to the same output frame as \"<output> ~ compare.png\", shown in the task's log. Applies from the next start of the queue.");
+
+                ui.horizontal(|ui| {
+                    ui.label("Delivery copy: scale down to");
+                    ui.add(egui::DragValue::new(&mut draft.delivery_height).clamp_range(0..=4320).suffix("p"));
+                    ui.checkbox(&mut draft.delivery_deinterlace, "Deinterlace");
+                })
+                .response
+                .on_hover_text("After each task, ffmpeg encodes a copy of the output as \"<output> ~ delivery.mp4\" (H.264/AAC), e.g. at 1080p for Discord, next to the full-quality render. Taller outputs are scaled down, smaller ones keep their size. 0p without deinterlacing renders no copy. Applies from the next start of the queue.");
+
+                ui.horizontal(|ui| {
+                    ui.label("Assemble dropped image sequences at");
+                    ui.add(egui::DragValue::new(&mut draft.sequence_frame_rate).clamp_range(1..=1000).suffix(" fps"));
+                })
+                .response
+                .on_hover_text("Frame rate of folders of numbered png/jpg/exr frames. Applies to newly added folders.");
+
+                ui.horizontal(|ui| {
+                    ui.label("Keep history for");
+                    ui.add(egui::DragValue::new(&mut draft.history_retention_days).clamp_range(0..=3650).suffix(" days"));
+                })
+                .response
+                .on_hover_text("Archived tasks that finished longer ago are deleted from the history at launch and when this changes. 0 keeps the whole history.");
+
+                ui.checkbox(&mut draft.requeue_failed, "Retry failed tasks once at the end of a run")
+                    .on_hover_text("Tasks that failed for a passing reason (a locked file, the GPU's encoder limit, a full disk, a network drop) are reset to pending and rendered again before the queue finishes. Errors that would happen again, like a bad recipe or an unsupported codec, are not retried. Applies from the next start of the queue.");
+
+                ui.horizontal(|ui| {
+                    ui.label("Render up to");
+                    ui.add(egui::DragValue::new(&mut draft.max_parallel).clamp_range(1..=8));
+                    ui.label("tasks at once");
+                })
+                .response
+                .on_hover_text("More than 1 only helps when one render does not use the whole GPU. Applies from the next start of the queue.");
+                ui.add_enabled_ui(draft.max_parallel > 1, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Wait");
+                        ui.add(egui::DragValue::new(&mut draft.start_stagger_seconds).clamp_range(0..=600).suffix(" s"));
+                        ui.label("between starting tasks");
+                    })
+                    .response
+                    .on_hover_text("Spaces out the start of tasks rendering side by side so their VapourSynth and ffprobe start-up does not hit the disk and GPU at the same moment");
+                });
+                ui.checkbox(&mut draft.spread_gpus, "Spread tasks across GPUs")
+                    .on_hover_text("On machines with several NVIDIA GPUs, each task renders on the GPU running the fewest tasks (the least busy one on a tie) instead of the first GPU. Applies from the next start of the queue.");
+
+                if cfg!(windows) {
+                    ui.checkbox(&mut draft.high_performance_power, "Use the High Performance power plan while rendering")
+                        .on_hover_text("Switches to High Performance when the queue starts and back to the previous plan when it finishes, so laptops do not render at Balanced clock speeds. Applies from the next start of the queue.");
+                }
+
+                ui.checkbox(&mut draft.safe_input_names, "Render exotic file names from a renamed temp copy")
+                    .on_hover_text("Inputs with emoji, quotes, brackets or other unusual characters in their name are linked or copied to the temp folder under a plain name before rendering. The output gets the original name back.");
+
+                ui.checkbox(&mut draft.stage_network_files, "Render files on network drives through a local copy")
+                    .on_hover_text("Inputs on a network share or mapped drive are copied to the temp folder before rendering, and outputs for a network folder are rendered locally and copied over when finished. Rendering straight off SMB is much slower and can fail when the connection drops.");
+
+                ui.checkbox(&mut draft.convert_vfr_inputs, "Convert variable frame rate inputs to CFR")
+                    .on_hover_text("Inputs ffprobe finds to have a variable frame rate, like phone and screen recordings, are re-encoded to constant frame rate with ffmpeg before rendering, since interpolating unevenly spaced frames stutters. Tasks with a pre-processing step of their own keep it. Applies from the next start of the queue.");
+
+                ui.checkbox(&mut draft.verbose_smoothie, "Verbose smoothie output")
+                    .on_hover_text("Runs smoothie-rs with --verbose. The extra output (the VapourSynth script, ffmpeg command and so on) goes to each task's log, which helps when a render fails. Applies from the next start of the queue.");
+
+                let console_hover = if cfg!(windows) {
+                    "Each render opens smoothie-rs' own console window with its native progress output. That output is not captured, so the task log stays empty and no progress bar is shown. Applies from the next start of the queue."
+                } else {
+                    "smoothie-rs writes its native progress output to the terminal this app was started from. That output is not captured, so the task log stays empty and no progress bar is shown. Applies from the next start of the queue."
+                };
+                ui.checkbox(&mut draft.show_smoothie_console, "Show the smoothie-rs console while rendering")
+                    .on_hover_text(console_hover);
+
+                ui.checkbox(&mut draft.resume_on_launch, "Resume the saved queue at launch")
+                    .on_hover_text("The queue is kept between launches. With this on, pending tasks left from last time start rendering as soon as the app opens.");
+
+                ui.horizontal(|ui| {
+                    ui.label("Kill renders running longer than");
+                    ui.add(egui::DragValue::new(&mut draft.task_timeout_minutes).clamp_range(0..=1440).suffix(" min"));
+                })
+                .response
+                .on_hover_text("0 disables the timeout. Applies from the next start of the queue.");
+
+                ui.horizontal(|ui| {
+                    ui.label("Pause the queue after");
+                    ui.add(egui::DragValue::new(&mut draft.budget_tasks).clamp_range(0..=10000).suffix(" tasks"));
+                    ui.label("or");
+                    ui.add(
+                        egui::DragValue::new(&mut draft.budget_hours)
+                            .clamp_range(0.0..=168.0)
+                            .speed(0.25)
+                            .max_decimals(2)
+                            .suffix(" h"),
+                    );
+                })
+                .response
+                .on_hover_text("A budget for each start of the queue: once that many tasks have been started, or that much time has passed, no new task starts and the queue pauses. Running tasks finish. 0 disables a limit.");
+
+                ui.separator();
+                ui.label("Accepted input extensions:");
+                ui.horizontal_wrapped(|ui| {
+                    let mut remove = None;
+                    for (index, extension) in draft.allowed_extensions.iter().enumerate() {
+                        if ui.small_button(format!(".{} ✖", extension)).on_hover_text("Remove").clicked() {
+                            remove = Some(index);
+                        }
+                    }
+                    if let Some(index) = remove {
+                        draft.allowed_extensions.remove(index);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    let input = ui.add(
+                        egui::TextEdit::singleline(&mut self.new_extension)
+                            .hint_text(".m4v")
+                            .desired_width(80.0),
+                    );
+                    let submitted = input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    if ui.button("Add").clicked() || submitted {
+                        match queue::parse_extension(&self.new_extension) {
+                            Ok(extension) => {
+                                if !draft.allowed_extensions.contains(&extension) {
+                                    draft.allowed_extensions.push(extension);
+                                }
+                                self.new_extension.clear();
+                                self.extension_error = None;
+                            }
+                            Err(e) => self.extension_error = Some(e),
+                        }
+                    }
+                    if let Some(error) = &self.extension_error {
+                        ui.colored_label(egui::Color32::RED, error);
+                    }
+                })
+                .response
+                .on_hover_text(format!("Formats ffmpeg can read: {}", queue::INGESTIBLE_EXTENSIONS.join(", ")));
+                ui.checkbox(&mut draft.vpy_inputs_enabled, "Accept .vpy VapourSynth scripts")
+                    .on_hover_text("Scripts are passed to smoothie-rs as-is; pre-processing is skipped and other backends refuse them");
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Extra arguments for every task:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut draft.extra_args)
+                            .hint_text("--tompv off")
+                            .desired_width(240.0),
+                    );
+                })
+                .response
+                .on_hover_text("Appended to every smoothie-rs, blur or rife-ncnn-vulkan command, before a task's own extra arguments (Edit). Applies from the next start of the queue.");
+
+                ui.horizontal(|ui| {
+                    ui.label("Working directory for backends:");
+                    let dir_text = draft.working_dir.as_ref()
+                        .map_or("Executable's folder".to_string(), |p| p.display().to_string());
+                    ui.label(dir_text);
+                    if ui.button("Browse").clicked() && let Some(path) = FileDialog::new().pick_folder() {
+                        draft.working_dir = Some(path);
+                    }
+                    if draft.working_dir.is_some() && ui.small_button("✖").on_hover_text("Use the executable's folder").clicked() {
+                        draft.working_dir = None;
+                    }
+                })
+                .response
+                .on_hover_text("Folder smoothie-rs, blur and rife-ncnn-vulkan run in. Some VapourSynth setups look up scripts relative to it. By default each runs from the folder of its own executable. Applies from the next start of the queue.");
+
+                ui.label("Environment variables for backends:")
+                    .on_hover_text("Set on every smoothie-rs, blur or rife-ncnn-vulkan process, e.g. VapourSynth plugin paths. A task's own variables (Edit) replace these. Applies from the next start of the queue.");
+                let mut remove_var = None;
+                for (index, var) in draft.env.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.add(egui::TextEdit::singleline(&mut var.name).hint_text("NAME").desired_width(140.0));
+                        ui.label("=");
+                        ui.add(egui::TextEdit::singleline(&mut var.value).hint_text("value").desired_width(240.0));
+                        if ui.small_button("✖").on_hover_text("Remove").clicked() {
+                            remove_var = Some(index);
+                        }
+                    });
+                }
+                if let Some(index) = remove_var {
+                    draft.env.remove(index);
+                }
+                if ui.button("Add Variable").clicked() {
+                    draft.env.push(EnvVar { name: String::new(), value: String::new() });
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut draft.api_enabled, "Enable local control API on port");
+                    ui.add(egui::DragValue::new(&mut draft.api_port).clamp_range(1024..=65535));
+                });
+                ui.checkbox(&mut draft.api_lan, "Show the status page to the local network")
+                    .on_hover_text("Anyone on the network can see the queue at http://<this PC>:<port>/; commands are still only accepted from this PC");
+                ui.label(
+                    egui::RichText::new(if draft.api_lan {
+                        "Status page and queue readable on the local network. Takes effect after a restart."
+                    } else {
+                        "Listens on 127.0.0.1 only; the status page is at http://127.0.0.1:<port>/. Takes effect after a restart."
+                    })
+                    .color(ui.visuals().weak_text_color()),
+                );
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut draft.mqtt_enabled, "Publish queue events to MQTT broker");
+                    ui.add(egui::TextEdit::singleline(&mut draft.mqtt_broker).desired_width(150.0).hint_text("host:1883"));
+                })
+                .response
+                .on_hover_text("task_started, task_completed, task_failed and queue_idle as JSON, e.g. to flash a light or shut down when the batch ends");
+                ui.add_enabled_ui(draft.mqtt_enabled, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Topic:");
+                        ui.add(egui::TextEdit::singleline(&mut draft.mqtt_topic).desired_width(150.0));
+                        ui.label(
+                            egui::RichText::new(format!("{}/<event>", draft.mqtt_topic.trim_end_matches('/')))
+                                .color(ui.visuals().weak_text_color()),
+                        );
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("User:");
+                        ui.add(egui::TextEdit::singleline(&mut draft.mqtt_username).desired_width(100.0));
+                        ui.label("Password:");
+                        ui.add(egui::TextEdit::singleline(&mut draft.mqtt_password).password(true).desired_width(100.0));
+                    })
+                    .response
+                    .on_hover_text("Leave empty for brokers without login; the password is stored in plain text in the settings file");
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut draft.ntfy_enabled, "Push notifications to ntfy topic");
+                    ui.add(egui::TextEdit::singleline(&mut draft.ntfy_topic).desired_width(150.0).hint_text("my-render-pc"));
+                })
+                .response
+                .on_hover_text("Subscribe to the same topic in the ntfy app to get failures and a summary when the queue finishes. Anyone who knows a topic on ntfy.sh can read it, so pick a hard to guess name");
+                ui.add_enabled_ui(draft.ntfy_enabled, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Server:");
+                        ui.add(egui::TextEdit::singleline(&mut draft.ntfy_server).desired_width(150.0));
+                        ui.label("Token:");
+                        ui.add(egui::TextEdit::singleline(&mut draft.ntfy_token).password(true).desired_width(100.0))
+                            .on_hover_text("Access token for protected topics; leave empty otherwise");
+                    });
+                    ui.checkbox(&mut draft.ntfy_each_task, "Also notify for each completed task");
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label(format!("Scripts: {} loaded", self.scripts.script_count()));
+                    if ui.button("Open Scripts Folder").clicked() {
+                        let dir = scripting::scripts_dir();
+                        if let Err(e) = std::fs::create_dir_all(&dir) {
+                            log::error!("Failed to create scripts folder {:?}: {}", dir, e);
+                        }
+                        let _ = opener::open(dir);
+                    }
+                    if ui.button("Reload Scripts").clicked() {
+                        self.scripts.reload();
+                    }
+                })
+                .response
+                .on_hover_text(format!("Hooks: {}", scripting::HOOKS.join(", ")));
+
+                if let Some(config) = &self.config
+                    && config.available_backends().len() > 1
+                {
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.label("Default backend:");
+                        egui::ComboBox::from_id_source("default_backend_select")
+                            .selected_text(draft.default_backend.label())
+                            .show_ui(ui, |ui| {
+                                for backend in config.available_backends() {
+                                    ui.selectable_value(&mut draft.default_backend, backend, backend.label());
+                                }
+                            });
+                    });
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut draft.ytdlp_enabled, "Download pasted/dropped URLs with");
+                    ui.add(egui::TextEdit::singleline(&mut draft.ytdlp_path).desired_width(150.0));
+                })
+                .response
+                .on_hover_text("Paste a link (Ctrl+V) in the main window to queue it");
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut draft.watch_enabled, "Watch folder for new videos:");
+                    let folder_text = draft.watch_folder.as_ref()
+                        .map_or("None".to_string(), |p| p.display().to_string());
+                    ui.label(folder_text);
+                    if ui.button("Browse").clicked() && let Some(path) = FileDialog::new().pick_folder() {
+                        draft.watch_folder = Some(path);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Recipe for watched files:");
+                    let selected = draft.watch_recipe.as_ref()
+                        .and_then(|r| r.file_name())
+                        .map_or_else(|| "Selected recipe".into(), |f| f.to_string_lossy());
+                    egui::ComboBox::from_id_source("watch_recipe_select")
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut draft.watch_recipe, None, "Selected recipe");
+                            for recipe in &self.available_recipes {
+                                let filename = recipe.file_name()
+                                    .map_or_else(|| "Invalid Path".into(), |f| f.to_string_lossy());
+                                ui.selectable_value(&mut draft.watch_recipe, Some(recipe.clone()), filename);
+                            }
+                        });
+                });
+                if ui
+                    .button("Watch OBS Recordings")
+                    .on_hover_text("Watches the recording folder of the active OBS profile and saves")
+                    .clicked()
+                {
+                    match obs::recordings_folder() {
+                        Ok(folder) => {
+                            self.integration_status = Some(format!("Watching OBS recordings in {}", folder.display()));
+                            draft.watch_folder = Some(folder);
+                            draft.watch_enabled = true;
+                            save = true;
+                        }
+                        Err(e) => self.integration_status = Some(e),
+                    }
+                }
+
+                ui.separator();
+                ui.label("Input rules (checked in order; each option comes from the first matching rule that sets it):")
+                    .on_hover_text("Applied to files dropped, watched, passed on the command line or sent to the API. A recipe given for the file itself, or the watch folder's own recipe, still wins.");
+                let mut remove_rule = None;
+                for (index, rule) in draft.input_rules.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_id_source(("rule_kind", index))
+                            .selected_text(rule.kind.label())
+                            .show_ui(ui, |ui| {
+                                for kind in RuleKind::ALL {
+                                    ui.selectable_value(&mut rule.kind, kind, kind.label());
+                                }
+                            });
+                        let hint = match rule.kind {
+                            RuleKind::Folder => "D:\\Captures\\Valorant",
+                            RuleKind::Extension => "mov",
+                            RuleKind::Glob => "*_ranked_*",
+                            RuleKind::Regex => "(?i)^clip\\d+",
+                        };
+                        ui.add(egui::TextEdit::singleline(&mut rule.pattern).hint_text(hint).desired_width(180.0));
+                        if rule.kind == RuleKind::Folder
+                            && ui.button("Browse").clicked()
+                            && let Some(path) = FileDialog::new().pick_folder()
+                        {
+                            rule.pattern = path.display().to_string();
+                        }
+                        if ui.small_button("✖").on_hover_text("Remove").clicked() {
+                            remove_rule = Some(index);
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.add_space(16.0);
+                        ui.label("Recipe:");
+                        let recipe_name = rule.recipe_path.as_ref()
+                            .and_then(|r| r.file_name())
+                            .map_or_else(|| "Unchanged".into(), |f| f.to_string_lossy());
+                        egui::ComboBox::from_id_source(("rule_recipe", index))
+                            .selected_text(recipe_name)
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut rule.recipe_path, None, "Unchanged");
+                                for recipe in &self.available_recipes {
+                                    let filename = recipe.file_name()
+                                        .map_or_else(|| "Invalid Path".into(), |f| f.to_string_lossy());
+                                    ui.selectable_value(&mut rule.recipe_path, Some(recipe.clone()), filename);
+                                }
+                            });
+                        ui.label("Subfolder:");
+                        ui.add(egui::TextEdit::singleline(&mut rule.output_subfolder).hint_text("none").desired_width(90.0))
+                            .on_hover_text("Created inside the task's output folder");
+                        let mut set_priority = rule.priority.is_some();
+                        ui.checkbox(&mut set_priority, "Priority:");
+                        let mut priority = rule.priority.unwrap_or(0);
+                        ui.add_enabled(set_priority, egui::DragValue::new(&mut priority));
+                        rule.priority = set_priority.then_some(priority);
+                        egui::ComboBox::from_id_source(("rule_label", index))
+                            .selected_text(rule.label.map_or_else(|| "No label".into(), label_text))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut rule.label, None, "No label");
+                                for option in TaskLabel::ALL {
+                                    ui.selectable_value(&mut rule.label, Some(option), label_text(option));
+                                }
+                            });
+                    });
+                    if let Some(error) = rule.error() {
+                        ui.colored_label(egui::Color32::RED, error);
+                    }
+                }
+                if let Some(index) = remove_rule {
+                    draft.input_rules.remove(index);
+                }
+                if ui.button("Add Rule").clicked() {
+                    draft.input_rules.push(InputRule::default());
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui
+                        .button("Install Explorer Menu")
+                        .on_hover_text("Adds \"Queue in Smoothie\" to the context and Send To menus of video files")
+                        .clicked()
+                    {
+                        self.integration_status = Some(match integration::install_shell_menu(&self.settings.allowed_extensions) {
+                            Ok(()) => "Explorer menu entries installed".to_string(),
+                            Err(e) => e,
+                        });
+                    }
+                    if ui.button("Remove Explorer Menu").clicked() {
+                        self.integration_status = Some(match integration::remove_shell_menu() {
+                            Ok(()) => "Explorer menu entries removed".to_string(),
+                            Err(e) => e,
+                        });
+                    }
+                });
+                if ui
+                    .button(format!("Register {}:// Links", URL_SCHEME))
+                    .on_hover_text(format!("Lets browsers and launchers queue files via {}://add?path=...", URL_SCHEME))
+                    .clicked()
+                {
+                    self.integration_status = Some(match integration::register_url_protocol() {
+                        Ok(()) => format!("{}:// links now open the queuer", URL_SCHEME),
+                        Err(e) => e,
+                    });
+                }
+                if let Some(status) = &self.integration_status {
+                    ui.label(egui::RichText::new(status).color(ui.visuals().weak_text_color()));
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut draft.check_updates, "Check for updates at launch");
+                    if ui.button("Check Now").clicked() {
+                        check_updates_now = true;
+                    }
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    export_settings = ui
+                        .button("Export Settings...")
+                        .on_hover_text("Saves the settings and queue templates to one file, to set up another machine or share with a team. Unsaved changes are not included.")
+                        .clicked();
+                    import_settings = ui
+                        .button("Import Settings...")
+                        .on_hover_text("Replaces the settings with those in an exported file and adds its queue templates")
+                        .clicked();
+                });
+                if let Some(status) = &self.transfer_status {
+                    ui.label(egui::RichText::new(status).color(ui.visuals().weak_text_color()));
+                }
+
+                ui.separator();
+                save |= ui.button("Save").clicked();
+            });
+
+        if check_updates_now {
+            self.start_update_check(ctx, true);
+        }
+        if export_settings
+            && let Some(path) = FileDialog::new()
+                .add_filter("Smoothie Queuer settings", &["json"])
+                .set_file_name("smoothie-queuer-settings.json")
+                .save_file()
+        {
+            self.transfer_status = Some(match bundle::export(&self.settings, &path) {
+                Ok(count) => format!("Exported the settings and {} template(s)", count),
+                Err(e) => e,
+            });
+        }
+        if import_settings
+            && let Some(path) = FileDialog::new()
+                .add_filter("Smoothie Queuer settings", &["json"])
+                .pick_file()
+        {
+            match bundle::read(&path).and_then(|bundle| {
+                let count = bundle.templates.len();
+                Ok((bundle.apply(&self.settings)?, count))
+            }) {
+                Ok((imported, count)) => {
+                    self.settings_draft = Some(imported.clone());
+                    self.apply_settings(ctx, imported);
+                    self.transfer_status = Some(format!("Imported the settings and {} template(s)", count));
+                }
+                Err(e) => self.transfer_status = Some(e),
+            }
+            return;
+        }
+        if save {
+            let mut draft = self.settings_draft.take().unwrap_or_default();
+            draft.env.retain_mut(|var| {
+                var.name = var.name.trim().to_string();
+                !var.name.is_empty()
+            });
+            // Changed outside the window while it was open
+            draft.auto_clear_completed = self.settings.auto_clear_completed;
+            draft.auto_start = self.settings.auto_start;
+            draft.skipped_update = self.settings.skipped_update.clone();
+            draft.profiles = self.settings.profiles.clone();
+            draft.active_profile = self.settings.active_profile.clone();
+            self.apply_settings(ctx, draft);
+        } else if !open {
+            self.settings_draft = None;
+        }
+    }
+
+    /// Saves `settings` when they differ from the current ones and restarts what
+    /// depends on them.
+    fn apply_settings(&mut self, ctx: &egui::Context, settings: AppSettings) {
+        if settings == self.settings {
+            return;
+        }
+        self.view.invalidate();
+        if settings.default_backend != self.settings.default_backend {
+            self.backend = settings.default_backend;
+        }
+        // An imported file may switch to another profile
+        if settings.active_profile != self.settings.active_profile
+            && let Some(profile) = settings.active_profile.as_ref().and_then(|name| settings.profile(name))
+        {
+            self.use_profile(&profile.clone());
+        }
+        let changed = events::changed_settings(&self.settings, &settings);
+        let prune = settings.history_retention_days != self.settings.history_retention_days;
+        self.settings = settings;
+        self.settings.save();
+        let mut manager = self.queue_manager.lock()
+            .expect("Failed to lock queue manager");
+        manager.events.record(EventKind::SettingsChanged, None, changed.join(", "));
+        manager.update_pending_post_commands(self.settings.post_command_template());
+        if prune && let Some(max_age) = self.settings.history_retention() {
+            match archive::prune(max_age) {
+                Ok(_) => {
+                    manager.open_archive();
+                    self.speeds = load_speeds(&manager.tasks);
+                }
+                Err(e) => log::warn!("Failed to prune the history: {}", e),
+            }
+        }
+        drop(manager);
+        self.restart_watcher(ctx);
+        self.mqtt = MqttPublisher::from_settings(&self.settings);
+        self.ntfy = NtfyNotifier::from_settings(&self.settings);
+    }
+
+    fn show_history_window(&mut self, ctx: &egui::Context) {
+        if !self.history_open {
+            self.history = None;
+            return;
+        }
+
+        // Read again whenever tasks were archived or the archive cleared
+        if self.history.as_ref().is_none_or(|(count, _)| *count != self.view.history_len) {
+            let page = archive::read(HISTORY_SHOWN).and_then(|page| Ok((page, archive::recipe_stats()?)));
+            self.history = Some((self.view.history_len, page));
+        }
+        let Some((_, page)) = &self.history else {
+            return;
+        };
+
+        let mut open = true;
+        let mut clear = false;
+        egui::Window::new("History")
+            .open(&mut open)
+            .default_size([500.0, 300.0])
+            .show(ctx, |ui| {
+                let (page, recipes) = match page {
+                    Ok((page, recipes)) => (page, recipes),
+                    Err(e) => {
+                        ui.colored_label(egui::Color32::RED, e);
+                        return;
+                    }
+                };
+                if page.energy_tasks > 0 {
+                    ui.label(format!(
+                        "{} estimated for {} task(s)",
+                        format_energy(page.energy_wh),
+                        page.energy_tasks
+                    ))
+                    .on_hover_text(ENERGY_HINT);
+                    ui.separator();
+                }
+                if !recipes.is_empty() {
+                    egui::CollapsingHeader::new("Per recipe").show(ui, |ui| {
+                        egui::Grid::new("history_recipe_stats").striped(true).show(ui, |ui| {
+                            for heading in ["Recipe", "Completed", "Cancelled", "Render time", "Energy"] {
+                                ui.strong(heading);
+                            }
+                            ui.end_row();
+                            for stats in recipes {
+                                let name = Path::new(&stats.recipe_path)
+                                    .file_stem()
+                                    .map_or_else(|| stats.recipe_path.clone(), |stem| stem.to_string_lossy().to_string());
+                                ui.label(name).on_hover_text(&stats.recipe_path);
+                                ui.label(stats.completed.to_string());
+                                ui.label(stats.cancelled.to_string());
+                                ui.label(format_duration(stats.render_time));
+                                ui.label(if stats.energy_wh > 0.0 { format_energy(stats.energy_wh) } else { String::new() });
+                                ui.end_row();
+                            }
+                        });
+                    });
+                    ui.separator();
+                }
+                if page.total > page.tasks.len() {
+                    ui.weak(format!(
+                        "Showing the newest {} of {} tasks; all are in {}",
+                        page.tasks.len(),
+                        page.total,
+                        store::database_path().display()
+                    ));
+                }
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    // Newest first
+                    for task in &page.tasks {
+                        ui.horizontal(|ui| {
+                            let filename = task.input_path.file_name()
+                                .map_or_else(|| "Invalid Path".to_string(), |name| name.to_string_lossy().to_string());
+                            ui.label(filename).on_hover_text(status_history(task));
+                            if task.status == TaskStatus::Cancelled {
+                                ui.colored_label(egui::Color32::LIGHT_RED, "⏹").on_hover_text("Cancelled");
+                            }
+                            if let Some(energy_wh) = task.energy_wh {
+                                ui.weak(format_energy(energy_wh)).on_hover_text(ENERGY_HINT);
+                            }
+                            if let Some(output) = &task.output_path
+                                && ui.small_button("Open").on_hover_text(output.display().to_string()).clicked()
+                            {
+                                let _ = opener::open(output);
+                            }
+                            if let Some(preview) = &task.preview_path
+                                && ui.small_button("Compare").clicked()
+                            {
+                                let _ = opener::open(preview);
+                            }
+                        });
+                    }
+                });
+                ui.separator();
+                clear = ui.button("Clear History").clicked();
+            });
+
+        if clear {
+            let result = self.queue_manager.lock()
+                .expect("Failed to lock queue manager")
+                .clear_history();
+            if let Err(e) = result {
+                log::error!("{}", e);
+            }
+            self.speeds = load_speeds(&self.queue_manager.lock().expect("Failed to lock queue manager").tasks);
+            self.history = None;
+        }
+        self.history_open = open && !clear;
+    }
+
+    fn show_events_window(&mut self, ctx: &egui::Context) {
+        if !self.events_open {
+            return;
+        }
+
+        let mut open = true;
+        let mut export = false;
+        let mut clear = false;
+        egui::Window::new("Event Log")
+            .open(&mut open)
+            .default_size([600.0, 350.0])
+            .show(ctx, |ui| {
+                let manager = self.queue_manager.lock()
+                    .expect("Failed to lock queue manager");
+                let events = manager.events.events();
+                if events.is_empty() {
+                    ui.weak("Nothing has happened yet this session");
+                }
+                let scroll = egui::ScrollArea::vertical()
+                    .stick_to_bottom(true)
+                    .auto_shrink([false; 2])
+                    .max_height(300.0);
+                scroll.show(ui, |ui| {
+                    egui::Grid::new("events_grid").num_columns(4).striped(true).show(ui, |ui| {
+                        for event in events {
+                            ui.weak(events::format_time(event.time));
+                            let color = match event.kind {
+                                EventKind::Failed => egui::Color32::RED,
+                                EventKind::Completed => egui::Color32::GREEN,
+                                _ => ui.visuals().text_color(),
+                            };
+                            ui.colored_label(color, event.kind.label());
+                            ui.label(event.task_id.map(|id| format!("Task {}", id)).unwrap_or_default());
+                            ui.label(&event.detail);
+                            ui.end_row();
+                        }
+                    });
+                });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    export = ui.add_enabled(!events.is_empty(), egui::Button::new("Export..."))
+                        .on_hover_text("Save the log as a tab-separated text file")
+                        .clicked();
+                    clear = ui.add_enabled(!events.is_empty(), egui::Button::new("Clear")).clicked();
+                });
+            });
+
+        if export
+            && let Some(path) = FileDialog::new()
+                .add_filter("Tab-separated text", &["tsv", "txt"])
+                .set_file_name("smoothie-queue-events.tsv")
+                .save_file()
+        {
+            let text = self.queue_manager.lock()
+                .expect("Failed to lock queue manager")
+                .events
+                .export();
+            match std::fs::write(&path, text) {
+                Ok(()) => log::info!("Exported the event log to {:?}", path),
+                Err(e) => log::error!("Failed to write {:?}: {}", path, e),
+            }
+        }
+        if clear {
+            self.queue_manager.lock()
+                .expect("Failed to lock queue manager")
+                .events
+                .clear();
+        }
+        self.events_open = open;
+    }
+
+    /// Looks for a newer release in the background. A `manual` check also reports
+    /// being up to date or failing, and ignores a skipped version.
+    fn start_update_check(&mut self, ctx: &egui::Context, manual: bool) {
+        if let Some(window) = self.update.as_mut().filter(|window| window.rx.is_some()) {
+            window.manual |= manual;
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let state = match update::check_latest() {
+                Ok(Some(release)) => UpdateState::Available(release),
+                Ok(None) => UpdateState::UpToDate,
+                Err(e) => UpdateState::Failed(e),
+            };
+            let _ = tx.send(state);
+            ctx.request_repaint();
+        });
+        self.update = Some(UpdateWindow {
+            state: UpdateState::Checking,
+            rx: Some(rx),
+            manual,
+        });
+    }
+
+    fn show_update_window(&mut self, ctx: &egui::Context) {
+        let Some(window) = self.update.as_mut() else {
+            return;
+        };
+        if let Some(state) = window.rx.as_ref().and_then(|rx| rx.try_recv().ok()) {
+            window.rx = None;
+            window.state = state;
+        }
+        match &window.state {
+            UpdateState::Installed(exe) => {
+                // Saved and relaunched in `on_exit`
+                self.relaunch = Some(exe.clone());
+                self.update = None;
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                return;
+            }
+            // The launch check only speaks up about new releases
+            UpdateState::Checking | UpdateState::UpToDate | UpdateState::Failed(_) if !window.manual => {
+                if let UpdateState::Failed(e) = &window.state {
+                    log::warn!("{}", e);
+                }
+                if window.rx.is_none() {
+                    self.update = None;
+                }
+                return;
+            }
+            UpdateState::Available(release)
+                if !window.manual && self.settings.skipped_update.as_deref() == Some(release.version.as_str()) =>
+            {
+                log::info!("Skipping update {} as asked", release.version);
+                self.update = None;
+                return;
+            }
+            _ => {}
+        }
+
+        let mut open = true;
+        let mut install = None;
+        let mut skip = None;
+        let mut close = false;
+        egui::Window::new("Update")
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| match &window.state {
+                UpdateState::Checking => {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Checking for updates...");
+                    });
+                }
+                UpdateState::UpToDate => {
+                    ui.label(format!("Smoothie Queuer {} is the latest version.", env!("CARGO_PKG_VERSION")));
+                }
+                UpdateState::Failed(e) => {
+                    ui.colored_label(egui::Color32::RED, e);
+                }
+                UpdateState::Installing(release) => {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(format!("Downloading Smoothie Queuer {}...", release.version));
+                    });
+                }
+                UpdateState::Installed(_) => {}
+                UpdateState::Available(release) => {
+                    ui.heading(format!("Smoothie Queuer {} is available", release.version));
+                    ui.label(
+                        egui::RichText::new(format!("You have {}", env!("CARGO_PKG_VERSION")))
+                            .color(ui.visuals().weak_text_color()),
+                    );
+                    ui.separator();
+                    egui::ScrollArea::vertical().max_height(250.0).show(ui, |ui| {
+                        if release.notes.trim().is_empty() {
+                            ui.label("(No release notes)");
+                        } else {
+                            ui.label(&release.notes);
+                        }
+                    });
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        let (enabled, hint) = match (&release.asset_url, self.worker_running) {
+                            (None, _) => (false, "This release has no build for this platform; use the release page"),
+                            (_, true) => (false, "Stop the queue first; restarting would cut off the running renders"),
+                            _ => (true, "Downloads the new version, replaces this one and restarts; the queue is kept"),
+                        };
+                        if ui.add_enabled(enabled, egui::Button::new("Install and Restart"))
+                            .on_hover_text(hint)
+                            .on_disabled_hover_text(hint)
+                            .clicked()
+                        {
+                            install = Some(release.clone());
+                        }
+                        if ui.button("Open Release Page").clicked() {
+                            let _ = opener::open(&release.page_url);
+                        }
+                        if ui.button("Skip This Version").clicked() {
+                            skip = Some(release.version.clone());
+                        }
+                        close |= ui.button("Later").clicked();
+                    });
+                }
+            });
+
+        if let Some(release) = install {
+            let (tx, rx) = mpsc::channel();
+            let ctx = ctx.clone();
+            let installing = release.clone();
+            thread::spawn(move || {
+                let state = match update::install(&release) {
+                    Ok(exe) => UpdateState::Installed(exe),
+                    Err(e) => UpdateState::Failed(e),
+                };
+                let _ = tx.send(state);
+                ctx.request_repaint();
+            });
+            window.state = UpdateState::Installing(installing);
+            window.rx = Some(rx);
+            window.manual = true;
+        }
+        if let Some(version) = skip {
+            self.settings.skipped_update = Some(version);
+            self.settings.save();
+            close = true;
+        }
+        // Closing while installing would leave nobody to relaunch
+        if (close || !open) && !matches!(window.state, UpdateState::Installing(_)) {
+            self.update = None;
+        }
+    }
+
+    fn show_new_recipe_window(&mut self, ctx: &egui::Context) {
+        let Some(window) = self.new_recipe.as_mut() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut create = false;
+        egui::Window::new("New Recipe")
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label("Start from:");
+                for (index, template) in recipes::RECIPE_TEMPLATES.iter().enumerate() {
+                    if ui
+                        .radio_value(&mut window.template, index, template.name)
+                        .on_hover_text(template.description)
+                        .clicked()
+                    {
+                        window.name = template.file_stem.to_string();
+                    }
+                    ui.label(egui::RichText::new(template.description).color(ui.visuals().weak_text_color()));
+                }
+                ui.label("Other settings are copied from the default recipe.");
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Name:");
+                    ui.add(egui::TextEdit::singleline(&mut window.name).desired_width(200.0));
+                    ui.label(".ini");
+                });
+                if let Some(error) = &window.error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+                create = ui.button("Create").clicked();
+            });
+
+        if create && let Some(config) = &self.config {
+            let template = &recipes::RECIPE_TEMPLATES[window.template];
+            let created = config
+                .recipes_dir()
+                .ok_or_else(|| "The Smoothie folder is not known".to_string())
+                .and_then(|dir| recipes::create(template, &config.recipe_path, &dir, &window.name));
+            match created {
+                Ok(path) => {
+                    self.available_recipes = scan_recipes(config.smoothie_dir());
+                    self.recipe_warnings = check_recipes(&self.available_recipes, &config.recipe_path);
+                    self.recipe_path = path;
+                    self.new_recipe = None;
+                    return;
+                }
+                Err(e) => window.error = Some(e),
+            }
+        }
+        if !open {
+            self.new_recipe = None;
+        }
+    }
+
+    fn show_import_recipe_window(&mut self, ctx: &egui::Context) {
+        let Some(window) = self.import_recipe.as_mut() else {
+            return;
+        };
+
+        if let Some(result) = window.rx.as_ref().and_then(|rx| rx.try_recv().ok()) {
+            window.rx = None;
+            if let (Ok(path), Some(config)) = (&result, &self.config) {
+                self.available_recipes = scan_recipes(config.smoothie_dir());
+                self.recipe_warnings = check_recipes(&self.available_recipes, &config.recipe_path);
+                self.recipe_path = path.clone();
+            }
+            window.result = Some(result);
+        }
+
+        let mut open = true;
+        let mut import = false;
+        let importing = window.rx.is_some();
+        egui::Window::new("Import Recipe")
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.add_enabled_ui(!importing, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Link:");
+                        let link = ui.add(
+                            egui::TextEdit::singleline(&mut window.url)
+                                .hint_text("https://gist.github.com/...")
+                                .desired_width(320.0),
+                        );
+                        if link.changed()
+                            && let Some(name) = recipes::name_from_url(&window.url)
+                        {
+                            window.name = name;
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Name:");
+                        ui.add(egui::TextEdit::singleline(&mut window.name).desired_width(200.0));
+                        ui.label(".ini");
+                    });
+                    import = ui.button("Import").clicked();
+                });
+                if importing {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Downloading...");
+                    });
+                }
+                match &window.result {
+                    Some(Ok(path)) => {
+                        ui.label(format!("Imported {} and selected it for new tasks", path.display()));
+                        let warnings = self.recipe_warnings.get(path).map_or(&[][..], Vec::as_slice);
+                        for warning in warnings {
+                            ui.colored_label(egui::Color32::YELLOW, format!("⚠ {}", warning));
+                        }
+                    }
+                    Some(Err(e)) => {
+                        ui.colored_label(egui::Color32::RED, e);
+                    }
+                    None => {}
+                }
+            });
+
+        if import && let Some(dir) = self.config.as_ref().and_then(SmoothieConfig::recipes_dir) {
+            let (tx, rx) = mpsc::channel();
+            let (url, name, ctx) = (window.url.clone(), window.name.clone(), ctx.clone());
+            thread::spawn(move || {
+                let _ = tx.send(recipes::import(&url, &dir, &name));
+                ctx.request_repaint();
+            });
+            window.rx = Some(rx);
+            window.result = None;
+        }
+        if !open {
+            self.import_recipe = None;
+        }
+    }
+
+    fn show_recipe_editor_window(&mut self, ctx: &egui::Context) {
+        let Some(window) = self.recipe_editor.as_mut() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut save = false;
+        let mut restore: Option<PathBuf> = None;
+        let warnings = self.recipe_warnings.get(&window.path);
+        egui::Window::new("Edit Recipe")
+            .open(&mut open)
+            .default_size([560.0, 480.0])
+            .show(ctx, |ui| {
+                ui.label(window.path.display().to_string());
+                ui.horizontal(|ui| {
+                    let changed = window.text != window.saved_text;
+                    save = ui
+                        .add_enabled(changed, egui::Button::new("Save"))
+                        .on_hover_text("Backs up the file to the backups folder next to it, then saves")
+                        .clicked();
+                    if ui.add_enabled(changed, egui::Button::new("Revert")).clicked() {
+                        window.text = window.saved_text.clone();
+                    }
+                    ui.add_enabled_ui(!window.backups.is_empty(), |ui| {
+                        ui.menu_button("Restore Previous Version", |ui| {
+                            for (backup, replaced_at) in &window.backups {
+                                if ui
+                                    .button(format!("Saved before {}", replaced_at))
+                                    .on_hover_text("Unsaved changes are discarded; the current file is backed up first")
+                                    .clicked()
+                                {
+                                    restore = Some(backup.clone());
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                    });
+                });
+                if let Some(status) = &window.status {
+                    ui.label(status);
+                }
+                for warning in warnings.into_iter().flatten() {
+                    ui.colored_label(egui::Color32::YELLOW, format!("⚠ {}", warning));
+                }
+                ui.separator();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.add(
+                        egui::TextEdit::multiline(&mut window.text)
+                            .code_editor()
+                            .desired_width(f32::INFINITY)
+                            .desired_rows(24),
+                    );
+                });
+            });
+
+        let mut saved = false;
+        if save {
+            match recipes::save(&window.path, &window.text) {
+                Ok(backup) => {
+                    window.saved_text = window.text.clone();
+                    window.status = Some(match backup {
+                        Some(backup) => format!("Saved; the previous version is in {}", backup.display()),
+                        None => "Saved".to_string(),
+                    });
+                    saved = true;
+                }
+                Err(e) => window.status = Some(e),
+            }
+        }
+        if let Some(backup) = restore {
+            let restored = recipes::restore(&window.path, &backup).and_then(|()| {
+                std::fs::read_to_string(&window.path)
+                    .map_err(|e| format!("Failed to read {:?}: {}", window.path, e))
+            });
+            match restored {
+                Ok(text) => {
+                    window.text = text.clone();
+                    window.saved_text = text;
+                    window.status = Some(format!("Restored {}", backup.display()));
+                    saved = true;
+                }
+                Err(e) => window.status = Some(e),
+            }
+        }
+        if saved {
+            window.backups = recipes::backups(&window.path);
+            let default_recipe = self.config.as_ref().map_or(self.recipe_path.as_path(), |config| &config.recipe_path);
+            let warnings = schema::check(&window.path, &Schema::for_default_recipe(default_recipe));
+            self.recipe_warnings.insert(window.path.clone(), warnings);
+        }
+        if !open {
+            self.recipe_editor = None;
+        }
+    }
+
+    fn show_templates_window(&mut self, ctx: &egui::Context) {
+        let Some(window) = self.templates.as_mut() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut to_load: Option<String> = None;
+        egui::Window::new("Queue Templates")
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut window.name)
+                            .hint_text("Template name")
+                            .desired_width(200.0),
+                    );
+                    if ui.button("Save Pending Tasks").clicked() {
+                        let manager = self.queue_manager.lock()
+                            .expect("Failed to lock queue manager");
+                        window.status = Some(match templates::save(&window.name, &manager.tasks) {
+                            Ok(count) => format!("Saved {} task(s) as {:?}", count, window.name.trim()),
+                            Err(e) => e,
+                        });
+                        window.names = templates::list();
+                    }
+                });
+                if let Some(status) = &window.status {
+                    ui.label(egui::RichText::new(status).color(ui.visuals().weak_text_color()));
+                }
+
+                ui.separator();
+                if window.names.is_empty() {
+                    ui.label("(No saved templates)");
+                }
+                let mut to_delete = None;
+                for name in &window.names {
+                    ui.horizontal(|ui| {
+                        ui.label(name);
+                        if ui.small_button("Load").clicked() {
+                            to_load = Some(name.clone());
+                        }
+                        if ui.small_button("Delete").clicked() {
+                            to_delete = Some(name.clone());
+                        }
+                    });
+                }
+                if let Some(name) = to_delete {
+                    if let Err(e) = templates::delete(&name) {
+                        window.status = Some(e);
+                    }
+                    window.names = templates::list();
+                }
+            });
+
+        if let Some(name) = to_load {
+            let status = match templates::load(&name) {
+                Ok(entries) => {
+                    let count = entries.len();
+                    for entry in entries {
+                        let id = self.queue_manager.lock()
+                            .expect("Failed to lock queue manager")
+                            .next_task_id();
+                        let mut task = entry.into_task(id);
+                        self.scripts.on_task_added(&mut task);
+                        self.queue_with_metadata(task);
+                    }
+                    self.files_dropped = true;
+                    format!("Queued {} task(s) from {:?}", count, name)
+                }
+                Err(e) => e,
+            };
+            if let Some(window) = self.templates.as_mut() {
+                window.status = Some(status);
+            }
+        }
+        if !open {
+            self.templates = None;
+        }
+    }
+
+    fn show_edit_window(&mut self, ctx: &egui::Context) {
+        let Some(window) = self.edit_window.as_mut() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut save = false;
+        egui::Window::new(format!("Edit Task {}", window.task_id))
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("edit_task_grid").num_columns(2).show(ui, |ui| {
+                    ui.label("Input:");
+                    ui.horizontal(|ui| {
+                        ui.add_enabled(
+                            !window.input_locked,
+                            egui::TextEdit::singleline(&mut window.input).desired_width(320.0),
+                        );
+                        if ui.add_enabled(!window.input_locked, egui::Button::new("Browse...")).clicked()
+                            && let Some(path) = FileDialog::new().pick_file()
+                        {
+                            window.input = path.display().to_string();
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Recipe:");
+                    let recipe_name = window.recipe_path.file_name()
+                        .map_or_else(|| "Invalid".into(), |f| f.to_string_lossy());
+                    egui::ComboBox::from_id_source("edit_task_recipe")
+                        .selected_text(recipe_name)
+                        .show_ui(ui, |ui| {
+                            for recipe in &self.available_recipes {
+                                let filename = recipe.file_name()
+                                    .map_or_else(|| "Invalid Path".into(), |f| f.to_string_lossy());
+                                ui.selectable_value(&mut window.recipe_path, recipe.clone(), filename);
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Output folder:");
+                    ui.horizontal(|ui| {
+                        ui.add(egui::TextEdit::singleline(&mut window.output_dir).desired_width(320.0));
+                        if ui.button("Browse...").clicked()
+                            && let Some(folder) = FileDialog::new().pick_folder()
+                        {
+                            window.output_dir = folder.display().to_string();
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Output name:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut window.output_name)
+                            .hint_text("Backend default")
+                            .desired_width(320.0),
+                    );
+                    ui.end_row();
+
+                    ui.label("Input frame rate:")
+                        .on_hover_text("For captures with wrong frame rate metadata: the rate the input was really recorded at. The input is re-encoded at that rate with ffmpeg before rendering, in place of its pre-processing step, so the interpolation targets the right fps");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut window.input_fps)
+                            .hint_text("From the file, e.g. 59.94 or 60000/1001")
+                            .desired_width(320.0),
+                    );
+                    ui.end_row();
+
+                    ui.label("Priority:");
+                    ui.add(egui::DragValue::new(&mut window.priority))
+                        .on_hover_text("Higher runs first; equal priorities keep queue order");
+                    ui.end_row();
+
+                    ui.label("Extra arguments:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut window.extra_args)
+                            .hint_text("Appended to the backend command")
+                            .desired_width(320.0),
+                    );
+                    ui.end_row();
+
+                    ui.label("Recipe overrides:")
+                        .on_hover_text("smoothie-rs only. Recipe values changed for this task alone, one section;key=value per line");
+                    ui.add(
+                        egui::TextEdit::multiline(&mut window.recipe_overrides)
+                            .hint_text("frame blending;fps=30")
+                            .desired_rows(2)
+                            .desired_width(320.0),
+                    );
+                    ui.end_row();
+
+                    ui.label("Environment:")
+                        .on_hover_text("Environment variables set on the backend's process for this task, one NAME=value per line. They replace the ones from Settings with the same name");
+                    ui.add(
+                        egui::TextEdit::multiline(&mut window.env)
+                            .hint_text("PYTHONPATH=D:\\vs-plugins")
+                            .desired_rows(2)
+                            .desired_width(320.0),
+                    );
+                    ui.end_row();
+                });
+
+                if let Some(error) = &window.error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+                ui.separator();
+                save = ui.button("Save").clicked();
+            });
+
+        let mut close = !open;
+        if save {
+            match window.to_edit(&self.settings) {
+                Ok(edit) => {
+                    let input_changed = window.original_input != edit.input_path;
+                    let input = edit.input_path.clone();
+                    let edited = self.queue_manager.lock()
+                        .expect("Failed to lock queue manager")
+                        .edit_task(window.task_id, edit);
+                    if !edited {
+                        window.error = Some("The task is no longer pending".to_string());
+                    } else {
+                        if input_changed {
+                            let probe = !queue::is_vapoursynth_script(&input);
+                            let request = MetadataRequest {
+                                task_id: window.task_id,
+                                input,
+                                ffmpeg_path: self.config.as_ref().filter(|_| probe).map(|config| config.ffmpeg_path.clone()),
+                            };
+                            let _ = self.metadata_tx.send(request);
+                        }
+                        close = true;
+                    }
+                }
+                Err(e) => window.error = Some(e),
+            }
+        }
+        if close {
+            self.edit_window = None;
+        }
+    }
+
+    fn show_batch_edit_window(&mut self, ctx: &egui::Context) {
+        let Some(window) = self.batch_edit.as_mut() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut apply = false;
+        egui::Window::new(format!("Edit {} Selected Tasks", self.selected_tasks.len()))
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label("Ticked fields are set on every selected pending task:");
+                egui::Grid::new("batch_edit_grid").num_columns(2).show(ui, |ui| {
+                    ui.checkbox(&mut window.apply_recipe, "Recipe:");
+                    let recipe_name = window.recipe_path.file_name()
+                        .map_or_else(|| "Invalid".to_string(), |f| f.to_string_lossy().to_string());
+                    ui.add_enabled_ui(window.apply_recipe, |ui| {
+                        egui::ComboBox::from_id_source("batch_edit_recipe")
+                            .selected_text(recipe_name)
+                            .show_ui(ui, |ui| {
+                                for recipe in &self.available_recipes {
+                                    let filename = recipe.file_name()
+                                        .map_or_else(|| "Invalid Path".into(), |f| f.to_string_lossy());
+                                    ui.selectable_value(&mut window.recipe_path, recipe.clone(), filename);
+                                }
+                            });
+                    });
+                    ui.end_row();
+
+                    ui.checkbox(&mut window.apply_output_dir, "Output folder:");
+                    ui.add_enabled_ui(window.apply_output_dir, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.add(egui::TextEdit::singleline(&mut window.output_dir).desired_width(280.0));
+                            if ui.button("Browse...").clicked()
+                                && let Some(folder) = FileDialog::new().pick_folder()
+                            {
+                                window.output_dir = folder.display().to_string();
+                            }
+                        });
+                    });
+                    ui.end_row();
+
+                    ui.checkbox(&mut window.apply_priority, "Priority:");
+                    ui.add_enabled(window.apply_priority, egui::DragValue::new(&mut window.priority));
+                    ui.end_row();
+
+                    ui.checkbox(&mut window.apply_extra_args, "Extra arguments:");
+                    ui.add_enabled(
+                        window.apply_extra_args,
+                        egui::TextEdit::singleline(&mut window.extra_args).desired_width(280.0),
+                    );
+                    ui.end_row();
+                });
+
+                if let Some(error) = &window.error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+                ui.separator();
+                apply = ui.button("Apply").clicked();
+            });
+
+        let mut close = !open;
+        if apply {
+            match window.to_edit() {
+                Ok(edit) => {
+                    let count = self.queue_manager.lock()
+                        .expect("Failed to lock queue manager")
+                        .edit_tasks(&self.selected_tasks, &edit);
+                    log::info!("Batch edited {} pending task(s)", count);
+                    close = true;
+                }
+                Err(e) => window.error = Some(e),
+            }
+        }
+        if close {
+            self.batch_edit = None;
+        }
+    }
+
+    fn show_inspect_window(&mut self, ctx: &egui::Context) {
+        let Some(window) = self.inspect.as_mut() else {
+            return;
+        };
+        let Some(task) = self.queue_manager.lock()
+            .expect("Failed to lock queue manager")
+            .tasks
+            .iter()
+            .find(|task| task.id == window.task_id)
+            .cloned()
+        else {
+            self.inspect = None;
+            return;
+        };
+
+        let mut open = true;
+        let mut refresh = false;
+        let title = task.input_path.file_name()
+            .map_or_else(|| "Invalid Path".to_string(), |name| name.to_string_lossy().to_string());
+        egui::Window::new(format!("Settings - {}", title))
+            .id(egui::Id::new("inspect_window"))
+            .open(&mut open)
+            .default_size([560.0, 480.0])
+            .show(ctx, |ui| {
+                let settings = &window.settings;
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.heading("Task");
+                    egui::Grid::new("inspect_task_grid").num_columns(2).show(ui, |ui| {
+                        let output_folder = task.output_dir.display().to_string();
+                        let rows = [
+                            ("Backend", task.backend.label().to_string()),
+                            ("Input", task.input_path.display().to_string()),
+                            (
+                                "Frame rate",
+                                match task.input_vfr {
+                                    Some(true) => "Variable".to_string(),
+                                    Some(false) => "Constant".to_string(),
+                                    None => "Not probed".to_string(),
+                                },
+                            ),
+                            ("Output folder", output_folder),
+                            ("Output name", task.output_name.clone().unwrap_or_else(|| "Backend's own".to_string())),
+                            ("Container", container_label(task.output_container).to_string()),
+                            ("Extra arguments", task.extra_args.clone()),
+                            (
+                                "Recipe overrides",
+                                task.recipe_overrides.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "),
+                            ),
+                            ("Environment", task.env.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")),
+                            ("Input frame rate", task.input_fps.map(|fps| format!("{} fps", format_fps(fps))).unwrap_or_default()),
+                            ("Pre-process", pre_process_label(task.pre_process).to_string()),
+                            ("Post-processing", task.post_command.clone().unwrap_or_default()),
+                            ("Priority", task.priority.to_string()),
+                        ];
+                        for (name, value) in rows {
+                            ui.label(name);
+                            ui.label(if value.is_empty() { "None".to_string() } else { value });
+                            ui.end_row();
+                        }
+                    });
+
+                    ui.separator();
+                    ui.heading("Recipe");
+                    if settings.recipe_path != task.recipe_path {
+                        ui.label(format!("{} (converted from {})", settings.recipe_path.display(), task.recipe_path.display()));
+                    } else {
+                        ui.label(settings.recipe_path.display().to_string());
+                    }
+                    match &settings.recipe {
+                        Ok(values) => {
+                            egui::Grid::new("inspect_recipe_grid").num_columns(3).striped(true).show(ui, |ui| {
+                                for setting in values {
+                                    ui.weak(&setting.section);
+                                    ui.label(&setting.key);
+                                    match &setting.default {
+                                        _ if setting.overridden => {
+                                            ui.colored_label(egui::Color32::LIGHT_BLUE, &setting.value)
+                                                .on_hover_text("Set by this task's recipe overrides");
+                                        }
+                                        Some(default) => {
+                                            ui.strong(&setting.value)
+                                                .on_hover_text(format!("The default recipe has {:?}", default));
+                                        }
+                                        None => {
+                                            ui.label(&setting.value);
+                                        }
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                            ui.weak("Bold values differ from the default recipe; blue ones are the task's overrides.");
+                        }
+                        Err(reason) => {
+                            ui.weak(reason);
+                        }
+                    }
+                    if let Some((preset, arguments)) = &settings.encoding {
+                        ui.separator();
+                        ui.heading("Encoding");
+                        if preset == arguments {
+                            ui.monospace(arguments);
+                        } else {
+                            ui.label(format!("Preset {:?}:", preset));
+                            ui.monospace(arguments);
+                        }
+                    }
+
+                    ui.separator();
+                    ui.heading("Command");
+                    match &settings.command_line {
+                        Ok(command_line) => {
+                            ui.add(
+                                egui::TextEdit::multiline(&mut command_line.as_str())
+                                    .code_editor()
+                                    .desired_width(f32::INFINITY),
+                            );
+                            if ui.button("Copy").clicked() {
+                                ui.output_mut(|output| output.copied_text = command_line.clone());
+                            }
+                        }
+                        Err(e) => {
+                            ui.colored_label(egui::Color32::RED, e);
+                        }
+                    }
+                });
+                ui.separator();
+                refresh = ui.button("Refresh").on_hover_text("Reads the recipe and the task's options again").clicked();
+            });
+
+        if refresh && let Some(config) = &self.config {
+            *window = InspectWindow::new(&task, config);
+        }
+        if !open {
+            self.inspect = None;
+        }
+    }
+
+    fn show_plugin_help_window(&mut self, ctx: &egui::Context) {
+        let Some(window) = self.plugin_help.as_mut() else {
+            return;
+        };
+        let smoothie_dir = self.config.as_ref().and_then(|config| config.smoothie_dir()).map(Path::to_path_buf);
+        let missing = &window.missing;
+
+        let mut open = true;
+        let mut retry = false;
+        egui::Window::new("Missing Plugin")
+            .open(&mut open)
+            .collapsible(false)
+            .default_width(460.0)
+            .show(ctx, |ui| {
+                ui.heading(missing.to_string());
+                ui.label(match missing.kind {
+                    MissingKind::Plugin => format!(
+                        "The VapourSynth script calls core.{}, but no plugin providing it was loaded. \
+                         Usually its .dll is missing from Smoothie's plugin folder, was quarantined by \
+                         an antivirus, or does not match the VapourSynth version.",
+                        missing.id
+                    ),
+                    MissingKind::PythonModule => format!(
+                        "The VapourSynth script imports {}, which Smoothie's Python cannot find.",
+                        missing.id
+                    ),
+                });
+                ui.add_space(6.0);
+                match missing.known {
+                    Some(known) => match known.url {
+                        Some(url) => {
+                            ui.horizontal_wrapped(|ui| {
+                                ui.label(format!("Download {} ({}) from", known.name, known.files));
+                                ui.hyperlink(url);
+                            });
+                        }
+                        None => {
+                            ui.label(format!(
+                                "{} comes with Smoothie: copy {} from a fresh Smoothie download, or reinstall Smoothie.",
+                                known.name, known.files
+                            ));
+                        }
+                    },
+                    None => {
+                        ui.label(format!("Look up the plugin or module providing {} and download it.", missing.name()));
+                    }
+                }
+
+                let Some(smoothie_dir) = &smoothie_dir else {
+                    ui.weak("smoothie-rs is not in a Smoothie folder, so there is no plugin folder to install into.");
+                    return;
+                };
+                let install_dir = missing.install_dir(smoothie_dir);
+                ui.add_space(6.0);
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Then install it into");
+                    ui.monospace(install_dir.display().to_string());
+                });
+                ui.horizontal(|ui| {
+                    let picked = if ui.button("Install File...").clicked() {
+                        let filter: &[&str] = match missing.kind {
+                            MissingKind::Plugin => &["dll", "so", "dylib"],
+                            MissingKind::PythonModule => &["py", "pyd"],
+                        };
+                        FileDialog::new().add_filter("Plugin", filter).pick_file()
+                    } else {
+                        None
+                    };
+                    let picked = picked.or_else(|| {
+                        (missing.kind == MissingKind::PythonModule
+                            && ui.button("Install Folder...").on_hover_text("A package folder, e.g. vsutil").clicked())
+                        .then(|| FileDialog::new().pick_folder())
+                        .flatten()
+                    });
+                    if let Some(path) = picked {
+                        window.status = Some(plugins::install(missing, &path, smoothie_dir));
+                    }
+                    if ui.button("Open Folder").clicked() {
+                        match std::fs::create_dir_all(&install_dir) {
+                            Ok(()) => {
+                                let _ = opener::open(&install_dir);
+                            }
+                            Err(e) => window.status = Some(Err(format!("Failed to create {:?}: {}", install_dir, e))),
+                        }
+                    }
+                });
+                match &window.status {
+                    Some(Ok(installed)) => {
+                        ui.colored_label(egui::Color32::GREEN, format!("Installed {}", installed.display()));
+                        retry = ui.button("Re-run Task").clicked();
+                    }
+                    Some(Err(e)) => {
+                        ui.colored_label(egui::Color32::RED, e);
+                    }
+                    None => {}
+                }
+            });
+
+        if retry {
+            self.queue_manager.lock()
+                .expect("Failed to lock queue manager")
+                .rerun_task(window.task_id);
+        }
+        if !open || retry {
+            self.plugin_help = None;
+        }
+    }
+
+    fn show_log_window(&mut self, ctx: &egui::Context) {
+        let Some(task_id) = self.log_task else {
+            return;
+        };
+        let (title, lines, screenshot_path) = {
+            let manager = self.queue_manager.lock()
+                .expect("Failed to lock queue manager");
+            match manager.tasks.iter().find(|t| t.id == task_id) {
+                Some(task) => (
+                    task.input_path.file_name()
+                        .map_or_else(|| "Invalid Path".to_string(), |name| name.to_string_lossy().to_string()),
+                    task.log.clone(),
+                    task.screenshot_path.clone(),
+                ),
+                None => {
+                    self.log_task = None;
+                    return;
+                }
+            }
+        };
+
+        let mut open = true;
+        egui::Window::new(format!("Log - {}", title))
+            .id(egui::Id::new("task_log_window"))
+            .open(&mut open)
+            .default_size([500.0, 300.0])
+            .show(ctx, |ui| {
+                if let Some(path) = &screenshot_path {
+                    if self.screenshot.as_ref().is_none_or(|(loaded, _)| loaded != path) {
+                        self.screenshot = Some((path.clone(), load_screenshot(ctx, path)));
+                    }
+                    if let Some((_, texture)) = &self.screenshot {
+                        egui::CollapsingHeader::new("Before / After").default_open(true).show(ui, |ui| {
+                            match texture {
+                                Ok(texture) => {
+                                    let size = texture.size_vec2();
+                                    let scale = (ui.available_width() / size.x).min(1.0);
+                                    ui.image((texture.id(), size * scale));
+                                }
+                                Err(e) => {
+                                    ui.colored_label(egui::Color32::RED, e);
+                                }
+                            }
+                            if ui.link(path.display().to_string()).clicked() {
+                                let _ = opener::open(path);
+                            }
+                        });
+                        ui.separator();
+                    }
+                }
+                egui::ScrollArea::vertical().stick_to_bottom(true).show(ui, |ui| {
+                    for line in &lines {
+                        ui.monospace(line);
+                    }
+                });
+            });
+        if !open {
+            self.log_task = None;
+        }
+    }
+}
+
+/// Decodes a comparison screenshot PNG into a texture for the log window.
+fn load_screenshot(ctx: &egui::Context, path: &Path) -> Result<egui::TextureHandle, String> {
+    let image = image::open(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?.to_rgba8();
+    let size = [image.width() as usize, image.height() as usize];
+    let image = egui::ColorImage::from_rgba_unmultiplied(size, &image.into_raw());
+    Ok(ctx.load_texture("comparison_screenshot", image, egui::TextureOptions::LINEAR))
+}
+
+/// Where an update check (or the update it found) stands.
+enum UpdateState {
+    Checking,
+    UpToDate,
+    Available(Release),
+    Installing(Release),
+    Installed(PathBuf), // Updated executable, started again on exit
+    Failed(String),
+}
+
+/// State of the update window, also kept while a launch check runs unseen.
+struct UpdateWindow {
+    state: UpdateState,
+    rx: Option<mpsc::Receiver<UpdateState>>, // Result of the check or install running in the background
+    manual: bool,                            // Asked for by the user, so every outcome is shown
+}
+
+/// State of the new recipe dialog while it is open.
+struct NewRecipeWindow {
+    template: usize, // Index into `recipes::RECIPE_TEMPLATES`
+    name: String,
+    error: Option<String>,
+}
+
+impl NewRecipeWindow {
+    fn new() -> Self {
+        Self {
+            template: 0,
+            name: recipes::RECIPE_TEMPLATES[0].file_stem.to_string(),
+            error: None,
+        }
+    }
+}
+
+/// Effective settings of a task, resolved when the window opens or is refreshed.
+struct InspectWindow {
+    task_id: usize,
+    settings: inspect::EffectiveSettings,
+}
+
+impl InspectWindow {
+    fn new(task: &VideoTask, config: &SmoothieConfig) -> Self {
+        Self {
+            task_id: task.id,
+            settings: inspect::resolve(task, config),
+        }
+    }
+}
+
+/// Help for a task that failed on a missing VapourSynth plugin or Python module.
+struct PluginHelpWindow {
+    task_id: usize,
+    missing: MissingPlugin,
+    status: Option<Result<PathBuf, String>>, // Where the last install went, or why it failed
+}
+
+/// State of the recipe import dialog while it is open.
+#[derive(Default)]
+struct ImportRecipeWindow {
+    url: String,
+    name: String,
+    rx: Option<mpsc::Receiver<Result<PathBuf, String>>>, // Result of the download running in the background
+    result: Option<Result<PathBuf, String>>,
+}
+
+/// State of the recipe editor while it is open.
+struct RecipeEditorWindow {
+    path: PathBuf,
+    text: String,
+    saved_text: String,              // As on disk, to tell unsaved changes
+    backups: Vec<(PathBuf, String)>, // Earlier versions from `recipes::backups`, newest first
+    status: Option<String>,          // Result of the last save or restore
+}
+
+impl RecipeEditorWindow {
+    fn open(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("Failed to read recipe {:?}: {}", path, e))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            saved_text: text.clone(),
+            text,
+            backups: recipes::backups(path),
+            status: None,
+        })
+    }
+}
+
+/// State of the queue templates window while it is open.
+struct TemplatesWindow {
+    name: String,           // Name to save the pending tasks under
+    names: Vec<String>,     // Saved templates, refreshed on changes
+    status: Option<String>, // Result of the last action
+}
+
+impl TemplatesWindow {
+    fn new() -> Self {
+        Self {
+            name: String::new(),
+            names: templates::list(),
+            status: None,
+        }
+    }
+}
+
+/// State of the edit dialog of one pending task while it is open.
+struct EditTaskWindow {
+    task_id: usize,
+    original_input: PathBuf,
+    input_locked: bool, // URL and image sequence inputs are not plain files
+    input: String,
+    recipe_path: PathBuf,
+    output_dir: String,
+    output_name: String,
+    priority: i32,
+    extra_args: String,
+    recipe_overrides: String, // One `section;key=value` per line
+    env: String,              // One `NAME=value` per line
+    input_fps: String,        // Empty trusts the input's metadata
+    error: Option<String>, // Why the last save was rejected
+}
+
+impl EditTaskWindow {
+    fn new(task: &VideoTask) -> Self {
+        let edit = TaskEdit::from_task(task);
+        Self {
+            task_id: task.id,
+            original_input: edit.input_path.clone(),
+            input_locked: task.source.is_some() || task.image_sequence.is_some(),
+            input: edit.input_path.display().to_string(),
+            recipe_path: edit.recipe_path,
+            output_dir: edit.output_dir.display().to_string(),
+            output_name: edit.output_name.unwrap_or_default(),
+            priority: edit.priority,
+            extra_args: edit.extra_args,
+            recipe_overrides: edit.recipe_overrides.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n"),
+            env: edit.env.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n"),
+            input_fps: edit.input_fps.map(format_fps).unwrap_or_default(),
+            error: None,
+        }
+    }
+
+    /// Validates the fields into a `TaskEdit`.
+    fn to_edit(&self, settings: &AppSettings) -> Result<TaskEdit, String> {
+        let input_path = PathBuf::from(self.input.trim());
+        if !self.input_locked && input_path != self.original_input {
+            if !input_path.is_file() {
+                return Err(format!("Input not found: {}", input_path.display()));
+            }
+            if !settings.accepts_input(&input_path) {
+                return Err(format!("Unsupported input: {}", input_path.display()));
+            }
+        }
+        let output_dir = PathBuf::from(self.output_dir.trim());
+        if output_dir.as_os_str().is_empty() {
+            return Err("Choose an output folder".to_string());
+        }
+        let output_name = self.output_name.trim();
+        if output_name.contains(['/', '\\', ':', '*', '?', '"', '<', '>', '|']) {
+            return Err(format!("Output name {:?} contains characters not allowed in file names", output_name));
+        }
+        Ok(TaskEdit {
+            input_path,
+            recipe_path: self.recipe_path.clone(),
+            output_dir,
+            output_name: Some(output_name.to_string()).filter(|name| !name.is_empty()),
+            priority: self.priority,
+            extra_args: self.extra_args.trim().to_string(),
+            recipe_overrides: RecipeOverride::parse_lines(&self.recipe_overrides)?,
+            env: EnvVar::parse_lines(&self.env)?,
+            input_fps: queue::parse_frame_rate(&self.input_fps)?,
+        })
+    }
+}
+
+/// State of the batch edit dialog while it is open. Each field is only applied
+/// when its checkbox is ticked.
+struct BatchEditWindow {
+    apply_recipe: bool,
+    recipe_path: PathBuf,
+    apply_output_dir: bool,
+    output_dir: String,
+    apply_priority: bool,
+    priority: i32,
+    apply_extra_args: bool,
+    extra_args: String,
+    error: Option<String>, // Why the last apply was rejected
+}
+
+impl BatchEditWindow {
+    fn new(recipe_path: PathBuf) -> Self {
+        Self {
+            apply_recipe: false,
+            recipe_path,
+            apply_output_dir: false,
+            output_dir: String::new(),
+            apply_priority: false,
+            priority: 0,
+            apply_extra_args: false,
+            extra_args: String::new(),
+            error: None,
+        }
+    }
+
+    fn to_edit(&self) -> Result<BatchEdit, String> {
+        let output_dir = PathBuf::from(self.output_dir.trim());
+        if self.apply_output_dir && output_dir.as_os_str().is_empty() {
+            return Err("Choose an output folder".to_string());
+        }
+        Ok(BatchEdit {
+            recipe_path: Some(self.recipe_path.clone()).filter(|_| self.apply_recipe),
+            output_dir: Some(output_dir).filter(|_| self.apply_output_dir),
+            priority: Some(self.priority).filter(|_| self.apply_priority),
+            extra_args: Some(self.extra_args.trim().to_string()).filter(|_| self.apply_extra_args),
+        })
+    }
+}
+
+/// Word used for `status` in `status_history`.
+fn status_event(status: &TaskStatus) -> &'static str {
+    match status {
+        TaskStatus::Pending => "Queued",
+        TaskStatus::Waiting => "Waiting for the input file",
+        TaskStatus::Downloading => "Downloading",
+        TaskStatus::Running => "Running",
+        TaskStatus::Completed => "Completed",
+        TaskStatus::Failed(_) => "Failed",
+        TaskStatus::Cancelled => "Cancelled",
+    }
+}
+
+/// Tooltip for a task's status icon: when it was queued, started and finished,
+/// e.g. "Queued 14:02 / Started 14:10 / Failed 14:31 after 21m".
+fn status_history(task: &VideoTask) -> String {
+    let mut lines = vec![format!("Queued {}", clock_time(task.queued_at))];
+    if let Some(started_at) = task.started_at {
+        lines.push(format!("Started {}", clock_time(started_at)));
+    }
+    match (task.started_at, task.finished_at) {
+        (Some(started_at), Some(finished_at)) => lines.push(format!(
+            "{} {} after {}",
+            status_event(&task.status),
+            clock_time(finished_at),
+            format_duration(finished_at.duration_since(started_at).unwrap_or_default())
+        )),
+        (None, Some(finished_at)) => {
+            lines.push(format!("{} {}", status_event(&task.status), clock_time(finished_at)));
+        }
+        _ if task.status != TaskStatus::Pending => lines.push(status_event(&task.status).to_string()),
+        _ => {}
+    }
+    if let Some(energy_wh) = task.energy_wh {
+        lines.push(format!("Used about {}", format_energy(energy_wh)));
+    }
+    lines.join("\n")
+}
+
+/// Local wall clock time (`14:02`) of `time`.
+fn clock_time(time: SystemTime) -> String {
+    jiff::Timestamp::try_from(time)
+        .map(|timestamp| timestamp.to_zoned(jiff::tz::TimeZone::system()).strftime("%H:%M").to_string())
+        .unwrap_or_else(|_| "?".to_string())
+}
+
+/// Short human duration: `45s`, `21m`, `1h 05m`.
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        0..60 => format!("{}s", seconds),
+        60..3600 => format!("{}m", seconds / 60),
+        _ => format!("{}h {:02}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+/// The render speeds of the history and of the completed `tasks`; none when the
+/// history cannot be read.
+fn load_speeds(tasks: &[VideoTask]) -> SpeedModel {
+    archive::speed_model(tasks).unwrap_or_else(|e| {
+        log::warn!("No render time estimates: {}", e);
+        SpeedModel::default()
+    })
+}
+
+/// Render time left for the whole queue from `SpeedModel::estimate_time_left`,
+/// with the number of unfinished tasks it could not estimate. `None` until any
+/// render speed was recorded.
+fn queue_estimate(speeds: &SpeedModel, tasks: &[VideoTask]) -> Option<(Duration, usize)> {
+    if speeds.is_empty() {
+        return None;
+    }
+    let mut total = Duration::ZERO;
+    let mut unknown = 0;
+    for task in tasks.iter().filter(|task| {
+        !matches!(task.status, TaskStatus::Completed | TaskStatus::Failed(_) | TaskStatus::Cancelled)
+    }) {
+        match speeds.estimate_time_left(task) {
+            Some(left) => total += left,
+            None => unknown += 1,
+        }
+    }
+    Some((total, unknown))
+}
+
+/// Task counts, input totals and the render time estimate above the task list.
+fn show_queue_summary(ui: &mut egui::Ui, summary: &QueueSummary, estimate: Option<(Duration, usize)>) {
+    let counts = [
+        (summary.pending, "pending", ui.visuals().text_color()),
+        (summary.running, "running", egui::Color32::YELLOW),
+        (summary.completed, "completed", egui::Color32::GREEN),
+        (summary.failed, "failed", egui::Color32::RED),
+        (summary.cancelled, "cancelled", egui::Color32::LIGHT_RED),
+    ];
+    for (count, name, color) in counts.into_iter().filter(|(count, _, _)| *count > 0) {
+        ui.colored_label(color, format!("{} {}", count, name));
+    }
+    ui.separator();
+    ui.label(format_size(summary.total_size));
+    ui.label(format!("{} of video", format_duration(summary.total_duration)));
+
+    let (text, unknown) = match estimate {
+        _ if summary.remaining_duration.is_zero() => (None, 0),
+        Some((left, unknown)) => (Some(format!("~{} to render", format_duration(left))), unknown),
+        None => (Some("render estimate after the first completed task".to_string()), 0),
+    };
+    if let Some(text) = text {
+        ui.separator();
+        let response = ui.label(text).on_hover_text(
+            "From the speed of past renders with the same recipe and resolution, or the overall average",
+        );
+        if unknown > 0 {
+            response.on_hover_text(format!("{} task(s) of unknown length are not included", unknown));
+        }
+    }
+    if summary.energy_wh > 0.0 {
+        ui.separator();
+        ui.label(format!("{} used", format_energy(summary.energy_wh)))
+            .on_hover_text(ENERGY_HINT);
+    }
+}
+
+/// `0.042 kWh`.
+fn format_energy(watt_hours: f64) -> String {
+    format!("{:.3} kWh", watt_hours / 1000.0)
+}
+
+/// Input size in MB/GB, e.g. `4.2 GB`.
+/// Gauges for CPU, RAM and each GPU, so a render that is not using the hardware
+/// (stuck, or waiting on something) stands out.
+fn show_hardware_usage(ui: &mut egui::Ui, usage: &HardwareUsage) {
+    let gauge = |ui: &mut egui::Ui, label: &str, fraction: f32, text: String| {
+        ui.label(label);
+        ui.add(egui::ProgressBar::new(fraction.clamp(0.0, 1.0)).desired_width(90.0).text(text))
+    };
+    gauge(ui, "CPU", usage.cpu / 100.0, format!("{:.0}%", usage.cpu));
+    let ram = usage.ram_used as f32 / usage.ram_total.max(1) as f32;
+    gauge(ui, "RAM", ram, format_size(usage.ram_used))
+        .on_hover_text(format!("{} of {}", format_size(usage.ram_used), format_size(usage.ram_total)));
+    for (index, gpu) in usage.gpus.iter().enumerate() {
+        let label = if usage.gpus.len() > 1 { format!("GPU {}", index) } else { "GPU".to_string() };
+        gauge(ui, &label, gpu.utilization as f32 / 100.0, format!("{}%", gpu.utilization)).on_hover_text(format!(
+            "{}\nVRAM: {} of {}",
+            gpu.name,
+            format_size(gpu.vram_used),
+            format_size(gpu.vram_total)
+        ));
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    const MB: f64 = 1024.0 * 1024.0;
+    let megabytes = bytes as f64 / MB;
+    if megabytes >= 1024.0 {
+        format!("{:.1} GB", megabytes / 1024.0)
+    } else {
+        format!("{:.0} MB", megabytes)
+    }
+}
+
+/// Running timer text: `12:37`, or `1:02:37` past an hour.
+fn format_elapsed(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    if seconds >= 3600 {
+        format!("{}:{:02}:{:02}", seconds / 3600, seconds % 3600 / 60, seconds % 60)
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+/// Stable color for a recipe, derived from its file name so it stays the same
+/// between runs and for tasks loaded from templates.
+fn recipe_color(recipe: &Path) -> egui::Color32 {
+    // FNV-1a, since std's hasher makes no promise of stable output
+    let name = recipe.file_name().unwrap_or(recipe.as_os_str()).to_string_lossy();
+    let hash = name.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    let hue = (hash % 360) as f32 / 360.0;
+    egui::ecolor::Hsva::new(hue, 0.55, 0.85, 1.0).into()
+}
+
+/// Recipes for the dropdowns: the Smoothie folder's `.ini` files, then the
+/// YAML/TOML sources.
+fn scan_recipes(smoothie_dir: Option<&Path>) -> Vec<PathBuf> {
+    let mut found = smoothie_dir.map(config::find_recipe_files).unwrap_or_default();
+    found.extend(recipes::source_files());
+    found
+}
+
+/// Checks `recipes` against the schema of the smoothie-rs with `default_recipe`.
+fn check_recipes(recipes: &[PathBuf], default_recipe: &Path) -> HashMap<PathBuf, Vec<RecipeWarning>> {
+    let schema = Schema::for_default_recipe(default_recipe);
+    recipes
+        .iter()
+        .map(|recipe| (recipe.clone(), schema::check(recipe, &schema)))
+        .collect()
+}
+
+/// Hover text listing a recipe's warnings, one per line.
+fn warnings_text(warnings: &[RecipeWarning]) -> String {
+    warnings.iter().map(RecipeWarning::to_string).collect::<Vec<_>>().join("\n")
+}
+
+fn label_color(label: TaskLabel) -> egui::Color32 {
+    let [r, g, b] = label.rgb();
+    egui::Color32::from_rgb(r, g, b)
+}
+
+/// Task list filters: text, label and statuses.
+type ListFilters<'a> = (&'a str, Option<TaskLabel>, &'a HashSet<StatusFilter>);
+
+/// What the main window shows of the queue, rebuilt only when the queue's
+/// version or the filters change rather than on every repaint.
+#[derive(Default)]
+struct QueueView {
+    version: Option<u64>, // Of the queue this was built from
+    activity: Option<u64>, // Of the task progress `estimate` was worked out from
+    filter_key: (String, Option<TaskLabel>, Vec<StatusFilter>),
+    has_tasks: bool,
+    summary: QueueSummary,
+    estimate: Option<(Duration, usize)>,
+    budget: Option<BudgetStatus>,
+    history_len: usize,
+    mixed_recipes: bool,
+    rows: Vec<(usize, String)>, // Index in `QueueManager::tasks` and display name of each listed task
+}
+
+impl QueueView {
+    /// Rebuilds the view if `manager` or `filters` changed since (only the
+    /// estimate when just task progress moved), and drops tasks that are no
+    /// longer pending from `selected`.
+    fn refresh(
+        &mut self,
+        manager: &QueueManager,
+        speeds: &SpeedModel,
+        (text, label, statuses): ListFilters,
+        selected: &mut HashSet<usize>,
+    ) {
+        let mut status_key: Vec<StatusFilter> = statuses.iter().copied().collect();
+        status_key.sort_by_key(|filter| *filter as u8);
+        let filter_key = (text.trim().to_lowercase(), label, status_key);
+        if self.activity != Some(manager.activity()) {
+            self.activity = Some(manager.activity());
+            self.estimate = queue_estimate(speeds, &manager.tasks);
+        }
+        if self.version == Some(manager.version()) && self.filter_key == filter_key {
+            return;
+        }
+
+        if self.version != Some(manager.version()) {
+            self.version = Some(manager.version());
+            self.has_tasks = !manager.tasks.is_empty();
+            self.summary = manager.summary();
+            self.estimate = queue_estimate(speeds, &manager.tasks);
+            self.budget = manager.budget;
+            self.history_len = manager.archived_count();
+            let recipes: HashSet<&PathBuf> = manager.tasks.iter().map(|t| &t.recipe_path).collect();
+            self.mixed_recipes = recipes.len() > 1;
+            // Tasks that started or were removed leave the selection
+            selected.retain(|id| manager.tasks.iter().any(|t| t.id == *id && t.status == TaskStatus::Pending));
+        }
+
+        let (text, label, statuses) = &filter_key;
+        self.rows = manager.tasks
+            .iter()
+            .enumerate()
+            .filter(|(_, task)| {
+                label.is_none_or(|label| task.label == Some(label))
+                    && (statuses.is_empty() || statuses.iter().any(|filter| filter.matches(&task.status)))
+                    && (text.is_empty()
+                        || task.input_path.to_string_lossy().to_lowercase().contains(text)
+                        || task.note.to_lowercase().contains(text))
+            })
+            .map(|(index, task)| {
+                let mut name = task.input_path.file_name()
+                    .map_or_else(|| "Invalid Path".to_string(), |name| name.to_string_lossy().to_string());
+                if let Some(sequence) = &task.image_sequence {
+                    name = format!("{} ({} frames)", name, sequence.frame_count);
+                }
+                (index, name)
+            })
+            .collect();
+        self.filter_key = filter_key;
+    }
+
+    /// Makes the next `refresh` rebuild, for changes outside the queue such as
+    /// the render speeds estimates come from.
+    fn invalidate(&mut self) {
+        self.version = None;
+    }
+}
+
+/// Status chips above the task list; any number can be on at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum StatusFilter {
+    Pending,
+    Running,
+    Failed,
+    Completed,
+}
+
+impl StatusFilter {
+    const ALL: [StatusFilter; 4] = [StatusFilter::Pending, StatusFilter::Running, StatusFilter::Failed, StatusFilter::Completed];
+
+    fn name(self) -> &'static str {
+        match self {
+            StatusFilter::Pending => "Pending",
+            StatusFilter::Running => "Running",
+            StatusFilter::Failed => "Failed",
+            StatusFilter::Completed => "Completed",
+        }
+    }
+
+    fn hint(self) -> &'static str {
+        match self {
+            StatusFilter::Pending => "Show tasks waiting their turn",
+            StatusFilter::Running => "Show tasks waiting for their input, downloading or rendering",
+            StatusFilter::Failed => "Show failed and cancelled tasks",
+            StatusFilter::Completed => "Show completed tasks",
+        }
+    }
+
+    fn matches(self, status: &TaskStatus) -> bool {
+        match self {
+            StatusFilter::Pending => *status == TaskStatus::Pending,
+            StatusFilter::Running => matches!(status, TaskStatus::Waiting | TaskStatus::Downloading | TaskStatus::Running),
+            StatusFilter::Failed => matches!(status, TaskStatus::Failed(_) | TaskStatus::Cancelled),
+            StatusFilter::Completed => *status == TaskStatus::Completed,
+        }
+    }
+
+    fn count(self, summary: &QueueSummary) -> usize {
+        match self {
+            StatusFilter::Pending => summary.pending,
+            StatusFilter::Running => summary.running,
+            StatusFilter::Failed => summary.failed + summary.cancelled,
+            StatusFilter::Completed => summary.completed,
+        }
+    }
+}
+
+/// Label name prefixed with a dot in its color, for menus.
+fn label_text(label: TaskLabel) -> egui::RichText {
+    egui::RichText::new(format!("● {}", label.name())).color(label_color(label))
+}
+
+fn container_label(container: Option<OutputContainer>) -> &'static str {
+    match container {
+        None => "Recipe default",
+        Some(OutputContainer::Mp4) => ".mp4",
+        Some(OutputContainer::Mkv) => ".mkv",
+        Some(OutputContainer::Mov) => ".mov",
+    }
+}
+
+/// A frame rate rounded to three decimals, `60000/1001` as `59.94`.
+fn format_fps(fps: f64) -> String {
+    ((fps * 1000.0).round() / 1000.0).to_string()
+}
+
+fn pre_process_label(pre_process: Option<PreProcess>) -> &'static str {
+    match pre_process {
+        None => "None",
+        Some(PreProcess::Remux) => "Remux input",
+        Some(PreProcess::ConvertToCfr) => "Convert VFR to CFR",
+    }
+}
+
+/// Reads the target of a Windows `.url` Internet shortcut.
+fn read_url_shortcut(path: &Path) -> Option<String> {
+    if !path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("url")) {
+        return None;
     }
+    let contents = std::fs::read_to_string(path).ok()?;
+    contents
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("URL="))
+        .find(|url| download::is_url(url))
+        .map(str::to_string)
 }
@@ -0,0 +1,249 @@
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// GitHub repository whose releases are checked for updates.
+const REPOSITORY: &str = "haikalllp/smoothie-rs-queuer";
+/// Release asset replacing the running executable (see `.github/workflows/release.yml`).
+/// Only a Windows build is published; elsewhere updates go through the release page.
+#[cfg(target_os = "windows")]
+const ASSET_NAME: Option<&str> = Some("smoothie-queuer.exe");
+#[cfg(not(target_os = "windows"))]
+const ASSET_NAME: Option<&str> = None;
+/// Suffix of the asset holding the SHA-256 of the one it is named after.
+const CHECKSUM_SUFFIX: &str = ".sha256";
+
+/// A release newer than the running build.
+#[derive(Debug, Clone)]
+pub struct Release {
+    pub version: String,
+    pub notes: String,    // Release notes (Markdown) shown as the changelog
+    pub page_url: String, // Release page, for downloading by hand
+    /// Executable for this platform, when the release has one along with its
+    /// checksum.
+    pub asset_url: Option<String>,
+    /// The executable's `.sha256` asset.
+    pub checksum_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubRelease {
+    tag_name: String,
+    html_url: String,
+    #[serde(default)]
+    body: Option<String>,
+    #[serde(default)]
+    assets: Vec<GitHubAsset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// Numeric parts of `1.2.3`/`v1.2.3`; a pre-release or build suffix is ignored.
+fn version_parts(version: &str) -> Vec<u64> {
+    version
+        .trim()
+        .trim_start_matches('v')
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+fn is_newer(candidate: &str, current: &str) -> bool {
+    let (mut candidate, mut current) = (version_parts(candidate), version_parts(current));
+    let len = candidate.len().max(current.len());
+    candidate.resize(len, 0);
+    current.resize(len, 0);
+    candidate > current
+}
+
+/// Runs `curl` with `args`, which fetches over HTTPS without a TLS stack of our
+/// own. Returns its output.
+fn curl(args: &[&str]) -> Result<Vec<u8>, String> {
+    let mut command = Command::new("curl");
+    command.args(["--silent", "--show-error", "--fail", "--location"]);
+    command.args(args);
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+    let output = command.output().map_err(|e| format!("cannot run curl: {}", e))?;
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// Asks GitHub for the latest release. `None` when this build is up to date.
+/// Blocks on the network, so call it off the UI thread.
+pub fn check_latest() -> Result<Option<Release>, String> {
+    let url = format!("https://api.github.com/repos/{}/releases/latest", REPOSITORY);
+    let body = curl(&[
+        "--max-time",
+        "20",
+        "-H",
+        "Accept: application/vnd.github+json",
+        "-H",
+        concat!("User-Agent: smoothie-queuer/", env!("CARGO_PKG_VERSION")),
+        &url,
+    ])
+    .map_err(|e| format!("Failed to check for updates: {}", e))?;
+    let release: GitHubRelease =
+        serde_json::from_slice(&body).map_err(|e| format!("Unexpected reply from GitHub: {}", e))?;
+
+    if !is_newer(&release.tag_name, env!("CARGO_PKG_VERSION")) {
+        log::info!("Smoothie Queuer {} is up to date", env!("CARGO_PKG_VERSION"));
+        return Ok(None);
+    }
+    log::info!("Smoothie Queuer {} is available", release.tag_name);
+    let find_asset = |name: &str| {
+        release
+            .assets
+            .iter()
+            .find(|asset| asset.name.eq_ignore_ascii_case(name))
+            .map(|asset| asset.browser_download_url.clone())
+    };
+    // An executable without a checksum is left to the release page
+    let (asset_url, checksum_url) = match ASSET_NAME
+        .and_then(|name| Some((find_asset(name)?, find_asset(&format!("{}{}", name, CHECKSUM_SUFFIX))?)))
+    {
+        Some((asset, checksum)) => (Some(asset), Some(checksum)),
+        None => (None, None),
+    };
+    Ok(Some(Release {
+        version: release.tag_name.trim_start_matches('v').to_string(),
+        notes: release.body.unwrap_or_default().replace("\r\n", "\n"),
+        page_url: release.html_url,
+        asset_url,
+        checksum_url,
+    }))
+}
+
+/// Where the running executable is moved by `install`; removed on the next start.
+fn previous_exe_path(exe: &Path) -> PathBuf {
+    exe.with_extension("old")
+}
+
+/// Downloads the release's executable, checks it against the published SHA-256
+/// and swaps it in for the running one, which
+/// is moved aside (Windows allows renaming a running executable, not replacing
+/// it). The running process carries on as the old version until `relaunch` is
+/// given the returned path, which `current_exe` no longer reports on every platform.
+pub fn install(release: &Release) -> Result<PathBuf, String> {
+    let (Some(url), Some(checksum_url)) = (release.asset_url.as_deref(), release.checksum_url.as_deref()) else {
+        return Err("This release has no executable for this platform; download it from the release page".to_string());
+    };
+    let checksum = curl(&["--max-time", "60", checksum_url]).map_err(|e| format!("Failed to fetch the checksum: {}", e))?;
+    let expected = parse_checksum(&String::from_utf8_lossy(&checksum))
+        .ok_or("The release's checksum file is not a SHA-256")?;
+    let exe = std::env::current_exe().map_err(|e| format!("Failed to locate the queuer executable: {}", e))?;
+    let download = exe.with_extension("new");
+    let download_str = download.to_string_lossy();
+    curl(&["--max-time", "600", "--output", &download_str, url]).map_err(|e| format!("Download failed: {}", e))?;
+
+    if let Err(e) = verify_checksum(&download, &expected) {
+        let _ = std::fs::remove_file(&download);
+        return Err(e);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&download, std::fs::Permissions::from_mode(0o755))
+            .map_err(|e| format!("Failed to make {} executable: {}", download.display(), e))?;
+    }
+
+    let previous = previous_exe_path(&exe);
+    let _ = std::fs::remove_file(&previous);
+    std::fs::rename(&exe, &previous).map_err(|e| format!("Failed to move {} aside: {}", exe.display(), e))?;
+    if let Err(e) = std::fs::rename(&download, &exe) {
+        // Put the running version back so the next launch still works
+        let _ = std::fs::rename(&previous, &exe);
+        return Err(format!("Failed to replace {}: {}", exe.display(), e));
+    }
+    log::info!("Updated {:?} to {}", exe, release.version);
+    Ok(exe)
+}
+
+/// The hash in a `sha256sum`-style line (`<hex>  <file name>`), lowercased.
+fn parse_checksum(contents: &str) -> Option<String> {
+    let hash = contents.split_whitespace().next()?;
+    (hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit())).then(|| hash.to_ascii_lowercase())
+}
+
+/// Rejects downloads whose SHA-256 is not `expected`, e.g. a truncated file or
+/// an error page saved in its place.
+fn verify_checksum(path: &Path, expected: &str) -> Result<(), String> {
+    use sha2::{Digest, Sha256};
+    let mut hasher = Sha256::new();
+    std::fs::File::open(path)
+        .and_then(|mut file| std::io::copy(&mut file, &mut hasher))
+        .map_err(|e| format!("Failed to read the download: {}", e))?;
+    let actual = format!("{:x}", hasher.finalize());
+    if actual != expected {
+        log::warn!("Update checksum mismatch: expected {}, got {}", expected, actual);
+        return Err("The download does not match the release's checksum".to_string());
+    }
+    Ok(())
+}
+
+/// Starts the updated executable at `exe`. The caller should exit right after,
+/// once the queue is saved.
+pub fn relaunch(exe: &Path) -> Result<(), String> {
+    Command::new(exe)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to start {}: {}", exe.display(), e))
+}
+
+/// Removes the executable an earlier update moved aside, once it no longer runs.
+pub fn clean_up_previous() {
+    if let Ok(exe) = std::env::current_exe() {
+        let previous = previous_exe_path(&exe);
+        if previous.exists() {
+            match std::fs::remove_file(&previous) {
+                Ok(()) => log::info!("Removed {:?} left by the last update", previous),
+                Err(e) => log::debug!("Cannot remove {:?} yet: {}", previous, e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn later_versions_are_newer() {
+        for (candidate, current) in [
+            ("v0.2.0", "0.1.0"),
+            ("0.1.1", "0.1.0"),
+            ("1.0", "0.9.9"),
+            ("0.10.0", "0.9.0"),
+            ("0.1.0.1", "0.1.0"),
+        ] {
+            assert!(is_newer(candidate, current), "{:?} > {:?}", candidate, current);
+        }
+    }
+
+    #[test]
+    fn same_and_earlier_versions_are_not_newer() {
+        for (candidate, current) in [
+            ("v0.1.0", "0.1.0"),
+            ("0.1", "0.1.0"),
+            ("0.1.0-beta", "0.1.0"),
+            ("0.1.0+build.5", "0.1.0"),
+            ("0.0.9", "0.1.0"),
+            ("garbage", "0.1.0"),
+        ] {
+            assert!(!is_newer(candidate, current), "{:?} <= {:?}", candidate, current);
+        }
+    }
+}
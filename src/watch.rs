@@ -0,0 +1,90 @@
+use crate::api::ControlCommand;
+use smoothie_queuer_core::queue;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, mpsc::Sender};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Polls a folder and queues supported videos that appear in it once they are
+/// fully written. Files already present when watching starts are ignored.
+/// Stops when dropped.
+pub struct FolderWatcher {
+    stop: Arc<AtomicBool>,
+}
+
+impl FolderWatcher {
+    /// `recipe` is attached to every queued file; `on_command` wakes the UI.
+    pub fn spawn(
+        folder: PathBuf,
+        recipe: Option<PathBuf>,
+        extensions: Vec<String>,
+        control_tx: Sender<ControlCommand>,
+        on_command: impl Fn() + Send + 'static,
+    ) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+
+        thread::spawn(move || {
+            log::info!("Watching {:?} for new videos", folder);
+            let mut seen: HashSet<PathBuf> = list_videos(&folder, &extensions).into_keys().collect();
+            // Size of each new file at the previous poll, to detect finished writes
+            let mut growing: HashMap<PathBuf, u64> = HashMap::new();
+
+            while !thread_stop.load(Ordering::Relaxed) {
+                thread::sleep(POLL_INTERVAL);
+
+                for (path, size) in list_videos(&folder, &extensions) {
+                    if seen.contains(&path) {
+                        continue;
+                    }
+                    let stable = growing.insert(path.clone(), size) == Some(size) && size > 0;
+                    // The recorder keeps the file locked while it is still writing
+                    if !stable || fs::OpenOptions::new().append(true).open(&path).is_err() {
+                        continue;
+                    }
+
+                    growing.remove(&path);
+                    seen.insert(path.clone());
+                    log::info!("Queueing new file from watch folder: {:?}", path);
+                    let command = ControlCommand::AddTask {
+                        path,
+                        recipe: recipe.clone(),
+                    };
+                    if control_tx.send(command).is_err() {
+                        return;
+                    }
+                    on_command();
+                }
+            }
+            log::info!("Stopped watching {:?}", folder);
+        });
+
+        Self { stop }
+    }
+}
+
+impl Drop for FolderWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Videos with one of `extensions` directly inside `folder`, with their current sizes.
+fn list_videos(folder: &Path, extensions: &[String]) -> HashMap<PathBuf, u64> {
+    let Ok(entries) = fs::read_dir(folder) else {
+        return HashMap::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let metadata = entry.metadata().ok().filter(|m| m.is_file())?;
+            queue::is_supported_video(&path, extensions).then_some((path, metadata.len()))
+        })
+        .collect()
+}